@@ -1,15 +1,20 @@
+use crate::commands::labelme_convert::ConvertLabelMeRequest;
 use crate::core::annotation_processor;
-use crate::core::bounding_box_drawer;
-use crate::core::image_annotator::ImageAnnotator;
-use crate::core::polygon_drawer;
+use crate::core::image_annotator::{AnnotatedImage, ImageAnnotator};
 use crate::crop_remap;
+use crate::crop_remap::{
+    CropDatasetResult, CropRemapOptions, CropRuleSet, CropRunResult, PasteBackOptions,
+    PasteBackResult,
+};
+use crate::labelme_convert::progress::ProgressEmitter;
+use crate::labelme_convert::{RenderOptions, SampleOptions, render_file, sample_annotated_images};
+use serde::Deserialize;
 use serde_json::json;
-use std::collections::hash_map::DefaultHasher;
 use std::fs;
-use std::hash::{Hash, Hasher};
 #[cfg(unix)]
 use std::os::unix::fs::PermissionsExt;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::AtomicBool;
 
 #[tauri::command]
 pub fn crop_and_remap_annotations(
@@ -19,6 +24,7 @@ pub fn crop_and_remap_annotations(
     parent_label: String,
     required_child_labels_str: String,
     padding_factor: f32,
+    options: Option<CropRemapOptions>,
 ) -> Result<(), String> {
     // Parse the comma-separated string into a vector of string references
     // Note: We need to own the strings for the thread move
@@ -37,6 +43,9 @@ pub fn crop_and_remap_annotations(
         return Err("Padding factor must be between 0.1 and 5.0".to_string());
     }
 
+    let options = options.unwrap_or_default();
+    options.validate()?;
+
     println!(
         "Received crop_and_remap request: source={}, output={}, parent_label={}, required_child={:?}, padding_factor={:.2}",
         source_dir, output_dir, parent_label, required_child_labels, padding_factor
@@ -75,6 +84,7 @@ pub fn crop_and_remap_annotations(
             &parent_label,
             &child_labels_refs,
             padding_factor,
+            &options,
             Some(move |current: usize, total: usize, message: String| {
                 let _ = window_clone.emit(
                     "crop-progress",
@@ -127,11 +137,306 @@ pub fn crop_and_remap_annotations(
     Ok(())
 }
 
+/// Crop-and-remap driven by a rule set, writing one output folder per rule.
+///
+/// The rules come either inline (`rules`) or from a YAML/JSON file (`rules_path`).
+/// Progress and results are reported with the same events as `crop_and_remap_annotations`.
+#[tauri::command]
+pub fn crop_and_remap_with_rules(
+    window: tauri::Window,
+    source_dir: String,
+    output_dir: String,
+    rules_path: Option<String>,
+    rules: Option<CropRuleSet>,
+    options: Option<CropRemapOptions>,
+) -> Result<(), String> {
+    let rule_set = match (rules, rules_path) {
+        (Some(rules), _) => rules,
+        (None, Some(path)) => CropRuleSet::load(Path::new(&path))?,
+        (None, None) => return Err("No crop rules or rule file specified".to_string()),
+    };
+    rule_set.validate()?;
+
+    let options = options.unwrap_or_default();
+    options.validate()?;
+
+    println!(
+        "Received crop_and_remap_with_rules request: source={}, output={}, rules={}",
+        source_dir,
+        output_dir,
+        rule_set.rules.len()
+    );
+
+    tauri::async_runtime::spawn(async move {
+        use tauri::Emitter;
+
+        let window_clone = window.clone();
+        let result = crop_remap::process_rule_set(
+            &source_dir,
+            &output_dir,
+            &rule_set,
+            &options,
+            Some(move |current: usize, total: usize, message: String| {
+                let _ = window_clone.emit(
+                    "crop-progress",
+                    json!({
+                        "current": current,
+                        "total": total,
+                        "message": message
+                    }),
+                );
+            }),
+        );
+
+        match result {
+            Ok(message) => {
+                let _ = window.emit(
+                    "crop-complete",
+                    json!({
+                        "tempPath": output_dir,
+                        "message": message
+                    }),
+                );
+            }
+            Err(e) => {
+                let _ = window.emit("crop-error", json!({ "message": e }));
+            }
+        }
+    });
+
+    Ok(())
+}
+
+/// Request parameters for an async crop-and-remap run
+///
+/// Either `rules`/`rules_path` or `parent_label` with `child_labels` must be given;
+/// rules win when both are present.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CropRemapRequest {
+    /// Id used to cancel the run with `cancel_crop_job`; must differ from running jobs
+    pub job_id: String,
+    pub source_dir: String,
+    pub output_dir: String,
+    /// Parent label for a single-level crop
+    #[serde(default)]
+    pub parent_label: Option<String>,
+    /// Child labels, any of which qualifies a parent
+    #[serde(default)]
+    pub child_labels: Vec<String>,
+    /// Factor to expand the parent bounding box (1.0 = no padding)
+    #[serde(default = "default_padding_factor")]
+    pub padding_factor: f32,
+    /// Inline rule set
+    #[serde(default)]
+    pub rules: Option<CropRuleSet>,
+    /// YAML/JSON rule set file
+    #[serde(default)]
+    pub rules_path: Option<String>,
+    #[serde(default)]
+    pub options: CropRemapOptions,
+}
+
+fn default_padding_factor() -> f32 {
+    1.0
+}
+
+impl CropRemapRequest {
+    /// Load and validate the request's rule set (None for a single-level crop)
+    fn rule_set(&self) -> Result<Option<CropRuleSet>, String> {
+        let rule_set = match (self.rules.clone(), &self.rules_path) {
+            (Some(rules), _) => Some(rules),
+            (None, Some(path)) => Some(CropRuleSet::load(Path::new(path))?),
+            (None, None) => None,
+        };
+        match &rule_set {
+            Some(rule_set) => rule_set.validate()?,
+            None => {
+                if self.parent_label.as_deref().unwrap_or("").trim().is_empty() {
+                    return Err("No crop rules or parent label specified".to_string());
+                }
+                if self.child_labels.iter().all(|l| l.trim().is_empty()) {
+                    return Err("No required child labels specified".to_string());
+                }
+                if self.padding_factor <= 0.0 || self.padding_factor > 5.0 {
+                    return Err("Padding factor must be between 0.1 and 5.0".to_string());
+                }
+            }
+        }
+        self.options.validate()?;
+        Ok(rule_set)
+    }
+
+    /// Run the crop into `output_dir`, streaming progress to `progress`
+    fn run(
+        &self,
+        rule_set: Option<&CropRuleSet>,
+        output_dir: &str,
+        progress: &ProgressEmitter,
+        cancel: &AtomicBool,
+    ) -> Result<CropRunResult, String> {
+        let on_progress = |current: usize, total: usize, message: String| {
+            progress.emit(current, total, message)
+        };
+        match rule_set {
+            Some(rule_set) => crop_remap::run_rule_set(
+                &self.source_dir,
+                output_dir,
+                rule_set,
+                &self.options,
+                Some(on_progress),
+                Some(cancel),
+            ),
+            None => {
+                let child_labels: Vec<&str> = self
+                    .child_labels
+                    .iter()
+                    .map(|s| s.trim())
+                    .filter(|s| !s.is_empty())
+                    .collect();
+                annotation_processor::run_parent_child_crop(
+                    &self.source_dir,
+                    output_dir,
+                    self.parent_label.as_deref().unwrap_or_default(),
+                    &child_labels,
+                    self.padding_factor,
+                    &self.options,
+                    Some(on_progress),
+                    Some(cancel),
+                )
+            }
+        }
+    }
+}
+
+/// Summary line for the final progress event of a crop run
+fn crop_run_message(run: &CropRunResult) -> String {
+    if run.cancelled {
+        format!(
+            "Cancelled after {} of {} files, {} crops written",
+            run.processed_files, run.total_files, run.total_crops
+        )
+    } else {
+        format!(
+            "Created {} crops from {} files ({} errors)",
+            run.total_crops,
+            run.processed_files,
+            run.errors.len()
+        )
+    }
+}
+
+/// Crop-and-remap in the background, returning per-file errors and crop counts
+///
+/// Reports progress via the "crop-progress" event. The run can be stopped with
+/// `cancel_crop_job`; files already started are finished and the result is
+/// marked `cancelled`.
+#[tauri::command]
+pub async fn crop_and_remap_async(
+    window: tauri::Window,
+    request: CropRemapRequest,
+) -> Result<CropRunResult, String> {
+    let rule_set = request.rule_set()?;
+    let progress = ProgressEmitter::new(window, "crop-progress");
+    let cancel = crop_remap::run::register_job(&request.job_id)?;
+    let job_id = request.job_id.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let result = request.run(
+            rule_set.as_ref(),
+            &request.output_dir,
+            &progress,
+            &cancel,
+        );
+        match &result {
+            Ok(run) => progress.complete(crop_run_message(run)),
+            Err(e) => progress.error(e.clone()),
+        }
+        result
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e));
+
+    crop_remap::run::finish_job(&job_id);
+    result?
+}
+
+/// Crop-and-remap straight into a YOLO or COCO training dataset
+///
+/// `export` carries the usual conversion settings (format, split sizes, seed,
+/// split grouping, label list); its `input_dir` and `output_dir` are replaced
+/// by the request's source and output directories. Crops of one source image
+/// always land in the same split. Rule sets give one dataset per rule.
+/// Progress and cancellation work as for `crop_and_remap_async`.
+#[tauri::command]
+pub async fn crop_and_remap_to_dataset(
+    window: tauri::Window,
+    request: CropRemapRequest,
+    export: ConvertLabelMeRequest,
+) -> Result<CropDatasetResult, String> {
+    let rule_set = request.rule_set()?;
+    let mut export = export.to_config()?;
+    export.input_dir = PathBuf::from(&request.source_dir);
+    export.output_dir = Some(PathBuf::from(&request.output_dir));
+    export.validate()?;
+
+    let progress = ProgressEmitter::new(window, "crop-progress");
+    let cancel = crop_remap::run::register_job(&request.job_id)?;
+    let job_id = request.job_id.clone();
+
+    let result = tokio::task::spawn_blocking(move || {
+        let result = crop_remap::crop_to_dataset(&export, |staging| {
+            let run = request.run(
+                rule_set.as_ref(),
+                &staging.to_string_lossy(),
+                &progress,
+                &cancel,
+            )?;
+            if !run.cancelled {
+                progress.emit(run.total_files, run.total_files, "Building training dataset...");
+            }
+            Ok(run)
+        });
+        match &result {
+            Ok(dataset) => progress.complete(crop_run_message(&dataset.crops)),
+            Err(e) => progress.error(e.clone()),
+        }
+        result
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e));
+
+    crop_remap::run::finish_job(&job_id);
+    result?
+}
+
+/// Cancel a crop job started with `crop_and_remap_async`
+///
+/// Returns false when no job with this id is running.
+#[tauri::command]
+pub fn cancel_crop_job(job_id: String) -> bool {
+    crop_remap::cancel_job(&job_id)
+}
+
+/// Project annotation edits made on crops back into the full-frame source files
+///
+/// `crop_dir` is a crop-and-remap output folder (one rule folder for rule sets);
+/// its `crop_report.json` maps every crop to its source file, parent shape and
+/// offset/scale.
+#[tauri::command]
+pub fn paste_back_crop_edits(
+    crop_dir: String,
+    options: Option<PasteBackOptions>,
+) -> Result<PasteBackResult, String> {
+    crop_remap::paste_back_crops(Path::new(&crop_dir), &options.unwrap_or_default())
+}
+
 #[tauri::command]
 pub fn generate_annotated_previews(
     source_dir: String,
     num_previews: usize,
     temp_dir: String,
+    sampling: Option<SampleOptions>,
+    render: Option<RenderOptions>,
 ) -> Result<String, String> {
     println!(
         "Generating {} annotated preview images from: {}",
@@ -153,65 +458,20 @@ pub fn generate_annotated_previews(
         }
     }
 
-    // Get annotated images using the ImageAnnotator - process all annotation types
-    let annotation_result = ImageAnnotator::auto_annotate_images(&source_dir, 1, 1000);
-    let annotation_result = annotation_result?;
-    let parsed_result: serde_json::Value = serde_json::from_str(&annotation_result)
-        .map_err(|e| format!("Failed to parse annotation result: {}", e))?;
-
-    let annotated_images_data = parsed_result["annotated_images"]
-        .as_array()
-        .ok_or("No annotated images found")?;
-
-    // Filter images with annotations
-    let mut annotated_images = Vec::new();
-    for image in annotated_images_data {
-        let path = image["path"].as_str().ok_or("Invalid image path")?;
-        let has_json = image["has_json"].as_bool().unwrap_or(false);
-        let empty_vec = vec![];
-        let annotations = image["annotations"].as_array().unwrap_or(&empty_vec);
-
-        if has_json && !annotations.is_empty() {
-            annotated_images.push((path.to_string(), annotations.clone()));
-        }
-    }
-
-    if annotated_images.is_empty() {
+    // Pick the previews from every annotated image in the dataset
+    let sample = sample_annotated_images(
+        Path::new(&source_dir),
+        num_previews,
+        &sampling.unwrap_or_default(),
+    )?;
+    if sample.images.is_empty() {
         return Err("No annotated images found".to_string());
     }
 
-    // Shuffle and take up to num_previews
-    let mut hasher = DefaultHasher::new();
-    annotated_images.hash(&mut hasher);
-    let seed = hasher.finish() as usize;
-
-    // Simple shuffle using seed
-    for i in (1..annotated_images.len()).rev() {
-        let j = seed.wrapping_mul(i) % (i + 1);
-        annotated_images.swap(i, j);
-    }
-
-    let selected_images = annotated_images.into_iter().take(num_previews);
-
     // Generate annotated preview images
+    let render = render.unwrap_or_default();
     let mut preview_paths = Vec::new();
-    for (image_path, _annotations) in selected_images {
-        // Find corresponding JSON file
-        let image_path_obj = Path::new(&image_path);
-        let file_stem = image_path_obj
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .ok_or("Invalid image path")?;
-
-        let json_path = image_path_obj
-            .parent()
-            .map(|p| p.join(format!("{}.json", file_stem)))
-            .ok_or("Cannot determine JSON path")?;
-
-        if !json_path.exists() {
-            continue; // Skip if no JSON file
-        }
-
+    for candidate in &sample.images {
         // Generate preview filename
         let timestamp = std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
@@ -221,146 +481,38 @@ pub fn generate_annotated_previews(
         let preview_filename = format!("preview_{}_{}.jpg", timestamp, preview_paths.len());
         let preview_path = Path::new(&temp_dir).join(preview_filename);
 
-        // Read JSON to determine annotation types
-        let json_content = match fs::read_to_string(&json_path) {
-            Ok(content) => content,
-            Err(e) => {
-                println!(
-                    "Warning: Failed to read JSON file {}: {}",
-                    json_path.display(),
-                    e
-                );
-                continue;
-            }
-        };
-
-        let json_value: serde_json::Value = match serde_json::from_str(&json_content) {
-            Ok(parsed) => parsed,
-            Err(e) => {
-                println!(
-                    "Warning: Failed to parse JSON file {}: {}",
-                    json_path.display(),
-                    e
-                );
-                continue;
-            }
-        };
-
-        // Check if we have polygons or rectangles
-        let shapes = match json_value.get("shapes").and_then(|s| s.as_array()) {
-            Some(shapes_array) => shapes_array,
-            None => {
-                println!(
-                    "Warning: No shapes found in JSON file {}",
-                    json_path.display()
-                );
-                continue;
-            }
-        };
-
-        let mut has_polygons = false;
-        let mut has_rectangles = false;
-
-        for shape in shapes {
-            if let Some(shape_type) = shape.get("shape_type").and_then(|st| st.as_str()) {
-                match shape_type {
-                    "polygon" => has_polygons = true,
-                    "rectangle" => has_rectangles = true,
-                    _ => {}
-                }
-            }
-        }
-
-        // Draw annotations based on detected types - prioritize polygons if both exist
-        let mut drawing_success = false;
-        let json_filename = json_path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .ok_or("Invalid JSON filename")?;
-
-        // Try polygon drawing first if polygons are present
-        if has_polygons {
-            if let Ok(_) =
-                polygon_drawer::draw_polygons(&source_dir, &json_path.to_string_lossy(), &temp_dir)
-            {
-                let expected_output = Path::new(&temp_dir).join(format!(
-                    "{}_polygons.jpg",
-                    json_filename.replace(".json", "")
-                ));
-                if expected_output.exists() {
-                    if let Err(e) = fs::rename(&expected_output, &preview_path) {
-                        println!("Warning: Failed to rename polygon preview file: {}", e);
-                    } else {
-                        drawing_success = true;
-                    }
-                }
-            }
-        }
-
-        // Try bounding box drawing if rectangles are present and polygon drawing didn't succeed
-        if !drawing_success && has_rectangles {
-            if let Ok(_) = bounding_box_drawer::draw_bounding_boxes(
-                &source_dir,
-                &json_path.to_string_lossy(),
-                &temp_dir,
-            ) {
-                let expected_output = Path::new(&temp_dir)
-                    .join(format!("{}_boxes.jpg", json_filename.replace(".json", "")));
-                if expected_output.exists() {
-                    if let Err(e) = fs::rename(&expected_output, &preview_path) {
-                        println!("Warning: Failed to rename bounding box preview file: {}", e);
-                    } else {
-                        drawing_success = true;
-                    }
-                }
-            }
-        }
-
-        if !drawing_success {
-            println!("Warning: Failed to draw annotations for {}", image_path);
+        // Draw every shape type with the shared renderer
+        if let Err(e) = render_file(&candidate.json_path, &preview_path, &render) {
+            println!(
+                "Warning: Failed to draw annotations for {}: {}",
+                candidate.image_path.display(),
+                e
+            );
             continue;
         }
 
-        // Add to preview paths if drawing was successful
-        if drawing_success {
-            // Set proper permissions for the generated preview file
-            #[cfg(unix)]
-            if let Ok(metadata) = fs::metadata(&preview_path) {
-                let mut permissions = metadata.permissions();
-                permissions.set_mode(0o644); // rw-r--r--
-                if let Err(e) = fs::set_permissions(&preview_path, permissions) {
-                    println!("Warning: Failed to set preview file permissions: {}", e);
-                }
+        // Set proper permissions for the generated preview file
+        #[cfg(unix)]
+        if let Ok(metadata) = fs::metadata(&preview_path) {
+            let mut permissions = metadata.permissions();
+            permissions.set_mode(0o644); // rw-r--r--
+            if let Err(e) = fs::set_permissions(&preview_path, permissions) {
+                println!("Warning: Failed to set preview file permissions: {}", e);
             }
-            preview_paths.push(preview_path.to_string_lossy().to_string());
         }
+        preview_paths.push(preview_path.to_string_lossy().to_string());
     }
 
-    // Use annotation data directly (no preview paths needed)
-    let mut annotated_images_result = Vec::new();
-    let empty_annotations = Vec::new();
-
-    for image in annotated_images_data {
-        let has_json = image["has_json"].as_bool().unwrap_or(false);
-        let annotations = image["annotations"]
-            .as_array()
-            .unwrap_or(&empty_annotations);
-
-        // Only include images that have annotations
-        if has_json && !annotations.is_empty() {
-            annotated_images_result.push(image.clone());
-        }
-    }
-
-    // Take only the first num_previews images
-    let annotated_images_result = annotated_images_result
-        .into_iter()
-        .take(num_previews)
-        .collect::<Vec<_>>();
+    // Annotation data of the sampled images (no preview paths needed)
+    let annotated_images_result: Vec<AnnotatedImage> = sample
+        .images
+        .iter()
+        .map(|c| ImageAnnotator::annotated_image(&c.image_path, &c.json_path))
+        .collect();
 
     let result = json!({
         "annotated_images": annotated_images_result,
-        "total": annotated_images_data.len(),
+        "total": sample.candidates,
         "preview_count": annotated_images_result.len()
     });
 
@@ -368,6 +520,11 @@ pub fn generate_annotated_previews(
 }
 
 #[tauri::command]
-pub fn crop_remap_adapter(source_dir: String, num_previews: usize) -> Result<String, String> {
-    crop_remap::crop_remap_adapter(source_dir, num_previews)
+pub fn crop_remap_adapter(
+    source_dir: String,
+    num_previews: usize,
+    sampling: Option<SampleOptions>,
+    render: Option<RenderOptions>,
+) -> Result<String, String> {
+    crop_remap::crop_remap_adapter(source_dir, num_previews, sampling, render)
 }
@@ -1,6 +1,9 @@
 use crate::core::labelme_viewer::LabelmeViewerModule;
-use glob::glob;
-use std::collections::{HashMap, HashSet};
+use crate::labelme_convert::statistics::{
+    compute_statistics, DatasetStatistics, DEFAULT_HISTOGRAM_BINS,
+};
+use std::collections::BTreeSet;
+use std::path::Path;
 
 #[tauri::command]
 pub fn get_dataset_stats(source_dir: String) -> Result<String, String> {
@@ -17,93 +20,52 @@ pub fn get_dataset_stats(source_dir: String) -> Result<String, String> {
     }
 }
 
+/// Label and shape type counts of a LabelMe directory
+///
+/// The counts come from the statistics engine; the full geometric statistics
+/// are included under `statistics`.
 #[tauri::command]
 pub fn get_labelme_summary(path: &str) -> Result<String, String> {
     println!("Generating LabelMe summary for: {}", path);
 
     // Validate input path
-    if !std::path::Path::new(path).exists() {
+    if !Path::new(path).exists() {
         return Err(format!("Path does not exist: {}", path));
     }
 
-    // Create a summary of the dataset
-    let mut total_images = 0;
-    let mut images_with_annotations = 0;
-    let mut total_annotations = 0;
-    let mut label_counts: HashMap<String, usize> = HashMap::new();
-    let mut annotation_types: HashSet<String> = HashSet::new();
-
-    // Pattern to find JSON files (LabelMe annotations)
-    let pattern = format!("{}/**/*.json", path);
-    match glob(&pattern) {
-        Ok(paths) => {
-            for entry in paths {
-                match entry {
-                    Ok(json_path) => {
-                        // Count total JSON files
-                        total_images += 1;
-
-                        // Read and parse the JSON file
-                        if let Ok(content) = std::fs::read_to_string(&json_path) {
-                            if let Ok(json) = serde_json::from_str::<serde_json::Value>(&content) {
-                                let _has_annotations = if let Some(shapes) =
-                                    json["shapes"].as_array()
-                                {
-                                    if !shapes.is_empty() {
-                                        images_with_annotations += 1;
-
-                                        // Process each annotation
-                                        for shape in shapes {
-                                            total_annotations += 1;
-
-                                            // Count labels
-                                            if let Some(label) = shape["label"].as_str() {
-                                                *label_counts
-                                                    .entry(label.to_string())
-                                                    .or_insert(0) += 1;
-                                            }
-
-                                            // Track annotation types
-                                            if let Some(shape_type) = shape["shape_type"].as_str() {
-                                                annotation_types.insert(shape_type.to_string());
-                                            }
-                                        }
-
-                                        true
-                                    } else {
-                                        false
-                                    }
-                                } else {
-                                    false
-                                };
-                                // Use _has_annotations to silence the warning if it's not used later.
-                                // If you intended to use it, uncomment the line below and integrate it.
-                                // let _ = has_annotations;
-                            }
-                        }
-                    }
-                    Err(e) => println!("Error accessing path: {:?}", e),
-                }
-            }
-        }
-        Err(e) => {
-            return Err(format!("Failed to read directory: {}", e));
-        }
+    let stats = compute_statistics(Path::new(path), DEFAULT_HISTOGRAM_BINS)?;
+    for error in &stats.errors {
+        println!("Error reading annotation: {}", error);
     }
+    let annotation_types: BTreeSet<&String> = stats
+        .shape_types
+        .values()
+        .flat_map(|types| types.keys())
+        .collect();
 
     // Create summary object
     let summary = serde_json::json!({
-        "total_images": total_images,
-        "images_with_annotations": images_with_annotations,
-        "total_annotations": total_annotations,
-        "unique_labels": label_counts.len(),
-        "label_counts": label_counts,
-        "annotation_types": annotation_types.into_iter().collect::<Vec<_>>(),
+        // Unreadable JSON files still count as images
+        "total_images": stats.total_images + stats.errors.len(),
+        "images_with_annotations": stats.images_with_annotations,
+        "total_annotations": stats.total_instances,
+        "unique_labels": stats.label_counts.len(),
+        "label_counts": stats.label_counts,
+        "annotation_types": annotation_types,
+        "statistics": stats,
     });
 
     Ok(summary.to_string())
 }
 
+#[tauri::command]
+pub fn get_labelme_statistics(
+    path: String,
+    bins: Option<usize>,
+) -> Result<DatasetStatistics, String> {
+    compute_statistics(Path::new(&path), bins.unwrap_or(DEFAULT_HISTOGRAM_BINS))
+}
+
 #[derive(Debug, serde::Deserialize)]
 pub struct AnnotationData {
     pub label: String,
@@ -1,5 +1,6 @@
 use crate::core::bounding_box_drawer;
 use crate::core::polygon_drawer;
+use crate::labelme_convert::RenderOptions;
 use glob::glob;
 use std::fs;
 use std::path::Path;
@@ -9,6 +10,7 @@ pub fn draw_bounding_boxes(
     source_dir: String,
     json_path: String,
     output_dir: String,
+    options: Option<RenderOptions>,
 ) -> Result<String, String> {
     // Create output directory if it doesn't exist
     if let Err(e) = fs::create_dir_all(&output_dir) {
@@ -16,7 +18,8 @@ pub fn draw_bounding_boxes(
     }
 
     // Draw bounding boxes
-    match bounding_box_drawer::draw_bounding_boxes(&source_dir, &json_path, &output_dir) {
+    let options = options.unwrap_or_default();
+    match bounding_box_drawer::draw_bounding_boxes(&source_dir, &json_path, &output_dir, &options) {
         Ok(_) => Ok("Bounding boxes drawn successfully".to_string()),
         Err(e) => Err(format!("Failed to draw bounding boxes: {}", e)),
     }
@@ -27,13 +30,15 @@ pub fn draw_polygons(
     source_dir: String,
     json_path: String,
     output_dir: String,
+    options: Option<RenderOptions>,
 ) -> Result<String, String> {
     // Create output directory if it doesn't exist
     if let Err(e) = fs::create_dir_all(&output_dir) {
         return Err(format!("Failed to create output directory: {}", e));
     }
 
-    match polygon_drawer::draw_polygons(&source_dir, &json_path, &output_dir) {
+    let options = options.unwrap_or_default();
+    match polygon_drawer::draw_polygons(&source_dir, &json_path, &output_dir, &options) {
         Ok(_) => Ok("Polygons drawn successfully".to_string()),
         Err(e) => Err(format!("Failed to draw polygons: {}", e)),
     }
@@ -45,12 +50,16 @@ pub fn visualize_dataset(
     output_dir: String,
     annotation_type: String,
     save_output: bool,
+    options: Option<RenderOptions>,
 ) -> Result<String, String> {
     // Validate parameters
     if !Path::new(&source_dir).exists() {
         return Err(format!("Source directory does not exist: {}", source_dir));
     }
 
+    let options = options.unwrap_or_default();
+    options.validate()?;
+
     if annotation_type != "bounding_box" && annotation_type != "polygon" {
         return Err(format!(
             "Invalid annotation type: {}. Must be 'bounding_box' or 'polygon'",
@@ -105,9 +114,10 @@ pub fn visualize_dataset(
                             &source_dir,
                             json_path,
                             &output_dir,
+                            &options,
                         )
                     } else {
-                        polygon_drawer::draw_polygons(&source_dir, json_path, &output_dir)
+                        polygon_drawer::draw_polygons(&source_dir, json_path, &output_dir, &options)
                     };
 
                     match result {
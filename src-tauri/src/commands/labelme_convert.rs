@@ -6,11 +6,20 @@
 // Adapted and modified for dataset-app
 
 use crate::labelme_convert::{
-    convert, AnnotationFormat, ConversionConfig, ConversionResult, LabelMeOutputFormat,
-    OutputFormat, SegmentationMode,
+    convert, diff_datasets, AnnotationFormat, AugmentConfig, BalanceConfig, ConversionConfig,
+    ConversionResult, DatasetDiff, DatasetSourceFormat, DiffConfig, LabelMeOutputFormat,
+    MaskDrawOrder, OutputFormat, PolygonOptions, PreviewConfig, SegmentationMode, ShapeOptions,
+    SplitGroupKey,
 };
+use crate::labelme_convert::cvat::{import_cvat, CvatImportResult};
+use crate::labelme_convert::label_studio::{import_label_studio, LabelStudioImportResult};
+use crate::labelme_convert::mask_import::{import_masks, MaskImportConfig, MaskImportResult};
+use crate::labelme_convert::merge::{
+    analyze_merge, merge_datasets, MergeAnalysis, MergeConfig, MergeResult,
+};
+use crate::labelme_convert::voc::{import_voc, VocImportResult};
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 /// Request parameters for LabelMe conversion
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -46,6 +55,35 @@ pub struct ConvertLabelMeRequest {
     #[serde(default = "default_seed")]
     pub seed: u64,
 
+    /// Keep related images in one split, e.g. {"type": "parent_folder"}
+    /// or {"type": "path_regex", "value": "(cam\\d+)/"}
+    #[serde(default)]
+    pub split_group: Option<SplitGroupKey>,
+
+    /// Balance label frequencies across splits and folds
+    #[serde(default)]
+    pub stratified: bool,
+
+    /// Number of cross-validation folds (0 = disabled)
+    #[serde(default)]
+    pub kfold: usize,
+
+    /// Class balancing for the train split (optional)
+    #[serde(default)]
+    pub balance: Option<BalanceConfig>,
+
+    /// Offline augmentation of the train split (YOLO and COCO only)
+    #[serde(default)]
+    pub augment: Option<AugmentConfig>,
+
+    /// Rendered sample images in `previews/` of the output (optional)
+    #[serde(default)]
+    pub previews: Option<PreviewConfig>,
+
+    /// Input file format: "label_me" or "pascal_voc" (optional, auto-detected)
+    #[serde(default)]
+    pub source_format: Option<DatasetSourceFormat>,
+
     /// Include images without annotations as background
     #[serde(default)]
     pub include_background: bool,
@@ -70,6 +108,28 @@ pub struct ConvertLabelMeRequest {
     /// LabelMe output point format: "original", "bbox_2point", or "bbox_4point"
     #[serde(default = "default_labelme_output_format")]
     pub labelme_output_format: String,
+
+    // Mask-specific options
+    /// Pixel value for ignored regions in semantic masks
+    #[serde(default = "default_mask_ignore_index")]
+    pub mask_ignore_index: u8,
+
+    /// Labels painted with the ignore index instead of a class index
+    #[serde(default)]
+    pub mask_ignore_labels: Vec<String>,
+
+    /// Paint order of overlapping shapes or instances:
+    /// "file_order", "area_descending" or "class_index"
+    #[serde(default)]
+    pub mask_draw_order: MaskDrawOrder,
+
+    /// Polygon simplification / resampling for YOLO-seg and COCO segmentation
+    #[serde(default)]
+    pub polygon_options: PolygonOptions,
+
+    /// Handling of circle, line and point shapes (lines/points are skipped by default)
+    #[serde(default)]
+    pub shape_options: ShapeOptions,
 }
 
 fn default_output_format() -> String {
@@ -96,6 +156,10 @@ fn default_labelme_output_format() -> String {
     "original".to_string()
 }
 
+fn default_mask_ignore_index() -> u8 {
+    255
+}
+
 impl ConvertLabelMeRequest {
     /// Convert request to internal ConversionConfig
     pub fn to_config(&self) -> Result<ConversionConfig, String> {
@@ -103,6 +167,10 @@ impl ConvertLabelMeRequest {
             "yolo" => OutputFormat::Yolo,
             "coco" => OutputFormat::Coco,
             "labelme" => OutputFormat::LabelMe,
+            "cvat" => OutputFormat::Cvat,
+            "label_studio" | "labelstudio" => OutputFormat::LabelStudio,
+            "semantic_mask" | "semanticmask" => OutputFormat::SemanticMask,
+            "instance_mask" | "instancemask" => OutputFormat::InstanceMask,
             other => return Err(format!("Unknown output format: {}", other)),
         };
 
@@ -124,12 +192,24 @@ impl ConvertLabelMeRequest {
             .with_val_size(self.val_size)
             .with_test_size(self.test_size)
             .with_seed(self.seed)
+            .with_split_group(self.split_group.clone())
+            .with_stratified(self.stratified)
+            .with_kfold(self.kfold)
+            .with_balance(self.balance.clone())
+            .with_augment(self.augment.clone())
             .with_background(self.include_background)
             .with_labels(self.label_list.clone())
             .with_custom_name(self.custom_dataset_name.clone());
 
         config.deterministic_labels = self.deterministic_labels;
         config.segmentation_mode = segmentation_mode;
+        config.mask_ignore_index = self.mask_ignore_index;
+        config.mask_ignore_labels = self.mask_ignore_labels.clone();
+        config.mask_draw_order = self.mask_draw_order;
+        config.polygon_options = self.polygon_options;
+        config.shape_options = self.shape_options;
+        config.previews = self.previews.clone();
+        config.source_format = self.source_format;
 
         // LabelMe-specific options
         if output_format == OutputFormat::LabelMe {
@@ -405,3 +485,167 @@ pub async fn analyze_labelme_dataset_async(
         format_description: analysis.format_description,
     })
 }
+
+// ===== Dataset merge =====
+
+/// Request parameters for merging LabelMe datasets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeLabelMeRequest {
+    /// Source dataset directories
+    pub sources: Vec<String>,
+
+    /// Directory receiving the merged dataset
+    pub output_dir: String,
+
+    /// Explicit label renames (source label → target label)
+    #[serde(default)]
+    pub label_mapping: std::collections::HashMap<String, String>,
+
+    /// Merge labels that only differ in case or whitespace
+    #[serde(default = "default_normalize_labels")]
+    pub normalize_labels: bool,
+
+    /// Remove imageData from the merged JSON files
+    #[serde(default)]
+    pub remove_image_data: bool,
+}
+
+fn default_normalize_labels() -> bool {
+    true
+}
+
+impl MergeLabelMeRequest {
+    /// Convert request to internal MergeConfig
+    pub fn to_config(&self) -> MergeConfig {
+        let mut config = MergeConfig::new(
+            self.sources.iter().map(PathBuf::from).collect(),
+            PathBuf::from(&self.output_dir),
+        );
+        config.label_mapping = self.label_mapping.clone();
+        config.normalize_labels = self.normalize_labels;
+        config.remove_image_data = self.remove_image_data;
+        config
+    }
+}
+
+/// Preview a merge: label conflicts, proposed label mapping and file name collisions
+#[tauri::command]
+pub fn analyze_labelme_merge(request: MergeLabelMeRequest) -> Result<MergeAnalysis, String> {
+    analyze_merge(&request.to_config())
+}
+
+/// Merge several LabelMe datasets into one
+///
+/// Reports progress via the "merge-progress" event.
+#[tauri::command]
+pub async fn merge_labelme_datasets(
+    window: tauri::Window,
+    request: MergeLabelMeRequest,
+) -> Result<MergeResult, String> {
+    use crate::labelme_convert::progress::ProgressEmitter;
+
+    let config = request.to_config();
+    let progress = ProgressEmitter::new(window, "merge-progress");
+
+    tokio::task::spawn_blocking(move || {
+        let result = merge_datasets(&config, Some(&progress));
+        if let Err(ref e) = result {
+            progress.error(e.clone());
+        }
+        result
+    })
+    .await
+    .map_err(|e| format!("Task join error: {}", e))?
+}
+
+// ===== Dataset diff =====
+
+/// Compare two versions of a LabelMe directory
+///
+/// Reports added/removed images, added/removed/relabeled/moved/reshaped
+/// shapes and per-label count deltas. Points and lines are matched within
+/// `match_distance` pixels. When `report_path` is given, the full diff is
+/// also written there as JSON.
+#[tauri::command]
+pub async fn diff_labelme_datasets(
+    old_dir: String,
+    new_dir: String,
+    match_iou: Option<f64>,
+    match_distance: Option<f64>,
+    report_path: Option<String>,
+) -> Result<DatasetDiff, String> {
+    let mut config = DiffConfig::new(PathBuf::from(&old_dir), PathBuf::from(&new_dir));
+    if let Some(iou) = match_iou {
+        config.match_iou = iou;
+    }
+    if let Some(distance) = match_distance {
+        config.match_distance = distance;
+    }
+    config.report_path = report_path.map(PathBuf::from);
+
+    tokio::task::spawn_blocking(move || diff_datasets(&config))
+        .await
+        .map_err(|e| format!("Task join error: {}", e))?
+}
+
+// ===== CVAT import =====
+
+/// Import a CVAT for images 1.1 XML file as LabelMe JSON files
+///
+/// When `images_dir` is given, the referenced images are copied next to the
+/// generated JSON files.
+#[tauri::command]
+pub fn import_cvat_annotations(
+    xml_path: String,
+    output_dir: String,
+    images_dir: Option<String>,
+) -> Result<CvatImportResult, String> {
+    let images_dir = images_dir.map(PathBuf::from);
+    import_cvat(
+        Path::new(&xml_path),
+        images_dir.as_deref(),
+        Path::new(&output_dir),
+    )
+}
+
+// ===== Label Studio import =====
+
+/// Import a Label Studio JSON export as LabelMe JSON files
+///
+/// `images_root` is the local-files document root the tasks refer to; when
+/// given, the referenced images are copied next to the generated JSON files.
+#[tauri::command]
+pub fn import_label_studio_tasks(
+    tasks_path: String,
+    output_dir: String,
+    images_root: Option<String>,
+) -> Result<LabelStudioImportResult, String> {
+    let images_root = images_root.map(PathBuf::from);
+    import_label_studio(
+        Path::new(&tasks_path),
+        images_root.as_deref(),
+        Path::new(&output_dir),
+    )
+}
+
+// ===== Pascal VOC import =====
+
+/// Import a Pascal VOC dataset as LabelMe JSON files
+///
+/// `input_dir` may point at the VOC root or a parent such as `VOCdevkit`.
+/// Images listed in `ImageSets/Main` are written to train/val/test subfolders.
+#[tauri::command]
+pub fn import_voc_dataset(input_dir: String, output_dir: String) -> Result<VocImportResult, String> {
+    import_voc(Path::new(&input_dir), Path::new(&output_dir))
+}
+
+// ===== Mask import =====
+
+/// Import a folder of binary, class-index or color masks as LabelMe polygons
+///
+/// Masks are matched to images by file stem; labels come from the class
+/// table in the request (e.g. the `classes.json` of a mask export).
+#[tauri::command]
+pub fn import_mask_dataset(config: MaskImportConfig) -> Result<MaskImportResult, String> {
+    import_masks(&config)
+}
@@ -1,9 +1,17 @@
-use crate::core::labelme_types::{/* BoundingBox, */ get_bounding_box, LabelMeFile, LabelMeShape,};
+use crate::core::labelme_types::{LabelMeFile, LabelMeShape};
+use crate::crop_remap::crop::{
+    crop_base_name, cut_crop, find_source_image, plan_parent_crop, save_crop, save_crop_image,
+    unique_owners, ParentCrop,
+};
+use crate::crop_remap::negative::{keep_negative_parent, negative_parent_crop, plan_background_crops};
+use crate::crop_remap::run::{find_source_files, run_crop_files, CropRunResult};
+use crate::crop_remap::{CropKind, CropRecord, CropRemapOptions, NegativeTarget};
+use image::DynamicImage;
 use std::fs;
 use std::path::{Path /* PathBuf */};
+use std::sync::atomic::AtomicBool;
 // use std::collections::HashMap;
 // use image::GenericImageView; // Import GenericImageView trait
-use glob::glob;
 
 #[derive(Debug, Clone)]
 #[allow(dead_code)]
@@ -12,13 +20,6 @@ struct ChildAnnotationInfo {
     remapped_shape: LabelMeShape,
 }
 
-// Helper to sanitize filenames
-fn sanitize_filename(name: &str) -> String {
-    name.chars()
-        .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
-        .collect()
-}
-
 /// Processes annotations: crops image based on parent label, remaps child annotations.
 ///
 /// # Arguments
@@ -27,6 +28,9 @@ fn sanitize_filename(name: &str) -> String {
 /// * `parent_label` - The label of the annotation to use for cropping (e.g., "person").
 /// * `required_child_labels` - The labels of the child annotations to look for (OR logic - any of these).
 /// * `padding_factor` - Factor to expand parent bounding box (1.0 = no padding, 1.2 = 20% larger).
+/// * `options` - Child clipping options; children are cut to the crop and dropped below `min_child_visibility`,
+///   and `association` decides which overlapping children belong to each parent. `negatives` adds crops of
+///   parents without children and of the background.
 /// * `progress_callback` - Optional callback function to report progress (current_file_index, total_files, message).
 ///
 /// # Returns
@@ -37,131 +41,115 @@ pub fn process_parent_child_annotations<F>(
     parent_label: &str,
     required_child_labels: &[&str],
     padding_factor: f32,
+    options: &CropRemapOptions,
     progress_callback: Option<F>,
 ) -> Result<String, String>
 where
     F: Fn(usize, usize, String) + Send + Sync + 'static,
 {
-    use rayon::prelude::*;
-    use std::sync::atomic::{AtomicUsize, Ordering};
-    use std::sync::Arc;
-
-    let source_dir = Path::new(source_dir_str);
-    let output_dir = Path::new(output_dir_str);
-
-    if !source_dir.exists() || !source_dir.is_dir() {
-        return Err(format!(
-            "Source directory not found or is not a directory: {}",
-            source_dir_str
-        ));
-    }
-    fs::create_dir_all(output_dir).map_err(|e| {
-        format!(
-            "Failed to create output directory {}: {}",
-            output_dir_str, e
-        )
-    })?;
-
-    let pattern = format!("{}/**/*.json", source_dir_str.replace("\\", "/")); // Handle windows paths for glob
-
-    // Collect all paths first to know total count for progress reporting
-    let glob_results: Vec<_> = glob(&pattern)
-        .map_err(|e| format!("Failed to read glob pattern {}: {}", pattern, e))?
-        .collect();
-
-    let total_files = glob_results.len();
-
-    // Use atomic counters for thread-safe progress tracking across parallel threads
-    let processed_files_count = Arc::new(AtomicUsize::new(0));
-    let total_processed_parents = Arc::new(AtomicUsize::new(0));
-    // Use a mutex to safely collect errors from multiple threads
-    let error_files = Arc::new(std::sync::Mutex::new(Vec::new()));
-
-    // Wrap callback in Arc to share across threads
-    // Note: The callback itself must be Thread-Safe (Sync)
-    let progress_callback = progress_callback.map(Arc::new);
-
-    // Use Rayon for parallel processing
-    glob_results.par_iter().enumerate().for_each(|(_index, entry)| {
-        // Create references to shared state for this thread
-        let processed_files_count_clone = Arc::clone(&processed_files_count);
-        let total_processed_parents_clone = Arc::clone(&total_processed_parents);
-        let error_files_clone = Arc::clone(&error_files);
-        let progress_callback_clone = progress_callback.as_ref().map(Arc::clone);
-
-        // We use a local counter for the *current* progress display, referencing the atomic global counter
-        // The original 'index' is not reliable for progress in parallel execution as order is not guaranteed.
-        // So we just increment the atomic counter to get the "Nth file processed" number.
-        let current_progress_count = processed_files_count_clone.fetch_add(1, Ordering::SeqCst) + 1;
-
-        if let Some(cb) = &progress_callback_clone {
-            // Note: Parallel threads might call this out of order or simultaneously.
-            // Ideally the callback handler (frontend) just updates a progress bar value.
-            let message = format!("Processing file {} of {}", current_progress_count, total_files);
-            cb(current_progress_count, total_files, message);
-        }
-
-        match entry {
-            Ok(json_path) => {
-                let file_name = json_path
-                    .file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .to_string();
-                println!("Processing JSON (Parallel): {}", file_name);
-
-                match process_single_file(
-                    json_path,
-                    source_dir,
-                    output_dir,
-                    parent_label,
-                    required_child_labels,
-                    padding_factor,
-                ) {
-                    Ok(count) => {
-                        if count > 0 {
-                            total_processed_parents_clone.fetch_add(count, Ordering::SeqCst);
-                            println!(
-                                " -> Successfully processed {} parent instances in this file.",
-                                count
-                            );
-                        } else {
-                            println!(" -> No parent {} instances with required children {:?} found or processed in this file.", parent_label, required_child_labels);
-                        }
-                    }
-                    Err(e) => {
-                        eprintln!(" -> Error processing {}: {}", file_name, e);
-                        if let Ok(mut errors) = error_files_clone.lock() {
-                            errors.push(format!("{}: {}", file_name, e));
-                        }
-                    }
-                }
-            }
-            Err(e) => eprintln!("Error accessing path via glob: {:?}", e),
-        }
-    });
-
-    let final_processed_files = processed_files_count.load(Ordering::SeqCst);
-    let final_total_parents = total_processed_parents.load(Ordering::SeqCst);
-    let final_errors = error_files.lock().unwrap().clone();
+    let result = run_parent_child_crop(
+        source_dir_str,
+        output_dir_str,
+        parent_label,
+        required_child_labels,
+        padding_factor,
+        options,
+        progress_callback,
+        None,
+    )?;
 
     let summary = format!(
-        "Processing complete. Found valid parent annotations in {} files checked, successfully processing a total of {} parent instances.",
-        final_processed_files, final_total_parents
+        "Processing complete. Found valid parent annotations in {} files checked, successfully processing a total of {} parent instances and writing {} negative crops. Clipped {} and dropped {} child annotations at crop borders.",
+        result.processed_files,
+        result.total_crops - result.negative_crops,
+        result.negative_crops,
+        result.children_clipped,
+        result.children_dropped
     );
 
-    if final_errors.is_empty() {
+    if result.errors.is_empty() {
         Ok(summary)
     } else {
         Err(format!(
             "{}\nEncountered errors in {} files:\n - {}",
             summary,
-            final_errors.len(),
-            final_errors.join("\n - ")
+            result.errors.len(),
+            result.error_lines().join("\n - ")
         ))
     }
 }
 
+/// Same as [`process_parent_child_annotations`], returning a typed result
+///
+/// Files not yet started when `cancel` is set are skipped.
+#[allow(clippy::too_many_arguments)]
+pub fn run_parent_child_crop<F>(
+    source_dir_str: &str,
+    output_dir_str: &str,
+    parent_label: &str,
+    required_child_labels: &[&str],
+    padding_factor: f32,
+    options: &CropRemapOptions,
+    progress_callback: Option<F>,
+    cancel: Option<&AtomicBool>,
+) -> Result<CropRunResult, String>
+where
+    F: Fn(usize, usize, String) + Sync,
+{
+    options.validate()?;
+
+    let source_dir = Path::new(source_dir_str);
+    let output_dir = Path::new(output_dir_str);
+
+    // Collect all paths first to know total count for progress reporting
+    let json_paths = find_source_files(source_dir)?;
+
+    // Use Rayon for parallel processing; crops go straight into the output directory,
+    // negatives optionally into their own folder
+    let mut folders = vec![String::new()];
+    let negative_folder = options.negatives.output_folder();
+    if options.negatives.is_enabled() && !negative_folder.is_empty() {
+        folders.push(negative_folder.to_string());
+    }
+    run_crop_files(
+        &json_paths,
+        output_dir,
+        &folders,
+        |json_path| {
+            let file_name = json_path.file_name().unwrap_or_default().to_string_lossy();
+            println!("Processing JSON (Parallel): {}", file_name);
+
+            let records = process_single_file(
+                json_path,
+                source_dir,
+                output_dir,
+                parent_label,
+                required_child_labels,
+                padding_factor,
+                options,
+            )?;
+            let negatives = records
+                .iter()
+                .filter(|(_, record)| record.kind != CropKind::Positive)
+                .count();
+            if records.len() == negatives {
+                println!(" -> No parent {} instances with required children {:?} found or processed in this file.", parent_label, required_child_labels);
+            } else {
+                println!(
+                    " -> Successfully processed {} parent instances in this file.",
+                    records.len() - negatives
+                );
+            }
+            if negatives > 0 {
+                println!(" -> Wrote {} negative crops for this file.", negatives);
+            }
+            Ok(records)
+        },
+        progress_callback,
+        cancel,
+    )
+}
+
 // Helper function needed by the updated process_single_file logic
 #[allow(dead_code)]
 fn required_child_label() -> &'static str {
@@ -175,19 +163,40 @@ fn process_single_file(
     parent_label: &str,
     required_child_labels: &[&str],
     padding_factor: f32,
-) -> Result<usize, String> {
-    // Returns count of successfully processed *parent annotations*
+    options: &CropRemapOptions,
+) -> Result<Vec<(String, CropRecord)>, String> {
+    // Returns one record per successfully processed *parent annotation* (and negative crop)
     let json_content =
         fs::read_to_string(json_path).map_err(|e| format!("Failed to read JSON file: {}", e))?;
     let original_labelme: LabelMeFile = serde_json::from_str(&json_content)
         .map_err(|e| format!("Failed to parse JSON content: {}", e))?;
 
-    let mut processed_parent_count = 0;
+    let mut crop_records = Vec::new();
+    // Loaded on the first parent that needs it and shared by the rest
+    let mut original_image: Option<DynamicImage> = None;
+
+    // With unique assignment every child is given to its single best parent up front
+    let is_parent = |shape: &LabelMeShape| shape.label == parent_label;
+    let is_required_child =
+        |shape: &LabelMeShape| required_child_labels.contains(&shape.label.as_str());
+    let owners = unique_owners(&original_labelme, is_parent, is_required_child, options);
+
+    let source_key = json_path.to_string_lossy().to_string();
+    let original_filename_stem = Path::new(&original_labelme.image_path)
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let original_extension = Path::new(&original_labelme.image_path)
+        .extension()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
 
     // --- Loop through all shapes to find potential parents ---
     for (parent_index, parent_shape) in original_labelme.shapes.iter().enumerate() {
         // --- 1. Check if it's the parent label we're looking for ---
-        if parent_shape.label != parent_label {
+        if !is_parent(parent_shape) {
             continue; // Skip if not the target parent label
         }
 
@@ -196,236 +205,200 @@ fn process_single_file(
             parent_label, parent_index
         );
 
-        // --- 2. Calculate Parent Bounding Box ---
-        let parent_bbox = match get_bounding_box(&parent_shape.points) {
-            Some(bbox) => bbox,
-            None => {
-                eprintln!(
-                    " -> Skipping parent at index {}: shape has no points.",
-                    parent_index
-                );
-                continue; // Skip this parent if it has no points
-            }
-        };
-
-        // --- 2.5. Expand Parent Bounding Box with Padding Factor ---
-        let expanded_parent_bbox = if padding_factor != 1.0 {
-            // Calculate center and current dimensions
-            let center_x = (parent_bbox.x_min + parent_bbox.x_max) / 2.0;
-            let center_y = (parent_bbox.y_min + parent_bbox.y_max) / 2.0;
-            let current_width = parent_bbox.x_max - parent_bbox.x_min;
-            let current_height = parent_bbox.y_max - parent_bbox.y_min;
-
-            // Calculate new dimensions with padding
-            let new_half_width = (current_width * padding_factor) / 2.0;
-            let new_half_height = (current_height * padding_factor) / 2.0;
-
-            // Create expanded bounding box (centered)
-            crate::core::labelme_types::BoundingBox {
-                x_min: center_x - new_half_width,
-                y_min: center_y - new_half_height,
-                x_max: center_x + new_half_width,
-                y_max: center_y + new_half_height,
-            }
-        } else {
-            parent_bbox // No expansion if padding_factor is 1.0
+        // --- 2. Plan the padded crop window and remap/clip this parent's children ---
+        let Some(mut crop) = plan_parent_crop(
+            &original_labelme,
+            parent_index,
+            padding_factor,
+            is_required_child,
+            owners.as_ref(),
+            options,
+        ) else {
+            eprintln!(
+                " -> Skipping parent at index {}: shape has no points or lies outside the image.",
+                parent_index
+            );
+            continue;
         };
 
-        println!(" -> Parent bbox: ({:.1}, {:.1}) to ({:.1}, {:.1}), expanded to: ({:.1}, {:.1}) to ({:.1}, {:.1}) with padding factor {:.2}",
-                 parent_bbox.x_min, parent_bbox.y_min, parent_bbox.x_max, parent_bbox.y_max,
-                 expanded_parent_bbox.x_min, expanded_parent_bbox.y_min, expanded_parent_bbox.x_max, expanded_parent_bbox.y_max,
+        println!(" -> Parent bbox: ({:.1}, {:.1}) to ({:.1}, {:.1}), crop window: {}x{} at ({}, {}) with padding factor {:.2}",
+                 crop.parent_bbox.x_min, crop.parent_bbox.y_min, crop.parent_bbox.x_max, crop.parent_bbox.y_max,
+                 crop.window.width, crop.window.height, crop.window.x, crop.window.y,
                  padding_factor);
 
-        // Clamp bbox to image dimensions to prevent cropping errors
-        let parent_crop_x = expanded_parent_bbox.x_min.max(0.0).floor() as u32;
-        let parent_crop_y = expanded_parent_bbox.y_min.max(0.0).floor() as u32;
-        let parent_crop_width = (expanded_parent_bbox
-            .x_max
-            .min(original_labelme.image_width as f32)
-            .ceil()
-            - parent_crop_x as f32)
-            .max(1.0) as u32;
-        let parent_crop_height = (expanded_parent_bbox
-            .y_max
-            .min(original_labelme.image_height as f32)
-            .ceil()
-            - parent_crop_y as f32)
-            .max(1.0) as u32;
-
-        // Check for zero or invalid dimensions after clamping
-        if parent_crop_width == 0 || parent_crop_height == 0 {
-            eprintln!(" -> Skipping parent at index {}: Calculated crop dimensions are invalid (W: {}, H: {})", parent_index, parent_crop_width, parent_crop_height);
-            continue; // Skip this parent if crop dimensions invalid
-        }
-
-        // --- 3. Identify Child Annotations & Check for Required Label (for *this* parent) ---
-        let mut child_annotations_to_remap: Vec<&LabelMeShape> = Vec::new();
-        let mut found_required_child = false;
-
-        for (child_index, child_shape) in original_labelme.shapes.iter().enumerate() {
-            if child_index == parent_index {
-                continue;
-            } // Skip the parent itself
-
-            if let Some(child_bbox) = get_bounding_box(&child_shape.points) {
-                // Check if the child's *bounding box* OVERLAPS with *this* parent's *bounding box*
-                if child_bbox.overlaps(&expanded_parent_bbox) {
-                    // 🔧 FIX: Only add child if it matches one of the required child labels
-                    if required_child_labels.contains(&child_shape.label.as_str()) {
-                        child_annotations_to_remap.push(child_shape); // Add only specified child labels
-                        found_required_child = true;
-                    }
-                }
-            }
-        }
-
-        // --- Check if the required child was found for *this* parent ---
-        if !found_required_child {
+        // --- 3. Check if the required child was found for *this* parent ---
+        if crop.children.is_empty() {
             println!(
                 " -> Parent {} at index {} found, but no required child {} inside its bbox. Skipping this instance.",
                 parent_label, parent_index,
                 required_child_labels.join(", ")
             );
-            continue; // Skip processing this parent instance
-        }
 
-        // Check if after filtering, there are actually children to remap (should be true if found_required_child is true)
-        if child_annotations_to_remap.is_empty() {
-            println!(" -> Parent {} at index {} found required children {}, but found no children to remap (this might indicate an issue). Skipping.", parent_label, parent_index, required_child_labels.join(", "));
-            continue; // Skip this parent instance
+            // --- 3b. Export it as a negative sample instead, if it is clean ---
+            let negatives = &options.negatives;
+            if !negatives.parents || !keep_negative_parent(negatives, &source_key, parent_index) {
+                continue; // Skip processing this parent instance
+            }
+            let Some(negative) = negative_parent_crop(&original_labelme, crop, is_required_child)
+            else {
+                continue; // A child still shows in the window
+            };
+
+            let img = load_source_image(
+                &mut original_image,
+                json_path,
+                source_dir,
+                &original_labelme.image_path,
+            )?;
+            let base_name = crop_base_name(&original_filename_stem, parent_label, parent_index);
+            match write_negative(
+                img,
+                &original_labelme,
+                negative,
+                &base_name,
+                &original_extension,
+                output_dir,
+                json_path,
+                options,
+            ) {
+                Ok(record) => crop_records.push(record),
+                Err(e) => eprintln!(
+                    " -> Error writing negative crop for parent at index {}: {}",
+                    parent_index, e
+                ),
+            }
+            continue;
         }
 
         println!(
-            " -> Parent {} at index {} found with required children {}. Processing {} child annotations.",
+            " -> Parent {} at index {} found with required children {}. Remapped {} child annotations ({} clipped, {} dropped).",
             parent_label, parent_index,
             required_child_labels.join(", "),
-            child_annotations_to_remap.len()
+            crop.children.len(),
+            crop.record.children_clipped,
+            crop.record.children_dropped
         );
 
-        // --- 4. Load Original Image (only if needed) ---
-        // Consider loading the image just once outside the loop if performance is critical
-        // and if all crops come from the same original image path.
-        let original_image_path_relative = Path::new(&original_labelme.image_path);
-        let mut original_image_path = json_path
-            .parent()
-            .unwrap_or(source_dir)
-            .join(original_image_path_relative);
-
-        if !original_image_path.exists() {
-            original_image_path = source_dir.join(original_image_path_relative);
-            if !original_image_path.exists() {
-                // Log error for this parent instance, but continue loop for others
-                eprintln!(" -> Error for parent at index {}: Original image not found at expected paths for {}", parent_index, original_labelme.image_path);
-                continue;
-            }
-        }
+        // --- 4. Load Original Image (only once, and only if needed) ---
+        let img = load_source_image(
+            &mut original_image,
+            json_path,
+            source_dir,
+            &original_labelme.image_path,
+        )?;
 
-        let img = match image::open(&original_image_path) {
-            Ok(img_data) => img_data,
-            Err(e) => {
-                eprintln!(
-                    " -> Error for parent at index {}: Failed to open original image {}: {}",
-                    parent_index,
-                    original_image_path.display(),
-                    e
-                );
-                continue; // Skip this parent instance
-            }
-        };
-
-        // --- 5. Crop Image (for *this* parent) ---
-        let cropped_img = img.crop_imm(
-            parent_crop_x,
-            parent_crop_y,
-            parent_crop_width,
-            parent_crop_height,
-        );
-
-        // --- 6. Save Cropped Image (unique name for *this* parent instance) ---
-        let original_filename_stem = Path::new(&original_labelme.image_path)
-            .file_stem()
-            .unwrap_or_default()
-            .to_string_lossy();
-        let original_extension = Path::new(&original_labelme.image_path)
-            .extension()
-            .unwrap_or_default()
-            .to_string_lossy();
-
-        let cropped_filename_base = format!(
-            "{}_crop_{}_{}", // Index makes it unique per parent instance
-            original_filename_stem,
-            sanitize_filename(parent_label),
-            parent_index
-        );
+        // --- 5. Crop Image and build the new LabelMe data (for *this* parent) ---
+        let cropped_filename_base = crop_base_name(&original_filename_stem, parent_label, parent_index);
         let cropped_image_filename = format!("{}.{}", cropped_filename_base, original_extension);
-        let cropped_image_path = output_dir.join(&cropped_image_filename);
-
-        if let Err(e) = cropped_img.save(&cropped_image_path) {
-            eprintln!(
-                " -> Error for parent at index {}: Failed to save cropped image {}: {}",
-                parent_index,
-                cropped_image_path.display(),
-                e
-            );
-            continue; // Skip saving JSON if image save failed
-        }
-        println!(" -> Saved cropped image: {}", cropped_image_filename);
+        let output = cut_crop(
+            img,
+            &original_labelme,
+            &crop,
+            &cropped_image_filename,
+            &options.geometry,
+        );
 
-        // --- 7. Remap Child Coordinates (for children of *this* parent) ---
-        let mut remapped_child_shapes: Vec<LabelMeShape> = Vec::new();
-        for child_shape in child_annotations_to_remap {
-            let mut remapped_shape = child_shape.clone();
-            remapped_shape.points = child_shape
-                .points
-                .iter()
-                .map(|&[x, y]| {
-                    let remapped_x = (x - expanded_parent_bbox.x_min).max(0.0);
-                    let remapped_y = (y - expanded_parent_bbox.y_min).max(0.0);
-                    [remapped_x, remapped_y]
-                })
-                .collect();
-            remapped_child_shapes.push(remapped_shape);
+        // --- 6. Save Cropped Image and JSON (unique name for *this* parent instance) ---
+        if let Err(e) = save_crop(output_dir, &cropped_filename_base, &output) {
+            eprintln!(" -> Error for parent at index {}: {}", parent_index, e);
+            continue; // Don't record the parent if saving failed
         }
+        println!(" -> Saved crop: {}", cropped_image_filename);
 
-        // --- 8. Create New LabelMe JSON (for *this* parent instance) ---
-        let new_labelme_data = LabelMeFile {
-            version: original_labelme.version.clone(),
-            flags: original_labelme.flags.clone(),
-            shapes: remapped_child_shapes,
-            image_path: cropped_image_filename.clone(),
-            image_data: None,
-            image_height: cropped_img.height(),
-            image_width: cropped_img.width(),
-            extra: original_labelme.extra.clone(),
-        };
+        crop.record.crop_image = cropped_image_filename;
+        crop.record.source_json = json_path.to_string_lossy().to_string();
+        crop_records.push((String::new(), crop.record)); // Record successfully processed parent
+    } // End loop through shapes
 
-        // --- 9. Save New JSON (for *this* parent instance) ---
-        let new_json_filename = format!("{}.json", cropped_filename_base);
-        let new_json_path = output_dir.join(&new_json_filename);
-        let new_json_content = match serde_json::to_string_pretty(&new_labelme_data) {
-            Ok(content) => content,
-            Err(e) => {
-                eprintln!(
-                    " -> Error for parent at index {}: Failed to serialize new LabelMe data: {}",
-                    parent_index, e
-                );
-                continue; // Skip saving this JSON
+    // --- Random background crops away from all parents and children ---
+    if options.negatives.background_crops > 0 {
+        let backgrounds = plan_background_crops(
+            &original_labelme,
+            padding_factor,
+            is_parent,
+            is_required_child,
+            options,
+            &source_key,
+        );
+        for (index, background) in backgrounds.into_iter().enumerate() {
+            let img = load_source_image(
+                &mut original_image,
+                json_path,
+                source_dir,
+                &original_labelme.image_path,
+            )?;
+            let base_name = format!("{}_background_{}", original_filename_stem, index);
+            match write_negative(
+                img,
+                &original_labelme,
+                background,
+                &base_name,
+                &original_extension,
+                output_dir,
+                json_path,
+                options,
+            ) {
+                Ok(record) => crop_records.push(record),
+                Err(e) => eprintln!(" -> Error writing background crop {}: {}", index, e),
             }
-        };
+        }
+    }
 
-        if let Err(e) = fs::write(&new_json_path, new_json_content) {
-            eprintln!(
-                " -> Error for parent at index {}: Failed to write new JSON file {}: {}",
-                parent_index,
-                new_json_path.display(),
-                e
-            );
-            // Don't increment count if JSON save failed
-        } else {
-            println!(" -> Saved new JSON: {}", new_json_filename);
-            processed_parent_count += 1; // Increment count for successfully processed parent
+    Ok(crop_records) // Return the parents processed successfully in this file
+}
+
+/// The source image of a file, loaded on first use
+fn load_source_image<'a>(
+    cache: &'a mut Option<DynamicImage>,
+    json_path: &Path,
+    source_dir: &Path,
+    image_path: &str,
+) -> Result<&'a DynamicImage, String> {
+    match cache {
+        Some(img) => Ok(img),
+        None => {
+            let original_image_path = find_source_image(json_path, source_dir, image_path)
+                .ok_or_else(|| {
+                    format!(
+                        "Original image not found at expected paths for {}",
+                        image_path
+                    )
+                })?;
+            let img = image::open(&original_image_path).map_err(|e| {
+                format!(
+                    "Failed to open original image {}: {}",
+                    original_image_path.display(),
+                    e
+                )
+            })?;
+            Ok(cache.insert(img))
         }
-    } // End loop through shapes
+    }
+}
+
+/// Write a negative crop (no annotations) and return its folder and record
+#[allow(clippy::too_many_arguments)]
+fn write_negative(
+    img: &DynamicImage,
+    source: &LabelMeFile,
+    mut crop: ParentCrop,
+    base_name: &str,
+    extension: &str,
+    output_dir: &Path,
+    json_path: &Path,
+    options: &CropRemapOptions,
+) -> Result<(String, CropRecord), String> {
+    let negatives = &options.negatives;
+    let folder = negatives.output_folder().to_string();
+    let image_file = format!("{}.{}", base_name, extension);
+    let output = cut_crop(img, source, &crop, &image_file, &options.geometry);
+    match negatives.target {
+        NegativeTarget::Folder => save_crop(&output_dir.join(&folder), base_name, &output)?,
+        NegativeTarget::Background => save_crop_image(output_dir, &output)?,
+    }
+    println!(" -> Saved negative crop: {}", image_file);
 
-    Ok(processed_parent_count) // Return total count of parents processed successfully in this file
+    crop.record.crop_image = image_file;
+    crop.record.source_json = json_path.to_string_lossy().to_string();
+    Ok((folder, crop.record))
 }
@@ -1,5 +1,6 @@
 use crate::core::bounding_box_drawer;
 use crate::core::polygon_drawer;
+use crate::labelme_convert::RenderOptions;
 use glob::glob;
 use std::fs;
 use std::path::Path;
@@ -33,6 +34,8 @@ pub fn visualize_dataset(
         println!("Visualization will be processed but not saved (save_output=false)");
     }
 
+    let options = RenderOptions::default();
+
     // Count of successfully processed files
     let mut success_count = 0;
     let mut error_count = 0;
@@ -70,9 +73,10 @@ pub fn visualize_dataset(
                             &source_dir,
                             json_path,
                             &output_dir,
+                            &options,
                         )
                     } else {
-                        polygon_drawer::draw_polygons(&source_dir, json_path, &output_dir)
+                        polygon_drawer::draw_polygons(&source_dir, json_path, &output_dir, &options)
                     };
 
                     match result {
@@ -131,9 +131,23 @@ impl ImageAnnotator {
         }
 
         println!("Found JSON file for {}: {:?}", image_path, json_path);
+        Self::read_annotations(&json_path)
+    }
+
+    // Annotation entry for an image whose LabelMe file is already known
+    pub fn annotated_image(image_path: &Path, json_path: &Path) -> AnnotatedImage {
+        let annotations = Self::read_annotations(json_path).unwrap_or_default();
+        AnnotatedImage {
+            path: image_path.to_string_lossy().to_string(),
+            has_json: json_path.is_file(),
+            annotations,
+        }
+    }
 
+    // Parse the shapes of a LabelMe JSON file
+    fn read_annotations(json_path: &Path) -> Result<Vec<Annotation>, String> {
         // Read and parse the JSON file
-        let json_content = match fs::read_to_string(&json_path) {
+        let json_content = match fs::read_to_string(json_path) {
             Ok(content) => content,
             Err(e) => {
                 println!("Error reading JSON file: {}", e);
@@ -1,3 +1,6 @@
+use crate::labelme_convert::statistics::{
+    compute_statistics, DatasetStatistics, DEFAULT_HISTOGRAM_BINS,
+};
 use glob::glob;
 use prettytable::{row, Table};
 use serde_json::{json, Value};
@@ -251,25 +254,28 @@ impl LabelmeViewerModule {
         Ok(())
     }
 
-    /// Get detailed statistics about the dataset
+    /// Get shape type counts per label (see `get_geometry_statistics` for the full statistics)
     pub fn get_statistics(&mut self) -> HashMap<String, HashMap<String, usize>> {
         // Reset counters
         self.label_counter.clear();
         self.shape_type_counter.clear();
 
-        if let Ok(entries) = fs::read_dir(&self.source_directory) {
-            for entry in entries {
-                if let Ok(entry) = entry {
-                    let path = entry.path();
-                    if path.extension().map_or(false, |ext| ext == "json") {
-                        self.summarize_annotation(&path);
-                    }
-                }
-            }
+        if let Ok(stats) = self.get_geometry_statistics() {
+            self.label_counter = stats.label_counts.into_iter().collect();
+            self.shape_type_counter = stats
+                .shape_types
+                .into_iter()
+                .map(|(label, types)| (label, types.into_iter().collect()))
+                .collect();
         }
 
         self.shape_type_counter.clone()
     }
+
+    /// Geometric statistics of all annotation files below the source directory
+    pub fn get_geometry_statistics(&self) -> Result<DatasetStatistics, String> {
+        compute_statistics(Path::new(&self.source_directory), DEFAULT_HISTOGRAM_BINS)
+    }
 }
 
 #[allow(dead_code)]
@@ -0,0 +1,212 @@
+//! Parent/child association rules
+//!
+//! Decides which child annotations belong to a parent instance. Without a
+//! rule every child overlapping the crop is attached, which hands helmets of
+//! neighbouring people to the wrong person crop.
+
+use crate::core::labelme_types::{BoundingBox, LabelMeShape, get_bounding_box};
+use crate::labelme_convert::conversion::{
+    CLIPPED_CIRCLE_SEGMENTS, calculate_polygon_area, circle_to_polygon, clip_polygon,
+    rectangle_to_polygon,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// How children are matched to parents
+///
+/// All tests run against the parent's own bounding box (before padding).
+/// The default rule accepts every child overlapping the crop.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct AssociationRule {
+    /// Minimum intersection over child area (0.0 - 1.0)
+    pub min_ioa: f32,
+    /// Require the child's bbox center to lie inside the parent
+    pub require_center_inside: bool,
+    /// Give every child only to the parent it overlaps best
+    pub unique_assignment: bool,
+}
+
+impl AssociationRule {
+    /// Validate the rule
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.min_ioa) {
+            return Err(format!(
+                "min_ioa must be between 0.0 and 1.0, got {}",
+                self.min_ioa
+            ));
+        }
+        Ok(())
+    }
+
+    /// Intersection over child area when `child` may attach to `parent`
+    pub fn score(&self, child: &LabelMeShape, parent: &BoundingBox) -> Option<f64> {
+        let child_bbox = get_bounding_box(&child.points)?;
+        if self.require_center_inside {
+            let cx = (child_bbox.x_min + child_bbox.x_max) / 2.0;
+            let cy = (child_bbox.y_min + child_bbox.y_max) / 2.0;
+            let inside = cx >= parent.x_min
+                && cx <= parent.x_max
+                && cy >= parent.y_min
+                && cy <= parent.y_max;
+            if !inside {
+                return None;
+            }
+        }
+
+        let ioa = intersection_over_area(child, parent);
+        (ioa >= self.min_ioa as f64).then_some(ioa)
+    }
+}
+
+/// Fraction of `child` that lies inside `region`
+///
+/// Area shapes use their outline; points and lines use the share of their
+/// vertices inside the region.
+pub fn intersection_over_area(child: &LabelMeShape, region: &BoundingBox) -> f64 {
+    let local: Vec<(f64, f64)> = child
+        .points
+        .iter()
+        .map(|&[x, y]| ((x - region.x_min) as f64, (y - region.y_min) as f64))
+        .collect();
+    let (width, height) = (
+        (region.x_max - region.x_min) as f64,
+        (region.y_max - region.y_min) as f64,
+    );
+
+    let outline = match child.shape_type.as_str() {
+        "rectangle" | "polygon" if local.len() == 2 => rectangle_to_polygon(&local),
+        "polygon" => local.clone(),
+        "circle" if local.len() >= 2 => {
+            let radius = (local[1].0 - local[0].0).hypot(local[1].1 - local[0].1);
+            circle_to_polygon(local[0], radius, CLIPPED_CIRCLE_SEGMENTS)
+        }
+        _ => Vec::new(),
+    };
+
+    let full_area = calculate_polygon_area(&outline);
+    if full_area > 0.0 {
+        return calculate_polygon_area(&clip_polygon(&outline, width, height)) / full_area;
+    }
+    if local.is_empty() {
+        return 0.0;
+    }
+    let inside = local
+        .iter()
+        .filter(|p| p.0 >= 0.0 && p.1 >= 0.0 && p.0 <= width && p.1 <= height)
+        .count();
+    inside as f64 / local.len() as f64
+}
+
+/// Pick the single best parent for every child accepted by `rule`
+///
+/// Returns child index → parent index; children touching no parent are left
+/// out. Ties go to the smaller parent, then to the earlier one.
+pub fn assign_unique_parents(
+    shapes: &[LabelMeShape],
+    parents: &[(usize, BoundingBox)],
+    is_child: impl Fn(&LabelMeShape) -> bool,
+    rule: &AssociationRule,
+) -> HashMap<usize, usize> {
+    let area = |b: &BoundingBox| (b.x_max - b.x_min) * (b.y_max - b.y_min);
+    let mut owners: HashMap<usize, (usize, f64, f32)> = HashMap::new();
+
+    for (child_index, child) in shapes.iter().enumerate() {
+        if !is_child(child) {
+            continue;
+        }
+        for (parent_index, parent_bbox) in parents {
+            if *parent_index == child_index {
+                continue;
+            }
+            let Some(ioa) = rule.score(child, parent_bbox).filter(|&ioa| ioa > 0.0) else {
+                continue;
+            };
+            let parent_area = area(parent_bbox);
+            let better = match owners.get(&child_index) {
+                None => true,
+                Some(&(_, best_ioa, best_area)) => {
+                    ioa > best_ioa || (ioa == best_ioa && parent_area < best_area)
+                }
+            };
+            if better {
+                owners.insert(child_index, (*parent_index, ioa, parent_area));
+            }
+        }
+    }
+
+    owners
+        .into_iter()
+        .map(|(child, (parent, _, _))| (child, parent))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(label: &str, points: Vec<[f32; 2]>) -> LabelMeShape {
+        LabelMeShape {
+            label: label.to_string(),
+            points,
+            shape_type: "rectangle".to_string(),
+            group_id: None,
+            flags: None,
+            extra: HashMap::new(),
+        }
+    }
+
+    fn bbox(x_min: f32, y_min: f32, x_max: f32, y_max: f32) -> BoundingBox {
+        BoundingBox {
+            x_min,
+            y_min,
+            x_max,
+            y_max,
+        }
+    }
+
+    #[test]
+    fn test_ioa_and_center_rules() {
+        // A quarter of the helmet lies inside the person
+        let helmet = shape("helmet", vec![[90.0, 0.0], [130.0, 20.0]]);
+        let person = bbox(0.0, 0.0, 100.0, 200.0);
+        assert!((intersection_over_area(&helmet, &person) - 0.25).abs() < 1e-6);
+
+        let loose = AssociationRule::default();
+        assert!(loose.score(&helmet, &person).is_some());
+
+        let strict = AssociationRule {
+            min_ioa: 0.5,
+            ..AssociationRule::default()
+        };
+        assert!(strict.score(&helmet, &person).is_none());
+
+        let centered = AssociationRule {
+            require_center_inside: true,
+            ..AssociationRule::default()
+        };
+        assert!(centered.score(&helmet, &person).is_none());
+    }
+
+    #[test]
+    fn test_unique_assignment_prefers_best_overlap() {
+        let shapes = vec![
+            shape("person", vec![[0.0, 0.0], [100.0, 200.0]]),
+            shape("person", vec![[80.0, 0.0], [180.0, 200.0]]),
+            // Mostly inside the second person
+            shape("helmet", vec![[90.0, 0.0], [130.0, 20.0]]),
+        ];
+        let parents: Vec<(usize, BoundingBox)> = (0..2)
+            .map(|i| (i, get_bounding_box(&shapes[i].points).unwrap()))
+            .collect();
+
+        let owners = assign_unique_parents(
+            &shapes,
+            &parents,
+            |s| s.label == "helmet",
+            &AssociationRule::default(),
+        );
+        assert_eq!(owners.get(&2), Some(&1));
+        assert_eq!(owners.len(), 1);
+    }
+}
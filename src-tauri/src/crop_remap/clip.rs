@@ -0,0 +1,193 @@
+//! Clipping of child annotations to a crop window
+//!
+//! Child shapes are shifted into crop coordinates and cut at the crop border,
+//! so no remapped point ends up outside the cropped image.
+
+use crate::core::labelme_types::{BoundingBox, LabelMeShape};
+use crate::labelme_convert::conversion::{
+    ClipOutcome, clip_area, clip_circle, clip_polyline, rectangle_to_polygon,
+};
+
+/// Outcome of carrying one child shape into a crop
+pub type ChildClip = ClipOutcome<LabelMeShape>;
+
+/// Remap `shape` into `crop` and clip it to the crop rectangle
+///
+/// Areas (polygons, rectangles, circles) are dropped when less than
+/// `min_visibility` of their area stays inside; lines use their length.
+pub fn clip_child_to_crop(
+    shape: &LabelMeShape,
+    crop: &BoundingBox,
+    min_visibility: f32,
+) -> ChildClip {
+    let (width, height) = (
+        (crop.x_max - crop.x_min) as f64,
+        (crop.y_max - crop.y_min) as f64,
+    );
+    let local: Vec<(f64, f64)> = shape
+        .points
+        .iter()
+        .map(|&[x, y]| ((x - crop.x_min) as f64, (y - crop.y_min) as f64))
+        .collect();
+    let min_visibility = min_visibility as f64;
+    let inside = |p: &(f64, f64)| p.0 >= 0.0 && p.1 >= 0.0 && p.0 <= width && p.1 <= height;
+    let with_points = |points: &[(f64, f64)], shape_type: &str| LabelMeShape {
+        points: points.iter().map(|&(x, y)| [x as f32, y as f32]).collect(),
+        shape_type: shape_type.to_string(),
+        ..shape.clone()
+    };
+
+    if local.is_empty() {
+        return ChildClip::Dropped;
+    }
+    if local.iter().all(inside) && shape.shape_type != "circle" {
+        return ChildClip::Kept(with_points(&local, &shape.shape_type));
+    }
+
+    match shape.shape_type.as_str() {
+        // 2-point polygons of bbox datasets are boxes
+        "rectangle" | "polygon" if local.len() == 2 => clip_area(
+            rectangle_to_polygon(&local),
+            width,
+            height,
+            min_visibility,
+            |points| {
+                let min_x = points.iter().map(|p| p.0).fold(f64::MAX, f64::min);
+                let max_x = points.iter().map(|p| p.0).fold(f64::MIN, f64::max);
+                let min_y = points.iter().map(|p| p.1).fold(f64::MAX, f64::min);
+                let max_y = points.iter().map(|p| p.1).fold(f64::MIN, f64::max);
+                with_points(&[(min_x, min_y), (max_x, max_y)], &shape.shape_type)
+            },
+        ),
+        "polygon" => clip_area(local, width, height, min_visibility, |points| {
+            with_points(&points, "polygon")
+        }),
+        "circle" if local.len() >= 2 => clip_circle(
+            local[0],
+            local[1],
+            width,
+            height,
+            min_visibility,
+            |points, shape_type| with_points(&points, shape_type),
+        ),
+        "line" | "linestrip" => {
+            let clipped = clip_polyline(&local, width, height);
+            if clipped.len() < 2
+                || polyline_length(&clipped) < polyline_length(&local) * min_visibility
+            {
+                return ChildClip::Dropped;
+            }
+            let shape_type = if clipped.len() == 2 {
+                &shape.shape_type
+            } else {
+                "linestrip"
+            };
+            ChildClip::Clipped(with_points(&clipped, shape_type))
+        }
+        // Points, masks and unknown types cannot be cut meaningfully
+        _ => ChildClip::Dropped,
+    }
+}
+
+fn polyline_length(points: &[(f64, f64)]) -> f64 {
+    points
+        .windows(2)
+        .map(|w| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1))
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labelme_convert::conversion::calculate_polygon_area;
+    use std::collections::HashMap;
+
+    fn shape(shape_type: &str, points: Vec<[f32; 2]>) -> LabelMeShape {
+        LabelMeShape {
+            label: "helmet".to_string(),
+            points,
+            shape_type: shape_type.to_string(),
+            group_id: None,
+            flags: None,
+            extra: HashMap::new(),
+        }
+    }
+
+    fn crop() -> BoundingBox {
+        BoundingBox {
+            x_min: 100.0,
+            y_min: 100.0,
+            x_max: 200.0,
+            y_max: 200.0,
+        }
+    }
+
+    #[test]
+    fn test_inside_child_is_offset() {
+        let child = shape(
+            "polygon",
+            vec![[110.0, 110.0], [150.0, 110.0], [150.0, 150.0]],
+        );
+        match clip_child_to_crop(&child, &crop(), 0.0) {
+            ChildClip::Kept(remapped) => {
+                assert_eq!(
+                    remapped.points,
+                    vec![[10.0, 10.0], [50.0, 10.0], [50.0, 50.0]]
+                );
+            }
+            other => panic!("expected kept, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_clip_polygon_and_rectangle_to_crop() {
+        // Half of this square sticks out on the left
+        let child = shape(
+            "polygon",
+            vec![[80.0, 120.0], [120.0, 120.0], [120.0, 160.0], [80.0, 160.0]],
+        );
+        let clipped = match clip_child_to_crop(&child, &crop(), 0.25) {
+            ChildClip::Clipped(shape) => shape,
+            other => panic!("expected clipped, got {:?}", other),
+        };
+        assert!(clipped.points.iter().all(|p| p[0] >= 0.0 && p[1] >= 0.0));
+        let area = calculate_polygon_area(
+            &clipped
+                .points
+                .iter()
+                .map(|p| (p[0] as f64, p[1] as f64))
+                .collect::<Vec<_>>(),
+        );
+        assert!((area - 800.0).abs() < 1e-3);
+
+        // The same box as a rectangle stays a 2-point rectangle
+        let rect = shape("rectangle", vec![[80.0, 120.0], [120.0, 160.0]]);
+        match clip_child_to_crop(&rect, &crop(), 0.25) {
+            ChildClip::Clipped(shape) => {
+                assert_eq!(shape.shape_type, "rectangle");
+                assert_eq!(shape.points, vec![[0.0, 20.0], [20.0, 60.0]]);
+            }
+            other => panic!("expected clipped, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_drop_below_visibility() {
+        // Only 10% of this box is inside the crop
+        let child = shape("rectangle", vec![[64.0, 120.0], [104.0, 160.0]]);
+        assert!(matches!(
+            clip_child_to_crop(&child, &crop(), 0.5),
+            ChildClip::Dropped
+        ));
+        assert!(matches!(
+            clip_child_to_crop(&child, &crop(), 0.05),
+            ChildClip::Clipped(_)
+        ));
+
+        let outside = shape("polygon", vec![[0.0, 0.0], [50.0, 0.0], [50.0, 50.0]]);
+        assert!(matches!(
+            clip_child_to_crop(&outside, &crop(), 0.0),
+            ChildClip::Dropped
+        ));
+    }
+}
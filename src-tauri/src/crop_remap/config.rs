@@ -0,0 +1,224 @@
+//! Crop-and-remap options
+
+use super::association::AssociationRule;
+use serde::{Deserialize, Serialize};
+
+/// Shape and size of the crop windows
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CropGeometry {
+    /// Expand the shorter side so every crop is square
+    pub square: bool,
+    /// Expand the crop to this width / height ratio
+    pub aspect_ratio: Option<f32>,
+    /// Fill the part of a crop outside the image with `pad_color` instead of
+    /// shifting (or, without an aspect constraint, clamping) it into the image
+    pub pad_outside: bool,
+    /// RGB border color for `pad_outside`
+    pub pad_color: [u8; 3],
+    /// Resize every crop to `[width, height]`; children are scaled along
+    pub resize: Option<[u32; 2]>,
+}
+
+impl CropGeometry {
+    /// The width / height ratio crops are expanded to, if any
+    pub fn target_aspect(&self) -> Option<f32> {
+        if self.square {
+            Some(1.0)
+        } else {
+            self.aspect_ratio
+        }
+    }
+
+    /// Validate the geometry
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(ratio) = self.aspect_ratio {
+            if ratio.is_nan() || ratio <= 0.0 {
+                return Err(format!("aspect_ratio must be positive, got {}", ratio));
+            }
+            if self.square && ratio != 1.0 {
+                return Err("square crops cannot use an aspect_ratio other than 1.0".to_string());
+            }
+        }
+        if let Some([width, height]) = self.resize.filter(|&[w, h]| w == 0 || h == 0) {
+            return Err(format!(
+                "resize must be at least 1x1, got {}x{}",
+                width, height
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Where negative crops are written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NegativeTarget {
+    /// A sub-folder of the output with LabelMe files without shapes
+    #[default]
+    Folder,
+    /// Images only, next to the positive crops, where the converters pick
+    /// them up as background images
+    Background,
+}
+
+/// Export of crops without children as negative samples
+///
+/// Used by the single-label crop; rule sets ignore it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct NegativeOptions {
+    /// Export parents without any required child
+    pub parents: bool,
+    /// Fraction (0.0 - 1.0) of those parents that is exported
+    pub ratio: f32,
+    /// Random crops per image placed away from all parents and children
+    pub background_crops: usize,
+    pub target: NegativeTarget,
+    /// Sub-folder for the `folder` target
+    pub folder: String,
+    /// Seed for the ratio sampling and the background placement
+    pub seed: u64,
+}
+
+impl Default for NegativeOptions {
+    fn default() -> Self {
+        Self {
+            parents: false,
+            ratio: 1.0,
+            background_crops: 0,
+            target: NegativeTarget::Folder,
+            folder: "negatives".to_string(),
+            seed: 42,
+        }
+    }
+}
+
+impl NegativeOptions {
+    /// Whether any negative crops are requested
+    pub fn is_enabled(&self) -> bool {
+        (self.parents && self.ratio > 0.0) || self.background_crops > 0
+    }
+
+    /// Output folder of the negatives, relative to the output directory
+    pub fn output_folder(&self) -> &str {
+        match self.target {
+            NegativeTarget::Folder => &self.folder,
+            NegativeTarget::Background => "",
+        }
+    }
+
+    /// Validate the options
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.ratio) {
+            return Err(format!(
+                "Negative ratio must be between 0.0 and 1.0, got {}",
+                self.ratio
+            ));
+        }
+        let valid_folder = !self.folder.is_empty()
+            && self
+                .folder
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+        if self.target == NegativeTarget::Folder && !valid_folder {
+            return Err(format!(
+                "Negative folder '{}' may only use letters, digits, '_' and '-'",
+                self.folder
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Options controlling how child annotations are carried into each crop
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CropRemapOptions {
+    /// Minimum fraction (0.0 - 1.0) of a child's area that must remain inside
+    /// the crop; children below it are dropped, 0.0 drops only invisible ones
+    pub min_child_visibility: f32,
+    /// Rule deciding which children belong to a parent
+    pub association: AssociationRule,
+    /// Crop window shape, border handling and output size
+    pub geometry: CropGeometry,
+    /// Negative crops of parents without children and of the background
+    pub negatives: NegativeOptions,
+}
+
+impl CropRemapOptions {
+    /// Validate the options
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.min_child_visibility) {
+            return Err(format!(
+                "min_child_visibility must be between 0.0 and 1.0, got {}",
+                self.min_child_visibility
+            ));
+        }
+        self.association.validate()?;
+        self.geometry.validate()?;
+        self.negatives.validate()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate_visibility() {
+        assert!(CropRemapOptions::default().validate().is_ok());
+
+        let options: CropRemapOptions =
+            serde_json::from_str(r#"{"min_child_visibility": 1.5}"#).unwrap();
+        assert!(options.validate().is_err());
+
+        let options: CropRemapOptions =
+            serde_json::from_str(r#"{"association": {"min_ioa": 0.6, "unique_assignment": true}}"#)
+                .unwrap();
+        assert!(options.validate().is_ok());
+        assert!(options.association.unique_assignment);
+        assert!(!options.association.require_center_inside);
+    }
+
+    #[test]
+    fn test_validate_geometry() {
+        let options: CropRemapOptions = serde_json::from_str(
+            r#"{"geometry": {"square": true, "pad_outside": true, "resize": [224, 224]}}"#,
+        )
+        .unwrap();
+        assert!(options.validate().is_ok());
+        assert_eq!(options.geometry.target_aspect(), Some(1.0));
+
+        let conflicting = CropGeometry {
+            square: true,
+            aspect_ratio: Some(2.0),
+            ..CropGeometry::default()
+        };
+        assert!(conflicting.validate().is_err());
+
+        let empty = CropGeometry {
+            resize: Some([224, 0]),
+            ..CropGeometry::default()
+        };
+        assert!(empty.validate().is_err());
+    }
+
+    #[test]
+    fn test_validate_negatives() {
+        let options: CropRemapOptions = serde_json::from_str(
+            r#"{"negatives": {"parents": true, "ratio": 0.5, "target": "background"}}"#,
+        )
+        .unwrap();
+        assert!(options.validate().is_ok());
+        assert!(options.negatives.is_enabled());
+        assert_eq!(options.negatives.output_folder(), "");
+        assert!(!CropRemapOptions::default().negatives.is_enabled());
+
+        let bad_folder = NegativeOptions {
+            folder: "../neg".to_string(),
+            ..NegativeOptions::default()
+        };
+        assert!(bad_folder.validate().is_err());
+    }
+}
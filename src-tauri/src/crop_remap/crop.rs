@@ -0,0 +1,553 @@
+//! Per-parent crop planning shared by the single-label and rule-set crops
+//!
+//! Planning only needs the LabelMe data: it picks the crop window around a
+//! parent, decides which children belong to it and remaps them. The image is
+//! loaded and cut only for parents that survive the caller's requirements.
+
+use super::association::assign_unique_parents;
+use super::clip::{ChildClip, clip_child_to_crop};
+use super::config::{CropGeometry, CropRemapOptions};
+use super::report::CropRecord;
+use crate::core::labelme_types::{BoundingBox, LabelMeFile, LabelMeShape, get_bounding_box};
+use image::imageops::{self, FilterType};
+use image::{DynamicImage, GenericImageView, Rgb, RgbImage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Pixel rectangle cut from the source image
+///
+/// With `pad_outside` the window may extend past the image border, so its
+/// origin can be negative.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CropWindow {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl CropWindow {
+    /// Window around `bbox` scaled by `padding_factor` and shaped by `geometry`
+    ///
+    /// Without an aspect constraint the window is clamped to the image. With
+    /// one it is shifted into the image to keep its shape (clamped only when
+    /// larger than the image), unless `pad_outside` leaves it where it is.
+    /// Returns `None` when the window does not touch the image.
+    pub fn around(
+        bbox: &BoundingBox,
+        padding_factor: f32,
+        image_width: u32,
+        image_height: u32,
+        geometry: &CropGeometry,
+    ) -> Option<Self> {
+        let mut expanded = expand_bbox(bbox, padding_factor);
+        let aspect = geometry.target_aspect();
+        if let Some(ratio) = aspect {
+            expanded = fit_aspect(&expanded, ratio);
+        }
+
+        let (mut x, mut width) = span(expanded.x_min, expanded.x_max);
+        let (mut y, mut height) = span(expanded.y_min, expanded.y_max);
+        if let Some(ratio) = aspect {
+            // Round once more so the pixel size keeps the exact ratio
+            height = ((width as f32 / ratio).round() as u32).max(1);
+            y = (((expanded.y_min + expanded.y_max) / 2.0) - height as f32 / 2.0).floor() as i32;
+        }
+
+        if !geometry.pad_outside {
+            let fit = |start: i32, size: u32, limit: u32| -> (i32, u32) {
+                if aspect.is_some() && size <= limit {
+                    (start.clamp(0, (limit - size) as i32), size)
+                } else {
+                    let begin = start.max(0);
+                    let end = (start + size as i32).min(limit as i32);
+                    (begin, (end - begin).max(1) as u32)
+                }
+            };
+            (x, width) = fit(x, width, image_width);
+            (y, height) = fit(y, height, image_height);
+        }
+
+        let touches = x < image_width as i32
+            && y < image_height as i32
+            && x + width as i32 > 0
+            && y + height as i32 > 0;
+        touches.then_some(Self {
+            x,
+            y,
+            width,
+            height,
+        })
+    }
+
+    /// The window as a bounding box in source image coordinates
+    pub fn bounds(&self) -> BoundingBox {
+        BoundingBox {
+            x_min: self.x as f32,
+            y_min: self.y as f32,
+            x_max: self.x as f32 + self.width as f32,
+            y_max: self.y as f32 + self.height as f32,
+        }
+    }
+}
+
+/// Integer origin and size covering `[min, max]`
+fn span(min: f32, max: f32) -> (i32, u32) {
+    let start = min.floor();
+    (start as i32, (max.ceil() - start).max(1.0) as u32)
+}
+
+/// Grow the shorter side of `bbox` around its center to reach `ratio` (width / height)
+pub fn fit_aspect(bbox: &BoundingBox, ratio: f32) -> BoundingBox {
+    let width = bbox.x_max - bbox.x_min;
+    let height = bbox.y_max - bbox.y_min;
+    let (width, height) = if width < height * ratio {
+        (height * ratio, height)
+    } else {
+        (width, width / ratio)
+    };
+    let center_x = (bbox.x_min + bbox.x_max) / 2.0;
+    let center_y = (bbox.y_min + bbox.y_max) / 2.0;
+
+    BoundingBox {
+        x_min: center_x - width / 2.0,
+        y_min: center_y - height / 2.0,
+        x_max: center_x + width / 2.0,
+        y_max: center_y + height / 2.0,
+    }
+}
+
+/// Scale a bounding box around its center (1.0 = unchanged)
+pub fn expand_bbox(bbox: &BoundingBox, padding_factor: f32) -> BoundingBox {
+    if padding_factor == 1.0 {
+        return *bbox;
+    }
+    let center_x = (bbox.x_min + bbox.x_max) / 2.0;
+    let center_y = (bbox.y_min + bbox.y_max) / 2.0;
+    let half_width = (bbox.x_max - bbox.x_min) * padding_factor / 2.0;
+    let half_height = (bbox.y_max - bbox.y_min) * padding_factor / 2.0;
+
+    BoundingBox {
+        x_min: center_x - half_width,
+        y_min: center_y - half_height,
+        x_max: center_x + half_width,
+        y_max: center_y + half_height,
+    }
+}
+
+/// A planned crop of one parent instance
+#[derive(Debug, Clone)]
+pub struct ParentCrop {
+    pub parent_index: usize,
+    pub parent_bbox: BoundingBox,
+    pub window: CropWindow,
+    /// Children remapped into crop coordinates (kept and clipped)
+    pub children: Vec<LabelMeShape>,
+    /// Clip statistics; file names are filled in once the crop is written
+    pub record: CropRecord,
+}
+
+impl ParentCrop {
+    /// Whether a child with `label` made it into the crop
+    pub fn has_child(&self, label: &str) -> bool {
+        self.children.iter().any(|child| child.label == label)
+    }
+}
+
+/// Child index → parent index when the association rule wants unique owners
+pub fn unique_owners(
+    labelme: &LabelMeFile,
+    is_parent: impl Fn(&LabelMeShape) -> bool,
+    is_child: impl Fn(&LabelMeShape) -> bool,
+    options: &CropRemapOptions,
+) -> Option<HashMap<usize, usize>> {
+    if !options.association.unique_assignment {
+        return None;
+    }
+    let parents: Vec<(usize, BoundingBox)> = labelme
+        .shapes
+        .iter()
+        .enumerate()
+        .filter(|(_, shape)| is_parent(shape))
+        .filter_map(|(index, shape)| get_bounding_box(&shape.points).map(|bbox| (index, bbox)))
+        .collect();
+    Some(assign_unique_parents(
+        &labelme.shapes,
+        &parents,
+        is_child,
+        &options.association,
+    ))
+}
+
+/// Plan the crop of the parent at `parent_index`
+///
+/// Children are shapes accepted by `is_child` that overlap the crop window
+/// and pass the association rule (or are owned by this parent when
+/// `owners` is given). They are clipped to the window.
+/// Returns `None` when the parent has no points or lies outside the image.
+pub fn plan_parent_crop(
+    labelme: &LabelMeFile,
+    parent_index: usize,
+    padding_factor: f32,
+    is_child: impl Fn(&LabelMeShape) -> bool,
+    owners: Option<&HashMap<usize, usize>>,
+    options: &CropRemapOptions,
+) -> Option<ParentCrop> {
+    let parent_bbox = get_bounding_box(&labelme.shapes.get(parent_index)?.points)?;
+    let window = CropWindow::around(
+        &parent_bbox,
+        padding_factor,
+        labelme.image_width,
+        labelme.image_height,
+        &options.geometry,
+    )?;
+    let bounds = window.bounds();
+
+    let mut children = Vec::new();
+    let mut record = crop_record(labelme, parent_index, &window, &options.geometry);
+
+    for (child_index, child_shape) in labelme.shapes.iter().enumerate() {
+        if child_index == parent_index || !is_child(child_shape) {
+            continue;
+        }
+        let overlaps = get_bounding_box(&child_shape.points).is_some_and(|b| b.overlaps(&bounds));
+        if !overlaps {
+            continue;
+        }
+        let associated = match owners {
+            Some(owners) => owners.get(&child_index) == Some(&parent_index),
+            None => options
+                .association
+                .score(child_shape, &parent_bbox)
+                .is_some(),
+        };
+        if !associated {
+            continue;
+        }
+
+        match clip_child_to_crop(child_shape, &bounds, options.min_child_visibility) {
+            ChildClip::Kept(shape) => {
+                record.children_kept += 1;
+                record.child_indices.push(child_index);
+                children.push(shape);
+            }
+            ChildClip::Clipped(shape) => {
+                record.children_clipped += 1;
+                record.child_indices.push(child_index);
+                children.push(shape);
+            }
+            ChildClip::Dropped => record.children_dropped += 1,
+        }
+    }
+
+    Some(ParentCrop {
+        parent_index,
+        parent_bbox,
+        window,
+        children,
+        record,
+    })
+}
+
+/// Provenance record of a crop of `window`, before any children are added
+pub fn crop_record(
+    labelme: &LabelMeFile,
+    parent_index: usize,
+    window: &CropWindow,
+    geometry: &CropGeometry,
+) -> CropRecord {
+    let scale = match geometry.resize {
+        Some([width, height]) => [
+            width as f32 / window.width as f32,
+            height as f32 / window.height as f32,
+        ],
+        None => [1.0, 1.0],
+    };
+    CropRecord {
+        parent_index,
+        source_image: labelme.image_path.clone(),
+        offset: [window.x as f32, window.y as f32],
+        scale,
+        ..CropRecord::default()
+    }
+}
+
+/// Base file name of a crop: `{stem}_crop_{label}_{index}`
+pub fn crop_base_name(stem: &str, parent_label: &str, parent_index: usize) -> String {
+    let label: String = parent_label
+        .chars()
+        .filter(|c| c.is_alphanumeric() || *c == '_' || *c == '-')
+        .collect();
+    format!("{}_crop_{}_{}", stem, label, parent_index)
+}
+
+/// Locate the image of a LabelMe file, next to the JSON or in `source_dir`
+pub fn find_source_image(json_path: &Path, source_dir: &Path, image_path: &str) -> Option<PathBuf> {
+    let relative = Path::new(image_path);
+    let next_to_json = json_path.parent().unwrap_or(source_dir).join(relative);
+    if next_to_json.exists() {
+        return Some(next_to_json);
+    }
+    let in_source = source_dir.join(relative);
+    in_source.exists().then_some(in_source)
+}
+
+/// A cut-out image with its remapped LabelMe annotation
+pub struct CropOutput {
+    pub image: DynamicImage,
+    pub labelme: LabelMeFile,
+}
+
+/// Cut `window` out of `image`, filling any part outside it with `pad_color`
+pub fn cut_window(image: &DynamicImage, window: &CropWindow, pad_color: [u8; 3]) -> DynamicImage {
+    let (image_width, image_height) = image.dimensions();
+    let x_start = window.x.max(0) as u32;
+    let y_start = window.y.max(0) as u32;
+    let x_end = (window.x + window.width as i32).clamp(0, image_width as i32) as u32;
+    let y_end = (window.y + window.height as i32).clamp(0, image_height as i32) as u32;
+
+    let inside = window.x >= 0
+        && window.y >= 0
+        && x_end - x_start == window.width
+        && y_end - y_start == window.height;
+    if inside {
+        return image.crop_imm(x_start, y_start, window.width, window.height);
+    }
+
+    let mut canvas = RgbImage::from_pixel(window.width, window.height, Rgb(pad_color));
+    if x_end > x_start && y_end > y_start {
+        let visible = image
+            .crop_imm(x_start, y_start, x_end - x_start, y_end - y_start)
+            .to_rgb8();
+        imageops::overlay(
+            &mut canvas,
+            &visible,
+            x_start as i64 - window.x as i64,
+            y_start as i64 - window.y as i64,
+        );
+    }
+    DynamicImage::ImageRgb8(canvas)
+}
+
+/// Cut `crop` out of `image` and build its LabelMe file
+///
+/// With `geometry.resize` the crop is scaled to the target size and the
+/// children are scaled with it.
+pub fn cut_crop(
+    image: &DynamicImage,
+    source: &LabelMeFile,
+    crop: &ParentCrop,
+    image_file: &str,
+    geometry: &CropGeometry,
+) -> CropOutput {
+    let mut cropped = cut_window(image, &crop.window, geometry.pad_color);
+    let mut shapes = crop.children.clone();
+
+    if let Some([width, height]) = geometry.resize {
+        let scale_x = width as f32 / cropped.width() as f32;
+        let scale_y = height as f32 / cropped.height() as f32;
+        cropped = cropped.resize_exact(width, height, FilterType::Triangle);
+        for shape in &mut shapes {
+            for point in &mut shape.points {
+                *point = [point[0] * scale_x, point[1] * scale_y];
+            }
+        }
+    }
+
+    let labelme = LabelMeFile {
+        version: source.version.clone(),
+        flags: source.flags.clone(),
+        shapes,
+        image_path: image_file.to_string(),
+        image_data: None,
+        image_height: cropped.height(),
+        image_width: cropped.width(),
+        extra: source.extra.clone(),
+    };
+    CropOutput {
+        image: cropped,
+        labelme,
+    }
+}
+
+/// Save only the image of a crop, under its LabelMe `image_path`
+pub fn save_crop_image(output_dir: &Path, output: &CropOutput) -> Result<(), String> {
+    let image_path = output_dir.join(&output.labelme.image_path);
+    output.image.save(&image_path).map_err(|e| {
+        format!(
+            "Failed to save cropped image {}: {}",
+            image_path.display(),
+            e
+        )
+    })
+}
+
+/// Save a crop as `{base_name}.{extension}` plus `{base_name}.json`
+pub fn save_crop(output_dir: &Path, base_name: &str, output: &CropOutput) -> Result<(), String> {
+    save_crop_image(output_dir, output)?;
+
+    let json_path = output_dir.join(format!("{}.json", base_name));
+    let content = serde_json::to_string_pretty(&output.labelme)
+        .map_err(|e| format!("Failed to serialize new LabelMe data: {}", e))?;
+    fs::write(&json_path, content).map_err(|e| {
+        format!(
+            "Failed to write new JSON file {}: {}",
+            json_path.display(),
+            e
+        )
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(label: &str, points: Vec<[f32; 2]>) -> LabelMeShape {
+        LabelMeShape {
+            label: label.to_string(),
+            points,
+            shape_type: "rectangle".to_string(),
+            group_id: None,
+            flags: None,
+            extra: HashMap::new(),
+        }
+    }
+
+    fn labelme(shapes: Vec<LabelMeShape>) -> LabelMeFile {
+        LabelMeFile {
+            version: None,
+            flags: None,
+            shapes,
+            image_path: "frame.jpg".to_string(),
+            image_data: None,
+            image_height: 200,
+            image_width: 200,
+            extra: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_window_is_padded_and_clamped() {
+        let bbox = BoundingBox {
+            x_min: 0.0,
+            y_min: 50.0,
+            x_max: 100.0,
+            y_max: 150.0,
+        };
+        let geometry = CropGeometry::default();
+        let window = CropWindow::around(&bbox, 1.5, 200, 200, &geometry).unwrap();
+        assert_eq!(
+            window,
+            CropWindow {
+                x: 0,
+                y: 25,
+                width: 125,
+                height: 150,
+            }
+        );
+
+        let outside = BoundingBox {
+            x_min: 300.0,
+            y_min: 0.0,
+            x_max: 400.0,
+            y_max: 10.0,
+        };
+        assert!(CropWindow::around(&outside, 1.0, 200, 200, &geometry).is_none());
+    }
+
+    #[test]
+    fn test_square_window_is_shifted_or_padded() {
+        // 40 x 100 box at the left border
+        let bbox = BoundingBox {
+            x_min: 0.0,
+            y_min: 50.0,
+            x_max: 40.0,
+            y_max: 150.0,
+        };
+        let square = CropGeometry {
+            square: true,
+            ..CropGeometry::default()
+        };
+        let shifted = CropWindow::around(&bbox, 1.0, 200, 200, &square).unwrap();
+        assert_eq!(
+            shifted,
+            CropWindow {
+                x: 0,
+                y: 50,
+                width: 100,
+                height: 100,
+            }
+        );
+
+        let padded_geometry = CropGeometry {
+            pad_outside: true,
+            pad_color: [255, 0, 0],
+            ..square
+        };
+        let padded = CropWindow::around(&bbox, 1.0, 200, 200, &padded_geometry).unwrap();
+        assert_eq!(padded.x, -30);
+        assert_eq!((padded.width, padded.height), (100, 100));
+
+        let image = DynamicImage::ImageRgb8(RgbImage::new(200, 200));
+        let cut = cut_window(&image, &padded, padded_geometry.pad_color).to_rgb8();
+        assert_eq!(cut.dimensions(), (100, 100));
+        assert_eq!(cut.get_pixel(0, 0), &Rgb([255, 0, 0]));
+        assert_eq!(cut.get_pixel(50, 50), &Rgb([0, 0, 0]));
+    }
+
+    #[test]
+    fn test_resize_scales_children() {
+        let file = labelme(vec![
+            shape("person", vec![[0.0, 0.0], [100.0, 100.0]]),
+            shape("helmet", vec![[20.0, 10.0], [60.0, 50.0]]),
+        ]);
+        let geometry = CropGeometry {
+            resize: Some([50, 50]),
+            ..CropGeometry::default()
+        };
+        let options = CropRemapOptions {
+            geometry,
+            ..CropRemapOptions::default()
+        };
+        let crop =
+            plan_parent_crop(&file, 0, 1.0, |s| s.label == "helmet", None, &options).unwrap();
+        let image = DynamicImage::ImageRgb8(RgbImage::new(200, 200));
+        let output = cut_crop(&image, &file, &crop, "crop.jpg", &geometry);
+
+        assert_eq!(
+            (output.labelme.image_width, output.labelme.image_height),
+            (50, 50)
+        );
+        assert_eq!(
+            output.labelme.shapes[0].points,
+            vec![[10.0, 5.0], [30.0, 25.0]]
+        );
+    }
+
+    #[test]
+    fn test_plan_parent_crop_remaps_children() {
+        let file = labelme(vec![
+            shape("person", vec![[50.0, 50.0], [150.0, 150.0]]),
+            shape("helmet", vec![[60.0, 60.0], [80.0, 80.0]]),
+            shape("vest", vec![[60.0, 100.0], [80.0, 120.0]]),
+            shape("helmet", vec![[170.0, 10.0], [190.0, 30.0]]),
+        ]);
+        let crop = plan_parent_crop(
+            &file,
+            0,
+            1.0,
+            |s| s.label == "helmet",
+            None,
+            &CropRemapOptions::default(),
+        )
+        .unwrap();
+
+        assert_eq!(crop.children.len(), 1);
+        assert_eq!(crop.children[0].points, vec![[10.0, 10.0], [30.0, 30.0]]);
+        assert!(crop.has_child("helmet"));
+        assert!(!crop.has_child("vest"));
+        assert_eq!(crop_base_name("frame", "per son", 0), "frame_crop_person_0");
+    }
+}
@@ -0,0 +1,281 @@
+//! Crop-and-remap straight into a YOLO or COCO training dataset
+//!
+//! Crops are staged as LabelMe files in a temporary directory and converted
+//! with the regular pipelines. Splits are decided per source image (honouring
+//! the export's split grouping and stratification) and every crop follows its
+//! source image, so crops of one frame never end up in different splits.
+//! Negative and background crops are exported as background images of the
+//! dataset built from the positive crops.
+
+use super::report::{CROP_REPORT_FILE, CropReport};
+use super::run::CropRunResult;
+use crate::labelme_convert::io::resolve_image_path;
+use crate::labelme_convert::split::{plan_splits, resolve_split};
+use crate::labelme_convert::{ConversionConfig, ConversionResult, OutputFormat, Split, convert};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Crops written and the datasets built from them
+#[derive(Debug, Clone, Serialize)]
+pub struct CropDatasetResult {
+    pub crops: CropRunResult,
+    /// One dataset per output folder with positive crops (one per rule for rule sets)
+    pub datasets: Vec<ConversionResult>,
+}
+
+/// Crop into a staging directory and convert the crops with `export`
+///
+/// `crop` runs the crop-and-remap into the directory it is given. `export`
+/// selects the format (YOLO or COCO), split ratios, split grouping and the
+/// output location; its `input_dir` must be the crop source directory. Rule
+/// sets produce one dataset per rule, named `{dataset}_{rule}`. Folders of
+/// negative crops join the dataset of the folder containing them, and
+/// `include_background` is turned on for datasets with negatives. Nothing is
+/// exported when the crop run was cancelled.
+pub fn crop_to_dataset<F>(export: &ConversionConfig, crop: F) -> Result<CropDatasetResult, String>
+where
+    F: FnOnce(&Path) -> Result<CropRunResult, String>,
+{
+    if !matches!(
+        export.output_format,
+        OutputFormat::Yolo | OutputFormat::Coco
+    ) {
+        return Err("Crop datasets can only be exported as YOLO or COCO".to_string());
+    }
+    if export.kfold_enabled() {
+        return Err("K-fold splitting is not supported for crop datasets".to_string());
+    }
+
+    let staging =
+        tempfile::tempdir().map_err(|e| format!("Failed to create staging directory: {}", e))?;
+    let crops = crop(staging.path())?;
+    if crops.cancelled {
+        return Ok(CropDatasetResult {
+            crops,
+            datasets: Vec::new(),
+        });
+    }
+
+    // Folders of the `folder` negative target hold nothing but negative crops
+    let (negative_folders, positive_folders): (Vec<_>, Vec<_>) = crops
+        .folders
+        .iter()
+        .filter(|f| f.crops > 0)
+        .partition(|f| f.crops == f.negative_crops);
+
+    let base_name = export.get_dataset_folder_name();
+    let mut datasets = Vec::new();
+    for folder in &positive_folders {
+        let crop_dir = staging.path().join(&folder.folder);
+        let mut has_negatives = folder.negative_crops > 0;
+        for negative in &negative_folders {
+            // Each negative folder belongs to the innermost positive folder containing it
+            let owner = positive_folders
+                .iter()
+                .filter(|p| Path::new(&negative.folder).starts_with(&p.folder))
+                .max_by_key(|p| p.folder.len());
+            if owner.is_some_and(|owner| owner.folder == folder.folder) {
+                absorb_negative_folder(&crop_dir, &staging.path().join(&negative.folder))?;
+                has_negatives = true;
+            }
+        }
+
+        let mut config = export.clone();
+        config.custom_dataset_name = Some(if folder.folder.is_empty() {
+            base_name.clone()
+        } else {
+            format!("{}_{}", base_name, folder.folder)
+        });
+        config.include_background |= has_negatives;
+        datasets.push(export_crop_folder(&crop_dir, &config)?);
+    }
+
+    Ok(CropDatasetResult { crops, datasets })
+}
+
+/// Move the report records of a negatives folder nested in `crop_dir` into its report
+///
+/// The converter finds the negative crops through the recursive file search;
+/// only their split assignment has to come from the outer report.
+fn absorb_negative_folder(crop_dir: &Path, negative_dir: &Path) -> Result<(), String> {
+    let relative = negative_dir.strip_prefix(crop_dir).map_err(|_| {
+        format!(
+            "Negative folder {} is not inside {}",
+            negative_dir.display(),
+            crop_dir.display()
+        )
+    })?;
+
+    let mut records = CropReport::load(crop_dir)?.crops;
+    for mut record in CropReport::load(negative_dir)?.crops {
+        record.crop_image = relative
+            .join(&record.crop_image)
+            .to_string_lossy()
+            .to_string();
+        records.push(record);
+    }
+    CropReport::from_records(records).write(crop_dir)?;
+    fs::remove_file(negative_dir.join(CROP_REPORT_FILE))
+        .map_err(|e| format!("Failed to remove staged crop report: {}", e))
+}
+
+/// Convert one staged crop folder, keeping crops in the split of their source image
+fn export_crop_folder(
+    crop_dir: &Path,
+    config: &ConversionConfig,
+) -> Result<ConversionResult, String> {
+    let report = CropReport::load(crop_dir)?;
+    // The report is not an annotation file and must not reach the converter
+    fs::remove_file(crop_dir.join(CROP_REPORT_FILE))
+        .map_err(|e| format!("Failed to remove staged crop report: {}", e))?;
+
+    let mut staged = config.clone();
+    staged.input_dir = crop_dir.to_path_buf();
+    staged.output_dir = Some(config.get_output_dir());
+    staged.split_group = None;
+    staged.stratified = false;
+    staged.fixed_splits = crop_splits(crop_dir, &report, config);
+    Ok(convert(&staged))
+}
+
+/// Split of every crop image key, taken from the split of its source image
+fn crop_splits(
+    crop_dir: &Path,
+    report: &CropReport,
+    config: &ConversionConfig,
+) -> HashMap<String, Split> {
+    let mut sources: Vec<PathBuf> = report
+        .crops
+        .iter()
+        .map(|c| PathBuf::from(&c.source_json))
+        .collect();
+    sources.sort();
+    sources.dedup();
+    let plan = plan_splits(config, &sources);
+
+    let mut splits = HashMap::new();
+    for record in &report.crops {
+        let source_key = resolve_image_path(Path::new(&record.source_json), &record.source_image)
+            .to_string_lossy()
+            .to_string();
+        let crop_key = resolve_image_path(&crop_dir.join("crop.json"), &record.crop_image)
+            .to_string_lossy()
+            .to_string();
+        splits.insert(crop_key, resolve_split(config, plan.as_ref(), &source_key));
+    }
+
+    splits
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crop_remap::report::{CropKind, CropRecord};
+    use crate::crop_remap::run::CropFolderSummary;
+
+    #[test]
+    fn test_crops_follow_their_source_split() {
+        let records: Vec<CropRecord> = (0..20)
+            .flat_map(|frame| {
+                (0..3).map(move |person| CropRecord {
+                    crop_image: format!("frame{}_crop_person_{}.jpg", frame, person),
+                    source_json: format!("/data/frame{}.json", frame),
+                    source_image: format!("frame{}.jpg", frame),
+                    ..CropRecord::default()
+                })
+            })
+            .collect();
+        let report = CropReport::from_records(records);
+        let mut config = ConversionConfig::new(PathBuf::from("/data"));
+        config.val_size = 0.3;
+
+        let splits = crop_splits(Path::new("/staging"), &report, &config);
+        assert_eq!(splits.len(), 60);
+        for frame in 0..20 {
+            let frame_splits: Vec<Split> = (0..3)
+                .map(|person| {
+                    splits[&format!("/staging/frame{}_crop_person_{}.jpg", frame, person)]
+                })
+                .collect();
+            assert!(frame_splits.iter().all(|s| *s == frame_splits[0]));
+        }
+        assert!(splits.values().any(|s| *s == Split::Val));
+        assert!(splits.values().any(|s| *s == Split::Train));
+    }
+
+    #[test]
+    fn test_negative_folder_joins_the_positive_dataset() {
+        let source = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        let mut export = ConversionConfig::new(source.path().to_path_buf());
+        export.output_dir = Some(output.path().to_path_buf());
+        export.custom_dataset_name = Some("crops".to_string());
+
+        // Stage one positive crop and one negative crop in the `negatives` folder
+        let stage = |dir: &Path, name: &str, shapes: serde_json::Value, kind: CropKind| {
+            fs::create_dir_all(dir).unwrap();
+            image::RgbImage::new(20, 20)
+                .save(dir.join(format!("{}.jpg", name)))
+                .unwrap();
+            let labelme = serde_json::json!({
+                "version": "5.0.1",
+                "flags": {},
+                "shapes": shapes,
+                "imagePath": format!("{}.jpg", name),
+                "imageData": null,
+                "imageHeight": 20,
+                "imageWidth": 20,
+            });
+            fs::write(dir.join(format!("{}.json", name)), labelme.to_string()).unwrap();
+            let record = CropRecord {
+                crop_image: format!("{}.jpg", name),
+                source_json: "/data/frame.json".to_string(),
+                source_image: "frame.jpg".to_string(),
+                kind,
+                ..CropRecord::default()
+            };
+            CropReport::from_records(vec![record]).write(dir).unwrap();
+        };
+        let summary = |folder: &str, negative_crops: usize| CropFolderSummary {
+            folder: folder.to_string(),
+            crops: 1,
+            negative_crops,
+            children_clipped: 0,
+            children_dropped: 0,
+        };
+
+        let result = crop_to_dataset(&export, |dir| {
+            let helmet = serde_json::json!([{
+                "label": "helmet",
+                "points": [[2.0, 2.0], [10.0, 10.0]],
+                "group_id": null,
+                "shape_type": "rectangle",
+                "flags": {},
+            }]);
+            stage(dir, "frame_crop_person_0", helmet, CropKind::Positive);
+            let negatives = dir.join("negatives");
+            stage(
+                &negatives,
+                "frame_crop_person_1",
+                serde_json::json!([]),
+                CropKind::NegativeParent,
+            );
+            Ok(CropRunResult {
+                total_crops: 2,
+                negative_crops: 1,
+                folders: vec![summary("", 0), summary("negatives", 1)],
+                ..CropRunResult::default()
+            })
+        })
+        .unwrap();
+
+        assert_eq!(result.datasets.len(), 1);
+        let dataset = &result.datasets[0];
+        assert!(dataset.success, "{:?}", dataset.errors);
+        assert_eq!(dataset.stats.processed_files, 2);
+        assert_eq!(dataset.stats.failed_files, 0);
+        assert_eq!(dataset.stats.total_annotations, 1);
+    }
+}
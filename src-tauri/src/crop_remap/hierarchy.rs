@@ -0,0 +1,578 @@
+//! Rule-set driven, multi-level crop-and-remap
+//!
+//! A rule set lists crop rules, each with its own parent labels, child
+//! requirements, padding and nested rules. Nested rules run on the crops of
+//! their parent rule, so vehicle → license_plate → characters is one rule
+//! tree. Every rule writes into its own folder of the output directory and
+//! all rules run in a single pass over the source files.
+//!
+//! ```yaml
+//! rules:
+//!   - name: vehicle_plates
+//!     parent_labels: [car, truck]
+//!     children:
+//!       any: [license_plate]
+//!       none: [occluded]
+//!     padding: 1.1
+//!     rules:
+//!       - name: plate_characters
+//!         parent_labels: [license_plate]
+//!         children:
+//!           any: [character]
+//! ```
+
+use super::config::CropRemapOptions;
+use super::crop::{
+    ParentCrop, crop_base_name, cut_crop, find_source_image, plan_parent_crop, save_crop,
+    unique_owners,
+};
+use super::report::CropRecord;
+use super::run::{CropRunResult, find_source_files, run_crop_files};
+use crate::core::labelme_types::{LabelMeFile, LabelMeShape};
+use image::DynamicImage;
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::AtomicBool;
+
+fn default_padding() -> f32 {
+    1.0
+}
+
+/// Child labels a parent must (not) have for its crop to be written
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ChildRequirement {
+    /// Every one of these labels must be present
+    pub all: Vec<String>,
+    /// At least one of these labels must be present (ignored when empty)
+    pub any: Vec<String>,
+    /// None of these labels may be present
+    pub none: Vec<String>,
+}
+
+impl ChildRequirement {
+    /// Whether the remapped children of `crop` satisfy `all` and `any`
+    pub fn is_met(&self, crop: &ParentCrop) -> bool {
+        self.all.iter().all(|label| crop.has_child(label))
+            && (self.any.is_empty() || self.any.iter().any(|label| crop.has_child(label)))
+    }
+}
+
+/// One crop rule, optionally with nested rules applied to its crops
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CropRule {
+    /// Rule name, also the name of its output folder
+    pub name: String,
+    /// Shapes with any of these labels are cropped
+    pub parent_labels: Vec<String>,
+    #[serde(default)]
+    pub children: ChildRequirement,
+    /// Factor to expand the parent bounding box (1.0 = no padding)
+    #[serde(default = "default_padding")]
+    pub padding: f32,
+    /// Extra child labels carried into the crops without being required
+    #[serde(default)]
+    pub keep_labels: Vec<String>,
+    /// Rules applied to the crops of this rule
+    #[serde(default)]
+    pub rules: Vec<CropRule>,
+}
+
+impl CropRule {
+    /// Labels remapped into this rule's crops
+    ///
+    /// Besides the required and kept children this includes every label the
+    /// nested rules need, so they find their parents and children in the crop.
+    pub fn carried_labels(&self) -> HashSet<&str> {
+        let mut labels: HashSet<&str> = self
+            .children
+            .all
+            .iter()
+            .chain(&self.children.any)
+            .chain(&self.keep_labels)
+            .map(String::as_str)
+            .collect();
+        for nested in &self.rules {
+            labels.extend(nested.parent_labels.iter().map(String::as_str));
+            labels.extend(nested.carried_labels());
+            labels.extend(nested.children.none.iter().map(String::as_str));
+        }
+        labels
+    }
+
+    fn validate(&self, names: &mut HashSet<String>) -> Result<(), String> {
+        let valid_name = !self.name.is_empty()
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_alphanumeric() || c == '_' || c == '-');
+        if !valid_name {
+            return Err(format!(
+                "Rule name '{}' must be non-empty and contain only letters, digits, '_' or '-'",
+                self.name
+            ));
+        }
+        if !names.insert(self.name.clone()) {
+            return Err(format!("Duplicate rule name '{}'", self.name));
+        }
+        if self.parent_labels.is_empty() {
+            return Err(format!("Rule '{}' has no parent labels", self.name));
+        }
+        if self.padding <= 0.0 || self.padding > 5.0 {
+            return Err(format!(
+                "Rule '{}': padding must be between 0.1 and 5.0, got {}",
+                self.name, self.padding
+            ));
+        }
+        let required: HashSet<&String> =
+            self.children.all.iter().chain(&self.children.any).collect();
+        if let Some(label) = self.children.none.iter().find(|l| required.contains(l)) {
+            return Err(format!(
+                "Rule '{}': label '{}' is both required and excluded",
+                self.name, label
+            ));
+        }
+        self.rules.iter().try_for_each(|rule| rule.validate(names))
+    }
+}
+
+/// A declarative set of crop rules, loaded from YAML or JSON
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CropRuleSet {
+    pub rules: Vec<CropRule>,
+}
+
+impl CropRuleSet {
+    /// Load a rule set; `.json` files are read as JSON, anything else as YAML
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read rule file {}: {}", path.display(), e))?;
+        let is_json = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("json"));
+        let rule_set: Self = if is_json {
+            serde_json::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            serde_yaml::from_str(&content).map_err(|e| e.to_string())
+        }
+        .map_err(|e| format!("Failed to parse rule file {}: {}", path.display(), e))?;
+        rule_set.validate()?;
+        Ok(rule_set)
+    }
+
+    /// Validate all rules; rule names must be unique across the whole tree
+    pub fn validate(&self) -> Result<(), String> {
+        if self.rules.is_empty() {
+            return Err("Rule set has no rules".to_string());
+        }
+        let mut names = HashSet::new();
+        self.rules
+            .iter()
+            .try_for_each(|rule| rule.validate(&mut names))
+    }
+
+    /// All rules, parents before their nested rules
+    pub fn all_rules(&self) -> Vec<&CropRule> {
+        fn collect<'a>(rules: &'a [CropRule], out: &mut Vec<&'a CropRule>) {
+            for rule in rules {
+                out.push(rule);
+                collect(&rule.rules, out);
+            }
+        }
+        let mut out = Vec::new();
+        collect(&self.rules, &mut out);
+        out
+    }
+}
+
+/// Source image of a rule level: the original file (loaded on first use) or a crop
+enum SourceImage<'a> {
+    Pending {
+        json_path: &'a Path,
+        source_dir: &'a Path,
+    },
+    Loaded(DynamicImage),
+}
+
+impl SourceImage<'_> {
+    fn get(&mut self, image_path: &str) -> Result<&DynamicImage, String> {
+        if let SourceImage::Pending {
+            json_path,
+            source_dir,
+        } = *self
+        {
+            let path = find_source_image(json_path, source_dir, image_path).ok_or_else(|| {
+                format!(
+                    "Original image not found at expected paths for {}",
+                    image_path
+                )
+            })?;
+            let image = image::open(&path)
+                .map_err(|e| format!("Failed to open original image {}: {}", path.display(), e))?;
+            *self = SourceImage::Loaded(image);
+        }
+        match self {
+            SourceImage::Loaded(image) => Ok(image),
+            SourceImage::Pending { .. } => unreachable!(),
+        }
+    }
+}
+
+/// Everything one rule level needs besides the rule and its input
+struct RuleContext<'a> {
+    output_dir: &'a Path,
+    source_json: String,
+    extension: String,
+    options: &'a CropRemapOptions,
+}
+
+/// Apply `rule` to `labelme`, write its crops and recurse into nested rules
+///
+/// `outer` is the record of the crop `labelme` was cut from, if any; the
+/// records of nested crops are mapped back onto the original source through it.
+fn apply_rule(
+    rule: &CropRule,
+    labelme: &LabelMeFile,
+    image: &mut SourceImage,
+    stem: &str,
+    context: &RuleContext,
+    outer: Option<&CropRecord>,
+    records: &mut Vec<(String, CropRecord)>,
+) -> Result<(), String> {
+    let carried = rule.carried_labels();
+    let is_parent = |shape: &LabelMeShape| rule.parent_labels.contains(&shape.label);
+    let is_child = |shape: &LabelMeShape| carried.contains(shape.label.as_str());
+    let is_excluded = |shape: &LabelMeShape| rule.children.none.contains(&shape.label);
+    let owners = unique_owners(
+        labelme,
+        is_parent,
+        |shape: &LabelMeShape| is_child(shape) || is_excluded(shape),
+        context.options,
+    );
+    let rule_dir = context.output_dir.join(&rule.name);
+
+    for (parent_index, parent_shape) in labelme.shapes.iter().enumerate() {
+        if !is_parent(parent_shape) {
+            continue;
+        }
+        let plan = |filter: &dyn Fn(&LabelMeShape) -> bool| {
+            plan_parent_crop(
+                labelme,
+                parent_index,
+                rule.padding,
+                filter,
+                owners.as_ref(),
+                context.options,
+            )
+        };
+        let Some(mut crop) = plan(&is_child) else {
+            continue;
+        };
+        if !rule.children.is_met(&crop) {
+            continue;
+        }
+        let excluded = !rule.children.none.is_empty()
+            && plan(&is_excluded).is_some_and(|c| !c.children.is_empty());
+        if excluded {
+            continue;
+        }
+
+        let base_name = crop_base_name(stem, &parent_shape.label, parent_index);
+        let image_file = format!("{}.{}", base_name, context.extension);
+        let output = cut_crop(
+            image.get(&labelme.image_path)?,
+            labelme,
+            &crop,
+            &image_file,
+            &context.options.geometry,
+        );
+        if let Err(e) = save_crop(&rule_dir, &base_name, &output) {
+            eprintln!(
+                " -> Rule {}: error for parent at index {}: {}",
+                rule.name, parent_index, e
+            );
+            continue;
+        }
+
+        crop.record.crop_image = image_file;
+        crop.record.source_json = context.source_json.clone();
+        if let Some(outer) = outer {
+            crop.record.nest_in(outer);
+        }
+        records.push((rule.name.clone(), crop.record.clone()));
+
+        if !rule.rules.is_empty() {
+            let mut crop_image = SourceImage::Loaded(output.image);
+            for nested in &rule.rules {
+                apply_rule(
+                    nested,
+                    &output.labelme,
+                    &mut crop_image,
+                    &base_name,
+                    context,
+                    Some(&crop.record),
+                    records,
+                )?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn process_file_with_rules(
+    json_path: &Path,
+    source_dir: &Path,
+    output_dir: &Path,
+    rule_set: &CropRuleSet,
+    options: &CropRemapOptions,
+) -> Result<Vec<(String, CropRecord)>, String> {
+    let json_content =
+        fs::read_to_string(json_path).map_err(|e| format!("Failed to read JSON file: {}", e))?;
+    let labelme: LabelMeFile = serde_json::from_str(&json_content)
+        .map_err(|e| format!("Failed to parse JSON content: {}", e))?;
+
+    let image_path = Path::new(&labelme.image_path);
+    let stem = image_path
+        .file_stem()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string();
+    let context = RuleContext {
+        output_dir,
+        source_json: json_path.to_string_lossy().to_string(),
+        extension: image_path
+            .extension()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string(),
+        options,
+    };
+
+    let mut image = SourceImage::Pending {
+        json_path,
+        source_dir,
+    };
+    let mut records = Vec::new();
+    for rule in &rule_set.rules {
+        apply_rule(
+            rule,
+            &labelme,
+            &mut image,
+            &stem,
+            &context,
+            None,
+            &mut records,
+        )?;
+    }
+    Ok(records)
+}
+
+/// Crop a dataset with a rule set, writing one folder per rule
+///
+/// # Arguments
+/// * `source_dir_str` - Directory containing original images and LabelMe JSON files.
+/// * `output_dir_str` - Directory receiving one sub-folder per rule.
+/// * `rule_set` - The rules to apply.
+/// * `options` - Child clipping and association options shared by all rules.
+/// * `progress_callback` - Optional callback (current_file_index, total_files, message).
+///
+/// # Returns
+/// A summary message, or an error listing the files that failed.
+pub fn process_rule_set<F>(
+    source_dir_str: &str,
+    output_dir_str: &str,
+    rule_set: &CropRuleSet,
+    options: &CropRemapOptions,
+    progress_callback: Option<F>,
+) -> Result<String, String>
+where
+    F: Fn(usize, usize, String) + Send + Sync + 'static,
+{
+    let result = run_rule_set(
+        source_dir_str,
+        output_dir_str,
+        rule_set,
+        options,
+        progress_callback,
+        None,
+    )?;
+
+    let rule_summaries: Vec<String> = result
+        .folders
+        .iter()
+        .map(|folder| format!("{}: {} crops", folder.folder, folder.crops))
+        .collect();
+    let summary = format!(
+        "Processing complete. Checked {} files with {} rules ({}).",
+        result.total_files,
+        result.folders.len(),
+        rule_summaries.join(", ")
+    );
+    if result.errors.is_empty() {
+        Ok(summary)
+    } else {
+        Err(format!(
+            "{}\nEncountered errors in {} files:\n - {}",
+            summary,
+            result.errors.len(),
+            result.error_lines().join("\n - ")
+        ))
+    }
+}
+
+/// Same as [`process_rule_set`], returning a typed result
+///
+/// Files not yet started when `cancel` is set are skipped.
+pub fn run_rule_set<F>(
+    source_dir_str: &str,
+    output_dir_str: &str,
+    rule_set: &CropRuleSet,
+    options: &CropRemapOptions,
+    progress_callback: Option<F>,
+    cancel: Option<&AtomicBool>,
+) -> Result<CropRunResult, String>
+where
+    F: Fn(usize, usize, String) + Sync,
+{
+    rule_set.validate()?;
+    options.validate()?;
+
+    let source_dir = Path::new(source_dir_str);
+    let output_dir = Path::new(output_dir_str);
+    let json_paths = find_source_files(source_dir)?;
+    let folders: Vec<String> = rule_set
+        .all_rules()
+        .iter()
+        .map(|rule| rule.name.clone())
+        .collect();
+
+    run_crop_files(
+        &json_paths,
+        output_dir,
+        &folders,
+        |json_path| process_file_with_rules(json_path, source_dir, output_dir, rule_set, options),
+        progress_callback,
+        cancel,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crop_remap::report::CropReport;
+    use std::collections::HashMap;
+
+    const RULES: &str = r#"
+rules:
+  - name: vehicles
+    parent_labels: [car]
+    children:
+      any: [license_plate]
+      none: [occluded]
+    rules:
+      - name: plates
+        parent_labels: [license_plate]
+        children:
+          all: [character]
+"#;
+
+    fn shape(label: &str, points: Vec<[f32; 2]>) -> LabelMeShape {
+        LabelMeShape {
+            label: label.to_string(),
+            points,
+            shape_type: "rectangle".to_string(),
+            group_id: None,
+            flags: None,
+            extra: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_and_validate_rule_set() {
+        let rule_set: CropRuleSet = serde_yaml::from_str(RULES).unwrap();
+        assert!(rule_set.validate().is_ok());
+        assert_eq!(rule_set.rules[0].padding, 1.0);
+
+        let carried = rule_set.rules[0].carried_labels();
+        assert!(carried.contains("license_plate"));
+        assert!(carried.contains("character"));
+        assert!(!carried.contains("occluded"));
+
+        let names: Vec<&str> = rule_set
+            .all_rules()
+            .iter()
+            .map(|r| r.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["vehicles", "plates"]);
+
+        let mut duplicate = rule_set.clone();
+        duplicate.rules[0].rules[0].name = "vehicles".to_string();
+        assert!(duplicate.validate().is_err());
+    }
+
+    #[test]
+    fn test_nested_rules_write_one_folder_per_rule() {
+        let dir = tempfile::tempdir().unwrap();
+        let source = dir.path().join("source");
+        let output = dir.path().join("output");
+        fs::create_dir_all(&source).unwrap();
+        image::RgbImage::new(300, 200)
+            .save(source.join("street.png"))
+            .unwrap();
+
+        let labelme = LabelMeFile {
+            version: Some("5.0.1".to_string()),
+            flags: None,
+            shapes: vec![
+                shape("car", vec![[10.0, 10.0], [140.0, 190.0]]),
+                shape("license_plate", vec![[40.0, 120.0], [100.0, 150.0]]),
+                shape("character", vec![[45.0, 125.0], [55.0, 145.0]]),
+                // Second car has a plate but is occluded
+                shape("car", vec![[160.0, 10.0], [290.0, 190.0]]),
+                shape("license_plate", vec![[200.0, 120.0], [260.0, 150.0]]),
+                shape("occluded", vec![[170.0, 20.0], [200.0, 60.0]]),
+            ],
+            image_path: "street.png".to_string(),
+            image_data: None,
+            image_height: 200,
+            image_width: 300,
+            extra: HashMap::new(),
+        };
+        fs::write(
+            source.join("street.json"),
+            serde_json::to_string(&labelme).unwrap(),
+        )
+        .unwrap();
+
+        let rule_set: CropRuleSet = serde_yaml::from_str(RULES).unwrap();
+        let result = process_rule_set(
+            source.to_str().unwrap(),
+            output.to_str().unwrap(),
+            &rule_set,
+            &CropRemapOptions::default(),
+            None::<fn(usize, usize, String)>,
+        );
+        assert!(result.is_ok(), "{:?}", result);
+
+        assert!(output.join("vehicles/street_crop_car_0.png").exists());
+        assert!(!output.join("vehicles/street_crop_car_3.json").exists());
+
+        let plate_json = output.join("plates/street_crop_car_0_crop_license_plate_0.json");
+        let plate: LabelMeFile =
+            serde_json::from_str(&fs::read_to_string(plate_json).unwrap()).unwrap();
+        assert_eq!(plate.image_width, 60);
+        assert_eq!(plate.shapes.len(), 1);
+        assert_eq!(plate.shapes[0].points, vec![[5.0, 5.0], [15.0, 25.0]]);
+
+        // Nested crops point back at the original file
+        let report = CropReport::load(&output.join("plates")).unwrap();
+        assert_eq!(report.crops.len(), 1);
+        assert_eq!(report.crops[0].parent_index, 1);
+        assert_eq!(report.crops[0].child_indices, vec![2]);
+        assert_eq!(report.crops[0].offset, [40.0, 120.0]);
+        assert!(report.crops[0].source_json.ends_with("street.json"));
+    }
+}
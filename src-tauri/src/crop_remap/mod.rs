@@ -1,3 +1,22 @@
 pub mod adapter;
+pub mod association;
+pub mod clip;
+pub mod config;
+pub mod crop;
+pub mod dataset;
+pub mod hierarchy;
+pub mod negative;
+pub mod paste_back;
+pub mod report;
+pub mod run;
 
 pub use adapter::crop_remap_adapter;
+pub use association::{AssociationRule, assign_unique_parents};
+pub use clip::{ChildClip, clip_child_to_crop};
+pub use config::{CropRemapOptions, NegativeOptions, NegativeTarget};
+pub use crop::{CropWindow, ParentCrop};
+pub use dataset::{CropDatasetResult, crop_to_dataset};
+pub use hierarchy::{ChildRequirement, CropRule, CropRuleSet, process_rule_set, run_rule_set};
+pub use paste_back::{PasteBackOptions, PasteBackResult, paste_back_crops};
+pub use report::{CROP_REPORT_FILE, CropKind, CropRecord, CropReport};
+pub use run::{CropFileError, CropFolderSummary, CropRunResult, cancel_job};
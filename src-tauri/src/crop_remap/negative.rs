@@ -0,0 +1,234 @@
+//! Negative samples for crop-and-remap
+//!
+//! Parents without any required child and random windows away from all
+//! parents are exported as crops without annotations. Only clean windows are
+//! used: a negative crop never shows any part of a child shape.
+
+use super::config::{CropRemapOptions, NegativeOptions};
+use super::crop::{CropWindow, ParentCrop, crop_record};
+use super::report::CropKind;
+use crate::core::labelme_types::{BoundingBox, LabelMeFile, LabelMeShape, get_bounding_box};
+use crate::labelme_convert::pipeline::hash_string;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+/// Random positions tried per background crop before giving up on a crowded image
+const PLACEMENT_ATTEMPTS: usize = 50;
+
+/// Whether a shape accepted by `filter` overlaps `window`
+fn touches_any(
+    labelme: &LabelMeFile,
+    window: &BoundingBox,
+    filter: impl Fn(&LabelMeShape) -> bool,
+) -> bool {
+    labelme
+        .shapes
+        .iter()
+        .filter(|shape| filter(shape))
+        .filter_map(|shape| get_bounding_box(&shape.points))
+        .any(|bbox| bbox.overlaps(window))
+}
+
+/// Deterministically keep `options.ratio` of the negative parents
+pub fn keep_negative_parent(
+    options: &NegativeOptions,
+    source_key: &str,
+    parent_index: usize,
+) -> bool {
+    if options.ratio >= 1.0 {
+        return true;
+    }
+    let draw = hash_string(&format!("{}:{}:{}", options.seed, source_key, parent_index)) % 10_000;
+    (draw as f32) < options.ratio * 10_000.0
+}
+
+/// Turn a planned parent crop without children into a negative crop
+///
+/// Returns `None` when a child still shows in the window, e.g. one dropped
+/// for low visibility or owned by a neighbouring parent.
+pub fn negative_parent_crop(
+    labelme: &LabelMeFile,
+    mut crop: ParentCrop,
+    is_child: impl Fn(&LabelMeShape) -> bool,
+) -> Option<ParentCrop> {
+    if !crop.children.is_empty() || touches_any(labelme, &crop.window.bounds(), is_child) {
+        return None;
+    }
+    crop.record.kind = CropKind::NegativeParent;
+    Some(crop)
+}
+
+/// Up to `options.negatives.background_crops` random windows showing no parent or child
+///
+/// Windows take the size of the image's parent crops in turn (with padding
+/// and geometry applied); images without parents use the resize target or a
+/// quarter of the image. Windows do not overlap each other.
+pub fn plan_background_crops(
+    labelme: &LabelMeFile,
+    padding_factor: f32,
+    is_parent: impl Fn(&LabelMeShape) -> bool,
+    is_child: impl Fn(&LabelMeShape) -> bool,
+    options: &CropRemapOptions,
+    source_key: &str,
+) -> Vec<ParentCrop> {
+    let (image_width, image_height) = (labelme.image_width, labelme.image_height);
+    let mut sizes: Vec<(u32, u32)> = labelme
+        .shapes
+        .iter()
+        .filter(|shape| is_parent(shape))
+        .filter_map(|shape| get_bounding_box(&shape.points))
+        .filter_map(|bbox| {
+            CropWindow::around(
+                &bbox,
+                padding_factor,
+                image_width,
+                image_height,
+                &options.geometry,
+            )
+        })
+        .map(|window| (window.width, window.height))
+        .filter(|&(width, height)| width <= image_width && height <= image_height)
+        .collect();
+    if sizes.is_empty() && labelme.shapes.iter().all(|shape| !is_parent(shape)) {
+        sizes.push(match options.geometry.resize {
+            Some([width, height]) => (width.min(image_width), height.min(image_height)),
+            None => ((image_width / 4).max(1), (image_height / 4).max(1)),
+        });
+    }
+    if sizes.is_empty() || image_width == 0 || image_height == 0 {
+        return Vec::new();
+    }
+
+    let mut rng = StdRng::seed_from_u64(options.negatives.seed ^ hash_string(source_key));
+    let mut crops: Vec<ParentCrop> = Vec::new();
+    for i in 0..options.negatives.background_crops {
+        let (width, height) = sizes[i % sizes.len()];
+        for _ in 0..PLACEMENT_ATTEMPTS {
+            let window = CropWindow {
+                x: rng.gen_range(0..=image_width - width) as i32,
+                y: rng.gen_range(0..=image_height - height) as i32,
+                width,
+                height,
+            };
+            let bounds = window.bounds();
+            let blocked = touches_any(labelme, &bounds, |s| is_parent(s) || is_child(s))
+                || crops.iter().any(|c| c.parent_bbox.overlaps(&bounds));
+            if blocked {
+                continue;
+            }
+
+            let mut record = crop_record(labelme, 0, &window, &options.geometry);
+            record.kind = CropKind::Background;
+            crops.push(ParentCrop {
+                parent_index: 0,
+                parent_bbox: bounds,
+                window,
+                children: Vec::new(),
+                record,
+            });
+            break;
+        }
+    }
+    crops
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crop_remap::crop::plan_parent_crop;
+    use std::collections::HashMap;
+
+    fn shape(label: &str, points: Vec<[f32; 2]>) -> LabelMeShape {
+        LabelMeShape {
+            label: label.to_string(),
+            points,
+            shape_type: "rectangle".to_string(),
+            group_id: None,
+            flags: None,
+            extra: HashMap::new(),
+        }
+    }
+
+    fn labelme(shapes: Vec<LabelMeShape>) -> LabelMeFile {
+        LabelMeFile {
+            version: None,
+            flags: None,
+            shapes,
+            image_path: "frame.jpg".to_string(),
+            image_data: None,
+            image_height: 400,
+            image_width: 400,
+            extra: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_negative_parents_must_be_clean() {
+        let file = labelme(vec![
+            shape("person", vec![[0.0, 0.0], [100.0, 200.0]]),
+            shape("person", vec![[200.0, 0.0], [300.0, 200.0]]),
+            // Only a sliver of this helmet reaches into the second person
+            shape("helmet", vec![[180.0, 10.0], [202.0, 30.0]]),
+        ]);
+        let is_helmet = |s: &LabelMeShape| s.label == "helmet";
+        let options = CropRemapOptions {
+            min_child_visibility: 0.5,
+            ..CropRemapOptions::default()
+        };
+
+        let first = plan_parent_crop(&file, 0, 1.0, is_helmet, None, &options).unwrap();
+        let negative = negative_parent_crop(&file, first, is_helmet).unwrap();
+        assert_eq!(negative.record.kind, CropKind::NegativeParent);
+
+        // The helmet is dropped from the second crop but would still show
+        let second = plan_parent_crop(&file, 1, 1.0, is_helmet, None, &options).unwrap();
+        assert!(second.children.is_empty());
+        assert!(negative_parent_crop(&file, second, is_helmet).is_none());
+
+        let mut sampling = NegativeOptions {
+            ratio: 0.0,
+            ..NegativeOptions::default()
+        };
+        assert!(!keep_negative_parent(&sampling, "frame.json", 0));
+        sampling.ratio = 1.0;
+        assert!(keep_negative_parent(&sampling, "frame.json", 0));
+    }
+
+    #[test]
+    fn test_background_crops_avoid_parents() {
+        let file = labelme(vec![
+            shape("person", vec![[0.0, 0.0], [100.0, 200.0]]),
+            shape("helmet", vec![[300.0, 300.0], [320.0, 320.0]]),
+        ]);
+        let mut options = CropRemapOptions::default();
+        options.negatives.background_crops = 3;
+
+        let crops = plan_background_crops(
+            &file,
+            1.0,
+            |s| s.label == "person",
+            |s| s.label == "helmet",
+            &options,
+            "frame.json",
+        );
+        assert!(!crops.is_empty());
+        for crop in &crops {
+            assert_eq!((crop.window.width, crop.window.height), (100, 200));
+            assert_eq!(crop.record.kind, CropKind::Background);
+            assert!(crop.window.x >= 100 || crop.window.y >= 200);
+            assert!(!touches_any(&file, &crop.window.bounds(), |_| true));
+        }
+
+        // Same seed, same windows
+        let again = plan_background_crops(
+            &file,
+            1.0,
+            |s| s.label == "person",
+            |s| s.label == "helmet",
+            &options,
+            "frame.json",
+        );
+        let windows = |crops: &[ParentCrop]| crops.iter().map(|c| c.window).collect::<Vec<_>>();
+        assert_eq!(windows(&crops), windows(&again));
+    }
+}
@@ -0,0 +1,583 @@
+//! Paste edits made on crops back into the source annotations
+//!
+//! The crop report records where every crop came from. The shapes of an
+//! edited crop are matched to the source children the crop was built from by
+//! bounding-box IoU: matched shapes take over the edited label and, when moved,
+//! the edited geometry; unmatched source children were deleted on the crop and
+//! are removed; unmatched crop shapes are new and are added unless the source
+//! already has a matching shape.
+//!
+//! When the sources are overwritten, the crop report is rewritten to follow
+//! the new shape indices, so pasting the same crops again changes nothing.
+
+use super::clip::{ChildClip, clip_child_to_crop};
+use super::report::{CropKind, CropRecord, CropReport};
+use super::run::CropFileError;
+use crate::core::labelme_types::{BoundingBox, LabelMeFile, LabelMeShape, get_bounding_box};
+use crate::labelme_convert::conversion::bbox_iou;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Largest coordinate difference (crop pixels) still counted as unchanged
+const POINT_TOLERANCE: f32 = 0.5;
+
+/// Options for pasting crop edits back
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PasteBackOptions {
+    /// Minimum bounding-box IoU for an edited shape to replace a source child
+    pub match_iou: f32,
+    /// Write the updated source files here instead of overwriting them,
+    /// keeping their folders below the common source folder
+    pub output_dir: Option<String>,
+}
+
+impl Default for PasteBackOptions {
+    fn default() -> Self {
+        Self {
+            match_iou: 0.5,
+            output_dir: None,
+        }
+    }
+}
+
+impl PasteBackOptions {
+    /// Validate the options
+    pub fn validate(&self) -> Result<(), String> {
+        if !(self.match_iou > 0.0 && self.match_iou <= 1.0) {
+            return Err(format!(
+                "match_iou must be greater than 0.0 and at most 1.0, got {}",
+                self.match_iou
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Changes written back to the source annotations
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct PasteBackResult {
+    pub crops_read: usize,
+    pub files_updated: usize,
+    pub shapes_updated: usize,
+    pub shapes_added: usize,
+    pub shapes_removed: usize,
+    pub errors: Vec<CropFileError>,
+}
+
+/// Edits collected for one source file, by source shape index
+#[derive(Default)]
+struct SourceEdits {
+    updated: HashMap<usize, LabelMeShape>,
+    removed: HashSet<usize>,
+    /// New shapes with the positions of the crops they were drawn on
+    added: Vec<(LabelMeShape, Vec<usize>)>,
+}
+
+/// Paste the edited crops of `crop_dir` back into their source files
+///
+/// `crop_dir` is a crop-and-remap output folder with its crop report (for
+/// rule sets, one rule folder). All crops of a source file are compared with
+/// the unmodified source, so overlapping crops do not see each other's edits.
+pub fn paste_back_crops(
+    crop_dir: &Path,
+    options: &PasteBackOptions,
+) -> Result<PasteBackResult, String> {
+    options.validate()?;
+    let report = CropReport::load(crop_dir)?;
+    let root = source_root(&report.crops);
+
+    let mut by_source: BTreeMap<String, Vec<CropRecord>> = BTreeMap::new();
+    for record in report.crops {
+        by_source
+            .entry(record.source_json.clone())
+            .or_default()
+            .push(record);
+    }
+
+    let mut result = PasteBackResult::default();
+    for (source_json, records) in &mut by_source {
+        let source_path = Path::new(source_json.as_str());
+        let target = match &options.output_dir {
+            Some(dir) => {
+                let relative = source_path
+                    .strip_prefix(&root)
+                    .unwrap_or_else(|_| Path::new(source_path.file_name().unwrap_or_default()));
+                Path::new(dir).join(relative)
+            }
+            None => source_path.to_path_buf(),
+        };
+        if let Err(e) = paste_into_source(
+            crop_dir,
+            source_path,
+            &target,
+            records,
+            options,
+            &mut result,
+        ) {
+            result.errors.push(CropFileError {
+                file: source_json.clone(),
+                message: e,
+            });
+        }
+    }
+
+    // Overwritten sources have new shape indices; keep the report in step
+    if options.output_dir.is_none() && result.files_updated > 0 {
+        CropReport::from_records(by_source.into_values().flatten().collect()).write(crop_dir)?;
+    }
+    Ok(result)
+}
+
+/// Deepest folder containing every source file of the report
+fn source_root(records: &[CropRecord]) -> PathBuf {
+    let mut root: Option<PathBuf> = None;
+    for record in records {
+        let folder = Path::new(&record.source_json)
+            .parent()
+            .unwrap_or(Path::new(""));
+        root = Some(match root {
+            None => folder.to_path_buf(),
+            Some(root) => root
+                .components()
+                .zip(folder.components())
+                .take_while(|(a, b)| a == b)
+                .map(|(a, _)| a)
+                .collect(),
+        });
+    }
+    root.unwrap_or_default()
+}
+
+fn read_labelme(path: &Path) -> Result<LabelMeFile, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    serde_json::from_str(&content).map_err(|e| format!("Failed to parse {}: {}", path.display(), e))
+}
+
+/// Apply the edits of all crops of one source file and write it to `target`
+///
+/// When the source is overwritten, `records` are re-indexed to the new file.
+fn paste_into_source(
+    crop_dir: &Path,
+    source_path: &Path,
+    target: &Path,
+    records: &mut [CropRecord],
+    options: &PasteBackOptions,
+    result: &mut PasteBackResult,
+) -> Result<(), String> {
+    let mut source = read_labelme(source_path)?;
+    let mut edits = SourceEdits::default();
+
+    for (position, record) in records.iter().enumerate() {
+        let crop_json = crop_dir.join(Path::new(&record.crop_image).with_extension("json"));
+        // Negatives exported as background images have no annotation file
+        if record.kind != CropKind::Positive && !crop_json.exists() {
+            continue;
+        }
+        match read_labelme(&crop_json) {
+            Ok(edited) => {
+                result.crops_read += 1;
+                collect_crop_edits(
+                    &source,
+                    record,
+                    position,
+                    &edited,
+                    options.match_iou,
+                    &mut edits,
+                );
+            }
+            Err(e) => result.errors.push(CropFileError {
+                file: record.crop_image.clone(),
+                message: e,
+            }),
+        }
+    }
+
+    let updated: Vec<(usize, LabelMeShape)> = edits
+        .updated
+        .into_iter()
+        .filter(|(index, _)| !edits.removed.contains(index))
+        .collect();
+    // Objects the source already has (e.g. from an earlier paste) are not added again
+    edits.added.retain(|(added, _)| {
+        !source.shapes.iter().enumerate().any(|(index, existing)| {
+            !edits.removed.contains(&index)
+                && existing.label == added.label
+                && shape_iou(existing, added) >= options.match_iou as f64
+        })
+    });
+    if updated.is_empty() && edits.removed.is_empty() && edits.added.is_empty() {
+        return Ok(());
+    }
+    result.shapes_updated += updated.len();
+    result.shapes_removed += edits.removed.len();
+    result.shapes_added += edits.added.len();
+
+    for (index, shape) in updated {
+        source.shapes[index] = shape;
+    }
+    let mut new_index = Vec::with_capacity(source.shapes.len());
+    let mut kept = Vec::with_capacity(source.shapes.len());
+    for (index, shape) in source.shapes.into_iter().enumerate() {
+        if edits.removed.contains(&index) {
+            new_index.push(None);
+        } else {
+            new_index.push(Some(kept.len()));
+            kept.push(shape);
+        }
+    }
+    source.shapes = kept;
+    let mut added_to = vec![Vec::new(); records.len()];
+    for (shape, crops) in edits.added {
+        for position in crops {
+            added_to[position].push(source.shapes.len());
+        }
+        source.shapes.push(shape);
+    }
+
+    if let Some(folder) = target.parent() {
+        fs::create_dir_all(folder).map_err(|e| {
+            format!(
+                "Failed to create output directory {}: {}",
+                folder.display(),
+                e
+            )
+        })?;
+    }
+    let content = serde_json::to_string_pretty(&source)
+        .map_err(|e| format!("Failed to serialize LabelMe data: {}", e))?;
+    fs::write(target, content)
+        .map_err(|e| format!("Failed to write {}: {}", target.display(), e))?;
+    result.files_updated += 1;
+
+    if target == source_path {
+        for (record, added) in records.iter_mut().zip(&added_to) {
+            record.reindex(&new_index, added);
+        }
+    }
+    Ok(())
+}
+
+/// Compare one edited crop with the source children it was cut from
+fn collect_crop_edits(
+    source: &LabelMeFile,
+    record: &CropRecord,
+    position: usize,
+    edited: &LabelMeFile,
+    match_iou: f32,
+    edits: &mut SourceEdits,
+) {
+    // Rebuild what the annotator was shown for every source child
+    let window = BoundingBox {
+        x_min: record.offset[0],
+        y_min: record.offset[1],
+        x_max: record.offset[0] + edited.image_width as f32 / record.scale[0],
+        y_max: record.offset[1] + edited.image_height as f32 / record.scale[1],
+    };
+    let shown: Vec<(usize, LabelMeShape)> = record
+        .child_indices
+        .iter()
+        .filter_map(|&index| {
+            let shape = source.shapes.get(index)?;
+            match clip_child_to_crop(shape, &window, 0.0) {
+                ChildClip::Kept(local) | ChildClip::Clipped(local) => Some((
+                    index,
+                    LabelMeShape {
+                        points: local
+                            .points
+                            .iter()
+                            .map(|p| [p[0] * record.scale[0], p[1] * record.scale[1]])
+                            .collect(),
+                        ..local
+                    },
+                )),
+                ChildClip::Dropped => None,
+            }
+        })
+        .collect();
+
+    // Greedy matching, best IoU first; same-label pairs win ties
+    let mut candidates = Vec::new();
+    for (i, (_, before)) in shown.iter().enumerate() {
+        for (j, after) in edited.shapes.iter().enumerate() {
+            let iou = shape_iou(before, after);
+            if iou >= match_iou as f64 {
+                candidates.push((iou, before.label == after.label, i, j));
+            }
+        }
+    }
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then(b.1.cmp(&a.1)));
+
+    let mut shown_matched = vec![false; shown.len()];
+    let mut edited_matched = vec![false; edited.shapes.len()];
+    for (_, _, i, j) in candidates {
+        if shown_matched[i] || edited_matched[j] {
+            continue;
+        }
+        shown_matched[i] = true;
+        edited_matched[j] = true;
+
+        let (index, before) = &shown[i];
+        let after = &edited.shapes[j];
+        let original = &source.shapes[*index];
+        // Unmoved shapes keep their source geometry, including parts cut off by the crop
+        let merged = if same_geometry(before, after) {
+            LabelMeShape {
+                points: original.points.clone(),
+                shape_type: original.shape_type.clone(),
+                ..after.clone()
+            }
+        } else {
+            to_source(after, record)
+        };
+        if merged != *original {
+            edits.updated.insert(*index, merged);
+        }
+    }
+
+    for (i, (index, _)) in shown.iter().enumerate() {
+        if !shown_matched[i] {
+            edits.removed.insert(*index);
+        }
+    }
+
+    // Objects added on several overlapping crops are added once
+    for (j, after) in edited.shapes.iter().enumerate() {
+        if edited_matched[j] {
+            continue;
+        }
+        let added = to_source(after, record);
+        let duplicate = edits.added.iter_mut().find(|(other, _)| {
+            other.label == added.label && shape_iou(other, &added) >= match_iou as f64
+        });
+        match duplicate {
+            Some((_, crops)) => crops.push(position),
+            None => edits.added.push((added, vec![position])),
+        }
+    }
+}
+
+/// A crop shape in source image coordinates
+fn to_source(shape: &LabelMeShape, record: &CropRecord) -> LabelMeShape {
+    LabelMeShape {
+        points: shape.points.iter().map(|&p| record.to_source(p)).collect(),
+        ..shape.clone()
+    }
+}
+
+fn same_geometry(a: &LabelMeShape, b: &LabelMeShape) -> bool {
+    a.shape_type == b.shape_type
+        && a.points.len() == b.points.len()
+        && a.points.iter().zip(&b.points).all(|(p, q)| {
+            (p[0] - q[0]).abs() <= POINT_TOLERANCE && (p[1] - q[1]).abs() <= POINT_TOLERANCE
+        })
+}
+
+/// Bounding-box IoU; shapes without area (points, lines) match when they coincide
+fn shape_iou(a: &LabelMeShape, b: &LabelMeShape) -> f64 {
+    let (Some(box_a), Some(box_b)) = (get_bounding_box(&a.points), get_bounding_box(&b.points))
+    else {
+        return 0.0;
+    };
+    let xywh = |b: &BoundingBox| {
+        [
+            b.x_min as f64,
+            b.y_min as f64,
+            (b.x_max - b.x_min) as f64,
+            (b.y_max - b.y_min) as f64,
+        ]
+    };
+    let (box_a, box_b) = (xywh(&box_a), xywh(&box_b));
+    if box_a[2] * box_a[3] > 0.0 && box_b[2] * box_b[3] > 0.0 {
+        return bbox_iou(&box_a, &box_b);
+    }
+    let tolerance = POINT_TOLERANCE as f64 * 2.0;
+    let coincide = box_a
+        .iter()
+        .zip(&box_b)
+        .all(|(p, q)| (p - q).abs() <= tolerance);
+    if coincide { 1.0 } else { 0.0 }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(label: &str, points: Vec<[f32; 2]>) -> LabelMeShape {
+        LabelMeShape {
+            label: label.to_string(),
+            points,
+            shape_type: "rectangle".to_string(),
+            group_id: None,
+            flags: None,
+            extra: HashMap::new(),
+        }
+    }
+
+    fn labelme(shapes: Vec<LabelMeShape>, width: u32, height: u32) -> LabelMeFile {
+        LabelMeFile {
+            version: None,
+            flags: None,
+            shapes,
+            image_path: "frame.jpg".to_string(),
+            image_data: None,
+            image_height: height,
+            image_width: width,
+            extra: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_crop_edits_are_projected_back() {
+        let dir = tempfile::tempdir().unwrap();
+        let source_json = dir.path().join("frame.json");
+        let crops = dir.path().join("crops");
+        fs::create_dir_all(&crops).unwrap();
+
+        let source = labelme(
+            vec![
+                shape("person", vec![[100.0, 100.0], [200.0, 300.0]]),
+                // Sticks out of the crop on the left
+                shape("helmet", vec![[80.0, 100.0], [130.0, 130.0]]),
+                shape("vest", vec![[120.0, 150.0], [180.0, 220.0]]),
+                shape("glove", vec![[110.0, 250.0], [120.0, 260.0]]),
+                shape("car", vec![[400.0, 0.0], [500.0, 100.0]]),
+            ],
+            640,
+            480,
+        );
+        fs::write(&source_json, serde_json::to_string(&source).unwrap()).unwrap();
+
+        // Crop at (100, 100), resized 2x; the annotator relabels the helmet,
+        // moves the vest, deletes the glove and adds a boot
+        let record = CropRecord {
+            crop_image: "frame_crop_person_0.jpg".to_string(),
+            source_json: source_json.to_string_lossy().to_string(),
+            parent_index: 0,
+            offset: [100.0, 100.0],
+            scale: [2.0, 2.0],
+            child_indices: vec![1, 2, 3],
+            ..CropRecord::default()
+        };
+        CropReport::from_records(vec![record])
+            .write(&crops)
+            .unwrap();
+        let edited = labelme(
+            vec![
+                shape("hard_hat", vec![[0.0, 0.0], [60.0, 60.0]]),
+                shape("vest", vec![[40.0, 110.0], [160.0, 250.0]]),
+                shape("boot", vec![[20.0, 360.0], [60.0, 400.0]]),
+            ],
+            200,
+            400,
+        );
+        fs::write(
+            crops.join("frame_crop_person_0.json"),
+            serde_json::to_string(&edited).unwrap(),
+        )
+        .unwrap();
+
+        let result = paste_back_crops(&crops, &PasteBackOptions::default()).unwrap();
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.crops_read, 1);
+        assert_eq!(result.files_updated, 1);
+        assert_eq!(
+            (
+                result.shapes_updated,
+                result.shapes_removed,
+                result.shapes_added
+            ),
+            (2, 1, 1)
+        );
+
+        let updated = read_labelme(&source_json).unwrap();
+        let labels: Vec<&str> = updated.shapes.iter().map(|s| s.label.as_str()).collect();
+        assert_eq!(labels, vec!["person", "hard_hat", "vest", "car", "boot"]);
+        // The relabeled helmet keeps the part outside the crop
+        assert_eq!(
+            updated.shapes[1].points,
+            vec![[80.0, 100.0], [130.0, 130.0]]
+        );
+        assert_eq!(
+            updated.shapes[2].points,
+            vec![[120.0, 155.0], [180.0, 225.0]]
+        );
+        assert_eq!(
+            updated.shapes[4].points,
+            vec![[110.0, 280.0], [130.0, 300.0]]
+        );
+
+        // The report follows the removed glove and the added boot...
+        let report = CropReport::load(&crops).unwrap();
+        assert_eq!(report.crops[0].child_indices, vec![1, 2, 4]);
+
+        // ...so pasting the same crops again changes nothing
+        let before = fs::read_to_string(&source_json).unwrap();
+        let again = paste_back_crops(&crops, &PasteBackOptions::default()).unwrap();
+        assert!(again.errors.is_empty(), "{:?}", again.errors);
+        assert_eq!(again.crops_read, 1);
+        assert_eq!(
+            (
+                again.files_updated,
+                again.shapes_updated,
+                again.shapes_removed,
+                again.shapes_added
+            ),
+            (0, 0, 0, 0)
+        );
+        assert_eq!(fs::read_to_string(&source_json).unwrap(), before);
+    }
+
+    #[test]
+    fn test_output_dir_keeps_source_folders() {
+        let dir = tempfile::tempdir().unwrap();
+        let crops = dir.path().join("crops");
+        let output = dir.path().join("pasted");
+        fs::create_dir_all(&crops).unwrap();
+
+        let mut records = Vec::new();
+        for folder in ["day", "night"] {
+            let source_json = dir.path().join("source").join(folder).join("frame.json");
+            fs::create_dir_all(source_json.parent().unwrap()).unwrap();
+            let source = labelme(
+                vec![shape("person", vec![[0.0, 0.0], [50.0, 50.0]])],
+                100,
+                100,
+            );
+            fs::write(&source_json, serde_json::to_string(&source).unwrap()).unwrap();
+
+            let crop_image = format!("{}_crop_person_0.jpg", folder);
+            let edited = labelme(
+                vec![shape(folder, vec![[10.0, 10.0], [20.0, 20.0]])],
+                50,
+                50,
+            );
+            fs::write(
+                crops.join(format!("{}_crop_person_0.json", folder)),
+                serde_json::to_string(&edited).unwrap(),
+            )
+            .unwrap();
+            records.push(CropRecord {
+                crop_image,
+                source_json: source_json.to_string_lossy().to_string(),
+                ..CropRecord::default()
+            });
+        }
+        CropReport::from_records(records).write(&crops).unwrap();
+
+        let options = PasteBackOptions {
+            output_dir: Some(output.to_string_lossy().to_string()),
+            ..PasteBackOptions::default()
+        };
+        let result = paste_back_crops(&crops, &options).unwrap();
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.files_updated, 2);
+        for folder in ["day", "night"] {
+            let pasted = read_labelme(&output.join(folder).join("frame.json")).unwrap();
+            assert_eq!(pasted.shapes[1].label, folder);
+        }
+    }
+}
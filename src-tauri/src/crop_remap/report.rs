@@ -0,0 +1,212 @@
+//! Per-crop report written next to the cropped dataset
+//!
+//! Besides the clipping counts, every record keeps the provenance of its crop
+//! (source file, parent index, offset and scale), so edits made on the crops
+//! can be projected back onto the source annotations.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// File name of the report in the output directory
+pub const CROP_REPORT_FILE: &str = "crop_report.json";
+
+/// Why a crop was written
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CropKind {
+    /// A parent with its children
+    #[default]
+    Positive,
+    /// A parent without any required child
+    NegativeParent,
+    /// A random window away from all parents (`parent_index` is unused)
+    Background,
+}
+
+/// Provenance of one written crop and what happened to its children
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CropRecord {
+    /// Cropped image file name (relative to the output directory)
+    pub crop_image: String,
+    /// Source LabelMe JSON file
+    pub source_json: String,
+    /// Index of the parent shape in the source file
+    pub parent_index: usize,
+    /// Children remapped without changes
+    pub children_kept: usize,
+    /// Children cut at the crop border
+    pub children_clipped: usize,
+    /// Children dropped for being (mostly) outside the crop
+    pub children_dropped: usize,
+    /// Image file of the source annotation
+    #[serde(default)]
+    pub source_image: String,
+    /// Crop origin in source image pixels (negative when padded outside)
+    #[serde(default)]
+    pub offset: [f32; 2],
+    /// Crop pixels per source pixel along x and y
+    #[serde(default = "unit_scale")]
+    pub scale: [f32; 2],
+    /// Source shape indices of the crop's shapes, in crop order
+    #[serde(default)]
+    pub child_indices: Vec<usize>,
+    #[serde(default)]
+    pub kind: CropKind,
+}
+
+fn unit_scale() -> [f32; 2] {
+    [1.0, 1.0]
+}
+
+impl Default for CropRecord {
+    fn default() -> Self {
+        Self {
+            crop_image: String::new(),
+            source_json: String::new(),
+            parent_index: 0,
+            children_kept: 0,
+            children_clipped: 0,
+            children_dropped: 0,
+            source_image: String::new(),
+            offset: [0.0, 0.0],
+            scale: unit_scale(),
+            child_indices: Vec::new(),
+            kind: CropKind::Positive,
+        }
+    }
+}
+
+impl CropRecord {
+    /// Map a crop point to source image coordinates
+    pub fn to_source(&self, point: [f32; 2]) -> [f32; 2] {
+        [
+            point[0] / self.scale[0] + self.offset[0],
+            point[1] / self.scale[1] + self.offset[1],
+        ]
+    }
+
+    /// Map a source point to crop coordinates
+    pub fn to_crop(&self, point: [f32; 2]) -> [f32; 2] {
+        [
+            (point[0] - self.offset[0]) * self.scale[0],
+            (point[1] - self.offset[1]) * self.scale[1],
+        ]
+    }
+
+    /// Re-express a crop cut out of the crop `outer` relative to outer's source
+    ///
+    /// Shape indices refer to the outer crop's shapes and are mapped through
+    /// `outer.child_indices`.
+    pub fn nest_in(&mut self, outer: &CropRecord) {
+        let to_source = |index: usize| outer.child_indices.get(index).copied();
+        self.parent_index = to_source(self.parent_index).unwrap_or(outer.parent_index);
+        self.child_indices = self
+            .child_indices
+            .iter()
+            .filter_map(|&index| to_source(index))
+            .collect();
+        self.offset = outer.to_source(self.offset);
+        self.scale = [
+            self.scale[0] * outer.scale[0],
+            self.scale[1] * outer.scale[1],
+        ];
+        self.source_json = outer.source_json.clone();
+        self.source_image = outer.source_image.clone();
+    }
+
+    /// Follow the source file after a paste-back removed or added shapes
+    ///
+    /// `new_index[i]` is the new index of source shape `i` (None when it was
+    /// removed); `added` are the indices of shapes added from this crop.
+    pub fn reindex(&mut self, new_index: &[Option<usize>], added: &[usize]) {
+        if let Some(Some(parent)) = new_index.get(self.parent_index) {
+            self.parent_index = *parent;
+        }
+        self.child_indices = self
+            .child_indices
+            .iter()
+            .filter_map(|&index| new_index.get(index).copied().flatten())
+            .chain(added.iter().copied())
+            .collect();
+    }
+}
+
+/// All crops of one crop-and-remap run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CropReport {
+    pub total_crops: usize,
+    /// Negative parent and background crops among `total_crops`
+    #[serde(default)]
+    pub negative_crops: usize,
+    pub children_clipped: usize,
+    pub children_dropped: usize,
+    pub crops: Vec<CropRecord>,
+}
+
+impl CropReport {
+    /// Build a report, ordering crops by file name for stable output
+    pub fn from_records(mut crops: Vec<CropRecord>) -> Self {
+        crops.sort_by(|a, b| a.crop_image.cmp(&b.crop_image));
+        Self {
+            total_crops: crops.len(),
+            negative_crops: crops
+                .iter()
+                .filter(|c| c.kind != CropKind::Positive)
+                .count(),
+            children_clipped: crops.iter().map(|c| c.children_clipped).sum(),
+            children_dropped: crops.iter().map(|c| c.children_dropped).sum(),
+            crops,
+        }
+    }
+
+    /// Read the report of a crop output directory
+    pub fn load(output_dir: &Path) -> Result<Self, String> {
+        let path = output_dir.join(CROP_REPORT_FILE);
+        let content = fs::read_to_string(&path)
+            .map_err(|e| format!("Failed to read crop report {}: {}", path.display(), e))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse crop report {}: {}", path.display(), e))
+    }
+
+    /// Write the report into `output_dir`
+    pub fn write(&self, output_dir: &Path) -> Result<(), String> {
+        let path = output_dir.join(CROP_REPORT_FILE);
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| format!("Failed to serialize crop report: {}", e))?;
+        fs::write(&path, content)
+            .map_err(|e| format!("Failed to write crop report {}: {}", path.display(), e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_nested_record_maps_to_source() {
+        // Outer crop at (100, 50), resized by 2x, holding source shapes 4 and 7
+        let outer = CropRecord {
+            source_json: "frame.json".to_string(),
+            parent_index: 2,
+            offset: [100.0, 50.0],
+            scale: [2.0, 2.0],
+            child_indices: vec![4, 7],
+            ..CropRecord::default()
+        };
+        let mut inner = CropRecord {
+            parent_index: 0,
+            offset: [20.0, 40.0],
+            child_indices: vec![1],
+            ..CropRecord::default()
+        };
+        inner.nest_in(&outer);
+
+        assert_eq!(inner.parent_index, 4);
+        assert_eq!(inner.child_indices, vec![7]);
+        assert_eq!(inner.offset, [110.0, 70.0]);
+        assert_eq!(inner.source_json, "frame.json");
+        assert_eq!(inner.to_source([10.0, 10.0]), [115.0, 75.0]);
+        assert_eq!(inner.to_crop([115.0, 75.0]), [10.0, 10.0]);
+    }
+}
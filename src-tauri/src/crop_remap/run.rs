@@ -0,0 +1,297 @@
+//! Parallel driver shared by the crop-and-remap entry points
+//!
+//! Walks the LabelMe files of a source directory with Rayon, reports progress,
+//! honours cancellation, and collects per-file errors and crop records into a
+//! typed [`CropRunResult`].
+
+use super::report::{CROP_REPORT_FILE, CropRecord, CropReport};
+use glob::glob;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+// Cancel flags of running crop jobs, keyed by job id
+lazy_static::lazy_static! {
+    static ref CROP_JOBS: Mutex<HashMap<String, Arc<AtomicBool>>> = Mutex::new(HashMap::new());
+}
+
+/// Register a crop job and get its cancel flag
+///
+/// Fails when a job with the same id is still running, so its cancel flag is
+/// never replaced.
+pub fn register_job(job_id: &str) -> Result<Arc<AtomicBool>, String> {
+    let mut jobs = CROP_JOBS.lock().unwrap();
+    if jobs.contains_key(job_id) {
+        return Err(format!("Crop job {} is already running", job_id));
+    }
+    let flag = Arc::new(AtomicBool::new(false));
+    jobs.insert(job_id.to_string(), Arc::clone(&flag));
+    Ok(flag)
+}
+
+/// Request cancellation of a running job; false if no such job is running
+pub fn cancel_job(job_id: &str) -> bool {
+    match CROP_JOBS.lock().unwrap().get(job_id) {
+        Some(flag) => {
+            flag.store(true, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
+}
+
+/// Forget a finished job
+pub fn finish_job(job_id: &str) {
+    CROP_JOBS.lock().unwrap().remove(job_id);
+}
+
+/// A source file that could not be processed
+#[derive(Debug, Clone, Serialize)]
+pub struct CropFileError {
+    pub file: String,
+    pub message: String,
+}
+
+/// Crops written into one output folder
+#[derive(Debug, Clone, Serialize)]
+pub struct CropFolderSummary {
+    /// Folder relative to the output directory ("" for the directory itself)
+    pub folder: String,
+    pub crops: usize,
+    pub negative_crops: usize,
+    pub children_clipped: usize,
+    pub children_dropped: usize,
+}
+
+/// Result of a crop-and-remap run
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CropRunResult {
+    pub output_dir: String,
+    pub total_files: usize,
+    pub processed_files: usize,
+    pub total_crops: usize,
+    pub negative_crops: usize,
+    pub children_clipped: usize,
+    pub children_dropped: usize,
+    pub folders: Vec<CropFolderSummary>,
+    pub errors: Vec<CropFileError>,
+    pub cancelled: bool,
+}
+
+impl CropRunResult {
+    /// Error lines in the `file: message` form used by the string results
+    pub fn error_lines(&self) -> Vec<String> {
+        self.errors
+            .iter()
+            .map(|e| format!("{}: {}", e.file, e.message))
+            .collect()
+    }
+}
+
+/// All LabelMe JSON files below `source_dir`, skipping crop reports of earlier runs
+pub fn find_source_files(source_dir: &Path) -> Result<Vec<PathBuf>, String> {
+    if !source_dir.is_dir() {
+        return Err(format!(
+            "Source directory not found or is not a directory: {}",
+            source_dir.display()
+        ));
+    }
+    // Handle windows paths for glob
+    let pattern = format!(
+        "{}/**/*.json",
+        source_dir.to_string_lossy().replace("\\", "/")
+    );
+    let paths = glob(&pattern)
+        .map_err(|e| format!("Failed to read glob pattern {}: {}", pattern, e))?
+        .filter_map(|entry| match entry {
+            Ok(path) => Some(path),
+            Err(e) => {
+                eprintln!("Error accessing path via glob: {:?}", e);
+                None
+            }
+        })
+        .filter(|path| path.file_name().and_then(|n| n.to_str()) != Some(CROP_REPORT_FILE))
+        .collect();
+    Ok(paths)
+}
+
+/// Crop every file in `json_paths` and write one report per output folder
+///
+/// `process_file` returns the crops written for a file, each tagged with its
+/// folder (one of `folders`, relative to `output_dir`). Files not yet started
+/// when `cancel` is set are skipped and the result is marked cancelled.
+pub fn run_crop_files<F, P>(
+    json_paths: &[PathBuf],
+    output_dir: &Path,
+    folders: &[String],
+    process_file: F,
+    progress: Option<P>,
+    cancel: Option<&AtomicBool>,
+) -> Result<CropRunResult, String>
+where
+    F: Fn(&Path) -> Result<Vec<(String, CropRecord)>, String> + Sync,
+    P: Fn(usize, usize, String) + Sync,
+{
+    use rayon::prelude::*;
+
+    for folder in folders {
+        let dir = output_dir.join(folder);
+        fs::create_dir_all(&dir)
+            .map_err(|e| format!("Failed to create output directory {}: {}", dir.display(), e))?;
+    }
+
+    let total_files = json_paths.len();
+    let is_cancelled = || cancel.is_some_and(|flag| flag.load(Ordering::SeqCst));
+    let processed_files = AtomicUsize::new(0);
+    let records: Mutex<Vec<(String, CropRecord)>> = Mutex::new(Vec::new());
+    let errors: Mutex<Vec<CropFileError>> = Mutex::new(Vec::new());
+
+    json_paths.par_iter().for_each(|json_path| {
+        if is_cancelled() {
+            return;
+        }
+        let file_name = json_path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+
+        match process_file(json_path) {
+            Ok(file_records) => records.lock().unwrap().extend(file_records),
+            Err(e) => {
+                eprintln!(" -> Error processing {}: {}", file_name, e);
+                errors.lock().unwrap().push(CropFileError {
+                    file: file_name,
+                    message: e,
+                });
+            }
+        }
+
+        // Parallel threads may report out of order; the count itself is exact
+        let current = processed_files.fetch_add(1, Ordering::SeqCst) + 1;
+        if let Some(cb) = &progress {
+            cb(
+                current,
+                total_files,
+                format!("Processing file {} of {}", current, total_files),
+            );
+        }
+    });
+
+    let mut by_folder: HashMap<String, Vec<CropRecord>> = HashMap::new();
+    for (folder, record) in records.into_inner().unwrap() {
+        by_folder.entry(folder).or_default().push(record);
+    }
+
+    let mut errors = errors.into_inner().unwrap();
+    errors.sort_by(|a, b| a.file.cmp(&b.file));
+    let mut result = CropRunResult {
+        output_dir: output_dir.to_string_lossy().to_string(),
+        total_files,
+        processed_files: processed_files.into_inner(),
+        cancelled: is_cancelled(),
+        ..CropRunResult::default()
+    };
+
+    for folder in folders {
+        let report = CropReport::from_records(by_folder.remove(folder).unwrap_or_default());
+        if let Err(e) = report.write(&output_dir.join(folder)) {
+            result.errors.push(CropFileError {
+                file: CROP_REPORT_FILE.to_string(),
+                message: e,
+            });
+        }
+        result.total_crops += report.total_crops;
+        result.negative_crops += report.negative_crops;
+        result.children_clipped += report.children_clipped;
+        result.children_dropped += report.children_dropped;
+        result.folders.push(CropFolderSummary {
+            folder: folder.clone(),
+            crops: report.total_crops,
+            negative_crops: report.negative_crops,
+            children_clipped: report.children_clipped,
+            children_dropped: report.children_dropped,
+        });
+    }
+    errors.append(&mut result.errors);
+    result.errors = errors;
+
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cancelled_run_skips_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = vec![dir.path().join("a.json"), dir.path().join("b.json")];
+        let cancel = AtomicBool::new(true);
+
+        let result = run_crop_files(
+            &paths,
+            dir.path(),
+            &[String::new()],
+            |_| Err("should not run".to_string()),
+            None::<fn(usize, usize, String)>,
+            Some(&cancel),
+        )
+        .unwrap();
+
+        assert!(result.cancelled);
+        assert_eq!(result.processed_files, 0);
+        assert!(result.errors.is_empty());
+        assert!(dir.path().join(CROP_REPORT_FILE).exists());
+    }
+
+    #[test]
+    fn test_errors_are_reported_per_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let paths = vec![dir.path().join("good.json"), dir.path().join("bad.json")];
+
+        let result = run_crop_files(
+            &paths,
+            dir.path(),
+            &["crops".to_string()],
+            |path| {
+                if path.ends_with("bad.json") {
+                    return Err("broken".to_string());
+                }
+                let record = CropRecord {
+                    crop_image: "good_crop_person_0.jpg".to_string(),
+                    children_clipped: 1,
+                    ..CropRecord::default()
+                };
+                Ok(vec![("crops".to_string(), record)])
+            },
+            None::<fn(usize, usize, String)>,
+            None,
+        )
+        .unwrap();
+
+        assert!(!result.cancelled);
+        assert_eq!(result.processed_files, 2);
+        assert_eq!(result.total_crops, 1);
+        assert_eq!(result.children_clipped, 1);
+        assert_eq!(result.error_lines(), vec!["bad.json: broken".to_string()]);
+    }
+
+    #[test]
+    fn test_job_ids_are_not_reused_while_running() {
+        let first = register_job("test-reuse").unwrap();
+        assert!(register_job("test-reuse").is_err());
+
+        assert!(cancel_job("test-reuse"));
+        assert!(first.load(Ordering::SeqCst));
+
+        finish_job("test-reuse");
+        assert!(!cancel_job("test-reuse"));
+        let second = register_job("test-reuse").unwrap();
+        assert!(!second.load(Ordering::SeqCst));
+        finish_job("test-reuse");
+    }
+}
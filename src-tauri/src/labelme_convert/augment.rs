@@ -0,0 +1,907 @@
+//! Offline augmentation of the train split
+//!
+//! For every exported train image, `copies` augmented images are written next
+//! to the original together with their converted annotations. Each copy draws
+//! its own parameters from an RNG seeded with the augmentation seed and the
+//! image key, so an export is reproducible regardless of processing order.
+//!
+//! Geometric transforms (flips, 90° turns, arbitrary rotation, scale/crop)
+//! are combined into one affine map that is applied to the pixels and to the
+//! shapes alike:
+//! - polygons are clipped to the image and dropped below `min_visibility`
+//! - rectangles become the clipped box around their transformed corners
+//! - circles stay circles while fully inside, otherwise become clipped polygons
+//! - lines keep their longest visible run
+//! - keypoints (`point`/`points`) are dropped once any point leaves the image,
+//!   and are reordered through `flip_idx` when the image is mirrored
+//! - labels listed in `flip_labels` (e.g. `left_eye` ↔ `right_eye`) are swapped
+//!   when the image is mirrored
+//! - mask shapes are not transformed and are dropped from the copies
+//!
+//! Photometric transforms (brightness, contrast, hue, blur, noise) only touch
+//! the pixels. All drawn parameters are written to `augmentation.json`.
+
+use crate::labelme_convert::config::ConversionConfig;
+use crate::labelme_convert::conversion::{
+    clip_area, clip_circle, clip_polyline, rectangle_to_polygon, ClipOutcome,
+};
+use crate::labelme_convert::pipeline::hash_string;
+use crate::labelme_convert::types::{Shape, ShapeKind};
+use image::{DynamicImage, Rgb, RgbImage};
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// File listing the parameters of every augmented image
+pub const AUGMENT_LOG_FILE: &str = "augmentation.json";
+
+/// Augmentation options for the train split
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AugmentConfig {
+    /// Augmented copies generated per train image
+    pub copies: usize,
+
+    /// RNG seed (None = use the split seed)
+    #[serde(default)]
+    pub seed: Option<u64>,
+
+    /// Probability of a horizontal flip
+    #[serde(default)]
+    pub horizontal_flip: f64,
+
+    /// Probability of a vertical flip
+    #[serde(default)]
+    pub vertical_flip: f64,
+
+    /// Probability of a 90°, 180° or 270° turn
+    #[serde(default)]
+    pub rotate90: f64,
+
+    /// Maximum arbitrary rotation in degrees (drawn from ±rotation)
+    #[serde(default)]
+    pub rotation: f64,
+
+    /// Scale range; values above 1 zoom in (crop), below 1 zoom out (pad)
+    #[serde(default = "default_scale")]
+    pub scale_min: f64,
+    #[serde(default = "default_scale")]
+    pub scale_max: f64,
+
+    /// Maximum brightness shift as a fraction of the full range
+    #[serde(default)]
+    pub brightness: f64,
+
+    /// Maximum contrast change (factor drawn from 1 ± contrast)
+    #[serde(default)]
+    pub contrast: f64,
+
+    /// Maximum hue shift in degrees
+    #[serde(default)]
+    pub hue: f64,
+
+    /// Probability of a gaussian blur
+    #[serde(default)]
+    pub blur: f64,
+
+    /// Maximum blur sigma in pixels
+    #[serde(default = "default_blur_sigma")]
+    pub blur_sigma: f32,
+
+    /// Standard deviation of additive gaussian noise (0-255 scale)
+    #[serde(default)]
+    pub noise: f64,
+
+    /// Keypoint order after a mirroring flip (`flip_idx[i]` = source index of
+    /// keypoint `i`), applied to `points` shapes with matching length
+    #[serde(default)]
+    pub flip_idx: Vec<usize>,
+
+    /// Label pairs swapped after a mirroring flip, applied to every shape
+    /// type; each pair only needs to be listed in one direction
+    #[serde(default)]
+    pub flip_labels: HashMap<String, String>,
+
+    /// Minimum fraction of a shape's area that must remain inside the image
+    #[serde(default)]
+    pub min_visibility: f64,
+}
+
+fn default_scale() -> f64 {
+    1.0
+}
+
+fn default_blur_sigma() -> f32 {
+    1.5
+}
+
+impl Default for AugmentConfig {
+    fn default() -> Self {
+        Self {
+            copies: 1,
+            seed: None,
+            horizontal_flip: 0.0,
+            vertical_flip: 0.0,
+            rotate90: 0.0,
+            rotation: 0.0,
+            scale_min: default_scale(),
+            scale_max: default_scale(),
+            brightness: 0.0,
+            contrast: 0.0,
+            hue: 0.0,
+            blur: 0.0,
+            blur_sigma: default_blur_sigma(),
+            noise: 0.0,
+            flip_idx: Vec::new(),
+            flip_labels: HashMap::new(),
+            min_visibility: 0.0,
+        }
+    }
+}
+
+impl AugmentConfig {
+    /// Validate the augmentation options
+    pub fn validate(&self) -> Result<(), String> {
+        if self.copies == 0 {
+            return Err("Augmentation copies must be at least 1".to_string());
+        }
+
+        let probabilities = [
+            ("horizontal_flip", self.horizontal_flip),
+            ("vertical_flip", self.vertical_flip),
+            ("rotate90", self.rotate90),
+            ("blur", self.blur),
+            ("min_visibility", self.min_visibility),
+        ];
+        for (name, value) in probabilities {
+            if !(0.0..=1.0).contains(&value) {
+                return Err(format!(
+                    "{} must be between 0.0 and 1.0, got {}",
+                    name, value
+                ));
+            }
+        }
+
+        if !(0.0..=180.0).contains(&self.rotation) {
+            return Err(format!(
+                "rotation must be between 0 and 180 degrees, got {}",
+                self.rotation
+            ));
+        }
+        if !self.scale_min.is_finite()
+            || !self.scale_max.is_finite()
+            || self.scale_min <= 0.0
+            || self.scale_min > self.scale_max
+        {
+            return Err(format!(
+                "Scale range must satisfy 0 < scale_min <= scale_max, got {} - {}",
+                self.scale_min, self.scale_max
+            ));
+        }
+        if !(0.0..=1.0).contains(&self.brightness) || !(0.0..=1.0).contains(&self.contrast) {
+            return Err("brightness and contrast must be between 0.0 and 1.0".to_string());
+        }
+        if !(0.0..=180.0).contains(&self.hue) {
+            return Err(format!(
+                "hue must be between 0 and 180 degrees, got {}",
+                self.hue
+            ));
+        }
+        if self.blur > 0.0 && (self.blur_sigma.is_nan() || self.blur_sigma < 0.1) {
+            return Err(format!(
+                "blur_sigma must be at least 0.1, got {}",
+                self.blur_sigma
+            ));
+        }
+        if self.noise.is_nan() || self.noise < 0.0 {
+            return Err(format!("noise must not be negative, got {}", self.noise));
+        }
+
+        let unique: HashSet<usize> = self.flip_idx.iter().copied().collect();
+        if unique.len() != self.flip_idx.len()
+            || self.flip_idx.iter().any(|&i| i >= self.flip_idx.len())
+        {
+            return Err("flip_idx must be a permutation of 0..n".to_string());
+        }
+
+        let mut partners: HashMap<&str, &str> = HashMap::new();
+        for (from, to) in &self.flip_labels {
+            for (label, partner) in [(from, to), (to, from)] {
+                if *partners.entry(label).or_insert(partner) != partner.as_str() {
+                    return Err(format!(
+                        "flip_labels pairs '{}' with more than one label",
+                        label
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Label of a shape in a mirrored image
+    pub fn flipped_label<'a>(&'a self, label: &'a str) -> &'a str {
+        if let Some(partner) = self.flip_labels.get(label) {
+            return partner;
+        }
+        self.flip_labels
+            .iter()
+            .find(|(_, to)| to.as_str() == label)
+            .map_or(label, |(from, _)| from)
+    }
+}
+
+/// Parameters drawn for one augmented image
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct AugmentParams {
+    pub horizontal_flip: bool,
+    pub vertical_flip: bool,
+    /// Clockwise quarter turns (0-3)
+    pub quarter_turns: u8,
+    /// Rotation in degrees
+    pub rotation: f64,
+    pub scale: f64,
+    /// Zoom center offset as a fraction (-1..1) of the allowed range
+    pub shift: (f64, f64),
+    /// Brightness shift as a fraction of the full range
+    pub brightness: f64,
+    pub contrast: f64,
+    /// Hue shift in degrees
+    pub hue: f64,
+    pub blur_sigma: Option<f32>,
+    pub noise: f64,
+    pub noise_seed: u64,
+}
+
+/// Draw the parameters of one augmented image
+pub fn sample_params(config: &AugmentConfig, rng: &mut StdRng) -> AugmentParams {
+    let symmetric = |rng: &mut StdRng, max: f64| {
+        if max > 0.0 {
+            rng.gen_range(-max..=max)
+        } else {
+            0.0
+        }
+    };
+
+    AugmentParams {
+        horizontal_flip: rng.gen_bool(config.horizontal_flip),
+        vertical_flip: rng.gen_bool(config.vertical_flip),
+        quarter_turns: if rng.gen_bool(config.rotate90) {
+            rng.gen_range(1..=3)
+        } else {
+            0
+        },
+        rotation: symmetric(rng, config.rotation),
+        scale: rng.gen_range(config.scale_min..=config.scale_max),
+        shift: (rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0)),
+        brightness: symmetric(rng, config.brightness),
+        contrast: 1.0 + symmetric(rng, config.contrast),
+        hue: symmetric(rng, config.hue),
+        blur_sigma: if rng.gen_bool(config.blur) {
+            Some(rng.gen_range(0.1..=config.blur_sigma.max(0.1)))
+        } else {
+            None
+        },
+        noise: config.noise,
+        noise_seed: rng.r#gen(),
+    }
+}
+
+// ============================================================================
+// Affine geometry
+// ============================================================================
+
+/// 2D affine map: x' = m[0]·x + m[1]·y + m[2], y' = m[3]·x + m[4]·y + m[5]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Affine([f64; 6]);
+
+impl Affine {
+    pub fn identity() -> Self {
+        Affine([1.0, 0.0, 0.0, 0.0, 1.0, 0.0])
+    }
+
+    /// Apply `self`, then `next`
+    pub fn then(self, next: Affine) -> Affine {
+        let [a, b, c, d, e, f] = self.0;
+        let [p, q, r, s, t, u] = next.0;
+        Affine([
+            p * a + q * d,
+            p * b + q * e,
+            p * c + q * f + r,
+            s * a + t * d,
+            s * b + t * e,
+            s * c + t * f + u,
+        ])
+    }
+
+    pub fn apply(&self, (x, y): (f64, f64)) -> (f64, f64) {
+        let m = &self.0;
+        (m[0] * x + m[1] * y + m[2], m[3] * x + m[4] * y + m[5])
+    }
+
+    pub fn determinant(&self) -> f64 {
+        self.0[0] * self.0[4] - self.0[1] * self.0[3]
+    }
+
+    pub fn inverse(&self) -> Affine {
+        let [a, b, c, d, e, f] = self.0;
+        let det = self.determinant();
+        Affine([
+            e / det,
+            -b / det,
+            (b * f - c * e) / det,
+            -d / det,
+            a / det,
+            (c * d - a * f) / det,
+        ])
+    }
+
+    /// Whether the map mirrors the image (left and right swap)
+    pub fn is_mirrored(&self) -> bool {
+        self.determinant() < 0.0
+    }
+}
+
+/// Combined geometric transform and the output size for an image of `width` × `height`
+pub fn geometric_transform(params: &AugmentParams, width: u32, height: u32) -> (Affine, u32, u32) {
+    let (w, h) = (width as f64, height as f64);
+    let mut transform = Affine::identity();
+
+    if params.horizontal_flip {
+        transform = transform.then(Affine([-1.0, 0.0, w, 0.0, 1.0, 0.0]));
+    }
+    if params.vertical_flip {
+        transform = transform.then(Affine([1.0, 0.0, 0.0, 0.0, -1.0, h]));
+    }
+
+    let (out_w, out_h) = match params.quarter_turns % 4 {
+        1 => {
+            transform = transform.then(Affine([0.0, -1.0, h, 1.0, 0.0, 0.0]));
+            (height, width)
+        }
+        2 => {
+            transform = transform.then(Affine([-1.0, 0.0, w, 0.0, -1.0, h]));
+            (width, height)
+        }
+        3 => {
+            transform = transform.then(Affine([0.0, 1.0, 0.0, -1.0, 0.0, w]));
+            (height, width)
+        }
+        _ => (width, height),
+    };
+
+    if params.rotation != 0.0 || params.scale != 1.0 {
+        let (cx, cy) = (out_w as f64 / 2.0, out_h as f64 / 2.0);
+        // Zooming in leaves room to move the crop window around
+        let room = if params.scale > 1.0 {
+            (1.0 - 1.0 / params.scale) / 2.0
+        } else {
+            0.0
+        };
+        let zx = cx + params.shift.0 * room * out_w as f64;
+        let zy = cy + params.shift.1 * room * out_h as f64;
+
+        let (sin, cos) = params.rotation.to_radians().sin_cos();
+        let (a, b) = (params.scale * cos, -params.scale * sin);
+        let (d, e) = (params.scale * sin, params.scale * cos);
+        transform = transform.then(Affine([
+            a,
+            b,
+            cx - a * zx - b * zy,
+            d,
+            e,
+            cy - d * zx - e * zy,
+        ]));
+    }
+
+    (transform, out_w, out_h)
+}
+
+// ============================================================================
+// Pixels
+// ============================================================================
+
+/// Resample an image through `transform` (bilinear, black outside the source)
+pub fn warp_image(image: &RgbImage, transform: &Affine, width: u32, height: u32) -> RgbImage {
+    let inverse = transform.inverse();
+    let (src_w, src_h) = (image.width() as i64, image.height() as i64);
+
+    RgbImage::from_fn(width, height, |x, y| {
+        let (sx, sy) = inverse.apply((x as f64 + 0.5, y as f64 + 0.5));
+        let (sx, sy) = (sx - 0.5, sy - 0.5);
+        let (x0, y0) = (sx.floor(), sy.floor());
+        let (fx, fy) = (sx - x0, sy - y0);
+
+        let mut sum = [0.0f64; 3];
+        for (dx, dy, weight) in [
+            (0, 0, (1.0 - fx) * (1.0 - fy)),
+            (1, 0, fx * (1.0 - fy)),
+            (0, 1, (1.0 - fx) * fy),
+            (1, 1, fx * fy),
+        ] {
+            let (px, py) = (x0 as i64 + dx, y0 as i64 + dy);
+            if weight > 0.0 && px >= 0 && py >= 0 && px < src_w && py < src_h {
+                let pixel = image.get_pixel(px as u32, py as u32);
+                for (channel, value) in sum.iter_mut().zip(pixel.0) {
+                    *channel += weight * value as f64;
+                }
+            }
+        }
+        Rgb(sum.map(|v| v.round().clamp(0.0, 255.0) as u8))
+    })
+}
+
+/// Apply brightness, contrast and hue changes in place
+pub fn adjust_colors(image: &mut RgbImage, brightness: f64, contrast: f64, hue: f64) {
+    if brightness == 0.0 && contrast == 1.0 && hue == 0.0 {
+        return;
+    }
+
+    // Luminance-preserving rotation around the gray axis
+    let (sin, cos) = hue.to_radians().sin_cos();
+    let third = (1.0 - cos) / 3.0;
+    let root = (1.0f64 / 3.0).sqrt() * sin;
+    let (m0, m1, m2) = (cos + third, third - root, third + root);
+    let shift = brightness * 255.0;
+
+    for pixel in image.pixels_mut() {
+        let [r, g, b] = pixel.0.map(|v| v as f64);
+        let rotated = [
+            r * m0 + g * m1 + b * m2,
+            r * m2 + g * m0 + b * m1,
+            r * m1 + g * m2 + b * m0,
+        ];
+        pixel.0 = rotated.map(|v| {
+            ((v - 128.0) * contrast + 128.0 + shift)
+                .round()
+                .clamp(0.0, 255.0) as u8
+        });
+    }
+}
+
+/// Add gaussian noise with standard deviation `sigma` in place
+pub fn add_noise(image: &mut RgbImage, sigma: f64, seed: u64) {
+    if sigma <= 0.0 {
+        return;
+    }
+
+    let mut rng = StdRng::seed_from_u64(seed);
+    for pixel in image.pixels_mut() {
+        for channel in pixel.0.iter_mut() {
+            // Box–Muller transform
+            let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+            let u2: f64 = rng.r#gen();
+            let normal = (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos();
+            *channel = (*channel as f64 + normal * sigma).round().clamp(0.0, 255.0) as u8;
+        }
+    }
+}
+
+// ============================================================================
+// Shapes
+// ============================================================================
+
+/// Outcome of transforming one shape
+pub type ShapeOutcome = ClipOutcome<Shape>;
+
+/// Transform a shape into an augmented image of `width` × `height`
+pub fn transform_shape(
+    shape: &Shape,
+    transform: &Affine,
+    width: u32,
+    height: u32,
+    config: &AugmentConfig,
+) -> ShapeOutcome {
+    let (w, h) = (width as f64, height as f64);
+    let mapped: Vec<(f64, f64)> = shape.points.iter().map(|&p| transform.apply(p)).collect();
+    let label = if transform.is_mirrored() {
+        config.flipped_label(&shape.label)
+    } else {
+        &shape.label
+    };
+    let with_points = |points: Vec<(f64, f64)>, shape_type: &str| Shape {
+        label: label.to_string(),
+        points,
+        shape_type: shape_type.to_string(),
+        ..shape.clone()
+    };
+
+    // 2-point polygons of bbox datasets are boxes
+    let kind = match ShapeKind::of(shape) {
+        ShapeKind::Polygon | ShapeKind::Other if shape.points.len() == 2 => ShapeKind::Rectangle,
+        kind => kind,
+    };
+
+    match kind {
+        ShapeKind::Polygon | ShapeKind::Other => {
+            clip_area(mapped, w, h, config.min_visibility, |points| {
+                with_points(points, &shape.shape_type)
+            })
+        }
+        ShapeKind::Rectangle => {
+            let corners: Vec<(f64, f64)> = rectangle_to_polygon(&shape.points)
+                .into_iter()
+                .map(|p| transform.apply(p))
+                .collect();
+            clip_area(corners, w, h, config.min_visibility, |points| {
+                let min_x = points.iter().map(|p| p.0).fold(f64::MAX, f64::min);
+                let max_x = points.iter().map(|p| p.0).fold(f64::MIN, f64::max);
+                let min_y = points.iter().map(|p| p.1).fold(f64::MAX, f64::min);
+                let max_y = points.iter().map(|p| p.1).fold(f64::MIN, f64::max);
+                with_points(vec![(min_x, min_y), (max_x, max_y)], "rectangle")
+            })
+        }
+        ShapeKind::Circle if mapped.len() >= 2 => clip_circle(
+            mapped[0],
+            mapped[1],
+            w,
+            h,
+            config.min_visibility,
+            with_points,
+        ),
+        ShapeKind::Line | ShapeKind::LineStrip => {
+            let clipped = clip_polyline(&mapped, w, h);
+            if clipped.len() < 2 {
+                ShapeOutcome::Dropped
+            } else if clipped == mapped {
+                ShapeOutcome::Kept(with_points(clipped, &shape.shape_type))
+            } else {
+                let shape_type = if clipped.len() == 2 {
+                    &shape.shape_type
+                } else {
+                    "linestrip"
+                };
+                ShapeOutcome::Clipped(with_points(clipped, shape_type))
+            }
+        }
+        ShapeKind::Point => {
+            let inside = |p: &(f64, f64)| p.0 >= 0.0 && p.1 >= 0.0 && p.0 <= w && p.1 <= h;
+            if mapped.is_empty() || !mapped.iter().all(inside) {
+                return ShapeOutcome::Dropped;
+            }
+            let points = if transform.is_mirrored() && config.flip_idx.len() == mapped.len() {
+                config.flip_idx.iter().map(|&i| mapped[i]).collect()
+            } else {
+                mapped
+            };
+            ShapeOutcome::Kept(with_points(points, &shape.shape_type))
+        }
+        ShapeKind::Circle | ShapeKind::Mask => ShapeOutcome::Dropped,
+    }
+}
+
+// ============================================================================
+// Augmenter
+// ============================================================================
+
+/// Summary of the generated augmentations
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct AugmentReport {
+    pub seed: u64,
+    pub copies_per_image: usize,
+    /// Train images that were augmented
+    pub source_images: usize,
+    /// Augmented images written
+    pub augmented_images: usize,
+    /// Annotations written for augmented images
+    pub augmented_annotations: usize,
+    /// Shapes cut at the image border
+    pub clipped_shapes: usize,
+    /// Shapes that left the image, fell below `min_visibility` or were masks
+    pub dropped_shapes: usize,
+}
+
+/// One augmented image in the log file
+#[derive(Debug, Clone, Serialize)]
+pub struct AugmentRecord {
+    pub image: String,
+    pub source: String,
+    pub copy: usize,
+    pub params: AugmentParams,
+}
+
+/// An augmented image with its transformed shapes
+#[derive(Debug, Clone)]
+pub struct AugmentedImage {
+    pub image: RgbImage,
+    pub shapes: Vec<Shape>,
+}
+
+/// Generates augmented copies and keeps the report and parameter log
+#[derive(Debug, Clone)]
+pub struct Augmenter {
+    config: AugmentConfig,
+    pub report: AugmentReport,
+    records: Vec<AugmentRecord>,
+}
+
+impl Augmenter {
+    /// Create an augmenter; `default_seed` is used when the config has no seed
+    pub fn new(config: &AugmentConfig, default_seed: u64) -> Self {
+        let seed = config.seed.unwrap_or(default_seed);
+        Self {
+            config: config.clone(),
+            report: AugmentReport {
+                seed,
+                copies_per_image: config.copies,
+                ..Default::default()
+            },
+            records: Vec::new(),
+        }
+    }
+
+    /// Augmenter for an export, or None when augmentation is not configured
+    pub fn from_config(config: &ConversionConfig) -> Option<Self> {
+        config
+            .augment
+            .as_ref()
+            .map(|augment| Self::new(augment, config.seed))
+    }
+
+    pub fn copies(&self) -> usize {
+        self.config.copies
+    }
+
+    /// Generate augmented copy `copy` (1-based) of an image and its shapes
+    ///
+    /// `output_name` is the file name the caller writes the image to.
+    pub fn augment(
+        &mut self,
+        image_key: &str,
+        copy: usize,
+        image: &DynamicImage,
+        shapes: &[Shape],
+        output_name: &str,
+    ) -> AugmentedImage {
+        let seed = self.report.seed ^ hash_string(&format!("{}#{}", image_key, copy));
+        let mut rng = StdRng::seed_from_u64(seed);
+        let params = sample_params(&self.config, &mut rng);
+
+        let source = image.to_rgb8();
+        let (transform, width, height) =
+            geometric_transform(&params, source.width(), source.height());
+        let mut pixels = if transform == Affine::identity() {
+            source
+        } else {
+            warp_image(&source, &transform, width, height)
+        };
+        adjust_colors(&mut pixels, params.brightness, params.contrast, params.hue);
+        if let Some(sigma) = params.blur_sigma {
+            pixels = imageproc::filter::gaussian_blur_f32(&pixels, sigma);
+        }
+        add_noise(&mut pixels, params.noise, params.noise_seed);
+
+        let mut augmented = Vec::with_capacity(shapes.len());
+        for shape in shapes {
+            match transform_shape(shape, &transform, width, height, &self.config) {
+                ShapeOutcome::Kept(shape) => augmented.push(shape),
+                ShapeOutcome::Clipped(shape) => {
+                    self.report.clipped_shapes += 1;
+                    augmented.push(shape);
+                }
+                ShapeOutcome::Dropped => self.report.dropped_shapes += 1,
+            }
+        }
+
+        if copy == 1 {
+            self.report.source_images += 1;
+        }
+        self.report.augmented_images += 1;
+        self.records.push(AugmentRecord {
+            image: output_name.to_string(),
+            source: image_key.to_string(),
+            copy,
+            params,
+        });
+
+        AugmentedImage {
+            image: pixels,
+            shapes: augmented,
+        }
+    }
+
+    /// Count annotations written for an augmented image
+    pub fn add_annotations(&mut self, count: usize) {
+        self.report.augmented_annotations += count;
+    }
+
+    /// Write the report and every drawn parameter set to `path`
+    pub fn write_log(&self, path: &Path) -> Result<(), String> {
+        let log = serde_json::json!({
+            "summary": self.report,
+            "config": self.config,
+            "images": self.records,
+        });
+        let content = serde_json::to_string_pretty(&log)
+            .map_err(|e| format!("Failed to serialize augmentation log: {}", e))?;
+        std::fs::write(path, content)
+            .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+    }
+}
+
+/// File name of an augmented copy, keeping the source extension when it can be encoded
+pub fn augmented_file_name(stem: &str, extension: &str, copy: usize) -> String {
+    let extension = extension.to_lowercase();
+    let extension = match extension.as_str() {
+        "jpg" | "jpeg" | "png" | "bmp" | "tif" | "tiff" | "webp" => extension.as_str(),
+        _ => "png",
+    };
+    format!("{}_aug{}.{}", stem, copy, extension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(shape_type: &str, points: Vec<(f64, f64)>) -> Shape {
+        Shape {
+            label: "a".to_string(),
+            points,
+            group_id: None,
+            shape_type: shape_type.to_string(),
+            description: None,
+            mask: None,
+            flags: None,
+        }
+    }
+
+    fn params() -> AugmentParams {
+        let config = AugmentConfig::default();
+        sample_params(&config, &mut StdRng::seed_from_u64(0))
+    }
+
+    #[test]
+    fn test_affine_flip_and_turns() {
+        let mut p = params();
+        p.horizontal_flip = true;
+        let (t, w, h) = geometric_transform(&p, 100, 50);
+        assert_eq!((w, h), (100, 50));
+        assert_eq!(t.apply((10.0, 5.0)), (90.0, 5.0));
+        assert!(t.is_mirrored());
+
+        p.horizontal_flip = false;
+        p.quarter_turns = 1;
+        let (t, w, h) = geometric_transform(&p, 100, 50);
+        assert_eq!((w, h), (50, 100));
+        assert_eq!(t.apply((0.0, 0.0)), (50.0, 0.0));
+        assert_eq!(t.apply((100.0, 50.0)), (0.0, 100.0));
+        let back = t.inverse().apply(t.apply((12.0, 34.0)));
+        assert!((back.0 - 12.0).abs() < 1e-9 && (back.1 - 34.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_warp_quarter_turn_is_exact() {
+        let image = RgbImage::from_fn(3, 2, |x, y| Rgb([(x * 10 + y) as u8, 0, 0]));
+        let mut p = params();
+        p.quarter_turns = 1;
+        let (t, w, h) = geometric_transform(&p, 3, 2);
+        let turned = warp_image(&image, &t, w, h);
+        // Clockwise: the bottom-left source pixel ends up top-left
+        assert_eq!(turned.get_pixel(0, 0).0[0], 1);
+        assert_eq!(turned.get_pixel(1, 0).0[0], 0);
+        assert_eq!(turned.get_pixel(0, 2).0[0], 21);
+    }
+
+    #[test]
+    fn test_transform_shapes_with_clipping() {
+        let config = AugmentConfig {
+            flip_idx: vec![1, 0, 2],
+            flip_labels: HashMap::from([("left_eye".to_string(), "right_eye".to_string())]),
+            ..Default::default()
+        };
+        let mirror = Affine([-1.0, 0.0, 100.0, 0.0, 1.0, 0.0]);
+
+        // Separate left/right shapes swap labels in both directions, only when mirrored
+        let mut eye = shape("point", vec![(10.0, 10.0)]);
+        for (label, flipped) in [
+            ("left_eye", "right_eye"),
+            ("right_eye", "left_eye"),
+            ("a", "a"),
+        ] {
+            eye.label = label.to_string();
+            match transform_shape(&eye, &mirror, 100, 100, &config) {
+                ShapeOutcome::Kept(s) => assert_eq!(s.label, flipped),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        match transform_shape(&eye, &Affine::identity(), 100, 100, &config) {
+            ShapeOutcome::Kept(s) => assert_eq!(s.label, eye.label),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Keypoints are mirrored and left/right swapped
+        let keypoints = shape("points", vec![(10.0, 10.0), (30.0, 10.0), (20.0, 20.0)]);
+        match transform_shape(&keypoints, &mirror, 100, 100, &config) {
+            ShapeOutcome::Kept(s) => {
+                assert_eq!(s.points, vec![(70.0, 10.0), (90.0, 10.0), (80.0, 20.0)])
+            }
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // Box half outside after a shift is clipped to the border
+        let shift = Affine([1.0, 0.0, 80.0, 0.0, 1.0, 0.0]);
+        let rect = shape("rectangle", vec![(0.0, 0.0), (40.0, 40.0)]);
+        match transform_shape(&rect, &shift, 100, 100, &config) {
+            ShapeOutcome::Clipped(s) => assert_eq!(s.points, vec![(80.0, 0.0), (100.0, 40.0)]),
+            other => panic!("unexpected {:?}", other),
+        }
+
+        // ...and dropped when too little of it stays visible
+        let strict = AugmentConfig {
+            min_visibility: 0.6,
+            ..Default::default()
+        };
+        assert!(matches!(
+            transform_shape(&rect, &shift, 100, 100, &strict),
+            ShapeOutcome::Dropped
+        ));
+    }
+
+    #[test]
+    fn test_augment_is_deterministic() {
+        let config = AugmentConfig {
+            copies: 2,
+            seed: Some(7),
+            horizontal_flip: 0.5,
+            rotation: 15.0,
+            scale_min: 0.8,
+            scale_max: 1.2,
+            brightness: 0.2,
+            noise: 3.0,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        let image = DynamicImage::ImageRgb8(RgbImage::from_pixel(16, 12, Rgb([100, 150, 200])));
+        let shapes = vec![shape("polygon", vec![(2.0, 2.0), (10.0, 2.0), (10.0, 8.0)])];
+
+        let mut first = Augmenter::new(&config, 42);
+        let mut second = Augmenter::new(&config, 42);
+        let a = first.augment("img.jpg", 1, &image, &shapes, "img_aug1.jpg");
+        let b = second.augment("img.jpg", 1, &image, &shapes, "img_aug1.jpg");
+        assert_eq!(a.image, b.image);
+        assert_eq!(a.shapes[0].points, b.shapes[0].points);
+        assert_eq!(first.records[0].params, second.records[0].params);
+        assert_eq!(first.report.seed, 7);
+        assert_eq!(first.report.augmented_images, 1);
+    }
+
+    #[test]
+    fn test_augment_config_validate() {
+        assert!(AugmentConfig::default().validate().is_ok());
+        let bad = [
+            AugmentConfig {
+                copies: 0,
+                ..Default::default()
+            },
+            AugmentConfig {
+                horizontal_flip: 1.5,
+                ..Default::default()
+            },
+            AugmentConfig {
+                scale_min: 1.2,
+                scale_max: 1.0,
+                ..Default::default()
+            },
+            AugmentConfig {
+                scale_max: f64::INFINITY,
+                ..Default::default()
+            },
+            AugmentConfig {
+                flip_idx: vec![0, 0],
+                ..Default::default()
+            },
+            AugmentConfig {
+                flip_labels: HashMap::from([
+                    ("left".to_string(), "right".to_string()),
+                    ("right".to_string(), "center".to_string()),
+                ]),
+                ..Default::default()
+            },
+        ];
+        for config in bad {
+            assert!(config.validate().is_err());
+        }
+    }
+}
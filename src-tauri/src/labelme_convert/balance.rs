@@ -0,0 +1,414 @@
+//! Export-time class balancing
+//!
+//! Balancing is planned before any file is written and only touches the train
+//! split, so validation and test metrics stay comparable between exports:
+//! - images are dropped once every class they contain has reached
+//!   `max_images_per_class`
+//! - images containing rare classes are repeated until each class reaches
+//!   `oversample_target` instances (repeats are list entries, not file copies,
+//!   wherever the output format allows it)
+//! - a fraction of background images (no exported labels) is dropped
+
+use crate::labelme_convert::config::ConversionConfig;
+use crate::labelme_convert::io::{find_background_images, read_labelme_json, resolve_image_path};
+use crate::labelme_convert::pipeline::Split;
+use crate::labelme_convert::split::{resolve_split, SplitPlan};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Balancing options for the train split
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BalanceConfig {
+    /// Maximum number of train images per class (None = no cap)
+    #[serde(default)]
+    pub max_images_per_class: Option<usize>,
+
+    /// Repeat images of rarer classes until each class has this many
+    /// train instances (None = no oversampling)
+    #[serde(default)]
+    pub oversample_target: Option<usize>,
+
+    /// Fraction of train background images to drop (0.0 - 1.0)
+    #[serde(default)]
+    pub background_drop_fraction: f32,
+}
+
+impl BalanceConfig {
+    /// Validate the balancing options
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_images_per_class == Some(0) {
+            return Err("max_images_per_class must be at least 1".to_string());
+        }
+        if self.oversample_target == Some(0) {
+            return Err("oversample_target must be at least 1".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.background_drop_fraction) {
+            return Err(format!(
+                "background_drop_fraction must be between 0.0 and 1.0, got {}",
+                self.background_drop_fraction
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Train split counts of one class before and after balancing
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClassBalance {
+    pub label: String,
+    pub images_before: usize,
+    pub images_after: usize,
+    pub instances_before: usize,
+    pub instances_after: usize,
+}
+
+/// Summary of the applied balancing
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct BalanceReport {
+    /// Per-class distribution, sorted by label
+    pub classes: Vec<ClassBalance>,
+    /// Annotated images dropped by the per-class cap
+    pub dropped_images: usize,
+    /// Background images dropped
+    pub dropped_backgrounds: usize,
+    /// Images referenced more than once
+    pub repeated_images: usize,
+    /// Additional references created by oversampling
+    pub extra_copies: usize,
+}
+
+/// Precomputed balancing decisions for every train image
+#[derive(Debug, Clone, Default)]
+pub struct BalancePlan {
+    /// Image keys that are not exported
+    dropped_images: HashSet<String>,
+    /// JSON files whose image is not exported
+    dropped_json: HashSet<PathBuf>,
+    /// Image key → number of references (only entries > 1)
+    copies: HashMap<String, usize>,
+    /// Summary of the applied balancing
+    pub report: BalanceReport,
+}
+
+impl BalancePlan {
+    /// Whether the image is left out of the export
+    pub fn is_dropped(&self, image_key: &str) -> bool {
+        self.dropped_images.contains(image_key)
+    }
+
+    /// Whether the image of this JSON file is left out of the export
+    pub fn is_dropped_json(&self, json_path: &Path) -> bool {
+        self.dropped_json.contains(json_path)
+    }
+
+    /// Number of times the image is referenced in the train split (at least 1)
+    pub fn copies(&self, image_key: &str) -> usize {
+        self.copies.get(image_key).copied().unwrap_or(1)
+    }
+
+    /// Whether any image is referenced more than once
+    pub fn has_repeats(&self) -> bool {
+        !self.copies.is_empty()
+    }
+}
+
+/// A train image considered for balancing
+#[derive(Debug, Clone, Default)]
+pub struct BalanceEntry {
+    pub image_key: String,
+    pub json_paths: Vec<PathBuf>,
+    /// Label → instance count of exported labels
+    pub labels: HashMap<String, usize>,
+}
+
+/// Build a balancing plan for the train split
+///
+/// Returns `None` when balancing is not configured.
+pub fn plan_balance(
+    config: &ConversionConfig,
+    json_files: &[PathBuf],
+    split_plan: Option<&SplitPlan>,
+) -> Option<BalancePlan> {
+    let balance = config.balance.as_ref()?;
+
+    let mut entries: Vec<BalanceEntry> = Vec::new();
+    let mut index: HashMap<String, usize> = HashMap::new();
+    let mut seen: HashSet<String> = HashSet::new();
+
+    for json_path in json_files {
+        let Ok(annotation) = read_labelme_json(json_path) else {
+            continue;
+        };
+        let image_path = resolve_image_path(json_path, &annotation.image_path);
+        let image_key = image_path.to_string_lossy().to_string();
+
+        // Duplicate JSONs for one image share the decision of the first one
+        if let Some(&idx) = index.get(&image_key) {
+            entries[idx].json_paths.push(json_path.clone());
+            continue;
+        }
+        if !seen.insert(image_key.clone()) {
+            continue;
+        }
+        if resolve_split(config, split_plan, &image_key) != Split::Train {
+            continue;
+        }
+
+        let mut labels: HashMap<String, usize> = HashMap::new();
+        for shape in &annotation.shapes {
+            if config.label_list.is_empty() || config.label_list.contains(&shape.label) {
+                *labels.entry(shape.label.clone()).or_insert(0) += 1;
+            }
+        }
+
+        index.insert(image_key.clone(), entries.len());
+        entries.push(BalanceEntry {
+            image_key,
+            json_paths: vec![json_path.clone()],
+            labels,
+        });
+    }
+
+    if config.include_background {
+        for image_path in find_background_images(&config.input_dir, &seen) {
+            let image_key = image_path.to_string_lossy().to_string();
+            if resolve_split(config, split_plan, &image_key) == Split::Train {
+                entries.push(BalanceEntry {
+                    image_key,
+                    ..Default::default()
+                });
+            }
+        }
+    }
+
+    Some(balance_entries(balance, entries, config.seed))
+}
+
+/// Apply the balancing rules to a list of train images
+pub fn balance_entries(
+    balance: &BalanceConfig,
+    mut entries: Vec<BalanceEntry>,
+    seed: u64,
+) -> BalancePlan {
+    // Sort first so the seeded shuffle is reproducible regardless of input order
+    entries.sort_by(|a, b| a.image_key.cmp(&b.image_key));
+    let mut rng = StdRng::seed_from_u64(seed);
+    entries.shuffle(&mut rng);
+
+    let mut plan = BalancePlan::default();
+    let mut kept: Vec<&BalanceEntry> = Vec::new();
+
+    // Undersample: keep an image while any of its classes is below the cap
+    let mut kept_images: HashMap<&str, usize> = HashMap::new();
+    let mut backgrounds: Vec<&BalanceEntry> = Vec::new();
+    for entry in &entries {
+        if entry.labels.is_empty() {
+            backgrounds.push(entry);
+            continue;
+        }
+        let keep = match balance.max_images_per_class {
+            Some(cap) => entry
+                .labels
+                .keys()
+                .any(|label| kept_images.get(label.as_str()).copied().unwrap_or(0) < cap),
+            None => true,
+        };
+        if keep {
+            for label in entry.labels.keys() {
+                *kept_images.entry(label.as_str()).or_insert(0) += 1;
+            }
+            kept.push(entry);
+        } else {
+            drop_entry(&mut plan, entry);
+            plan.report.dropped_images += 1;
+        }
+    }
+
+    // Drop a fraction of the background images
+    let drop_count = (backgrounds.len() as f32 * balance.background_drop_fraction).round() as usize;
+    for (i, entry) in backgrounds.into_iter().enumerate() {
+        if i < drop_count {
+            drop_entry(&mut plan, entry);
+            plan.report.dropped_backgrounds += 1;
+        } else {
+            kept.push(entry);
+        }
+    }
+
+    // Oversample: repeat images by the factor of their rarest class
+    let mut instances: HashMap<&str, usize> = HashMap::new();
+    for entry in &kept {
+        for (label, count) in &entry.labels {
+            *instances.entry(label.as_str()).or_insert(0) += count;
+        }
+    }
+    if let Some(target) = balance.oversample_target {
+        for entry in &kept {
+            let copies = entry
+                .labels
+                .keys()
+                .map(|label| {
+                    let count = instances[label.as_str()];
+                    if count < target {
+                        target.div_ceil(count)
+                    } else {
+                        1
+                    }
+                })
+                .max()
+                .unwrap_or(1);
+            if copies > 1 {
+                plan.copies.insert(entry.image_key.clone(), copies);
+                plan.report.repeated_images += 1;
+                plan.report.extra_copies += copies - 1;
+            }
+        }
+    }
+
+    plan.report.classes = class_distribution(&entries, &kept, &plan);
+    plan
+}
+
+fn drop_entry(plan: &mut BalancePlan, entry: &BalanceEntry) {
+    plan.dropped_images.insert(entry.image_key.clone());
+    plan.dropped_json.extend(entry.json_paths.iter().cloned());
+}
+
+fn class_distribution(
+    before: &[BalanceEntry],
+    after: &[&BalanceEntry],
+    plan: &BalancePlan,
+) -> Vec<ClassBalance> {
+    let mut classes: HashMap<&str, ClassBalance> = HashMap::new();
+
+    for entry in before {
+        for (label, count) in &entry.labels {
+            let class = classes
+                .entry(label.as_str())
+                .or_insert_with(|| ClassBalance {
+                    label: label.clone(),
+                    ..Default::default()
+                });
+            class.images_before += 1;
+            class.instances_before += count;
+        }
+    }
+
+    for entry in after {
+        let copies = plan.copies(&entry.image_key);
+        for (label, count) in &entry.labels {
+            if let Some(class) = classes.get_mut(label.as_str()) {
+                class.images_after += copies;
+                class.instances_after += count * copies;
+            }
+        }
+    }
+
+    let mut classes: Vec<ClassBalance> = classes.into_values().collect();
+    classes.sort_by(|a, b| a.label.cmp(&b.label));
+    classes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(key: &str, labels: &[(&str, usize)]) -> BalanceEntry {
+        BalanceEntry {
+            image_key: key.to_string(),
+            json_paths: vec![PathBuf::from(format!("{}.json", key))],
+            labels: labels.iter().map(|(l, c)| (l.to_string(), *c)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_cap_keeps_images_with_rare_classes() {
+        let mut entries: Vec<BalanceEntry> = (0..10)
+            .map(|i| entry(&format!("car{}", i), &[("car", 1)]))
+            .collect();
+        entries.push(entry("mixed", &[("car", 1), ("bike", 1)]));
+
+        let balance = BalanceConfig {
+            max_images_per_class: Some(3),
+            ..Default::default()
+        };
+        let plan = balance_entries(&balance, entries, 42);
+
+        assert!(!plan.is_dropped("mixed"));
+        let car = plan
+            .report
+            .classes
+            .iter()
+            .find(|c| c.label == "car")
+            .unwrap();
+        assert_eq!(car.images_before, 11);
+        // "mixed" may be kept after the cap was reached because of "bike"
+        assert!(car.images_after <= 4);
+        assert_eq!(plan.report.dropped_images, 11 - car.images_after);
+        assert_eq!(plan.dropped_json.len(), plan.report.dropped_images);
+    }
+
+    #[test]
+    fn test_oversample_repeats_rare_images() {
+        let mut entries: Vec<BalanceEntry> = (0..6)
+            .map(|i| entry(&format!("ok{}", i), &[("ok", 2)]))
+            .collect();
+        entries.push(entry("defect", &[("defect", 3)]));
+
+        let balance = BalanceConfig {
+            oversample_target: Some(12),
+            ..Default::default()
+        };
+        let plan = balance_entries(&balance, entries, 1);
+
+        assert_eq!(plan.copies("defect"), 4);
+        assert_eq!(plan.copies("ok0"), 1);
+        assert!(plan.has_repeats());
+
+        let defect = plan
+            .report
+            .classes
+            .iter()
+            .find(|c| c.label == "defect")
+            .unwrap();
+        assert_eq!(defect.instances_before, 3);
+        assert_eq!(defect.instances_after, 12);
+    }
+
+    #[test]
+    fn test_background_drop_fraction() {
+        let entries: Vec<BalanceEntry> = (0..10).map(|i| entry(&format!("bg{}", i), &[])).collect();
+        let balance = BalanceConfig {
+            background_drop_fraction: 0.3,
+            ..Default::default()
+        };
+        let plan = balance_entries(&balance, entries, 5);
+
+        assert_eq!(plan.report.dropped_backgrounds, 3);
+        assert_eq!(
+            (0..10)
+                .filter(|i| plan.is_dropped(&format!("bg{}", i)))
+                .count(),
+            3
+        );
+    }
+
+    #[test]
+    fn test_balance_config_validate() {
+        assert!(BalanceConfig::default().validate().is_ok());
+        let invalid = BalanceConfig {
+            background_drop_fraction: 1.5,
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+        let invalid = BalanceConfig {
+            max_images_per_class: Some(0),
+            ..Default::default()
+        };
+        assert!(invalid.validate().is_err());
+    }
+}
@@ -5,22 +5,25 @@
 //
 // Adapted and modified for dataset-app
 
+use crate::labelme_convert::augment::{augmented_file_name, Augmenter, AUGMENT_LOG_FILE};
+use crate::labelme_convert::balance::{plan_balance, BalancePlan};
 use crate::labelme_convert::config::ConversionConfig;
 use crate::labelme_convert::conversion::{
-    calculate_coco_bbox, calculate_polygon_area, flatten_polygon, rectangle_to_polygon,
+    calculate_coco_bbox, calculate_polygon_area, flatten_polygon, PolygonRefiner,
 };
-use crate::labelme_convert::detection::validate_shape_points;
+use crate::labelme_convert::geometry::{shape_geometry, ShapeGeometry};
 use crate::labelme_convert::io::{
     copy_image, extract_embedded_image, find_background_images, find_json_files, read_labelme_json,
     resolve_image_path, setup_coco_directories, write_file,
 };
 use crate::labelme_convert::pipeline::{
-    determine_split, hash_string, ConversionPipeline, FileType, OutputDirectories,
+    ConversionPipeline, FileType, OutputDirectories,
     ProcessedFileResult, ProcessingContext, Split,
 };
+use crate::labelme_convert::split::{plan_splits, resolve_split, SplitPlan};
 use crate::labelme_convert::types::{
-    CocoOutputDirs, ConversionResult, InputAnnotationFormat, InvalidAnnotation, ProcessingStats,
-    Shape,
+    CocoOutputDirs, ConversionResult, InputAnnotationFormat, InvalidAnnotation, InvalidReason,
+    ProcessingStats, Shape, ShapeKind, ShapeTypeReport,
 };
 use serde::{Deserialize, Serialize};
 use std::collections::{HashMap, HashSet};
@@ -107,6 +110,11 @@ pub struct CocoAnnotation {
     pub iscrowd: u32,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub segmentation: Option<Vec<Vec<f64>>>,
+    /// Flattened [x, y, visibility] triplets (point shapes in keypoint mode)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub keypoints: Option<Vec<f64>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub num_keypoints: Option<usize>,
 }
 
 /// Complete COCO dataset file
@@ -175,8 +183,7 @@ impl ConversionPipeline for CocoPipeline {
         context.mark_image_processed(image_key.clone());
 
         // Determine split
-        let path_hash = hash_string(&image_key);
-        let split = determine_split(path_hash, config.val_size, config.test_size);
+        let split = resolve_split(config, context.split_plan.as_ref(), &image_key);
 
         // Get output directory for this split
         let images_dir = output_dirs.get_output_dir(split, FileType::Image);
@@ -222,18 +229,20 @@ impl ConversionPipeline for CocoPipeline {
 
         for shape in &annotation.shapes {
             if context.label_map.contains_key(&shape.label) {
-                // Validate points count based on detected input format
-                if let Err(reason) = validate_shape_points(shape, input_format) {
-                    invalid_annotations.push(InvalidAnnotation {
-                        file: json_file_name.clone(),
-                        label: shape.label.clone(),
-                        reason: reason.as_str(),
-                        shape_type: shape.shape_type.clone(),
-                        points_count: shape.points.len(),
-                    });
-                    skipped_count += 1;
-                } else {
-                    annotation_count += 1;
+                // Validate points count based on shape type and detected input format
+                match shape_geometry(shape, input_format, &config.shape_options) {
+                    Ok(ShapeGeometry::Skipped) => skipped_count += 1,
+                    Ok(_) => annotation_count += 1,
+                    Err(reason) => {
+                        invalid_annotations.push(InvalidAnnotation {
+                            file: json_file_name.clone(),
+                            label: shape.label.clone(),
+                            reason: reason.as_str(),
+                            shape_type: shape.shape_type.clone(),
+                            points_count: shape.points.len(),
+                        });
+                        skipped_count += 1;
+                    }
                 }
             } else {
                 context.add_skipped_label(&shape.label);
@@ -311,6 +320,14 @@ pub fn convert_to_coco(config: &ConversionConfig) -> ConversionResult {
         gather_labels_coco(&json_files, &mut label_map);
     }
 
+    // Plan group-aware splits up front so whole groups land in one split
+    let split_plan = plan_splits(config, &json_files);
+    stats.split_report = split_plan.as_ref().map(|p| p.report.clone());
+
+    // Plan train split balancing (dropped images and repeats)
+    let balance_plan = plan_balance(config, &json_files, split_plan.as_ref());
+    stats.balance_report = balance_plan.as_ref().map(|p| p.report.clone());
+
     // Split datasets
     let mut train_dataset = CocoDataset::default();
     let mut val_dataset = CocoDataset::default();
@@ -319,12 +336,24 @@ pub fn convert_to_coco(config: &ConversionConfig) -> ConversionResult {
     let mut image_id_counter = config.start_image_id;
     let mut annotation_id_counter = config.start_annotation_id;
     let mut skipped_labels: HashSet<String> = HashSet::new();
+    // Image ID → source image key (for fold and balancing lookups)
+    let mut image_keys: HashMap<u32, String> = HashMap::new();
 
     // Get the pre-detected input format from config
     let input_format = config.detected_input_format.unwrap_or(InputAnnotationFormat::Unknown);
 
+    let mut refiner = PolygonRefiner::new(config.polygon_options);
+
+    // Augmented copies are generated while the train images are exported
+    let mut augmenter = Augmenter::from_config(config);
+
     // Process each JSON file
     for json_path in &json_files {
+        if balance_plan.as_ref().is_some_and(|p| p.is_dropped_json(json_path)) {
+            stats.increment_skipped();
+            continue;
+        }
+
         match process_single_file_coco(
             json_path,
             config,
@@ -338,6 +367,11 @@ pub fn convert_to_coco(config: &ConversionConfig) -> ConversionResult {
             &mut image_id_counter,
             &mut annotation_id_counter,
             input_format,
+            split_plan.as_ref(),
+            &mut image_keys,
+            &mut refiner,
+            &mut stats.shape_types,
+            augmenter.as_mut(),
         ) {
             Ok((annotation_count, skipped_count, invalid_list, filtered_empty_file_name)) => {
                 stats.increment_processed();
@@ -358,6 +392,14 @@ pub fn convert_to_coco(config: &ConversionConfig) -> ConversionResult {
         }
     }
 
+    stats.polygon_vertices = refiner.report();
+    stats.augment_report = augmenter.as_ref().map(|a| a.report.clone());
+    if let Some(augmenter) = &augmenter {
+        if let Err(e) = augmenter.write_log(&output_dirs.base_dir.join(AUGMENT_LOG_FILE)) {
+            errors.push(e);
+        }
+    }
+
     // Process background images if enabled
     if config.include_background {
         let bg_files = process_background_images_coco(
@@ -368,6 +410,9 @@ pub fn convert_to_coco(config: &ConversionConfig) -> ConversionResult {
             &mut val_dataset,
             &mut test_dataset,
             &mut image_id_counter,
+            split_plan.as_ref(),
+            balance_plan.as_ref(),
+            &mut image_keys,
         );
         for file_name in bg_files {
             stats.add_background_file(file_name);
@@ -390,6 +435,27 @@ pub fn convert_to_coco(config: &ConversionConfig) -> ConversionResult {
         stats.add_skipped_label(label);
     }
 
+    // Split the train pool into cross-validation folds before repeating images
+    let mut fold_datasets = Vec::new();
+    if let Some(plan) = split_plan.as_ref().filter(|p| p.folds > 0) {
+        let image_folds: HashMap<u32, usize> = image_keys
+            .iter()
+            .filter_map(|(id, key)| plan.fold_for(key).map(|fold| (*id, fold)))
+            .collect();
+        for fold in 0..plan.folds {
+            fold_datasets.push(split_fold_dataset(&train_dataset, &image_folds, fold));
+        }
+    }
+
+    // Oversampled images get additional entries referencing the same file
+    if let Some(plan) = balance_plan.as_ref().filter(|p| p.has_repeats()) {
+        let copies = |id: u32| image_keys.get(&id).map(|key| plan.copies(key)).unwrap_or(1);
+        repeat_images(&mut train_dataset, copies, &mut image_id_counter, &mut annotation_id_counter);
+        for (fold_train, _) in &mut fold_datasets {
+            repeat_images(fold_train, copies, &mut image_id_counter, &mut annotation_id_counter);
+        }
+    }
+
     // Write COCO JSON files
     if let Err(e) = write_coco_json(&output_dirs.annotations_dir.join("instances_train.json"), &train_dataset) {
         errors.push(format!("Failed to write train annotations: {}", e));
@@ -405,6 +471,22 @@ pub fn convert_to_coco(config: &ConversionConfig) -> ConversionResult {
         }
     }
 
+    // Write per-fold train/val JSON files referencing the shared train images
+    for (fold, (fold_train, fold_val)) in fold_datasets.iter().enumerate() {
+        let fold_dir = output_dirs.annotations_dir.join(format!("fold_{}", fold + 1));
+        if let Err(e) = std::fs::create_dir_all(&fold_dir) {
+            errors.push(format!("Failed to create {}: {}", fold_dir.display(), e));
+            continue;
+        }
+
+        if let Err(e) = write_coco_json(&fold_dir.join("instances_train.json"), fold_train) {
+            errors.push(format!("Failed to write fold {} train annotations: {}", fold + 1, e));
+        }
+        if let Err(e) = write_coco_json(&fold_dir.join("instances_val.json"), fold_val) {
+            errors.push(format!("Failed to write fold {} val annotations: {}", fold + 1, e));
+        }
+    }
+
     if errors.is_empty() {
         ConversionResult::success(output_dirs.base_dir.to_string_lossy().to_string(), stats)
     } else {
@@ -451,6 +533,11 @@ fn process_single_file_coco(
     image_id_counter: &mut u32,
     annotation_id_counter: &mut u32,
     input_format: InputAnnotationFormat,
+    split_plan: Option<&SplitPlan>,
+    image_keys: &mut HashMap<u32, String>,
+    refiner: &mut PolygonRefiner,
+    shape_types: &mut ShapeTypeReport,
+    augmenter: Option<&mut Augmenter>,
 ) -> Result<(usize, usize, Vec<InvalidAnnotation>, Option<String>), String> {
     // Read and parse JSON
     let annotation = read_labelme_json(json_path)?;
@@ -463,8 +550,7 @@ fn process_single_file_coco(
     processed_images.insert(image_key.clone());
 
     // Determine split
-    let path_hash = hash_string(&image_key);
-    let split = determine_split(path_hash, config.val_size, config.test_size);
+    let split = resolve_split(config, split_plan, &image_key);
 
     // Get output directory for this split
     let images_dir = get_split_images_dir(output_dirs, split);
@@ -492,20 +578,23 @@ fn process_single_file_coco(
         .unwrap_or_else(|| "unknown.json".to_string());
 
     // Copy or extract image
-    if let Some(image_data) = &annotation.image_data {
+    let output_image = if let Some(image_data) = &annotation.image_data {
         let dest_path = images_dir.join(&file_name);
         extract_embedded_image(image_data, &dest_path)?;
+        dest_path
     } else if image_path.exists() {
         copy_image(&image_path, images_dir)
-            .map_err(|e| format!("Failed to copy image: {}", e))?;
+            .map_err(|e| format!("Failed to copy image: {}", e))?
     } else {
         return Err(format!("Image file not found: {}", image_path.display()));
-    }
+    };
 
     // Create COCO image entry
     let image_id = *image_id_counter;
     *image_id_counter += 1;
 
+    image_keys.insert(image_id, image_key.clone());
+
     let coco_image = CocoImage {
         id: image_id,
         file_name: file_name.clone(),
@@ -524,28 +613,35 @@ fn process_single_file_coco(
 
     for shape in &annotation.shapes {
         if let Some(class_id) = label_map.get(&shape.label) {
-            // Validate points count based on detected input format
-            if let Err(reason) = validate_shape_points(shape, input_format) {
-                invalid_annotations.push(InvalidAnnotation {
-                    file: json_file_name.clone(),
-                    label: shape.label.clone(),
-                    reason: reason.as_str(),
-                    shape_type: shape.shape_type.clone(),
-                    points_count: shape.points.len(),
-                });
-                skipped_count += 1;
-                continue;
-            }
-
-            if let Some(coco_ann) = shape_to_coco_annotation(
+            match shape_to_coco_annotation(
                 shape,
                 image_id,
                 (*class_id + 1) as u32, // COCO category IDs are 1-indexed
                 *annotation_id_counter,
                 config,
+                input_format,
+                refiner,
             ) {
-                coco_annotations.push(coco_ann);
-                *annotation_id_counter += 1;
+                Ok(Some(coco_ann)) => {
+                    coco_annotations.push(coco_ann);
+                    *annotation_id_counter += 1;
+                    shape_types.record_converted(&shape.shape_type);
+                }
+                Ok(None) => {
+                    // Line or point shape skipped by configuration
+                    shape_types.record_skipped(&shape.shape_type);
+                    skipped_count += 1;
+                }
+                Err(reason) => {
+                    invalid_annotations.push(InvalidAnnotation {
+                        file: json_file_name.clone(),
+                        label: shape.label.clone(),
+                        reason: reason.as_str(),
+                        shape_type: shape.shape_type.clone(),
+                        points_count: shape.points.len(),
+                    });
+                    skipped_count += 1;
+                }
             }
         } else {
             // Label not in the predefined list
@@ -577,66 +673,87 @@ fn process_single_file_coco(
     dataset.images.push(coco_image);
     dataset.annotations.extend(coco_annotations);
 
+    // Add augmented copies of train images
+    if let Some(augmenter) = augmenter.filter(|_| split == Split::Train) {
+        write_augmented_copies_coco(
+            augmenter,
+            &annotation.shapes,
+            &image_key,
+            &output_image,
+            images_dir,
+            label_map,
+            config,
+            input_format,
+            dataset,
+            image_id_counter,
+            annotation_id_counter,
+            image_keys,
+        )?;
+    }
+
     Ok((annotation_count, skipped_count, invalid_annotations, filtered_empty_file_name))
 }
 
 /// Convert a LabelMe shape to COCO annotation
+/// Returns Ok(None) if the shape is skipped by the line/point settings
+#[allow(clippy::too_many_arguments)]
 fn shape_to_coco_annotation(
     shape: &Shape,
     image_id: u32,
     category_id: u32,
     annotation_id: u32,
     config: &ConversionConfig,
-) -> Option<CocoAnnotation> {
-    let points = match shape.shape_type.as_str() {
-        "polygon" => {
-            let mut pts = shape.points.clone();
-            // Remove duplicate last point if present
-            if pts.len() >= 4 {
-                let first = pts[0];
-                let last = pts[pts.len() - 1];
-                if (first.0 - last.0).abs() < 0.001 && (first.1 - last.1).abs() < 0.001 {
-                    pts.pop();
-                }
-            }
-            pts
-        }
-        "rectangle" => {
-            if shape.points.len() < 2 {
-                return None;
-            }
-            rectangle_to_polygon(&shape.points)
-        }
-        "circle" => {
-            if shape.points.len() < 2 {
-                return None;
-            }
-            let (cx, cy) = shape.points[0];
-            let (px, py) = shape.points[1];
-            let radius = ((cx - px).powi(2) + (cy - py).powi(2)).sqrt();
-            crate::labelme_convert::conversion::circle_to_polygon((cx, cy), radius, 12)
+    input_format: InputAnnotationFormat,
+    refiner: &mut PolygonRefiner,
+) -> Result<Option<CocoAnnotation>, InvalidReason> {
+    let geometry = shape_geometry(shape, input_format, &config.shape_options)?;
+
+    let (mut points, keypoints) = match geometry {
+        ShapeGeometry::Outline(outline) => (outline, None),
+        ShapeGeometry::Keypoints { points, outline } => {
+            // Visibility flag 2 = labeled and visible
+            let flat: Vec<f64> = points.iter().flat_map(|(x, y)| [*x, *y, 2.0]).collect();
+            (outline, Some((flat, points.len())))
         }
-        _ => return None,
+        ShapeGeometry::Skipped => return Ok(None),
     };
 
+    let is_polygon = ShapeKind::of(shape) == ShapeKind::Polygon;
+
+    // Remove duplicate last point if present
+    if is_polygon && points.len() >= 4 {
+        let first = points[0];
+        let last = points[points.len() - 1];
+        if (first.0 - last.0).abs() < 0.001 && (first.1 - last.1).abs() < 0.001 {
+            points.pop();
+        }
+    }
+
     if points.len() < 3 {
-        return None;
+        return Err(InvalidReason::InsufficientPoints);
     }
 
     let area = calculate_polygon_area(&points);
     if area <= 0.0 {
-        return None;
+        return Err(InvalidReason::ZeroArea);
     }
 
+    // bbox and area come from the original outline; only the segmentation is refined
     let bbox = calculate_coco_bbox(&points);
     let segmentation = match config.segmentation_mode {
-        crate::labelme_convert::config::SegmentationMode::Polygon => {
-            Some(vec![flatten_polygon(&points)])
+        // Keypoint annotations carry no segmentation
+        crate::labelme_convert::config::SegmentationMode::Polygon if keypoints.is_none() => {
+            if is_polygon {
+                Some(vec![flatten_polygon(&refiner.refine(&points))])
+            } else {
+                Some(vec![flatten_polygon(&points)])
+            }
         }
-        crate::labelme_convert::config::SegmentationMode::BboxOnly => None,
+        _ => None,
     };
+    let (keypoints, num_keypoints) = keypoints.unzip();
 
-    Some(CocoAnnotation {
+    Ok(Some(CocoAnnotation {
         id: annotation_id,
         image_id,
         category_id,
@@ -644,7 +761,87 @@ fn shape_to_coco_annotation(
         area,
         iscrowd: 0,
         segmentation,
-    })
+        keypoints,
+        num_keypoints,
+    }))
+}
+
+/// Add the augmented copies of one train image to the dataset
+#[allow(clippy::too_many_arguments)]
+fn write_augmented_copies_coco(
+    augmenter: &mut Augmenter,
+    shapes: &[Shape],
+    image_key: &str,
+    output_image: &Path,
+    images_dir: &Path,
+    label_map: &HashMap<String, usize>,
+    config: &ConversionConfig,
+    input_format: InputAnnotationFormat,
+    dataset: &mut CocoDataset,
+    image_id_counter: &mut u32,
+    annotation_id_counter: &mut u32,
+    image_keys: &mut HashMap<u32, String>,
+) -> Result<(), String> {
+    let image = image::open(output_image)
+        .map_err(|e| format!("Failed to read image for augmentation: {}", e))?;
+    let stem = output_image
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+    let extension = output_image
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    // Vertex statistics only cover the original annotations
+    let mut refiner = PolygonRefiner::new(config.polygon_options);
+
+    for copy in 1..=augmenter.copies() {
+        let aug_name = augmented_file_name(&stem, &extension, copy);
+        let augmented = augmenter.augment(image_key, copy, &image, shapes, &aug_name);
+        augmented
+            .image
+            .save(images_dir.join(&aug_name))
+            .map_err(|e| format!("Failed to write augmented image: {}", e))?;
+
+        let image_id = *image_id_counter;
+        *image_id_counter += 1;
+        image_keys.insert(image_id, format!("{}#aug{}", image_key, copy));
+
+        dataset.images.push(CocoImage {
+            id: image_id,
+            file_name: aug_name,
+            width: augmented.image.width(),
+            height: augmented.image.height(),
+            license: 1,
+            flickr_url: None,
+            coco_url: None,
+            date_captured: None,
+        });
+
+        let mut annotation_count = 0;
+        for shape in &augmented.shapes {
+            let Some(class_id) = label_map.get(&shape.label) else {
+                continue;
+            };
+            if let Ok(Some(coco_ann)) = shape_to_coco_annotation(
+                shape,
+                image_id,
+                (*class_id + 1) as u32,
+                *annotation_id_counter,
+                config,
+                input_format,
+                &mut refiner,
+            ) {
+                dataset.annotations.push(coco_ann);
+                *annotation_id_counter += 1;
+                annotation_count += 1;
+            }
+        }
+        augmenter.add_annotations(annotation_count);
+    }
+
+    Ok(())
 }
 
 /// Get the images directory for a given split
@@ -685,6 +882,80 @@ fn write_coco_json(path: &Path, dataset: &CocoDataset) -> Result<(), String> {
     Ok(())
 }
 
+/// Split the train pool into the train/val datasets of one cross-validation fold
+fn split_fold_dataset(
+    pool: &CocoDataset,
+    image_folds: &HashMap<u32, usize>,
+    fold: usize,
+) -> (CocoDataset, CocoDataset) {
+    let mut train = CocoDataset {
+        info: pool.info.clone(),
+        licenses: pool.licenses.clone(),
+        categories: pool.categories.clone(),
+        images: Vec::new(),
+        annotations: Vec::new(),
+    };
+    let mut val = train.clone();
+
+    let is_val = |image_id: u32| image_folds.get(&image_id) == Some(&fold);
+
+    for image in &pool.images {
+        if is_val(image.id) {
+            val.images.push(image.clone());
+        } else {
+            train.images.push(image.clone());
+        }
+    }
+    for annotation in &pool.annotations {
+        if is_val(annotation.image_id) {
+            val.annotations.push(annotation.clone());
+        } else {
+            train.annotations.push(annotation.clone());
+        }
+    }
+
+    (train, val)
+}
+
+/// Add extra image entries (and their annotations) for repeated images
+///
+/// The duplicates reference the same file name, so no image is copied twice.
+fn repeat_images(
+    dataset: &mut CocoDataset,
+    copies: impl Fn(u32) -> usize,
+    image_id_counter: &mut u32,
+    annotation_id_counter: &mut u32,
+) {
+    let mut annotations_by_image: HashMap<u32, Vec<CocoAnnotation>> = HashMap::new();
+    for annotation in &dataset.annotations {
+        annotations_by_image
+            .entry(annotation.image_id)
+            .or_default()
+            .push(annotation.clone());
+    }
+
+    let originals = dataset.images.clone();
+    for image in originals {
+        for _ in 1..copies(image.id) {
+            let new_id = *image_id_counter;
+            *image_id_counter += 1;
+
+            dataset.images.push(CocoImage {
+                id: new_id,
+                ..image.clone()
+            });
+            for annotation in annotations_by_image.get(&image.id).into_iter().flatten() {
+                dataset.annotations.push(CocoAnnotation {
+                    id: *annotation_id_counter,
+                    image_id: new_id,
+                    ..annotation.clone()
+                });
+                *annotation_id_counter += 1;
+            }
+        }
+    }
+}
+
 /// Process background images (images without annotations) for COCO
 /// Returns the list of background image file names
 #[allow(clippy::too_many_arguments)]
@@ -696,6 +967,9 @@ fn process_background_images_coco(
     val_dataset: &mut CocoDataset,
     test_dataset: &mut CocoDataset,
     image_id_counter: &mut u32,
+    split_plan: Option<&SplitPlan>,
+    balance_plan: Option<&BalancePlan>,
+    image_keys: &mut HashMap<u32, String>,
 ) -> Vec<String> {
     let bg_images = find_background_images(&config.input_dir, processed_images);
     let mut bg_files = Vec::new();
@@ -703,9 +977,13 @@ fn process_background_images_coco(
     for image_path in bg_images {
         let image_key = image_path.to_string_lossy().to_string();
 
+        // Skip backgrounds dropped by balancing
+        if balance_plan.is_some_and(|p| p.is_dropped(&image_key)) {
+            continue;
+        }
+
         // Determine split
-        let path_hash = hash_string(&image_key);
-        let split = determine_split(path_hash, config.val_size, config.test_size);
+        let split = resolve_split(config, split_plan, &image_key);
 
         let images_dir = get_split_images_dir(output_dirs, split);
 
@@ -728,6 +1006,8 @@ fn process_background_images_coco(
         let image_id = *image_id_counter;
         *image_id_counter += 1;
 
+        image_keys.insert(image_id, image_key.clone());
+
         let coco_image = CocoImage {
             id: image_id,
             file_name: file_name.clone(),
@@ -782,4 +1062,53 @@ mod tests {
         assert_eq!(categories[1].id, 2);
         assert_eq!(categories[1].name, "dog");
     }
+
+    #[test]
+    fn test_split_fold_dataset() {
+        let mut pool = CocoDataset::default();
+        for id in 1..=3 {
+            pool.images.push(CocoImage {
+                id,
+                file_name: format!("{}.jpg", id),
+                width: 10,
+                height: 10,
+                license: 1,
+                flickr_url: None,
+                coco_url: None,
+                date_captured: None,
+            });
+            pool.annotations.push(CocoAnnotation {
+                id,
+                image_id: id,
+                category_id: 1,
+                bbox: [0.0, 0.0, 1.0, 1.0],
+                area: 1.0,
+                iscrowd: 0,
+                segmentation: None,
+                keypoints: None,
+                num_keypoints: None,
+            });
+        }
+        let image_folds: HashMap<u32, usize> = [(1, 0), (2, 1), (3, 1)].into_iter().collect();
+
+        let (train, val) = split_fold_dataset(&pool, &image_folds, 1);
+        assert_eq!(train.images.len(), 1);
+        assert_eq!(train.annotations[0].image_id, 1);
+        assert_eq!(val.images.len(), 2);
+        assert!(val.annotations.iter().all(|a| a.image_id != 1));
+
+        // Repeating image 2 three times adds two entries with fresh IDs
+        let mut repeated = pool.clone();
+        let mut next_image_id = 10;
+        let mut next_annotation_id = 20;
+        let copies = |id: u32| if id == 2 { 3 } else { 1 };
+        repeat_images(&mut repeated, copies, &mut next_image_id, &mut next_annotation_id);
+
+        assert_eq!(repeated.images.len(), 5);
+        assert_eq!(repeated.annotations.len(), 5);
+        assert_eq!(next_image_id, 12);
+        let duplicates: Vec<_> = repeated.images.iter().filter(|i| i.file_name == "2.jpg").collect();
+        assert_eq!(duplicates.len(), 3);
+        assert!(repeated.annotations.iter().any(|a| a.id == 21 && a.image_id == 11));
+    }
 }
@@ -5,9 +5,15 @@
 //
 // Adapted and modified for dataset-app
 
-use crate::labelme_convert::types::InputAnnotationFormat;
+use crate::labelme_convert::augment::AugmentConfig;
+use crate::labelme_convert::balance::BalanceConfig;
+use crate::labelme_convert::pipeline::Split;
+use crate::labelme_convert::render::PreviewConfig;
+use crate::labelme_convert::split::SplitGroupKey;
+use crate::labelme_convert::types::{DatasetSourceFormat, InputAnnotationFormat};
 use chrono;
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::path::PathBuf;
 
 /// Output format for dataset export
@@ -19,6 +25,17 @@ pub enum OutputFormat {
     Coco,
     /// LabelMe to LabelMe (filter/reorder labels, no train/val/test split)
     LabelMe,
+    /// CVAT 1.1 "for images" XML (no train/val/test split)
+    Cvat,
+    /// Label Studio JSON tasks (no train/val/test split)
+    #[serde(rename = "label_studio")]
+    LabelStudio,
+    /// Single-channel class-index PNG masks (semantic segmentation)
+    #[serde(rename = "semantic_mask")]
+    SemanticMask,
+    /// 16-bit instance-ID PNG masks with per-image instance JSON (panoptic)
+    #[serde(rename = "instance_mask")]
+    InstanceMask,
 }
 
 /// Annotation format for YOLO export
@@ -57,6 +74,125 @@ pub enum LabelMeOutputFormat {
     Bbox4Point,
 }
 
+/// Order in which overlapping shapes or instances are painted into masks
+/// (later shapes overwrite earlier ones)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskDrawOrder {
+    /// Paint shapes in the order they appear in the JSON file
+    #[default]
+    FileOrder,
+    /// Paint larger shapes first so smaller ones stay on top
+    AreaDescending,
+    /// Paint by class index (higher indices on top)
+    ClassIndex,
+}
+
+/// Vertex processing applied to polygons on YOLO and COCO export
+///
+/// Simplification runs first, resampling last; all values 0 = disabled.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, Default)]
+pub struct PolygonOptions {
+    /// Douglas–Peucker tolerance in pixels
+    #[serde(default)]
+    pub tolerance: f64,
+    /// Maximum vertex count; the tolerance is raised until the polygon fits
+    #[serde(default)]
+    pub max_points: usize,
+    /// Resample the outline to exactly this many evenly spaced vertices
+    #[serde(default)]
+    pub resample_points: usize,
+}
+
+impl PolygonOptions {
+    /// Check if any polygon processing is requested
+    pub fn is_enabled(&self) -> bool {
+        self.tolerance > 0.0 || self.max_points > 0 || self.resample_points > 0
+    }
+
+    /// Validate the options
+    pub fn validate(&self) -> Result<(), String> {
+        if self.tolerance.is_nan() || self.tolerance < 0.0 {
+            return Err(format!("Polygon tolerance must not be negative, got {}", self.tolerance));
+        }
+        if matches!(self.max_points, 1 | 2) || matches!(self.resample_points, 1 | 2) {
+            return Err("Polygon vertex counts must be 0 (disabled) or at least 3".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// How `line` and `linestrip` shapes are converted to box/polygon formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum LineShapeMode {
+    /// Drop lines and report them as skipped
+    #[default]
+    Skip,
+    /// Expand lines into a polygon of `line_width` pixels
+    Buffer,
+}
+
+/// How `point` and `points` shapes are converted to box/polygon formats
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PointShapeMode {
+    /// Drop points and report them as skipped
+    #[default]
+    Skip,
+    /// Keep points as keypoints (COCO `keypoints`, a `point_size` box elsewhere)
+    Keypoint,
+}
+
+/// Shape-type specific conversion options
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ShapeOptions {
+    #[serde(default)]
+    pub line_mode: LineShapeMode,
+    /// Total width in pixels of buffered lines
+    #[serde(default = "default_line_width")]
+    pub line_width: f64,
+    #[serde(default)]
+    pub point_mode: PointShapeMode,
+    /// Side length in pixels of the box written for keypoints
+    #[serde(default = "default_point_size")]
+    pub point_size: f64,
+    /// Number of vertices used to approximate circles (default 12)
+    #[serde(default = "default_circle_segments")]
+    pub circle_segments: usize,
+}
+
+impl Default for ShapeOptions {
+    fn default() -> Self {
+        Self {
+            line_mode: LineShapeMode::default(),
+            line_width: default_line_width(),
+            point_mode: PointShapeMode::default(),
+            point_size: default_point_size(),
+            circle_segments: default_circle_segments(),
+        }
+    }
+}
+
+impl ShapeOptions {
+    /// Validate the options
+    pub fn validate(&self) -> Result<(), String> {
+        if self.line_width.is_nan() || self.line_width <= 0.0 {
+            return Err(format!("Line width must be positive, got {}", self.line_width));
+        }
+        if self.point_size.is_nan() || self.point_size <= 0.0 {
+            return Err(format!("Point size must be positive, got {}", self.point_size));
+        }
+        if self.circle_segments < 8 {
+            return Err(format!(
+                "Circle segments must be at least 8, got {}",
+                self.circle_segments
+            ));
+        }
+        Ok(())
+    }
+}
+
 /// Source for category definitions
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", content = "value")]
@@ -108,6 +244,35 @@ pub struct ConversionConfig {
     #[serde(default = "default_seed")]
     pub seed: u64,
 
+    /// Keep related images in the same split (None = split each image independently)
+    #[serde(default)]
+    pub split_group: Option<SplitGroupKey>,
+
+    /// Balance label frequencies across splits and folds
+    #[serde(default)]
+    pub stratified: bool,
+
+    /// Number of cross-validation folds (0 = disabled, otherwise at least 2)
+    #[serde(default)]
+    pub kfold: usize,
+
+    /// Predefined split per image key (e.g. VOC `ImageSets/Main` lists),
+    /// used when no other split mode is set. Filled by importers, not by users.
+    #[serde(skip)]
+    pub fixed_splits: HashMap<String, Split>,
+
+    /// Class balancing applied to the train split (None = export as-is)
+    #[serde(default)]
+    pub balance: Option<BalanceConfig>,
+
+    /// Offline augmentation of the train split (None = no augmented copies)
+    #[serde(default)]
+    pub augment: Option<AugmentConfig>,
+
+    /// Rendered sample images written to `previews/` in the output (None = no previews)
+    #[serde(default)]
+    pub previews: Option<PreviewConfig>,
+
     /// Include images without annotations as background
     #[serde(default)]
     pub include_background: bool,
@@ -141,6 +306,14 @@ pub struct ConversionConfig {
     #[serde(default)]
     pub categories_source: CategoriesSource,
 
+    /// Polygon simplification / resampling for YOLO and COCO export
+    #[serde(default)]
+    pub polygon_options: PolygonOptions,
+
+    /// Conversion of circles, lines and points for YOLO, COCO and bbox LabelMe output
+    #[serde(default)]
+    pub shape_options: ShapeOptions,
+
     // LabelMe-specific options
     /// Skip train/val/test split (for LabelMe output)
     #[serde(default)]
@@ -157,10 +330,27 @@ pub struct ConversionConfig {
     #[serde(default)]
     pub labelme_output_format: LabelMeOutputFormat,
 
+    // Mask-specific options
+    /// Pixel value for ignored regions in semantic masks
+    #[serde(default = "default_mask_ignore_index")]
+    pub mask_ignore_index: u8,
+
+    /// Labels painted with `mask_ignore_index` instead of a class index
+    #[serde(default)]
+    pub mask_ignore_labels: Vec<String>,
+
+    /// Paint order of overlapping shapes (semantic) or instances (instance masks)
+    #[serde(default)]
+    pub mask_draw_order: MaskDrawOrder,
+
     /// Detected input annotation format (auto-detected before conversion)
     /// This is set by the conversion pipeline and can be manually overridden
     #[serde(default)]
     pub detected_input_format: Option<InputAnnotationFormat>,
+
+    /// File format of the input dataset (None = auto-detect LabelMe or VOC)
+    #[serde(default)]
+    pub source_format: Option<DatasetSourceFormat>,
 }
 
 fn default_val_size() -> f32 {
@@ -175,6 +365,22 @@ fn default_start_id() -> u32 {
     1
 }
 
+fn default_mask_ignore_index() -> u8 {
+    255
+}
+
+fn default_line_width() -> f64 {
+    4.0
+}
+
+fn default_point_size() -> f64 {
+    4.0
+}
+
+fn default_circle_segments() -> usize {
+    12
+}
+
 impl Default for ConversionConfig {
     fn default() -> Self {
         Self {
@@ -186,6 +392,13 @@ impl Default for ConversionConfig {
             val_size: default_val_size(),
             test_size: 0.0,
             seed: default_seed(),
+            split_group: None,
+            stratified: false,
+            kfold: 0,
+            fixed_splits: HashMap::new(),
+            balance: None,
+            augment: None,
+            previews: None,
             include_background: false,
             label_list: Vec::new(),
             deterministic_labels: false,
@@ -194,12 +407,19 @@ impl Default for ConversionConfig {
             start_image_id: default_start_id(),
             start_annotation_id: default_start_id(),
             categories_source: CategoriesSource::default(),
+            polygon_options: PolygonOptions::default(),
+            shape_options: ShapeOptions::default(),
             // LabelMe-specific
             skip_split: false,
             remove_image_data: false,
             labelme_output_format: LabelMeOutputFormat::default(),
+            // Mask-specific
+            mask_ignore_index: default_mask_ignore_index(),
+            mask_ignore_labels: Vec::new(),
+            mask_draw_order: MaskDrawOrder::default(),
             // Auto-detection
             detected_input_format: None,
+            source_format: None,
         }
     }
 }
@@ -237,6 +457,10 @@ impl ConversionConfig {
             OutputFormat::Yolo => "yolo",
             OutputFormat::Coco => "coco",
             OutputFormat::LabelMe => "labelme",
+            OutputFormat::Cvat => "cvat",
+            OutputFormat::LabelStudio => "label_studio",
+            OutputFormat::SemanticMask => "semantic_mask",
+            OutputFormat::InstanceMask => "instance_mask",
         };
 
         let annotation_str = match self.annotation_format {
@@ -285,6 +509,39 @@ impl ConversionConfig {
             ));
         }
 
+        if self.kfold == 1 {
+            return Err("kfold must be 0 (disabled) or at least 2".to_string());
+        }
+
+        if let Some(ref key) = self.split_group {
+            key.validate()?;
+        }
+
+        if let Some(ref balance) = self.balance {
+            balance.validate()?;
+        }
+
+        if let Some(ref augment) = self.augment {
+            augment.validate()?;
+            if !matches!(self.output_format, OutputFormat::Yolo | OutputFormat::Coco) {
+                return Err("Augmentation is only supported for YOLO and COCO export".to_string());
+            }
+            if self.kfold_enabled() {
+                return Err("Augmentation cannot be combined with k-fold export".to_string());
+            }
+        }
+
+        if let Some(ref previews) = self.previews {
+            previews.render.validate()?;
+        }
+
+        self.polygon_options.validate()?;
+        self.shape_options.validate()?;
+
+        if self.output_format == OutputFormat::SemanticMask && self.mask_ignore_index == 0 {
+            return Err("mask_ignore_index must not be 0 (reserved for background)".to_string());
+        }
+
         Ok(())
     }
 
@@ -336,6 +593,41 @@ impl ConversionConfig {
         self
     }
 
+    /// Builder pattern: set split grouping key
+    pub fn with_split_group(mut self, key: Option<SplitGroupKey>) -> Self {
+        self.split_group = key;
+        self
+    }
+
+    /// Builder pattern: enable stratified splitting
+    pub fn with_stratified(mut self, stratified: bool) -> Self {
+        self.stratified = stratified;
+        self
+    }
+
+    /// Builder pattern: set number of cross-validation folds
+    pub fn with_kfold(mut self, folds: usize) -> Self {
+        self.kfold = folds;
+        self
+    }
+
+    /// Builder pattern: set train split balancing
+    pub fn with_balance(mut self, balance: Option<BalanceConfig>) -> Self {
+        self.balance = balance;
+        self
+    }
+
+    /// Builder pattern: set train split augmentation
+    pub fn with_augment(mut self, augment: Option<AugmentConfig>) -> Self {
+        self.augment = augment;
+        self
+    }
+
+    /// Check if k-fold cross-validation export is enabled
+    pub fn kfold_enabled(&self) -> bool {
+        self.kfold >= 2
+    }
+
     /// Check if test split is enabled
     pub fn has_test_split(&self) -> bool {
         self.test_size > 0.0
@@ -368,6 +660,34 @@ mod tests {
         config.val_size = 0.5;
         config.test_size = 0.6;
         assert!(config.validate().is_err());
+
+        config.test_size = 0.1;
+        config.kfold = 1;
+        assert!(config.validate().is_err());
+
+        config.kfold = 5;
+        assert!(config.validate().is_ok());
+        assert!(config.kfold_enabled());
+
+        config.polygon_options.max_points = 2;
+        assert!(config.validate().is_err());
+        config.polygon_options.max_points = 3;
+        assert!(config.validate().is_ok());
+
+        config.shape_options.line_width = 0.0;
+        assert!(config.validate().is_err());
+        config.shape_options.line_width = 2.0;
+        assert!(config.validate().is_ok());
+
+        config.augment = Some(AugmentConfig::default());
+        assert!(config.validate().is_err()); // k-fold is enabled above
+        config.kfold = 0;
+        assert!(config.validate().is_ok());
+        config.augment = None;
+
+        config.output_format = OutputFormat::SemanticMask;
+        config.mask_ignore_index = 0;
+        assert!(config.validate().is_err());
     }
 
     #[test]
@@ -5,9 +5,11 @@
 //
 // Adapted and modified for dataset-app
 
-use crate::labelme_convert::config::AnnotationFormat;
-use crate::labelme_convert::detection::validate_shape_points;
-use crate::labelme_convert::types::{InputAnnotationFormat, InvalidReason, Shape};
+use crate::labelme_convert::config::{AnnotationFormat, PolygonOptions, ShapeOptions};
+use crate::labelme_convert::geometry::shape_geometry;
+use crate::labelme_convert::types::{
+    InputAnnotationFormat, InvalidReason, Shape, ShapeKind, VertexReport,
+};
 
 // Re-export Split, determine_split, hash_string from pipeline for backward compatibility
 pub use crate::labelme_convert::pipeline::{determine_split, hash_string, Split};
@@ -19,11 +21,21 @@ pub fn calculate_bbox(
     image_width: u32,
     image_height: u32,
 ) -> Option<(f64, f64, f64, f64)> {
-    if shape.points.is_empty() {
+    calculate_points_bbox(&shape.points, image_width, image_height)
+}
+
+/// Calculate bounding box from points
+/// Returns (x_center, y_center, width, height) normalized to [0, 1]
+pub fn calculate_points_bbox(
+    points: &[(f64, f64)],
+    image_width: u32,
+    image_height: u32,
+) -> Option<(f64, f64, f64, f64)> {
+    if points.is_empty() {
         return None;
     }
 
-    let (min_x, max_x, min_y, max_y) = get_bounding_coords(&shape.points);
+    let (min_x, max_x, min_y, max_y) = get_bounding_coords(points);
 
     // Clamp to image bounds
     let min_x = min_x.max(0.0).min(image_width as f64);
@@ -91,8 +103,11 @@ pub fn normalize_polygon(
         .collect()
 }
 
-/// Convert shape to YOLO format string with strict validation based on detected input format
-/// Returns Err with reason if the shape cannot be converted
+/// Convert shape to YOLO format string with strict validation based on shape type
+/// and detected input format
+/// Returns Ok(None) if the shape is skipped by the line/point settings,
+/// Err with reason if the shape cannot be converted
+#[allow(clippy::too_many_arguments)]
 pub fn shape_to_yolo_line(
     shape: &Shape,
     class_id: usize,
@@ -100,52 +115,47 @@ pub fn shape_to_yolo_line(
     image_height: u32,
     format: AnnotationFormat,
     input_format: InputAnnotationFormat,
-) -> Result<String, InvalidReason> {
-    // Use the shared validation function from detection.rs
-    validate_shape_points(shape, input_format)?;
+    shape_options: &ShapeOptions,
+    refiner: &mut PolygonRefiner,
+) -> Result<Option<String>, InvalidReason> {
+    // Validate and expand circles, lines and points (see geometry.rs)
+    let geometry = shape_geometry(shape, input_format, shape_options)?;
+    let Some(outline) = geometry.outline() else {
+        return Ok(None);
+    };
 
     // Now proceed with conversion based on output format
     match format {
         AnnotationFormat::Bbox => {
             // For bbox output, we always calculate bounding box regardless of input format
             let (x_center, y_center, width, height) =
-                calculate_bbox(shape, image_width, image_height)
-                    .ok_or_else(|| {
-                        if shape.points.is_empty() {
-                            InvalidReason::EmptyPoints
-                        } else {
-                            InvalidReason::ZeroArea
-                        }
-                    })?;
-
-            Ok(format!(
+                calculate_points_bbox(outline, image_width, image_height)
+                    .ok_or(InvalidReason::ZeroArea)?;
+
+            Ok(Some(format!(
                 "{} {:.6} {:.6} {:.6} {:.6}",
                 class_id, x_center, y_center, width, height
-            ))
+            )))
         }
         AnnotationFormat::Polygon => {
-            // For polygon output
-            match input_format {
-                InputAnnotationFormat::Bbox2Point => {
-                    // Convert 2-point bbox to 4-point polygon
-                    let expanded = rectangle_to_polygon(&shape.points);
-                    let normalized = normalize_polygon(&expanded, image_width, image_height);
-                    let mut line = class_id.to_string();
-                    for (x, y) in normalized {
-                        line.push_str(&format!(" {:.6} {:.6}", x, y));
-                    }
-                    Ok(line)
-                }
-                InputAnnotationFormat::Bbox4Point | InputAnnotationFormat::Polygon | InputAnnotationFormat::Unknown => {
-                    // Already has enough points, output as-is
-                    let normalized = normalize_polygon(&shape.points, image_width, image_height);
-                    let mut line = class_id.to_string();
-                    for (x, y) in normalized {
-                        line.push_str(&format!(" {:.6} {:.6}", x, y));
-                    }
-                    Ok(line)
-                }
+            // Simplify/resample hand-drawn polygons in pixel space before normalizing
+            let refine = ShapeKind::of(shape) == ShapeKind::Polygon
+                && matches!(
+                    input_format,
+                    InputAnnotationFormat::Polygon | InputAnnotationFormat::Unknown
+                );
+            let points = if refine {
+                refiner.refine(outline)
+            } else {
+                outline.to_vec()
+            };
+
+            let normalized = normalize_polygon(&points, image_width, image_height);
+            let mut line = class_id.to_string();
+            for (x, y) in normalized {
+                line.push_str(&format!(" {:.6} {:.6}", x, y));
             }
+            Ok(Some(line))
         }
     }
 }
@@ -177,6 +187,20 @@ pub fn circle_to_polygon(center: (f64, f64), radius: f64, num_points: usize) ->
     polygon
 }
 
+/// Outline points of a shape (rectangles and circles expanded to polygons)
+pub fn shape_outline(shape: &Shape, shape_options: &ShapeOptions) -> Vec<(f64, f64)> {
+    match shape.shape_type.as_str() {
+        "rectangle" => rectangle_to_polygon(&shape.points),
+        "circle" if shape.points.len() >= 2 => {
+            let (cx, cy) = shape.points[0];
+            let (px, py) = shape.points[1];
+            let radius = ((px - cx).powi(2) + (py - cy).powi(2)).sqrt();
+            circle_to_polygon((cx, cy), radius, shape_options.circle_segments)
+        }
+        _ => shape.points.clone(),
+    }
+}
+
 /// Calculate polygon area using the shoelace formula
 pub fn calculate_polygon_area(points: &[(f64, f64)]) -> f64 {
     if points.len() < 3 {
@@ -195,6 +219,177 @@ pub fn calculate_polygon_area(points: &[(f64, f64)]) -> f64 {
     (area / 2.0).abs()
 }
 
+/// Clip a polygon to the rectangle [0, width] × [0, height] (Sutherland–Hodgman)
+pub fn clip_polygon(points: &[(f64, f64)], width: f64, height: f64) -> Vec<(f64, f64)> {
+    // (axis, bound, keep values above the bound)
+    let edges = [
+        (0, 0.0, true),
+        (0, width, false),
+        (1, 0.0, true),
+        (1, height, false),
+    ];
+    let mut output = points.to_vec();
+
+    for (axis, bound, above) in edges {
+        let input = std::mem::take(&mut output);
+        let Some(&last) = input.last() else {
+            break;
+        };
+        let coord = |p: (f64, f64)| if axis == 0 { p.0 } else { p.1 };
+        let inside = |p: (f64, f64)| {
+            if above {
+                coord(p) >= bound
+            } else {
+                coord(p) <= bound
+            }
+        };
+        let intersect = |a: (f64, f64), b: (f64, f64)| {
+            let t = (bound - coord(a)) / (coord(b) - coord(a));
+            (a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1))
+        };
+
+        let mut prev = last;
+        for &current in &input {
+            match (inside(current), inside(prev)) {
+                (true, true) => output.push(current),
+                (true, false) => {
+                    output.push(intersect(prev, current));
+                    output.push(current);
+                }
+                (false, true) => output.push(intersect(prev, current)),
+                (false, false) => {}
+            }
+            prev = current;
+        }
+    }
+
+    output
+}
+
+/// Clip a polyline to the image and keep its longest visible run (Liang–Barsky)
+pub fn clip_polyline(points: &[(f64, f64)], width: f64, height: f64) -> Vec<(f64, f64)> {
+    let mut runs: Vec<Vec<(f64, f64)>> = Vec::new();
+
+    for segment in points.windows(2) {
+        let (a, b) = (segment[0], segment[1]);
+        let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+        let (mut t0, mut t1) = (0.0f64, 1.0f64);
+        let mut visible = true;
+
+        for (p, q) in [
+            (-dx, a.0),
+            (dx, width - a.0),
+            (-dy, a.1),
+            (dy, height - a.1),
+        ] {
+            if p == 0.0 {
+                if q < 0.0 {
+                    visible = false;
+                }
+            } else {
+                let t = q / p;
+                if p < 0.0 {
+                    t0 = t0.max(t);
+                } else {
+                    t1 = t1.min(t);
+                }
+            }
+        }
+        if !visible || t0 > t1 {
+            continue;
+        }
+
+        let start = (a.0 + t0 * dx, a.1 + t0 * dy);
+        let end = (a.0 + t1 * dx, a.1 + t1 * dy);
+        match runs.last_mut() {
+            Some(run) if t0 == 0.0 && run.last() == Some(&start) => run.push(end),
+            _ => runs.push(vec![start, end]),
+        }
+    }
+
+    let length = |run: &Vec<(f64, f64)>| {
+        run.windows(2)
+            .map(|w| (w[1].0 - w[0].0).hypot(w[1].1 - w[0].1))
+            .sum::<f64>()
+    };
+    runs.into_iter()
+        .max_by(|a, b| length(a).total_cmp(&length(b)))
+        .unwrap_or_default()
+}
+
+/// Vertices used when a circle cut at the border is turned into a polygon
+pub const CLIPPED_CIRCLE_SEGMENTS: usize = 36;
+
+/// Outcome of clipping a shape to the rectangle [0, width] × [0, height]
+#[derive(Debug, Clone)]
+pub enum ClipOutcome<T> {
+    /// Entirely inside, only moved
+    Kept(T),
+    /// Cut at the border
+    Clipped(T),
+    /// Outside or less visible than required
+    Dropped,
+}
+
+/// Clip an area outline and keep it when enough of its area remains visible
+///
+/// `build` turns the kept outline (clipped or not) into a shape.
+pub fn clip_area<T>(
+    outline: Vec<(f64, f64)>,
+    width: f64,
+    height: f64,
+    min_visibility: f64,
+    build: impl Fn(Vec<(f64, f64)>) -> T,
+) -> ClipOutcome<T> {
+    let full_area = calculate_polygon_area(&outline);
+    let clipped = clip_polygon(&outline, width, height);
+    let area = calculate_polygon_area(&clipped);
+
+    if clipped.len() < 3 || area <= 0.0 {
+        return ClipOutcome::Dropped;
+    }
+    if full_area > 0.0 && area / full_area < min_visibility {
+        return ClipOutcome::Dropped;
+    }
+    if area < full_area - 1e-6 {
+        ClipOutcome::Clipped(build(clipped))
+    } else {
+        ClipOutcome::Kept(build(outline))
+    }
+}
+
+/// Clip a circle given by its center and a point on its edge
+///
+/// Circles inside the rectangle are kept as `circle`; circles crossing the
+/// border become a `polygon` of [`CLIPPED_CIRCLE_SEGMENTS`] vertices. `build`
+/// receives the points and shape type of the result.
+pub fn clip_circle<T>(
+    center: (f64, f64),
+    edge: (f64, f64),
+    width: f64,
+    height: f64,
+    min_visibility: f64,
+    build: impl Fn(Vec<(f64, f64)>, &str) -> T,
+) -> ClipOutcome<T> {
+    let radius = (edge.0 - center.0).hypot(edge.1 - center.1);
+    let fits = center.0 - radius >= 0.0
+        && center.1 - radius >= 0.0
+        && center.0 + radius <= width
+        && center.1 + radius <= height;
+    if fits {
+        return ClipOutcome::Kept(build(vec![center, edge], "circle"));
+    }
+
+    let outline = circle_to_polygon(center, radius, CLIPPED_CIRCLE_SEGMENTS);
+    match clip_area(outline, width, height, min_visibility, |points| {
+        build(points, "polygon")
+    }) {
+        // The polygon fits even though the circle does not
+        ClipOutcome::Kept(shape) => ClipOutcome::Clipped(shape),
+        outcome => outcome,
+    }
+}
+
 /// Calculate bounding box from polygon points for COCO format
 /// Returns [x, y, width, height] where (x, y) is top-left corner
 pub fn calculate_coco_bbox(points: &[(f64, f64)]) -> [f64; 4] {
@@ -207,12 +402,241 @@ pub fn calculate_coco_bbox(points: &[(f64, f64)]) -> [f64; 4] {
     [min_x, min_y, max_x - min_x, max_y - min_y]
 }
 
+/// Intersection over union of two [x, y, width, height] boxes
+pub fn bbox_iou(a: &[f64; 4], b: &[f64; 4]) -> f64 {
+    let inter_w = ((a[0] + a[2]).min(b[0] + b[2]) - a[0].max(b[0])).max(0.0);
+    let inter_h = ((a[1] + a[3]).min(b[1] + b[3]) - a[1].max(b[1])).max(0.0);
+    let intersection = inter_w * inter_h;
+    let union = a[2] * a[3] + b[2] * b[3] - intersection;
+
+    if union <= 0.0 {
+        0.0
+    } else {
+        intersection / union
+    }
+}
+
 /// Flatten polygon points for COCO segmentation format
 /// Converts [(x1, y1), (x2, y2), ...] to [x1, y1, x2, y2, ...]
 pub fn flatten_polygon(points: &[(f64, f64)]) -> Vec<f64> {
     points.iter().flat_map(|(x, y)| vec![*x, *y]).collect()
 }
 
+/// Perpendicular distance from `p` to the segment `a`-`b`
+fn segment_distance(p: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let len_sq = dx * dx + dy * dy;
+    if len_sq == 0.0 {
+        return ((p.0 - a.0).powi(2) + (p.1 - a.1).powi(2)).sqrt();
+    }
+    let t = (((p.0 - a.0) * dx + (p.1 - a.1) * dy) / len_sq).clamp(0.0, 1.0);
+    ((p.0 - a.0 - t * dx).powi(2) + (p.1 - a.1 - t * dy).powi(2)).sqrt()
+}
+
+/// Douglas–Peucker on an open polyline, marking kept vertices in `keep`
+fn douglas_peucker(points: &[(f64, f64)], first: usize, last: usize, epsilon: f64, keep: &mut [bool]) {
+    if last <= first + 1 {
+        return;
+    }
+
+    let mut max_dist = 0.0;
+    let mut index = first;
+    for i in (first + 1)..last {
+        let dist = segment_distance(points[i], points[first], points[last]);
+        if dist > max_dist {
+            max_dist = dist;
+            index = i;
+        }
+    }
+
+    if max_dist > epsilon {
+        keep[index] = true;
+        douglas_peucker(points, first, index, epsilon, keep);
+        douglas_peucker(points, index, last, epsilon, keep);
+    }
+}
+
+/// Simplify a closed polygon with Douglas–Peucker
+///
+/// The ring is split at vertex 0 and the vertex farthest from it, so the
+/// result never drops below 3 vertices for a non-degenerate input.
+pub fn simplify_polygon(points: &[(f64, f64)], epsilon: f64) -> Vec<(f64, f64)> {
+    if points.len() <= 3 || epsilon <= 0.0 {
+        return points.to_vec();
+    }
+
+    let origin = points[0];
+    let far = (1..points.len())
+        .max_by(|&a, &b| {
+            let da = (points[a].0 - origin.0).powi(2) + (points[a].1 - origin.1).powi(2);
+            let db = (points[b].0 - origin.0).powi(2) + (points[b].1 - origin.1).powi(2);
+            da.total_cmp(&db)
+        })
+        .unwrap_or(1);
+
+    // Close the ring so the second half ends back at vertex 0
+    let mut ring = points.to_vec();
+    ring.push(origin);
+
+    let mut keep = vec![false; ring.len()];
+    keep[0] = true;
+    keep[far] = true;
+    douglas_peucker(&ring, 0, far, epsilon, &mut keep);
+    douglas_peucker(&ring, far, ring.len() - 1, epsilon, &mut keep);
+
+    let mut simplified: Vec<(f64, f64)> = points
+        .iter()
+        .zip(&keep)
+        .filter(|(_, k)| **k)
+        .map(|(p, _)| *p)
+        .collect();
+
+    // Keep a valid polygon: re-add the farthest remaining vertex from the chord
+    if simplified.len() < 3 {
+        let extra = (1..points.len())
+            .filter(|&i| i != far)
+            .max_by(|&a, &b| {
+                let da = segment_distance(points[a], origin, points[far]);
+                let db = segment_distance(points[b], origin, points[far]);
+                da.total_cmp(&db)
+            });
+        if let Some(extra) = extra {
+            keep[extra] = true;
+        }
+        simplified = points
+            .iter()
+            .zip(&keep)
+            .filter(|(_, k)| **k)
+            .map(|(p, _)| *p)
+            .collect();
+    }
+
+    simplified
+}
+
+/// Simplify a polygon until it has at most `max_points` vertices
+///
+/// Binary-searches the smallest tolerance (at least `min_epsilon`) that fits.
+pub fn simplify_to_count(points: &[(f64, f64)], max_points: usize, min_epsilon: f64) -> Vec<(f64, f64)> {
+    let base = simplify_polygon(points, min_epsilon);
+    if max_points < 3 || base.len() <= max_points {
+        return base;
+    }
+
+    let (min_x, max_x, min_y, max_y) = get_bounding_coords(points);
+    let mut low = min_epsilon.max(0.0);
+    let mut high = (max_x - min_x).hypot(max_y - min_y).max(low + 1.0);
+    let mut best = simplify_polygon(points, high);
+
+    for _ in 0..40 {
+        let mid = (low + high) / 2.0;
+        let candidate = simplify_polygon(points, mid);
+        if candidate.len() <= max_points {
+            high = mid;
+            best = candidate;
+        } else {
+            low = mid;
+        }
+        if high - low < 1e-3 {
+            break;
+        }
+    }
+
+    best
+}
+
+/// Resample a closed polygon to `count` vertices evenly spaced along its perimeter
+pub fn resample_polygon(points: &[(f64, f64)], count: usize) -> Vec<(f64, f64)> {
+    if points.len() < 2 || count < 3 {
+        return points.to_vec();
+    }
+
+    let n = points.len();
+    let edges: Vec<f64> = (0..n)
+        .map(|i| {
+            let (a, b) = (points[i], points[(i + 1) % n]);
+            (b.0 - a.0).hypot(b.1 - a.1)
+        })
+        .collect();
+    let perimeter: f64 = edges.iter().sum();
+    if perimeter <= 0.0 {
+        return points.to_vec();
+    }
+
+    let step = perimeter / count as f64;
+    let mut resampled = Vec::with_capacity(count);
+    let mut edge = 0;
+    let mut edge_start = 0.0;
+
+    for k in 0..count {
+        let target = k as f64 * step;
+        while edge < n - 1 && edge_start + edges[edge] < target {
+            edge_start += edges[edge];
+            edge += 1;
+        }
+        let (a, b) = (points[edge], points[(edge + 1) % n]);
+        let t = if edges[edge] > 0.0 {
+            ((target - edge_start) / edges[edge]).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+        resampled.push((a.0 + t * (b.0 - a.0), a.1 + t * (b.1 - a.1)));
+    }
+
+    resampled
+}
+
+/// Apply simplification and resampling from `options` to a polygon (pixel coordinates)
+pub fn refine_polygon(points: &[(f64, f64)], options: &PolygonOptions) -> Vec<(f64, f64)> {
+    if points.len() < 3 {
+        return points.to_vec();
+    }
+
+    let mut refined = if options.max_points > 0 {
+        simplify_to_count(points, options.max_points, options.tolerance)
+    } else {
+        simplify_polygon(points, options.tolerance)
+    };
+
+    if options.resample_points > 0 {
+        refined = resample_polygon(&refined, options.resample_points);
+    }
+
+    refined
+}
+
+/// Applies [`PolygonOptions`] to polygons and tallies the vertex reduction
+#[derive(Debug, Clone, Default)]
+pub struct PolygonRefiner {
+    options: PolygonOptions,
+    report: VertexReport,
+}
+
+impl PolygonRefiner {
+    pub fn new(options: PolygonOptions) -> Self {
+        Self {
+            options,
+            report: VertexReport::default(),
+        }
+    }
+
+    /// Refine a polygon, recording vertex counts when processing is enabled
+    pub fn refine(&mut self, points: &[(f64, f64)]) -> Vec<(f64, f64)> {
+        if !self.options.is_enabled() {
+            return points.to_vec();
+        }
+
+        let refined = refine_polygon(points, &self.options);
+        self.report.record(points.len(), refined.len());
+        refined
+    }
+
+    /// Vertex totals, or None when processing is disabled
+    pub fn report(&self) -> Option<VertexReport> {
+        self.options.is_enabled().then(|| self.report.clone())
+    }
+}
+
 // Note: Split, determine_split, hash_string are re-exported from pipeline.rs
 // Note: detect_input_format and detect_input_format_from_annotations
 // have been moved to detection.rs for better modularity.
@@ -262,6 +686,67 @@ mod tests {
         assert_eq!(polygon[3], (10.0, 30.0));
     }
 
+    #[test]
+    fn test_clip_circle() {
+        let build = |points: Vec<(f64, f64)>, shape_type: &str| (points, shape_type.to_string());
+
+        match clip_circle((50.0, 50.0), (60.0, 50.0), 100.0, 100.0, 0.0, build) {
+            ClipOutcome::Kept((points, shape_type)) => {
+                assert_eq!(points, vec![(50.0, 50.0), (60.0, 50.0)]);
+                assert_eq!(shape_type, "circle");
+            }
+            other => panic!("expected kept, got {:?}", other),
+        }
+
+        // Centered on the left border: the right half remains
+        match clip_circle((0.0, 50.0), (10.0, 50.0), 100.0, 100.0, 0.25, build) {
+            ClipOutcome::Clipped((points, shape_type)) => {
+                assert_eq!(shape_type, "polygon");
+                assert!(points.iter().all(|p| p.0 >= 0.0));
+                let full = calculate_polygon_area(&circle_to_polygon(
+                    (0.0, 50.0),
+                    10.0,
+                    CLIPPED_CIRCLE_SEGMENTS,
+                ));
+                assert!((calculate_polygon_area(&points) - full / 2.0).abs() < 1e-6);
+            }
+            other => panic!("expected clipped, got {:?}", other),
+        }
+        assert!(matches!(
+            clip_circle((0.0, 50.0), (10.0, 50.0), 100.0, 100.0, 0.75, build),
+            ClipOutcome::Dropped
+        ));
+    }
+
+    #[test]
+    fn test_shape_outline_circle_segments() {
+        let circle = Shape {
+            label: "test".to_string(),
+            points: vec![(50.0, 50.0), (60.0, 50.0)],
+            group_id: None,
+            shape_type: "circle".to_string(),
+            description: None,
+            mask: None,
+            flags: None,
+        };
+        let options = ShapeOptions {
+            circle_segments: 24,
+            ..ShapeOptions::default()
+        };
+
+        assert_eq!(shape_outline(&circle, &ShapeOptions::default()).len(), 12);
+        assert_eq!(shape_outline(&circle, &options).len(), 24);
+    }
+
+    #[test]
+    fn test_bbox_iou() {
+        let a = [0.0, 0.0, 10.0, 10.0];
+        assert!((bbox_iou(&a, &a) - 1.0).abs() < 1e-9);
+        assert!((bbox_iou(&a, &[5.0, 0.0, 10.0, 10.0]) - 50.0 / 150.0).abs() < 1e-9);
+        assert_eq!(bbox_iou(&a, &[20.0, 20.0, 5.0, 5.0]), 0.0);
+        assert_eq!(bbox_iou(&[0.0, 0.0, 0.0, 0.0], &[0.0, 0.0, 0.0, 0.0]), 0.0);
+    }
+
     #[test]
     fn test_polygon_area() {
         // Square 10x10
@@ -270,6 +755,45 @@ mod tests {
         assert!((area - 100.0).abs() < 0.001);
     }
 
+    #[test]
+    fn test_simplify_polygon() {
+        // Square with redundant midpoints on every edge
+        let points = vec![
+            (0.0, 0.0), (5.0, 0.0), (10.0, 0.0), (10.0, 5.0),
+            (10.0, 10.0), (5.0, 10.0), (0.0, 10.0), (0.0, 5.0),
+        ];
+        let simplified = simplify_polygon(&points, 0.5);
+        assert_eq!(simplified, vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)]);
+
+        // Tolerance 0 leaves the polygon untouched
+        assert_eq!(simplify_polygon(&points, 0.0), points);
+
+        let circle = circle_to_polygon((50.0, 50.0), 40.0, 300);
+        let capped = simplify_to_count(&circle, 20, 0.0);
+        assert!(capped.len() <= 20 && capped.len() >= 3);
+    }
+
+    #[test]
+    fn test_resample_polygon() {
+        let square = vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        let resampled = resample_polygon(&square, 8);
+        assert_eq!(resampled.len(), 8);
+        assert_eq!(resampled[0], (0.0, 0.0));
+        assert!((resampled[1].0 - 5.0).abs() < 1e-9 && resampled[1].1.abs() < 1e-9);
+        assert!((resampled[4].0 - 10.0).abs() < 1e-9 && (resampled[4].1 - 10.0).abs() < 1e-9);
+
+        let mut refiner = PolygonRefiner::new(PolygonOptions {
+            tolerance: 0.5,
+            max_points: 0,
+            resample_points: 0,
+        });
+        let octagon = resample_polygon(&square, 8);
+        assert_eq!(refiner.refine(&octagon).len(), 4);
+        let report = refiner.report().unwrap();
+        assert_eq!((report.polygons, report.vertices_before, report.vertices_after), (1, 8, 4));
+        assert!(PolygonRefiner::default().report().is_none());
+    }
+
     #[test]
     fn test_determine_split() {
         // Test with specific hash values
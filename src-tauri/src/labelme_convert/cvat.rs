@@ -0,0 +1,1149 @@
+//! CVAT for images 1.1 XML export and import
+//!
+//! Export writes a single `annotations.xml` next to an `images/` directory,
+//! which CVAT accepts as "CVAT for images 1.1". Shape mapping:
+//! - rectangle → `<box>`
+//! - polygon → `<polygon>`
+//! - line / linestrip → `<polyline>`
+//! - point → `<points>`
+//! - circle → `<ellipse>` with equal radii
+//!
+//! Shape `flags` become checkbox attributes, except `occluded` which maps to
+//! CVAT's occluded state. `group_id` is kept as CVAT's `group_id`. True
+//! image-level flags are exported as `<tag>` elements.
+//!
+//! The importer reverses the mapping so a LabelMe → CVAT → LabelMe round trip
+//! keeps labels, group ids and attributes. Image names are paths relative to
+//! the dataset root in both directions, so images with the same file name in
+//! different folders stay apart, and coordinates are written at full precision.
+
+use crate::labelme_convert::config::ConversionConfig;
+use crate::labelme_convert::io::{
+    escape_xml, extract_embedded_image, find_background_images, find_json_files, read_labelme_json,
+    resolve_image_path, setup_cvat_directories, write_file, write_labelme_json,
+};
+use crate::labelme_convert::pipeline::{
+    ConversionPipeline, FileType, OutputDirectories, ProcessedFileResult, ProcessingContext, Split,
+};
+use crate::labelme_convert::types::{
+    ConversionResult, InvalidAnnotation, InvalidReason, LabelMeAnnotation, Shape,
+};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+use std::sync::Mutex;
+
+/// Name of the annotation file inside the exported dataset
+pub const CVAT_ANNOTATION_FILE: &str = "annotations.xml";
+
+/// Shape flag mapped to CVAT's occluded state
+const OCCLUDED_FLAG: &str = "occluded";
+
+/// Number of vertices used when a rotated or non-circular ellipse becomes a polygon
+const ELLIPSE_POLYGON_POINTS: usize = 24;
+
+// ============================================================================
+// CVAT data structures
+// ============================================================================
+
+/// CVAT shape geometry
+#[derive(Debug, Clone, PartialEq)]
+pub enum CvatGeometry {
+    Box {
+        xtl: f64,
+        ytl: f64,
+        xbr: f64,
+        ybr: f64,
+    },
+    Polygon(Vec<(f64, f64)>),
+    Polyline(Vec<(f64, f64)>),
+    Points(Vec<(f64, f64)>),
+    Ellipse {
+        cx: f64,
+        cy: f64,
+        rx: f64,
+        ry: f64,
+        rotation: f64,
+    },
+}
+
+impl CvatGeometry {
+    fn tag_name(&self) -> &'static str {
+        match self {
+            CvatGeometry::Box { .. } => "box",
+            CvatGeometry::Polygon(_) => "polygon",
+            CvatGeometry::Polyline(_) => "polyline",
+            CvatGeometry::Points(_) => "points",
+            CvatGeometry::Ellipse { .. } => "ellipse",
+        }
+    }
+}
+
+/// One annotated shape
+#[derive(Debug, Clone, PartialEq)]
+pub struct CvatShape {
+    pub label: String,
+    pub geometry: CvatGeometry,
+    pub occluded: bool,
+    pub group_id: Option<i64>,
+    pub z_order: i64,
+    /// Attribute name → value, in output order
+    pub attributes: Vec<(String, String)>,
+}
+
+/// One `<image>` element
+#[derive(Debug, Clone, Default)]
+pub struct CvatImage {
+    pub id: usize,
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    pub shapes: Vec<CvatShape>,
+    /// Image-level tag labels
+    pub tags: Vec<String>,
+    /// Shapes of types LabelMe cannot represent (mask, cuboid, skeleton, ...)
+    pub unsupported: usize,
+}
+
+/// Label declaration in the task meta
+#[derive(Debug, Clone)]
+pub struct CvatLabel {
+    pub name: String,
+    /// Checkbox attribute names
+    pub attributes: Vec<String>,
+}
+
+// ============================================================================
+// LabelMe ↔ CVAT shape mapping
+// ============================================================================
+
+/// Convert a LabelMe shape to a CVAT shape
+pub fn shape_to_cvat(shape: &Shape, z_order: i64) -> Result<CvatShape, InvalidReason> {
+    if shape.points.is_empty() {
+        return Err(InvalidReason::EmptyPoints);
+    }
+
+    let geometry = match shape.shape_type.as_str() {
+        "rectangle" => {
+            if shape.points.len() != 2 {
+                return Err(InvalidReason::InsufficientPoints);
+            }
+            let (x1, y1) = shape.points[0];
+            let (x2, y2) = shape.points[1];
+            CvatGeometry::Box {
+                xtl: x1.min(x2),
+                ytl: y1.min(y2),
+                xbr: x1.max(x2),
+                ybr: y1.max(y2),
+            }
+        }
+        "polygon" => {
+            if shape.points.len() < 3 {
+                return Err(InvalidReason::InsufficientPoints);
+            }
+            CvatGeometry::Polygon(shape.points.clone())
+        }
+        "line" | "linestrip" => {
+            if shape.points.len() < 2 {
+                return Err(InvalidReason::InsufficientPoints);
+            }
+            CvatGeometry::Polyline(shape.points.clone())
+        }
+        "point" | "points" => CvatGeometry::Points(shape.points.clone()),
+        "circle" => {
+            if shape.points.len() < 2 {
+                return Err(InvalidReason::InsufficientPoints);
+            }
+            let (cx, cy) = shape.points[0];
+            let (px, py) = shape.points[1];
+            let radius = ((px - cx).powi(2) + (py - cy).powi(2)).sqrt();
+            CvatGeometry::Ellipse {
+                cx,
+                cy,
+                rx: radius,
+                ry: radius,
+                rotation: 0.0,
+            }
+        }
+        _ => return Err(InvalidReason::UnsupportedShape),
+    };
+
+    let flags = shape.flags.clone().unwrap_or_default();
+    let mut attributes: Vec<(String, String)> = flags
+        .iter()
+        .filter(|(name, _)| name.as_str() != OCCLUDED_FLAG)
+        .map(|(name, value)| (name.clone(), value.to_string()))
+        .collect();
+    attributes.sort();
+
+    Ok(CvatShape {
+        label: shape.label.clone(),
+        geometry,
+        occluded: flags.get(OCCLUDED_FLAG).copied().unwrap_or(false),
+        group_id: shape.group_id,
+        z_order,
+        attributes,
+    })
+}
+
+/// Convert a CVAT shape to LabelMe shapes
+///
+/// A `<points>` element with several points becomes one LabelMe point each.
+pub fn cvat_to_shapes(shape: &CvatShape) -> Vec<Shape> {
+    let mut flags: HashMap<String, bool> = HashMap::new();
+    let mut text_attributes = Vec::new();
+    for (name, value) in &shape.attributes {
+        match value.to_lowercase().as_str() {
+            "true" => {
+                flags.insert(name.clone(), true);
+            }
+            "false" => {
+                flags.insert(name.clone(), false);
+            }
+            _ => text_attributes.push(format!("{}={}", name, value)),
+        }
+    }
+    if shape.occluded {
+        flags.insert(OCCLUDED_FLAG.to_string(), true);
+    }
+
+    // Non-boolean attributes do not fit LabelMe flags; keep them in the description
+    let description = if text_attributes.is_empty() {
+        None
+    } else {
+        Some(text_attributes.join("\n"))
+    };
+
+    let make = |points: Vec<(f64, f64)>, shape_type: &str| Shape {
+        label: shape.label.clone(),
+        points,
+        group_id: shape.group_id,
+        shape_type: shape_type.to_string(),
+        description: description.clone(),
+        mask: None,
+        flags: Some(flags.clone()),
+    };
+
+    match &shape.geometry {
+        CvatGeometry::Box { xtl, ytl, xbr, ybr } => {
+            vec![make(vec![(*xtl, *ytl), (*xbr, *ybr)], "rectangle")]
+        }
+        CvatGeometry::Polygon(points) => vec![make(points.clone(), "polygon")],
+        CvatGeometry::Polyline(points) => {
+            let shape_type = if points.len() == 2 {
+                "line"
+            } else {
+                "linestrip"
+            };
+            vec![make(points.clone(), shape_type)]
+        }
+        CvatGeometry::Points(points) => points
+            .iter()
+            .map(|&point| make(vec![point], "point"))
+            .collect(),
+        CvatGeometry::Ellipse {
+            cx,
+            cy,
+            rx,
+            ry,
+            rotation,
+        } => {
+            if (rx - ry).abs() < 0.5 {
+                vec![make(vec![(*cx, *cy), (cx + rx, *cy)], "circle")]
+            } else {
+                vec![make(
+                    ellipse_to_polygon(*cx, *cy, *rx, *ry, *rotation),
+                    "polygon",
+                )]
+            }
+        }
+    }
+}
+
+/// Approximate a (rotated) ellipse with a polygon
+fn ellipse_to_polygon(cx: f64, cy: f64, rx: f64, ry: f64, rotation_deg: f64) -> Vec<(f64, f64)> {
+    let (sin_r, cos_r) = rotation_deg.to_radians().sin_cos();
+    (0..ELLIPSE_POLYGON_POINTS)
+        .map(|i| {
+            let t = 2.0 * std::f64::consts::PI * i as f64 / ELLIPSE_POLYGON_POINTS as f64;
+            let (x, y) = (rx * t.cos(), ry * t.sin());
+            (cx + x * cos_r - y * sin_r, cy + x * sin_r + y * cos_r)
+        })
+        .collect()
+}
+
+// ============================================================================
+// XML writing
+// ============================================================================
+
+fn format_points(points: &[(f64, f64)]) -> String {
+    points
+        .iter()
+        .map(|(x, y)| format!("{},{}", x, y))
+        .collect::<Vec<_>>()
+        .join(";")
+}
+
+fn write_shape_xml(xml: &mut String, shape: &CvatShape) {
+    let geometry_attrs = match &shape.geometry {
+        CvatGeometry::Box { xtl, ytl, xbr, ybr } => {
+            format!(r#"xtl="{}" ytl="{}" xbr="{}" ybr="{}""#, xtl, ytl, xbr, ybr)
+        }
+        CvatGeometry::Polygon(points)
+        | CvatGeometry::Polyline(points)
+        | CvatGeometry::Points(points) => format!(r#"points="{}""#, format_points(points)),
+        CvatGeometry::Ellipse {
+            cx,
+            cy,
+            rx,
+            ry,
+            rotation,
+        } => {
+            let mut attrs = format!(r#"cx="{}" cy="{}" rx="{}" ry="{}""#, cx, cy, rx, ry);
+            if *rotation != 0.0 {
+                attrs.push_str(&format!(r#" rotation="{}""#, rotation));
+            }
+            attrs
+        }
+    };
+
+    let tag = shape.geometry.tag_name();
+    xml.push_str(&format!(
+        r#"    <{} label="{}" source="manual" occluded="{}" {} z_order="{}""#,
+        tag,
+        escape_xml(&shape.label),
+        u8::from(shape.occluded),
+        geometry_attrs,
+        shape.z_order
+    ));
+    if let Some(group_id) = shape.group_id {
+        xml.push_str(&format!(r#" group_id="{}""#, group_id));
+    }
+
+    if shape.attributes.is_empty() {
+        xml.push_str("/>\n");
+        return;
+    }
+
+    xml.push_str(">\n");
+    for (name, value) in &shape.attributes {
+        xml.push_str(&format!(
+            "      <attribute name=\"{}\">{}</attribute>\n",
+            escape_xml(name),
+            escape_xml(value)
+        ));
+    }
+    xml.push_str(&format!("    </{}>\n", tag));
+}
+
+/// Render a complete CVAT for images 1.1 document
+pub fn write_cvat_xml(task_name: &str, labels: &[CvatLabel], images: &[CvatImage]) -> String {
+    let mut xml = String::new();
+    xml.push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    xml.push_str("<annotations>\n");
+    xml.push_str("  <version>1.1</version>\n");
+    xml.push_str("  <meta>\n    <task>\n");
+    xml.push_str(&format!("      <name>{}</name>\n", escape_xml(task_name)));
+    xml.push_str(&format!("      <size>{}</size>\n", images.len()));
+    xml.push_str("      <mode>annotation</mode>\n");
+    xml.push_str("      <labels>\n");
+    for label in labels {
+        xml.push_str("        <label>\n");
+        xml.push_str(&format!(
+            "          <name>{}</name>\n",
+            escape_xml(&label.name)
+        ));
+        xml.push_str("          <attributes>\n");
+        for attribute in &label.attributes {
+            xml.push_str("            <attribute>\n");
+            xml.push_str(&format!(
+                "              <name>{}</name>\n",
+                escape_xml(attribute)
+            ));
+            xml.push_str("              <mutable>False</mutable>\n");
+            xml.push_str("              <input_type>checkbox</input_type>\n");
+            xml.push_str("              <default_value>false</default_value>\n");
+            xml.push_str("              <values>false\ntrue</values>\n");
+            xml.push_str("            </attribute>\n");
+        }
+        xml.push_str("          </attributes>\n");
+        xml.push_str("        </label>\n");
+    }
+    xml.push_str("      </labels>\n");
+    xml.push_str("    </task>\n  </meta>\n");
+
+    for image in images {
+        xml.push_str(&format!(
+            "  <image id=\"{}\" name=\"{}\" width=\"{}\" height=\"{}\">\n",
+            image.id,
+            escape_xml(&image.name),
+            image.width,
+            image.height
+        ));
+        for shape in &image.shapes {
+            write_shape_xml(&mut xml, shape);
+        }
+        for tag in &image.tags {
+            xml.push_str(&format!(
+                "    <tag label=\"{}\" source=\"manual\"/>\n",
+                escape_xml(tag)
+            ));
+        }
+        xml.push_str("  </image>\n");
+    }
+
+    xml.push_str("</annotations>\n");
+    xml
+}
+
+// ============================================================================
+// XML parsing
+// ============================================================================
+
+fn parse_points(value: &str) -> Result<Vec<(f64, f64)>, String> {
+    value
+        .split(';')
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| {
+            let (x, y) = pair
+                .split_once(',')
+                .ok_or_else(|| format!("Invalid point: {}", pair))?;
+            Ok((parse_number(x)?, parse_number(y)?))
+        })
+        .collect()
+}
+
+fn parse_number(value: &str) -> Result<f64, String> {
+    value
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("Invalid number: {}", value))
+}
+
+fn number_attr(node: roxmltree::Node, name: &str) -> Result<f64, String> {
+    let value = node.attribute(name).ok_or_else(|| {
+        format!(
+            "<{}> is missing attribute '{}'",
+            node.tag_name().name(),
+            name
+        )
+    })?;
+    parse_number(value)
+}
+
+fn parse_shape(node: roxmltree::Node) -> Result<Option<CvatShape>, String> {
+    let points = || {
+        node.attribute("points")
+            .ok_or_else(|| format!("<{}> is missing attribute 'points'", node.tag_name().name()))
+            .and_then(parse_points)
+    };
+
+    let geometry = match node.tag_name().name() {
+        "box" => CvatGeometry::Box {
+            xtl: number_attr(node, "xtl")?,
+            ytl: number_attr(node, "ytl")?,
+            xbr: number_attr(node, "xbr")?,
+            ybr: number_attr(node, "ybr")?,
+        },
+        "polygon" => CvatGeometry::Polygon(points()?),
+        "polyline" => CvatGeometry::Polyline(points()?),
+        "points" => CvatGeometry::Points(points()?),
+        "ellipse" => CvatGeometry::Ellipse {
+            cx: number_attr(node, "cx")?,
+            cy: number_attr(node, "cy")?,
+            rx: number_attr(node, "rx")?,
+            ry: number_attr(node, "ry")?,
+            rotation: node
+                .attribute("rotation")
+                .map(parse_number)
+                .transpose()?
+                .unwrap_or(0.0),
+        },
+        _ => return Ok(None),
+    };
+
+    let attributes = node
+        .children()
+        .filter(|child| child.has_tag_name("attribute"))
+        .filter_map(|child| {
+            let name = child.attribute("name")?;
+            Some((
+                name.to_string(),
+                child.text().unwrap_or("").trim().to_string(),
+            ))
+        })
+        .collect();
+
+    let group_id = node
+        .attribute("group_id")
+        .map(|v| v.trim().parse::<i64>())
+        .transpose()
+        .map_err(|e| format!("Invalid group_id: {}", e))?;
+
+    Ok(Some(CvatShape {
+        label: node.attribute("label").unwrap_or_default().to_string(),
+        geometry,
+        occluded: node.attribute("occluded") == Some("1"),
+        group_id,
+        z_order: node
+            .attribute("z_order")
+            .and_then(|v| v.trim().parse().ok())
+            .unwrap_or(0),
+        attributes,
+    }))
+}
+
+/// Parse a CVAT for images 1.1 document
+pub fn parse_cvat_xml(content: &str) -> Result<Vec<CvatImage>, String> {
+    let document =
+        roxmltree::Document::parse(content).map_err(|e| format!("Failed to parse XML: {}", e))?;
+
+    let root = document.root_element();
+    if !root.has_tag_name("annotations") {
+        return Err(format!(
+            "Not a CVAT annotation file: root element is <{}>",
+            root.tag_name().name()
+        ));
+    }
+
+    let mut images = Vec::new();
+    for (index, node) in root
+        .children()
+        .filter(|n| n.has_tag_name("image"))
+        .enumerate()
+    {
+        let mut image = CvatImage {
+            id: node
+                .attribute("id")
+                .and_then(|v| v.parse().ok())
+                .unwrap_or(index),
+            name: node.attribute("name").unwrap_or_default().to_string(),
+            width: number_attr(node, "width")? as u32,
+            height: number_attr(node, "height")? as u32,
+            ..Default::default()
+        };
+
+        for child in node.children().filter(|n| n.is_element()) {
+            if child.has_tag_name("tag") {
+                if let Some(label) = child.attribute("label") {
+                    image.tags.push(label.to_string());
+                }
+                continue;
+            }
+            match parse_shape(child)? {
+                Some(shape) => image.shapes.push(shape),
+                None => image.unsupported += 1,
+            }
+        }
+
+        images.push(image);
+    }
+
+    Ok(images)
+}
+
+// ============================================================================
+// CvatPipeline: ConversionPipeline trait implementation
+// ============================================================================
+
+/// CVAT for images export pipeline
+///
+/// Images are collected while processing files and written as one XML
+/// document in `finalize`.
+#[derive(Default)]
+pub struct CvatPipeline {
+    images: Mutex<Vec<CvatImage>>,
+}
+
+impl CvatPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push_image(&self, mut image: CvatImage) {
+        let mut images = self.images.lock().unwrap_or_else(|e| e.into_inner());
+        image.id = images.len();
+        images.push(image);
+    }
+
+    /// Add images without annotation files as empty `<image>` entries
+    fn process_background_images(
+        &self,
+        config: &ConversionConfig,
+        output_dirs: &dyn OutputDirectories,
+        context: &ProcessingContext,
+    ) -> Vec<String> {
+        let bg_images = find_background_images(&config.input_dir, &context.processed_images);
+        let images_dir = output_dirs.get_output_dir(Split::None, FileType::Image);
+        let mut bg_files = Vec::new();
+
+        for image_path in bg_images {
+            let (width, height) = match imagesize::size(&image_path) {
+                Ok(size) => (size.width as u32, size.height as u32),
+                Err(e) => {
+                    eprintln!(
+                        "Failed to read background image size {}: {}",
+                        image_path.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+
+            let name = relative_image_name(&config.input_dir, &image_path);
+            if let Err(e) = copy_image_as(&image_path, images_dir, &name) {
+                eprintln!("{}", e);
+                continue;
+            }
+
+            bg_files.push(file_name_of(&image_path));
+            self.push_image(CvatImage {
+                name,
+                width,
+                height,
+                ..Default::default()
+            });
+        }
+
+        bg_files
+    }
+}
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// CVAT image name: the path below `root` with `/` separators
+///
+/// Images outside `root` fall back to their file name.
+fn relative_image_name(root: &Path, image_path: &Path) -> String {
+    match image_path.strip_prefix(root) {
+        Ok(relative) => relative_path(relative).to_string_lossy().replace('\\', "/"),
+        Err(_) => file_name_of(image_path),
+    }
+}
+
+/// The plain components of a relative path; roots, prefixes and `..` are dropped
+/// so imported names cannot escape the output directory
+fn relative_path(path: &Path) -> PathBuf {
+    path.components()
+        .filter_map(|c| match c {
+            Component::Normal(part) => Some(part),
+            _ => None,
+        })
+        .collect()
+}
+
+/// `images_dir/name`, with its subdirectories created
+fn image_dest(images_dir: &Path, name: &str) -> Result<PathBuf, String> {
+    let dest_path = images_dir.join(name);
+    if let Some(parent) = dest_path.parent() {
+        fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    Ok(dest_path)
+}
+
+/// Copy `source` to `images_dir/name`
+fn copy_image_as(source: &Path, images_dir: &Path, name: &str) -> Result<(), String> {
+    fs::copy(source, image_dest(images_dir, name)?)
+        .map_err(|e| format!("Failed to copy image {}: {}", source.display(), e))?;
+    Ok(())
+}
+
+impl ConversionPipeline for CvatPipeline {
+    fn needs_split(&self) -> bool {
+        false
+    }
+
+    fn setup_output_dirs(
+        &self,
+        config: &ConversionConfig,
+    ) -> Result<Box<dyn OutputDirectories>, String> {
+        let dirs = setup_cvat_directories(config)
+            .map_err(|e| format!("Failed to create output directories: {}", e))?;
+        Ok(Box::new(dirs))
+    }
+
+    fn process_file(
+        &self,
+        json_path: &Path,
+        config: &ConversionConfig,
+        output_dirs: &dyn OutputDirectories,
+        context: &mut ProcessingContext,
+    ) -> Result<ProcessedFileResult, String> {
+        let annotation = read_labelme_json(json_path)?;
+
+        let image_path = resolve_image_path(json_path, &annotation.image_path);
+        let image_key = image_path.to_string_lossy().to_string();
+
+        if context.is_image_processed(&image_key) {
+            return Ok(ProcessedFileResult::default());
+        }
+        context.mark_image_processed(image_key);
+
+        if config.label_list.is_empty() && !config.deterministic_labels {
+            for shape in &annotation.shapes {
+                context.ensure_label(&shape.label);
+            }
+        }
+
+        // Copy or extract image, keeping its folder below the input directory
+        let images_dir = output_dirs.get_output_dir(Split::None, FileType::Image);
+        let image_name = relative_image_name(&config.input_dir, &image_path);
+        if let Some(image_data) = &annotation.image_data {
+            extract_embedded_image(image_data, &image_dest(images_dir, &image_name)?)?;
+        } else if image_path.exists() {
+            copy_image_as(&image_path, images_dir, &image_name)?;
+        } else {
+            return Err(format!("Image file not found: {}", image_path.display()));
+        }
+
+        let json_file_name = file_name_of(json_path);
+        let mut shapes = Vec::new();
+        let mut skipped_count = 0;
+        let mut invalid_annotations = Vec::new();
+
+        for shape in &annotation.shapes {
+            if !context.label_map.contains_key(&shape.label) {
+                context.add_skipped_label(&shape.label);
+                skipped_count += 1;
+                continue;
+            }
+            match shape_to_cvat(shape, shapes.len() as i64) {
+                Ok(cvat_shape) => shapes.push(cvat_shape),
+                Err(reason) => {
+                    invalid_annotations.push(InvalidAnnotation {
+                        file: json_file_name.clone(),
+                        label: shape.label.clone(),
+                        reason: reason.as_str(),
+                        shape_type: shape.shape_type.clone(),
+                        points_count: shape.points.len(),
+                    });
+                    skipped_count += 1;
+                }
+            }
+        }
+
+        let mut tags: Vec<String> = annotation
+            .flags
+            .iter()
+            .flatten()
+            .filter(|(_, value)| **value)
+            .map(|(name, _)| name.clone())
+            .collect();
+        tags.sort();
+
+        let annotations_processed = shapes.len();
+        let is_filtered_empty = annotations_processed == 0
+            && !annotation.shapes.is_empty()
+            && !config.label_list.is_empty();
+
+        self.push_image(CvatImage {
+            name: image_name,
+            width: annotation.image_width,
+            height: annotation.image_height,
+            shapes,
+            tags,
+            ..Default::default()
+        });
+
+        Ok(ProcessedFileResult {
+            annotations_processed,
+            annotations_skipped: skipped_count,
+            invalid_annotations,
+            is_filtered_empty,
+            filtered_empty_file_name: if is_filtered_empty {
+                Some(json_file_name)
+            } else {
+                None
+            },
+        })
+    }
+
+    fn finalize(
+        &self,
+        config: &ConversionConfig,
+        output_dirs: &dyn OutputDirectories,
+        context: &ProcessingContext,
+    ) -> Result<(), String> {
+        let images = self.images.lock().unwrap_or_else(|e| e.into_inner());
+
+        // Declare every checkbox attribute used by each label
+        let mut attributes: HashMap<&str, BTreeSet<&str>> = HashMap::new();
+        for shape in images.iter().flat_map(|image| &image.shapes) {
+            let entry = attributes.entry(shape.label.as_str()).or_default();
+            entry.extend(shape.attributes.iter().map(|(name, _)| name.as_str()));
+        }
+
+        let mut sorted_labels: Vec<_> = context.label_map.iter().collect();
+        sorted_labels.sort_by_key(|(_, id)| *id);
+        let mut labels: Vec<CvatLabel> = sorted_labels
+            .into_iter()
+            .map(|(name, _)| CvatLabel {
+                name: name.clone(),
+                attributes: attributes
+                    .get(name.as_str())
+                    .map(|set| set.iter().map(|a| a.to_string()).collect())
+                    .unwrap_or_default(),
+            })
+            .collect();
+
+        // Tags need a label declaration too
+        let tag_labels: BTreeSet<&str> = images
+            .iter()
+            .flat_map(|image| image.tags.iter().map(|t| t.as_str()))
+            .filter(|tag| !context.label_map.contains_key(*tag))
+            .collect();
+        labels.extend(tag_labels.into_iter().map(|name| CvatLabel {
+            name: name.to_string(),
+            attributes: Vec::new(),
+        }));
+
+        let task_name = config
+            .input_dir
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "dataset".to_string());
+
+        let xml = write_cvat_xml(&task_name, &labels, &images);
+        let xml_path = output_dirs
+            .get_output_dir(Split::None, FileType::Annotation)
+            .join(CVAT_ANNOTATION_FILE);
+        write_file(&xml_path, &xml)
+            .map_err(|e| format!("Failed to write {}: {}", CVAT_ANNOTATION_FILE, e))
+    }
+}
+
+// ============================================================================
+// Public conversion function
+// ============================================================================
+
+/// Main CVAT export function
+pub fn convert_to_cvat(config: &ConversionConfig) -> ConversionResult {
+    if let Err(e) = config.validate() {
+        return ConversionResult::failure(vec![e]);
+    }
+
+    let pipeline = CvatPipeline::new();
+
+    let output_dirs = match pipeline.setup_output_dirs(config) {
+        Ok(dirs) => dirs,
+        Err(e) => return ConversionResult::failure(vec![e]),
+    };
+
+    let mut context = if config.label_list.is_empty() {
+        ProcessingContext::new()
+    } else {
+        ProcessingContext::with_labels(&config.label_list)
+    };
+
+    let json_files = find_json_files(&config.input_dir);
+    context.stats.total_files = json_files.len();
+
+    if config.deterministic_labels && config.label_list.is_empty() {
+        pipeline.gather_labels(&json_files, &mut context);
+    }
+
+    for json_path in &json_files {
+        match pipeline.process_file(json_path, config, output_dirs.as_ref(), &mut context) {
+            Ok(result) => {
+                context.stats.increment_processed();
+                context.stats.add_annotations(result.annotations_processed);
+                context
+                    .stats
+                    .add_skipped_annotations(result.annotations_skipped);
+                for invalid in result.invalid_annotations {
+                    context.stats.add_invalid_annotation(invalid);
+                }
+                if let Some(file_name) = result.filtered_empty_file_name {
+                    context.stats.add_filtered_empty_file(file_name);
+                }
+            }
+            Err(e) => {
+                context.stats.increment_failed();
+                context.add_error(format!("{}: {}", json_path.display(), e));
+            }
+        }
+    }
+
+    if config.include_background {
+        let bg_files = pipeline.process_background_images(config, output_dirs.as_ref(), &context);
+        for file_name in bg_files {
+            context.stats.add_background_file(file_name);
+        }
+    }
+
+    for label in context.label_map.keys() {
+        context.stats.add_label(label.clone());
+    }
+    for label in &context.skipped_labels {
+        context.stats.add_skipped_label(label.clone());
+    }
+
+    if let Err(e) = pipeline.finalize(config, output_dirs.as_ref(), &context) {
+        context.add_error(e);
+    }
+
+    let mut result = ConversionResult::success(
+        output_dirs.base_dir().to_string_lossy().to_string(),
+        context.stats,
+    );
+    result.errors = context.errors;
+    result
+}
+
+// ============================================================================
+// CVAT → LabelMe import
+// ============================================================================
+
+/// Result of importing a CVAT annotation file
+#[derive(Debug, Clone, Serialize)]
+pub struct CvatImportResult {
+    pub output_dir: String,
+    /// LabelMe JSON files written
+    pub images: usize,
+    /// LabelMe shapes written
+    pub shapes: usize,
+    /// CVAT shapes LabelMe cannot represent (mask, cuboid, skeleton, ...)
+    pub skipped_shapes: usize,
+    /// Images listed in the XML but not found in the image directory
+    pub missing_images: Vec<String>,
+    pub labels: Vec<String>,
+}
+
+/// Convert a CVAT for images XML file into LabelMe JSON files
+///
+/// One JSON is written per `<image>` into `output_dir`. When `images_dir` is
+/// given, images are copied next to their JSON; otherwise `imagePath` only
+/// holds the file name and the images are expected to be placed there later.
+pub fn import_cvat(
+    xml_path: &Path,
+    images_dir: Option<&Path>,
+    output_dir: &Path,
+) -> Result<CvatImportResult, String> {
+    let content = fs::read_to_string(xml_path)
+        .map_err(|e| format!("Failed to read {}: {}", xml_path.display(), e))?;
+    let images = parse_cvat_xml(&content)?;
+
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+
+    let mut result = CvatImportResult {
+        output_dir: output_dir.to_string_lossy().to_string(),
+        images: 0,
+        shapes: 0,
+        skipped_shapes: 0,
+        missing_images: Vec::new(),
+        labels: Vec::new(),
+    };
+    let mut labels = BTreeSet::new();
+
+    for image in &images {
+        // CVAT names may contain task subdirectories; keep them so images with
+        // the same file name in different folders do not overwrite each other
+        let mut relative = relative_path(Path::new(&image.name));
+        if relative.file_name().is_none() {
+            relative = PathBuf::from(format!("image_{}", image.id));
+        }
+        let file_name = file_name_of(&relative);
+        let name = relative.to_string_lossy().to_string();
+        let json_path = image_dest(output_dir, &name)?.with_extension("json");
+
+        if let Some(dir) = images_dir {
+            let source = [dir.join(&relative), dir.join(&file_name)]
+                .into_iter()
+                .find(|p| p.exists());
+            match source {
+                Some(source) => copy_image_as(&source, output_dir, &name)?,
+                None => result.missing_images.push(image.name.clone()),
+            }
+        }
+
+        let shapes: Vec<Shape> = image.shapes.iter().flat_map(cvat_to_shapes).collect();
+        labels.extend(shapes.iter().map(|s| s.label.clone()));
+        result.shapes += shapes.len();
+        result.skipped_shapes += image.unsupported;
+
+        let annotation = LabelMeAnnotation {
+            version: "5.0.1".to_string(),
+            flags: Some(image.tags.iter().map(|t| (t.clone(), true)).collect()),
+            shapes,
+            image_path: file_name,
+            image_data: None,
+            image_height: image.height,
+            image_width: image.width,
+        };
+        write_labelme_json(&json_path, &annotation)?;
+        result.images += 1;
+    }
+
+    result.labels = labels.into_iter().collect();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(label: &str, shape_type: &str, points: Vec<(f64, f64)>) -> Shape {
+        Shape {
+            label: label.to_string(),
+            points,
+            group_id: None,
+            shape_type: shape_type.to_string(),
+            description: None,
+            mask: None,
+            flags: None,
+        }
+    }
+
+    #[test]
+    fn test_shape_to_cvat_mapping() {
+        let rect = shape("car", "rectangle", vec![(30.0, 40.0), (10.0, 20.0)]);
+        let cvat = shape_to_cvat(&rect, 0).unwrap();
+        assert_eq!(
+            cvat.geometry,
+            CvatGeometry::Box {
+                xtl: 10.0,
+                ytl: 20.0,
+                xbr: 30.0,
+                ybr: 40.0
+            }
+        );
+
+        let line = shape(
+            "lane",
+            "linestrip",
+            vec![(0.0, 0.0), (1.0, 1.0), (2.0, 0.0)],
+        );
+        assert!(matches!(
+            shape_to_cvat(&line, 0).unwrap().geometry,
+            CvatGeometry::Polyline(_)
+        ));
+
+        let mask = shape("blob", "mask", vec![(0.0, 0.0), (1.0, 1.0)]);
+        assert!(matches!(
+            shape_to_cvat(&mask, 0),
+            Err(InvalidReason::UnsupportedShape)
+        ));
+    }
+
+    #[test]
+    fn test_round_trip_preserves_label_group_and_attributes() {
+        let mut polygon = shape(
+            "person",
+            "polygon",
+            vec![(1.5, 2.25), (10.123456789, 2.0), (10.0, 12.987654321)],
+        );
+        polygon.group_id = Some(3);
+        polygon.flags = Some(HashMap::from([
+            ("truncated".to_string(), true),
+            ("difficult".to_string(), false),
+            (OCCLUDED_FLAG.to_string(), true),
+        ]));
+        let point = shape("head", "point", vec![(5.0, 5.0)]);
+
+        let image = CvatImage {
+            name: "a & b.jpg".to_string(),
+            width: 640,
+            height: 480,
+            shapes: vec![
+                shape_to_cvat(&polygon, 0).unwrap(),
+                shape_to_cvat(&point, 1).unwrap(),
+            ],
+            tags: vec!["night".to_string()],
+            ..Default::default()
+        };
+        let labels = vec![CvatLabel {
+            name: "person".to_string(),
+            attributes: vec!["difficult".to_string(), "truncated".to_string()],
+        }];
+
+        let xml = write_cvat_xml("task", &labels, &[image]);
+        let parsed = parse_cvat_xml(&xml).unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed[0].name, "a & b.jpg");
+        assert_eq!(parsed[0].tags, vec!["night"]);
+        assert!(parsed[0].shapes[0].occluded);
+
+        let shapes: Vec<Shape> = parsed[0].shapes.iter().flat_map(cvat_to_shapes).collect();
+        assert_eq!(shapes.len(), 2);
+        assert_eq!(shapes[0].label, "person");
+        assert_eq!(shapes[0].shape_type, "polygon");
+        assert_eq!(shapes[0].group_id, Some(3));
+        assert_eq!(shapes[0].points, polygon.points);
+        assert_eq!(shapes[0].flags, polygon.flags);
+        assert_eq!(shapes[1].shape_type, "point");
+        assert_eq!(shapes[1].group_id, None);
+    }
+
+    #[test]
+    fn test_parse_cvat_xml_import_cases() {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+<annotations>
+  <version>1.1</version>
+  <image id="7" name="sub/img.png" width="100" height="50">
+    <ellipse label="ball" occluded="0" cx="10" cy="10" rx="5" ry="5" z_order="0"/>
+    <points label="kp" occluded="0" points="1,2;3,4" z_order="0"/>
+    <mask label="blob" occluded="0" rle="1, 2" left="0" top="0" width="2" height="2"/>
+    <box label="car" occluded="0" xtl="0" ytl="0" xbr="5" ybr="5" z_order="0">
+      <attribute name="color">red</attribute>
+    </box>
+  </image>
+</annotations>"#;
+
+        let images = parse_cvat_xml(xml).unwrap();
+        assert_eq!(images[0].id, 7);
+        assert_eq!(images[0].unsupported, 1);
+
+        let shapes: Vec<Shape> = images[0].shapes.iter().flat_map(cvat_to_shapes).collect();
+        assert_eq!(shapes[0].shape_type, "circle");
+        assert_eq!(shapes[0].points, vec![(10.0, 10.0), (15.0, 10.0)]);
+        assert_eq!(shapes[1].shape_type, "point");
+        assert_eq!(shapes[2].shape_type, "point");
+        assert_eq!(shapes[3].description.as_deref(), Some("color=red"));
+
+        assert!(parse_cvat_xml("<root/>").is_err());
+    }
+
+    #[test]
+    fn test_import_keeps_task_folders_apart() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let images_dir = root.join("images");
+        for task in ["task1", "task2"] {
+            fs::create_dir_all(images_dir.join(task)).unwrap();
+            fs::write(images_dir.join(task).join("frame.jpg"), task).unwrap();
+        }
+        let xml_path = root.join(CVAT_ANNOTATION_FILE);
+        fs::write(
+            &xml_path,
+            r#"<annotations>
+  <image id="0" name="task1/frame.jpg" width="10" height="10">
+    <box label="car" occluded="0" xtl="0" ytl="0" xbr="5" ybr="5" z_order="0"/>
+  </image>
+  <image id="1" name="task2/frame.jpg" width="10" height="10">
+    <box label="bus" occluded="0" xtl="0" ytl="0" xbr="5" ybr="5" z_order="0"/>
+  </image>
+</annotations>"#,
+        )
+        .unwrap();
+
+        let output_dir = root.join("labelme");
+        let result = import_cvat(&xml_path, Some(&images_dir), &output_dir).unwrap();
+        assert_eq!(result.images, 2);
+        for (task, label) in [("task1", "car"), ("task2", "bus")] {
+            let annotation = read_labelme_json(&output_dir.join(task).join("frame.json")).unwrap();
+            assert_eq!(annotation.shapes[0].label, label);
+            assert_eq!(annotation.image_path, "frame.jpg");
+            let image = fs::read_to_string(output_dir.join(task).join("frame.jpg")).unwrap();
+            assert_eq!(image, task);
+        }
+    }
+}
@@ -2,7 +2,8 @@
 // Provides intelligent detection of input annotation formats
 
 use crate::labelme_convert::io::{find_json_files, read_labelme_json};
-use crate::labelme_convert::types::{InputAnnotationFormat, Shape};
+use crate::labelme_convert::types::{DatasetSourceFormat, InputAnnotationFormat, Shape};
+use crate::labelme_convert::voc::{find_voc_root, find_voc_xml_files, read_voc_annotation};
 use serde::Serialize;
 use std::collections::HashMap;
 use std::path::Path;
@@ -24,6 +25,8 @@ pub struct DatasetAnalysis {
     pub points_distribution: HashMap<usize, usize>,
     /// Human-readable description of the detected format
     pub format_description: String,
+    /// File format of the dataset (LabelMe JSON or Pascal VOC XML)
+    pub source_format: DatasetSourceFormat,
 }
 
 impl Default for DatasetAnalysis {
@@ -36,6 +39,7 @@ impl Default for DatasetAnalysis {
             confidence: 0.0,
             points_distribution: HashMap::new(),
             format_description: "未知格式".to_string(),
+            source_format: DatasetSourceFormat::default(),
         }
     }
 }
@@ -77,6 +81,9 @@ pub fn analyze_dataset_with_config(input_dir: &Path, config: &AnalysisConfig) ->
     let json_files = find_json_files(input_dir);
 
     if json_files.is_empty() {
+        if let Some(voc_root) = find_voc_root(input_dir) {
+            return analyze_voc_dataset(&voc_root, config);
+        }
         return DatasetAnalysis {
             format_description: "找不到 JSON 檔案".to_string(),
             ..Default::default()
@@ -104,6 +111,31 @@ pub fn analyze_dataset_with_config(input_dir: &Path, config: &AnalysisConfig) ->
     analyze_shapes(&shape_refs, total_files, sample_files, config)
 }
 
+/// Analyze a Pascal VOC dataset by sampling its XML annotations
+fn analyze_voc_dataset(voc_root: &Path, config: &AnalysisConfig) -> DatasetAnalysis {
+    let xml_files = find_voc_xml_files(voc_root);
+    let total_files = xml_files.len();
+    let sample_files = total_files.min(config.max_sample_files);
+
+    let mut all_shapes: Vec<Shape> = Vec::new();
+    for xml_path in xml_files.iter().take(sample_files) {
+        if let Ok(annotation) = read_voc_annotation(xml_path) {
+            all_shapes.extend(annotation.shapes);
+            if all_shapes.len() >= config.max_sample_annotations {
+                break;
+            }
+        }
+    }
+
+    let shape_refs: Vec<&Shape> = all_shapes.iter().collect();
+    let analysis = analyze_shapes(&shape_refs, total_files, sample_files, config);
+    DatasetAnalysis {
+        format_description: format!("Pascal VOC：{}", analysis.format_description),
+        source_format: DatasetSourceFormat::PascalVoc,
+        ..analysis
+    }
+}
+
 /// Analyze a collection of shapes to determine the input format
 pub fn analyze_shapes(
     shapes: &[&Shape],
@@ -155,6 +187,7 @@ pub fn analyze_shapes(
         confidence,
         points_distribution,
         format_description,
+        source_format: DatasetSourceFormat::default(),
     }
 }
 
@@ -290,13 +323,17 @@ pub fn detect_input_format_from_annotations(
     detect_input_format(&shapes)
 }
 
-/// Validate shape points count based on detected input format
+/// Validate shape points count based on its shape type and the detected input format
 /// This is a shared validation function used by both YOLO and COCO converters
+///
+/// Shapes with an explicit non-polygon type (rectangle, circle, line, linestrip,
+/// point, mask) are checked against that type; polygons and untyped shapes are
+/// checked against the detected input format.
 pub fn validate_shape_points(
     shape: &Shape,
     input_format: InputAnnotationFormat,
 ) -> Result<(), crate::labelme_convert::types::InvalidReason> {
-    use crate::labelme_convert::types::InvalidReason;
+    use crate::labelme_convert::types::{InvalidReason, ShapeKind};
 
     let points_count = shape.points.len();
 
@@ -305,6 +342,34 @@ pub fn validate_shape_points(
         return Err(InvalidReason::EmptyPoints);
     }
 
+    let shape_kind = ShapeKind::of(shape);
+    let valid = match shape_kind {
+        ShapeKind::Rectangle => points_count == 2 || points_count == 4,
+        ShapeKind::Circle | ShapeKind::Line | ShapeKind::Mask => points_count == 2,
+        ShapeKind::LineStrip => points_count >= 2,
+        ShapeKind::Point => true,
+        ShapeKind::Polygon | ShapeKind::Other => {
+            return validate_format_points(points_count, input_format)
+        }
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(InvalidReason::ShapePointsMismatch {
+            shape_kind,
+            actual_points: points_count,
+        })
+    }
+}
+
+/// Validate a polygon's points count against the detected input format
+fn validate_format_points(
+    points_count: usize,
+    input_format: InputAnnotationFormat,
+) -> Result<(), crate::labelme_convert::types::InvalidReason> {
+    use crate::labelme_convert::types::InvalidReason;
+
     // Validate based on detected input format
     match input_format {
         InputAnnotationFormat::Bbox2Point => {
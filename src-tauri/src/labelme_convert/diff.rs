@@ -0,0 +1,590 @@
+//! Compare two versions of a LabelMe dataset
+//!
+//! Annotation files are paired by their path relative to the dataset root.
+//! Within a pair, shapes are matched greedily by bounding-box IoU; points,
+//! lines and line strips are matched by the distance between their vertices
+//! instead. A matched pair is reported as relabeled when the
+//! labels differ, as moved when the IoU is below `unchanged_iou` (or a point
+//! moved further than `vertex_tolerance`) and as reshaped when only its
+//! vertices were edited. Unmatched shapes are reported as added or removed.
+
+use crate::labelme_convert::config::ShapeOptions;
+use crate::labelme_convert::conversion::{bbox_iou, calculate_coco_bbox, shape_outline};
+use crate::labelme_convert::io::{find_json_files, read_labelme_json, write_file};
+use crate::labelme_convert::types::{Shape, ShapeKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+
+/// Options for comparing two dataset versions
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DiffConfig {
+    /// Directory of the older annotation round
+    pub old_dir: PathBuf,
+
+    /// Directory of the newer annotation round
+    pub new_dir: PathBuf,
+
+    /// Minimum IoU for two shapes to be considered the same object
+    #[serde(default = "default_match_iou")]
+    pub match_iou: f64,
+
+    /// Matched shapes with at least this IoU count as unchanged in position
+    #[serde(default = "default_unchanged_iou")]
+    pub unchanged_iou: f64,
+
+    /// Maximum mean vertex distance in pixels for matching shapes without area
+    #[serde(default = "default_match_distance")]
+    pub match_distance: f64,
+
+    /// Vertices of matched shapes moving further than this (pixels) count as edited
+    #[serde(default = "default_vertex_tolerance")]
+    pub vertex_tolerance: f64,
+
+    /// Optional path for writing the full diff as JSON
+    #[serde(default)]
+    pub report_path: Option<PathBuf>,
+}
+
+fn default_match_iou() -> f64 {
+    0.5
+}
+
+fn default_unchanged_iou() -> f64 {
+    0.95
+}
+
+fn default_match_distance() -> f64 {
+    10.0
+}
+
+fn default_vertex_tolerance() -> f64 {
+    0.5
+}
+
+impl DiffConfig {
+    /// Create a diff config with default thresholds
+    pub fn new(old_dir: PathBuf, new_dir: PathBuf) -> Self {
+        Self {
+            old_dir,
+            new_dir,
+            match_iou: default_match_iou(),
+            unchanged_iou: default_unchanged_iou(),
+            match_distance: default_match_distance(),
+            vertex_tolerance: default_vertex_tolerance(),
+            report_path: None,
+        }
+    }
+
+    /// Validate the configuration
+    pub fn validate(&self) -> Result<(), String> {
+        for dir in [&self.old_dir, &self.new_dir] {
+            if !dir.is_dir() {
+                return Err(format!("Directory does not exist: {}", dir.display()));
+            }
+        }
+        if !(0.0..=1.0).contains(&self.match_iou) || !(0.0..=1.0).contains(&self.unchanged_iou) {
+            return Err("IoU thresholds must be between 0.0 and 1.0".to_string());
+        }
+        if self.match_iou > self.unchanged_iou {
+            return Err(format!(
+                "match_iou ({}) must not exceed unchanged_iou ({})",
+                self.match_iou, self.unchanged_iou
+            ));
+        }
+        if self.match_distance < 0.0 || self.vertex_tolerance < 0.0 {
+            return Err("match_distance and vertex_tolerance must not be negative".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// A shape referenced in the diff
+#[derive(Debug, Clone, Serialize)]
+pub struct ShapeRef {
+    pub label: String,
+    pub shape_type: String,
+    /// [x, y, width, height] in pixels
+    pub bbox: [f64; 4],
+}
+
+/// A matched shape whose label or geometry changed
+#[derive(Debug, Clone, Serialize)]
+pub struct ShapeChange {
+    pub old: ShapeRef,
+    pub new: ShapeRef,
+    /// Bounding-box IoU (0.0 for shapes without area)
+    pub iou: f64,
+}
+
+/// Changes within one annotation file present in both versions
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct FileDiff {
+    /// JSON path relative to the dataset root
+    pub file: String,
+    pub added: Vec<ShapeRef>,
+    pub removed: Vec<ShapeRef>,
+    pub relabeled: Vec<ShapeChange>,
+    pub moved: Vec<ShapeChange>,
+    /// Same position and label, but vertices were added, removed or dragged
+    pub reshaped: Vec<ShapeChange>,
+}
+
+impl FileDiff {
+    fn is_empty(&self) -> bool {
+        self.added.is_empty()
+            && self.removed.is_empty()
+            && self.relabeled.is_empty()
+            && self.moved.is_empty()
+            && self.reshaped.is_empty()
+    }
+}
+
+/// Shape count change of one label
+#[derive(Debug, Clone, Serialize)]
+pub struct LabelDelta {
+    pub label: String,
+    pub old_count: usize,
+    pub new_count: usize,
+    pub delta: i64,
+}
+
+/// Totals for display in the UI
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DiffSummary {
+    pub old_files: usize,
+    pub new_files: usize,
+    pub images_added: usize,
+    pub images_removed: usize,
+    pub files_changed: usize,
+    pub files_unchanged: usize,
+    pub shapes_added: usize,
+    pub shapes_removed: usize,
+    pub shapes_relabeled: usize,
+    pub shapes_moved: usize,
+    pub shapes_reshaped: usize,
+}
+
+/// Full comparison result
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DatasetDiff {
+    pub summary: DiffSummary,
+    /// Annotation files only present in the new version
+    pub images_added: Vec<String>,
+    /// Annotation files only present in the old version
+    pub images_removed: Vec<String>,
+    /// Files present in both versions with at least one change
+    pub files: Vec<FileDiff>,
+    /// Per-label shape counts, sorted by label
+    pub label_deltas: Vec<LabelDelta>,
+    /// Files that could not be read
+    pub errors: Vec<String>,
+}
+
+/// Compare two LabelMe directories
+pub fn diff_datasets(config: &DiffConfig) -> Result<DatasetDiff, String> {
+    config.validate()?;
+
+    let old_files = index_json_files(&config.old_dir);
+    let new_files = index_json_files(&config.new_dir);
+
+    let mut diff = DatasetDiff::default();
+    diff.summary.old_files = old_files.len();
+    diff.summary.new_files = new_files.len();
+
+    let mut old_counts: HashMap<String, usize> = HashMap::new();
+    let mut new_counts: HashMap<String, usize> = HashMap::new();
+
+    for (key, old_path) in &old_files {
+        let old_shapes = match read_labelme_json(old_path) {
+            Ok(annotation) => annotation.shapes,
+            Err(e) => {
+                diff.errors.push(e);
+                continue;
+            }
+        };
+        count_labels(&old_shapes, &mut old_counts);
+
+        let Some(new_path) = new_files.get(key) else {
+            diff.images_removed.push(key.clone());
+            continue;
+        };
+        let new_shapes = match read_labelme_json(new_path) {
+            Ok(annotation) => annotation.shapes,
+            Err(e) => {
+                diff.errors.push(e);
+                continue;
+            }
+        };
+
+        let file_diff = diff_shapes(key, &old_shapes, &new_shapes, config);
+        if file_diff.is_empty() {
+            diff.summary.files_unchanged += 1;
+        } else {
+            diff.files.push(file_diff);
+        }
+    }
+
+    for (key, new_path) in &new_files {
+        match read_labelme_json(new_path) {
+            Ok(annotation) => count_labels(&annotation.shapes, &mut new_counts),
+            Err(e) => {
+                // Reported above when the file exists in both versions
+                if !old_files.contains_key(key) {
+                    diff.errors.push(e);
+                }
+                continue;
+            }
+        }
+        if !old_files.contains_key(key) {
+            diff.images_added.push(key.clone());
+        }
+    }
+
+    diff.label_deltas = label_deltas(&old_counts, &new_counts);
+
+    let summary = &mut diff.summary;
+    summary.images_added = diff.images_added.len();
+    summary.images_removed = diff.images_removed.len();
+    summary.files_changed = diff.files.len();
+    for file in &diff.files {
+        summary.shapes_added += file.added.len();
+        summary.shapes_removed += file.removed.len();
+        summary.shapes_relabeled += file.relabeled.len();
+        summary.shapes_moved += file.moved.len();
+        summary.shapes_reshaped += file.reshaped.len();
+    }
+
+    if let Some(report_path) = &config.report_path {
+        let json = serde_json::to_string_pretty(&diff)
+            .map_err(|e| format!("Failed to serialize diff: {}", e))?;
+        write_file(report_path, &json)
+            .map_err(|e| format!("Failed to write diff report: {}", e))?;
+    }
+
+    Ok(diff)
+}
+
+/// Compare the shapes of one annotation file
+pub fn diff_shapes(file: &str, old: &[Shape], new: &[Shape], config: &DiffConfig) -> FileDiff {
+    let old_refs: Vec<ShapeRef> = old.iter().map(shape_ref).collect();
+    let new_refs: Vec<ShapeRef> = new.iter().map(shape_ref).collect();
+
+    // All candidate pairs above the match threshold, best first; same-label
+    // pairs win ties so an unchanged shape is not reported as relabeled
+    let mut candidates = Vec::new();
+    for (i, old_ref) in old_refs.iter().enumerate() {
+        for (j, new_ref) in new_refs.iter().enumerate() {
+            let iou = bbox_iou(&old_ref.bbox, &new_ref.bbox);
+            let score = if has_area(&old[i]) && has_area(&new[j]) {
+                if iou < config.match_iou || iou <= 0.0 {
+                    continue;
+                }
+                iou
+            } else {
+                let distance = vertex_distance(&old[i], &new[j]);
+                if distance > config.match_distance {
+                    continue;
+                }
+                // Identical shapes score 1.0 like a perfect IoU
+                1.0 / (1.0 + distance)
+            };
+            candidates.push((score, iou, old_ref.label == new_ref.label, i, j));
+        }
+    }
+    candidates.sort_by(|a, b| b.0.total_cmp(&a.0).then(b.2.cmp(&a.2)));
+
+    let mut old_matched = vec![false; old_refs.len()];
+    let mut new_matched = vec![false; new_refs.len()];
+    let mut file_diff = FileDiff {
+        file: file.to_string(),
+        ..Default::default()
+    };
+
+    for (_, iou, same_label, i, j) in candidates {
+        if old_matched[i] || new_matched[j] {
+            continue;
+        }
+        old_matched[i] = true;
+        new_matched[j] = true;
+
+        let change = ShapeChange {
+            old: old_refs[i].clone(),
+            new: new_refs[j].clone(),
+            iou,
+        };
+        let area = has_area(&old[i]) && has_area(&new[j]);
+        if !same_label {
+            file_diff.relabeled.push(change);
+        } else if area && iou < config.unchanged_iou {
+            file_diff.moved.push(change);
+        } else if vertices_changed(&old[i], &new[j], config.vertex_tolerance) {
+            // Points and lines have no bbox overlap to tell moving from reshaping
+            if area {
+                file_diff.reshaped.push(change);
+            } else {
+                file_diff.moved.push(change);
+            }
+        }
+    }
+
+    for (i, matched) in old_matched.iter().enumerate() {
+        if !matched {
+            file_diff.removed.push(old_refs[i].clone());
+        }
+    }
+    for (j, matched) in new_matched.iter().enumerate() {
+        if !matched {
+            file_diff.added.push(new_refs[j].clone());
+        }
+    }
+
+    file_diff
+}
+
+/// Map relative JSON paths (without the root, `/`-separated) to absolute paths
+fn index_json_files(root: &Path) -> BTreeMap<String, PathBuf> {
+    find_json_files(root)
+        .into_iter()
+        .map(|path| {
+            let key = path
+                .strip_prefix(root)
+                .unwrap_or(&path)
+                .to_string_lossy()
+                .replace('\\', "/");
+            (key, path)
+        })
+        .collect()
+}
+
+fn shape_ref(shape: &Shape) -> ShapeRef {
+    ShapeRef {
+        label: shape.label.clone(),
+        shape_type: shape.shape_type.clone(),
+        bbox: calculate_coco_bbox(&shape_outline(shape, &ShapeOptions::default())),
+    }
+}
+
+/// Whether a shape covers an area and is matched by IoU (points and lines do not)
+fn has_area(shape: &Shape) -> bool {
+    !matches!(
+        ShapeKind::of(shape),
+        ShapeKind::Point | ShapeKind::Line | ShapeKind::LineStrip
+    )
+}
+
+fn distance(a: (f64, f64), b: (f64, f64)) -> f64 {
+    ((a.0 - b.0).powi(2) + (a.1 - b.1).powi(2)).sqrt()
+}
+
+/// Mean distance between corresponding vertices, in either direction
+///
+/// Shapes with different vertex counts are compared by their centroids.
+fn vertex_distance(old: &Shape, new: &Shape) -> f64 {
+    let (a, b) = (&old.points, &new.points);
+    if a.is_empty() || b.is_empty() {
+        return f64::INFINITY;
+    }
+    if a.len() != b.len() {
+        let centroid = |points: &[(f64, f64)]| {
+            let n = points.len() as f64;
+            let (x, y) = points
+                .iter()
+                .fold((0.0, 0.0), |acc, p| (acc.0 + p.0, acc.1 + p.1));
+            (x / n, y / n)
+        };
+        return distance(centroid(a), centroid(b));
+    }
+    let n = a.len() as f64;
+    let forward: f64 = a.iter().zip(b.iter()).map(|(p, q)| distance(*p, *q)).sum();
+    let reverse: f64 = a
+        .iter()
+        .zip(b.iter().rev())
+        .map(|(p, q)| distance(*p, *q))
+        .sum();
+    forward.min(reverse) / n
+}
+
+/// Whether the vertex lists of two matched shapes differ beyond `tolerance`
+///
+/// Reversing the vertex order alone is not an edit.
+fn vertices_changed(old: &Shape, new: &Shape, tolerance: f64) -> bool {
+    let (a, b) = (&old.points, &new.points);
+    let moved = |p: &(f64, f64), q: &(f64, f64)| distance(*p, *q) > tolerance;
+    a.len() != b.len()
+        || (a.iter().zip(b.iter()).any(|(p, q)| moved(p, q))
+            && a.iter().zip(b.iter().rev()).any(|(p, q)| moved(p, q)))
+}
+
+fn count_labels(shapes: &[Shape], counts: &mut HashMap<String, usize>) {
+    for shape in shapes {
+        *counts.entry(shape.label.clone()).or_insert(0) += 1;
+    }
+}
+
+fn label_deltas(old: &HashMap<String, usize>, new: &HashMap<String, usize>) -> Vec<LabelDelta> {
+    let mut labels: Vec<&String> = old.keys().chain(new.keys()).collect();
+    labels.sort();
+    labels.dedup();
+
+    labels
+        .into_iter()
+        .map(|label| {
+            let old_count = old.get(label).copied().unwrap_or(0);
+            let new_count = new.get(label).copied().unwrap_or(0);
+            LabelDelta {
+                label: label.clone(),
+                old_count,
+                new_count,
+                delta: new_count as i64 - old_count as i64,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rect(label: &str, x1: f64, y1: f64, x2: f64, y2: f64) -> Shape {
+        Shape {
+            label: label.to_string(),
+            points: vec![(x1, y1), (x2, y2)],
+            group_id: None,
+            shape_type: "rectangle".to_string(),
+            description: None,
+            mask: None,
+            flags: None,
+        }
+    }
+
+    fn shape(label: &str, shape_type: &str, points: Vec<(f64, f64)>) -> Shape {
+        Shape {
+            shape_type: shape_type.to_string(),
+            points,
+            ..rect(label, 0.0, 0.0, 0.0, 0.0)
+        }
+    }
+
+    fn config() -> DiffConfig {
+        DiffConfig::new(PathBuf::from("old"), PathBuf::from("new"))
+    }
+
+    #[test]
+    fn test_diff_shapes_classifies_changes() {
+        let old = vec![
+            rect("car", 0.0, 0.0, 10.0, 10.0),
+            rect("dog", 50.0, 50.0, 60.0, 60.0),
+            rect("cat", 100.0, 100.0, 110.0, 110.0),
+            rect("tree", 200.0, 200.0, 210.0, 210.0),
+        ];
+        let new = vec![
+            rect("car", 0.0, 0.0, 10.0, 10.0),
+            rect("cat", 50.0, 50.0, 60.0, 60.0),
+            rect("cat", 101.0, 100.0, 111.0, 110.0),
+            rect("bike", 300.0, 300.0, 310.0, 310.0),
+        ];
+
+        let diff = diff_shapes("a.json", &old, &new, &config());
+
+        assert_eq!(diff.relabeled.len(), 1);
+        assert_eq!(diff.relabeled[0].old.label, "dog");
+        assert_eq!(diff.relabeled[0].new.label, "cat");
+        assert_eq!(diff.moved.len(), 1);
+        assert_eq!(diff.moved[0].old.label, "cat");
+        assert_eq!(diff.removed.len(), 1);
+        assert_eq!(diff.removed[0].label, "tree");
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].label, "bike");
+    }
+
+    #[test]
+    fn test_diff_shapes_prefers_same_label() {
+        let old = vec![rect("car", 0.0, 0.0, 10.0, 10.0)];
+        let new = vec![
+            rect("truck", 0.0, 0.0, 10.0, 10.0),
+            rect("car", 0.0, 0.0, 10.0, 10.0),
+        ];
+
+        let diff = diff_shapes("a.json", &old, &new, &config());
+        assert!(diff.relabeled.is_empty());
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.added[0].label, "truck");
+    }
+
+    #[test]
+    fn test_diff_shapes_points_and_vertex_edits() {
+        let square = vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        let mut dented = square.clone();
+        dented.insert(1, (5.0, 3.0));
+        let old = vec![
+            shape("eye", "point", vec![(20.0, 20.0)]),
+            shape("nose", "point", vec![(40.0, 40.0)]),
+            shape("edge", "line", vec![(0.0, 50.0), (30.0, 50.0)]),
+            shape("roof", "polygon", square.clone()),
+        ];
+        let new = vec![
+            shape("eye", "point", vec![(20.0, 20.0)]),
+            shape("nose", "point", vec![(43.0, 44.0)]),
+            shape("edge", "line", vec![(30.0, 50.0), (0.0, 50.0)]),
+            shape("roof", "polygon", dented),
+        ];
+
+        let diff = diff_shapes("a.json", &old, &new, &config());
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert_eq!(diff.moved.len(), 1);
+        assert_eq!(diff.moved[0].old.label, "nose");
+        assert_eq!(diff.reshaped.len(), 1);
+        assert_eq!(diff.reshaped[0].old.label, "roof");
+
+        let unchanged = diff_shapes("a.json", &old, &old, &config());
+        assert!(unchanged.is_empty());
+    }
+
+    #[test]
+    fn test_diff_shapes_diagonal_lines_match_by_distance() {
+        let old = vec![
+            shape("cable", "line", vec![(0.0, 0.0), (100.0, 100.0)]),
+            shape(
+                "rail",
+                "linestrip",
+                vec![(0.0, 100.0), (50.0, 150.0), (100.0, 200.0)],
+            ),
+        ];
+        // Shifted by a few pixels, within match_distance
+        let new = vec![
+            shape("cable", "line", vec![(4.0, 3.0), (104.0, 103.0)]),
+            shape(
+                "rail",
+                "linestrip",
+                vec![(0.0, 100.0), (50.0, 150.0), (100.0, 200.0)],
+            ),
+        ];
+
+        let diff = diff_shapes("a.json", &old, &new, &config());
+        assert!(diff.added.is_empty() && diff.removed.is_empty());
+        assert!(diff.reshaped.is_empty());
+        assert_eq!(diff.moved.len(), 1);
+        assert_eq!(diff.moved[0].old.label, "cable");
+
+        // The crossing diagonal has the same bbox but is a different line
+        let crossing = vec![shape("cable", "line", vec![(0.0, 100.0), (100.0, 0.0)])];
+        let diff = diff_shapes("a.json", &old[..1], &crossing, &config());
+        assert_eq!(diff.added.len(), 1);
+        assert_eq!(diff.removed.len(), 1);
+    }
+
+    #[test]
+    fn test_label_deltas() {
+        let old: HashMap<String, usize> = [("car".to_string(), 3)].into_iter().collect();
+        let new: HashMap<String, usize> = [("car".to_string(), 1), ("bike".to_string(), 2)]
+            .into_iter()
+            .collect();
+
+        let deltas = label_deltas(&old, &new);
+        assert_eq!(deltas.len(), 2);
+        assert_eq!(deltas[0].label, "bike");
+        assert_eq!(deltas[0].delta, 2);
+        assert_eq!(deltas[1].delta, -2);
+    }
+}
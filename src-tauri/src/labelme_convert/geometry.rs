@@ -0,0 +1,325 @@
+//! Shape-type aware geometry for box and polygon exports
+//!
+//! Converts each LabelMe shape type into the outline the YOLO, COCO and
+//! bbox LabelMe exporters work with:
+//! - polygon: its points (2-point polygons in a 2-point bbox dataset become rectangles)
+//! - rectangle: the 4 corners of the box
+//! - mask: the contours of all its regions, joined into one outline (the box
+//!   when the bitmap is unreadable)
+//! - circle: a regular polygon of `circle_segments` vertices
+//! - line / linestrip: skipped, or buffered into a polygon of `line_width` pixels
+//! - point / points: skipped, or kept as keypoints boxed by `point_size` pixels
+
+use crate::labelme_convert::config::{LineShapeMode, PointShapeMode, ShapeOptions};
+use crate::labelme_convert::conversion::{circle_to_polygon, rectangle_to_polygon};
+use crate::labelme_convert::detection::validate_shape_points;
+use crate::labelme_convert::raster::mask_contours;
+use crate::labelme_convert::types::{InputAnnotationFormat, InvalidReason, Shape, ShapeKind};
+
+/// Miter length limit (in half-widths) at sharp line joints
+const MITER_LIMIT: f64 = 4.0;
+
+/// Geometry of a shape in pixel coordinates
+#[derive(Debug, Clone, PartialEq)]
+pub enum ShapeGeometry {
+    /// Closed outline
+    Outline(Vec<(f64, f64)>),
+    /// Keypoints and the box outline that encloses them
+    Keypoints {
+        points: Vec<(f64, f64)>,
+        outline: Vec<(f64, f64)>,
+    },
+    /// Dropped by the line/point settings
+    Skipped,
+}
+
+impl ShapeGeometry {
+    /// Outline to export, or None when the shape is skipped
+    pub fn outline(&self) -> Option<&[(f64, f64)]> {
+        match self {
+            ShapeGeometry::Outline(outline) | ShapeGeometry::Keypoints { outline, .. } => {
+                Some(outline)
+            }
+            ShapeGeometry::Skipped => None,
+        }
+    }
+}
+
+/// Validate a shape and convert it to exportable geometry
+pub fn shape_geometry(
+    shape: &Shape,
+    input_format: InputAnnotationFormat,
+    options: &ShapeOptions,
+) -> Result<ShapeGeometry, InvalidReason> {
+    validate_shape_points(shape, input_format)?;
+
+    let geometry = match ShapeKind::of(shape) {
+        ShapeKind::Rectangle => ShapeGeometry::Outline(rectangle_to_polygon(&shape.points)),
+        ShapeKind::Mask => {
+            let regions: Vec<_> = mask_contours(shape)
+                .into_iter()
+                .filter(|c| c.len() >= 3)
+                .collect();
+            if regions.is_empty() {
+                ShapeGeometry::Outline(rectangle_to_polygon(&shape.points))
+            } else {
+                ShapeGeometry::Outline(join_regions(regions))
+            }
+        }
+        ShapeKind::Circle => {
+            let (cx, cy) = shape.points[0];
+            let (px, py) = shape.points[1];
+            let radius = (px - cx).hypot(py - cy);
+            if radius <= 0.0 {
+                return Err(InvalidReason::ZeroArea);
+            }
+            ShapeGeometry::Outline(circle_to_polygon((cx, cy), radius, options.circle_segments))
+        }
+        ShapeKind::Line | ShapeKind::LineStrip => match options.line_mode {
+            LineShapeMode::Skip => ShapeGeometry::Skipped,
+            LineShapeMode::Buffer => {
+                let outline = buffer_polyline(&shape.points, options.line_width / 2.0);
+                if outline.is_empty() {
+                    return Err(InvalidReason::ZeroArea);
+                }
+                ShapeGeometry::Outline(outline)
+            }
+        },
+        ShapeKind::Point => match options.point_mode {
+            PointShapeMode::Skip => ShapeGeometry::Skipped,
+            PointShapeMode::Keypoint => ShapeGeometry::Keypoints {
+                outline: point_box(&shape.points, options.point_size),
+                points: shape.points.clone(),
+            },
+        },
+        ShapeKind::Polygon | ShapeKind::Other => match input_format {
+            InputAnnotationFormat::Bbox2Point => {
+                ShapeGeometry::Outline(rectangle_to_polygon(&shape.points))
+            }
+            _ => ShapeGeometry::Outline(shape.points.clone()),
+        },
+    };
+
+    Ok(geometry)
+}
+
+/// Join separate outlines into one, largest first
+///
+/// Each region is attached to the outline at its closest vertex by a
+/// zero-width bridge that is walked there and back, so the area of the
+/// result is the sum of the regions and its bounds cover all of them.
+pub fn join_regions(mut regions: Vec<Vec<(f64, f64)>>) -> Vec<(f64, f64)> {
+    if regions.is_empty() {
+        return Vec::new();
+    }
+    let mut outline = regions.remove(0);
+    for region in regions {
+        let mut closest = (f64::INFINITY, 0, 0);
+        for (i, p) in outline.iter().enumerate() {
+            for (j, q) in region.iter().enumerate() {
+                let distance = (p.0 - q.0).hypot(p.1 - q.1);
+                if distance < closest.0 {
+                    closest = (distance, i, j);
+                }
+            }
+        }
+        let (_, i, j) = closest;
+
+        let mut joined = Vec::with_capacity(outline.len() + region.len() + 2);
+        joined.extend_from_slice(&outline[..=i]);
+        joined.extend_from_slice(&region[j..]);
+        joined.extend_from_slice(&region[..=j]);
+        joined.extend_from_slice(&outline[i..]);
+        outline = joined;
+    }
+    outline
+}
+
+/// Expand a polyline into a closed polygon extending `half_width` to each side
+///
+/// Joints are mitered (capped at [`MITER_LIMIT`]); ends are cut square.
+/// Returns an empty outline for zero-length lines.
+pub fn buffer_polyline(points: &[(f64, f64)], half_width: f64) -> Vec<(f64, f64)> {
+    let mut path: Vec<(f64, f64)> = Vec::with_capacity(points.len());
+    for &p in points {
+        if path.last().is_none_or(|&q| q != p) {
+            path.push(p);
+        }
+    }
+    if path.len() < 2 || half_width <= 0.0 {
+        return Vec::new();
+    }
+
+    // Unit left normal of each segment
+    let normals: Vec<(f64, f64)> = path
+        .windows(2)
+        .map(|w| {
+            let (dx, dy) = (w[1].0 - w[0].0, w[1].1 - w[0].1);
+            let len = dx.hypot(dy);
+            (-dy / len, dx / len)
+        })
+        .collect();
+
+    let offsets: Vec<(f64, f64)> = (0..path.len())
+        .map(|i| {
+            let n = if i == 0 {
+                normals[0]
+            } else if i == path.len() - 1 {
+                normals[i - 1]
+            } else {
+                let (a, b) = (normals[i - 1], normals[i]);
+                let (mx, my) = (a.0 + b.0, a.1 + b.1);
+                let len = mx.hypot(my);
+                if len < 1e-9 {
+                    // Line doubles back on itself
+                    a
+                } else {
+                    let miter = (mx / len, my / len);
+                    let scale = 1.0 / (miter.0 * a.0 + miter.1 * a.1).max(1.0 / MITER_LIMIT);
+                    (miter.0 * scale, miter.1 * scale)
+                }
+            };
+            (n.0 * half_width, n.1 * half_width)
+        })
+        .collect();
+
+    let left = path.iter().zip(&offsets).map(|(p, o)| (p.0 + o.0, p.1 + o.1));
+    let right = path.iter().zip(&offsets).rev().map(|(p, o)| (p.0 - o.0, p.1 - o.1));
+    left.chain(right).collect()
+}
+
+/// Box of `size` pixels around a set of points
+pub fn point_box(points: &[(f64, f64)], size: f64) -> Vec<(f64, f64)> {
+    let half = size / 2.0;
+    let min_x = points.iter().map(|p| p.0).fold(f64::MAX, f64::min) - half;
+    let max_x = points.iter().map(|p| p.0).fold(f64::MIN, f64::max) + half;
+    let min_y = points.iter().map(|p| p.1).fold(f64::MAX, f64::min) - half;
+    let max_y = points.iter().map(|p| p.1).fold(f64::MIN, f64::max) + half;
+    vec![(min_x, min_y), (max_x, min_y), (max_x, max_y), (min_x, max_y)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labelme_convert::conversion::{calculate_coco_bbox, calculate_polygon_area};
+
+    fn shape(shape_type: &str, points: Vec<(f64, f64)>) -> Shape {
+        Shape {
+            label: "a".to_string(),
+            points,
+            group_id: None,
+            shape_type: shape_type.to_string(),
+            description: None,
+            mask: None,
+            flags: None,
+        }
+    }
+
+    #[test]
+    fn test_circle_geometry() {
+        let options = ShapeOptions::default();
+        let circle = shape("circle", vec![(50.0, 50.0), (60.0, 50.0)]);
+        let geometry = shape_geometry(&circle, InputAnnotationFormat::Polygon, &options).unwrap();
+        let outline = geometry.outline().unwrap();
+        assert_eq!(outline.len(), 12);
+
+        // Bounds are center ± radius, not the center-to-edge box
+        let min_x = outline.iter().map(|p| p.0).fold(f64::MAX, f64::min);
+        let max_y = outline.iter().map(|p| p.1).fold(f64::MIN, f64::max);
+        assert!((min_x - 40.0).abs() < 1e-9);
+        assert!((max_y - 60.0).abs() < 1e-9);
+
+        let bad = shape("circle", vec![(50.0, 50.0), (60.0, 50.0), (1.0, 1.0)]);
+        assert!(shape_geometry(&bad, InputAnnotationFormat::Polygon, &options).is_err());
+    }
+
+    #[test]
+    fn test_mask_geometry() {
+        use base64::Engine;
+        use std::io::Cursor;
+
+        // Right triangle of pixels below the diagonal
+        let mut bitmap = image::GrayImage::new(8, 8);
+        for y in 0..8 {
+            for x in 0..=y {
+                bitmap.put_pixel(x, y, image::Luma([255]));
+            }
+        }
+        let mut png = Vec::new();
+        bitmap
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let mut mask = shape("mask", vec![(100.0, 50.0), (108.0, 58.0)]);
+        mask.mask = Some(base64::engine::general_purpose::STANDARD.encode(png));
+        let options = ShapeOptions::default();
+        let geometry = shape_geometry(&mask, InputAnnotationFormat::Polygon, &options).unwrap();
+        let mut outline = geometry.outline().unwrap().to_vec();
+        outline.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(outline, vec![(100.0, 50.0), (100.0, 57.0), (107.0, 57.0)]);
+
+        // Both blocks of a split mask are exported
+        let mut bitmap = image::GrayImage::new(12, 4);
+        for y in 0..4 {
+            for x in (0..3).chain(6..12) {
+                bitmap.put_pixel(x, y, image::Luma([255]));
+            }
+        }
+        let mut png = Vec::new();
+        bitmap
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let mut split = shape("mask", vec![(0.0, 0.0), (12.0, 4.0)]);
+        split.mask = Some(base64::engine::general_purpose::STANDARD.encode(png));
+        let geometry = shape_geometry(&split, InputAnnotationFormat::Polygon, &options).unwrap();
+        let outline = geometry.outline().unwrap();
+        assert_eq!(outline.len(), 4 + 4 + 2);
+        // Pixel-center contours: 2x3 and 5x3
+        assert_eq!(calculate_polygon_area(outline), 6.0 + 15.0);
+        assert_eq!(calculate_coco_bbox(outline), [0.0, 0.0, 11.0, 3.0]);
+
+        // Without a bitmap the mask falls back to its box
+        mask.mask = None;
+        let geometry = shape_geometry(&mask, InputAnnotationFormat::Polygon, &options).unwrap();
+        assert_eq!(geometry.outline().unwrap().len(), 4);
+    }
+
+    #[test]
+    fn test_line_and_point_modes() {
+        let mut options = ShapeOptions::default();
+        let line = shape("line", vec![(10.0, 10.0), (20.0, 10.0)]);
+        let point = shape("point", vec![(5.0, 5.0)]);
+
+        let format = InputAnnotationFormat::Polygon;
+        assert_eq!(shape_geometry(&line, format, &options).unwrap(), ShapeGeometry::Skipped);
+        assert_eq!(shape_geometry(&point, format, &options).unwrap(), ShapeGeometry::Skipped);
+
+        options.line_mode = LineShapeMode::Buffer;
+        options.line_width = 4.0;
+        options.point_mode = PointShapeMode::Keypoint;
+        let buffered = shape_geometry(&line, format, &options).unwrap();
+        assert_eq!(
+            buffered.outline().unwrap(),
+            &[(10.0, 12.0), (20.0, 12.0), (20.0, 8.0), (10.0, 8.0)]
+        );
+        match shape_geometry(&point, format, &options).unwrap() {
+            ShapeGeometry::Keypoints { points, outline } => {
+                assert_eq!(points, vec![(5.0, 5.0)]);
+                assert_eq!(outline[0], (3.0, 3.0));
+                assert_eq!(outline[2], (7.0, 7.0));
+            }
+            other => panic!("expected keypoints, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_buffer_polyline_joint() {
+        // Right-angle strip: inner corner at (9, 1), mitered outer corner at (11, -1)
+        let outline = buffer_polyline(&[(0.0, 0.0), (10.0, 0.0), (10.0, 10.0)], 1.0);
+        assert_eq!(outline.len(), 6);
+        assert!((outline[1].0 - 9.0).abs() < 1e-9 && (outline[1].1 - 1.0).abs() < 1e-9);
+        assert!((outline[4].0 - 11.0).abs() < 1e-9 && (outline[4].1 + 1.0).abs() < 1e-9);
+
+        assert!(buffer_polyline(&[(1.0, 1.0), (1.0, 1.0)], 1.0).is_empty());
+    }
+}
@@ -7,7 +7,8 @@
 
 use crate::labelme_convert::config::ConversionConfig;
 use crate::labelme_convert::types::{
-    is_image_extension, CocoOutputDirs, LabelMeAnnotation, YoloOutputDirs,
+    is_image_extension, CocoOutputDirs, CvatOutputDirs, LabelMeAnnotation, LabelStudioOutputDirs,
+    MaskOutputDirs, YoloOutputDirs,
 };
 use std::fs::{self, File};
 use std::io::{BufReader, BufWriter, Write};
@@ -104,6 +105,74 @@ pub fn setup_labelme_directories(config: &ConversionConfig) -> std::io::Result<c
     })
 }
 
+/// Set up output directories for CVAT dataset (no split)
+pub fn setup_cvat_directories(config: &ConversionConfig) -> std::io::Result<CvatOutputDirs> {
+    let dataset_name = config.get_dataset_folder_name();
+    let base_dir = config.get_output_dir().join(&dataset_name);
+    let images_dir = base_dir.join("images");
+
+    fs::create_dir_all(&images_dir)?;
+
+    Ok(CvatOutputDirs {
+        base_dir,
+        images_dir,
+    })
+}
+
+/// Set up output directories for Label Studio tasks (no split)
+pub fn setup_label_studio_directories(
+    config: &ConversionConfig,
+) -> std::io::Result<LabelStudioOutputDirs> {
+    let dataset_name = config.get_dataset_folder_name();
+    let base_dir = config.get_output_dir().join(&dataset_name);
+    let images_dir = base_dir.join("images");
+
+    fs::create_dir_all(&images_dir)?;
+
+    Ok(LabelStudioOutputDirs {
+        base_dir,
+        images_dir,
+    })
+}
+
+/// Set up output directories for mask datasets
+///
+/// Creates `images/` and `masks/` per split, plus the same split folders
+/// under each of `extra_dirs` (e.g. `visualizations`).
+pub fn setup_mask_directories(
+    config: &ConversionConfig,
+    extra_dirs: &[&str],
+) -> std::io::Result<MaskOutputDirs> {
+    let dataset_name = config.get_dataset_folder_name();
+    let base_dir = config.get_output_dir().join(&dataset_name);
+
+    let mut splits = vec!["train", "val"];
+    if config.has_test_split() {
+        splits.push("test");
+    }
+    for folder in ["images", "masks"].iter().chain(extra_dirs) {
+        for split in &splits {
+            fs::create_dir_all(base_dir.join(folder).join(split))?;
+        }
+    }
+
+    let test_dir = |folder: &str| {
+        config
+            .has_test_split()
+            .then(|| base_dir.join(folder).join("test"))
+    };
+
+    Ok(MaskOutputDirs {
+        train_masks_dir: base_dir.join("masks").join("train"),
+        val_masks_dir: base_dir.join("masks").join("val"),
+        train_images_dir: base_dir.join("images").join("train"),
+        val_images_dir: base_dir.join("images").join("val"),
+        test_masks_dir: test_dir("masks"),
+        test_images_dir: test_dir("images"),
+        base_dir,
+    })
+}
+
 /// Read and parse a LabelMe JSON file
 pub fn read_labelme_json(path: &Path) -> Result<LabelMeAnnotation, String> {
     let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
@@ -170,7 +239,7 @@ pub fn copy_image(src: &Path, dest_dir: &Path) -> std::io::Result<PathBuf> {
 }
 
 /// Generate a unique file path by adding a numeric suffix
-fn generate_unique_path(path: &Path) -> PathBuf {
+pub fn generate_unique_path(path: &Path) -> PathBuf {
     let stem = path.file_stem().unwrap_or_default().to_string_lossy();
     let ext = path.extension().unwrap_or_default().to_string_lossy();
     let parent = path.parent().unwrap_or(Path::new("."));
@@ -255,14 +324,49 @@ pub fn sanitize_filename(name: &str) -> String {
         .collect()
 }
 
+/// Escape text for use in XML content and attribute values
+pub fn escape_xml(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&apos;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
 /// Create dataset.yaml file for YOLO
 pub fn create_dataset_yaml(
     output_dir: &Path,
     label_map: &std::collections::HashMap<String, usize>,
     has_test: bool,
 ) -> std::io::Result<()> {
-    let yaml_path = output_dir.join("dataset.yaml");
+    let test = if has_test { Some("images/test") } else { None };
+    write_dataset_yaml(
+        &output_dir.join("dataset.yaml"),
+        output_dir,
+        "images/train",
+        "images/val",
+        test,
+        label_map,
+    )
+}
 
+/// Write a YOLO dataset.yaml with the given train/val/test entries
+/// (relative to `dataset_root`)
+fn write_dataset_yaml(
+    yaml_path: &Path,
+    dataset_root: &Path,
+    train: &str,
+    val: &str,
+    test: Option<&str>,
+    label_map: &std::collections::HashMap<String, usize>,
+) -> std::io::Result<()> {
     // Sort labels by ID
     let mut sorted_labels: Vec<_> = label_map.iter().collect();
     sorted_labels.sort_by_key(|(_, id)| *id);
@@ -270,15 +374,15 @@ pub fn create_dataset_yaml(
     let mut content = String::new();
 
     // Use absolute path
-    let abs_path = fs::canonicalize(output_dir)
-        .unwrap_or_else(|_| output_dir.to_path_buf());
+    let abs_path = fs::canonicalize(dataset_root)
+        .unwrap_or_else(|_| dataset_root.to_path_buf());
 
     content.push_str(&format!("path: {}\n", abs_path.display()));
-    content.push_str("train: images/train\n");
-    content.push_str("val: images/val\n");
+    content.push_str(&format!("train: {}\n", train));
+    content.push_str(&format!("val: {}\n", val));
 
-    if has_test {
-        content.push_str("test: images/test\n");
+    if let Some(test) = test {
+        content.push_str(&format!("test: {}\n", test));
     } else {
         content.push_str("test:\n");
     }
@@ -289,7 +393,86 @@ pub fn create_dataset_yaml(
         content.push_str(&format!("  {}: {}\n", id, label));
     }
 
-    write_file(&yaml_path, &content)?;
+    write_file(yaml_path, &content)?;
+    Ok(())
+}
+
+/// Create dataset.yaml with a `train.txt` image list for a balanced YOLO dataset
+///
+/// Each train image is listed as often as its reference count, so oversampled
+/// images are repeated without copying the files.
+pub fn create_balanced_dataset_yaml(
+    output_dir: &Path,
+    label_map: &std::collections::HashMap<String, usize>,
+    has_test: bool,
+    train_images: &[(PathBuf, usize)],
+) -> std::io::Result<()> {
+    let mut train_list = String::new();
+    for (image_path, copies) in train_images {
+        let abs_image = fs::canonicalize(image_path).unwrap_or_else(|_| image_path.clone());
+        for _ in 0..*copies {
+            train_list.push_str(&format!("{}\n", abs_image.display()));
+        }
+    }
+    write_file(&output_dir.join("train.txt"), &train_list)?;
+
+    let test = if has_test { Some("images/test") } else { None };
+    write_dataset_yaml(
+        &output_dir.join("dataset.yaml"),
+        output_dir,
+        "train.txt",
+        "images/val",
+        test,
+        label_map,
+    )
+}
+
+/// Create k-fold cross-validation definitions for a YOLO dataset
+///
+/// Writes `folds/fold_N/{train.txt,val.txt,dataset.yaml}` for every fold.
+/// Fold N uses its own images as validation set and all other folds for
+/// training; the image files themselves are not duplicated. Images listed in
+/// `train_copies` are repeated that many times in the train lists.
+pub fn create_fold_datasets(
+    output_dir: &Path,
+    label_map: &std::collections::HashMap<String, usize>,
+    has_test: bool,
+    folds: usize,
+    fold_images: &[(usize, PathBuf)],
+    train_copies: &std::collections::HashMap<PathBuf, usize>,
+) -> std::io::Result<()> {
+    let test = if has_test { Some("images/test") } else { None };
+
+    for fold in 0..folds {
+        let fold_name = format!("fold_{}", fold + 1);
+        let fold_dir = output_dir.join("folds").join(&fold_name);
+        fs::create_dir_all(&fold_dir)?;
+
+        let mut train_list = String::new();
+        let mut val_list = String::new();
+        for (image_fold, image_path) in fold_images {
+            let abs_image = fs::canonicalize(image_path).unwrap_or_else(|_| image_path.clone());
+            let line = format!("{}\n", abs_image.display());
+            if *image_fold == fold {
+                val_list.push_str(&line);
+            } else {
+                let copies = train_copies.get(image_path).copied().unwrap_or(1);
+                train_list.push_str(&line.repeat(copies));
+            }
+        }
+
+        write_file(&fold_dir.join("train.txt"), &train_list)?;
+        write_file(&fold_dir.join("val.txt"), &val_list)?;
+        write_dataset_yaml(
+            &fold_dir.join("dataset.yaml"),
+            output_dir,
+            &format!("folds/{}/train.txt", fold_name),
+            &format!("folds/{}/val.txt", fold_name),
+            test,
+            label_map,
+        )?;
+    }
+
     Ok(())
 }
 
@@ -346,4 +529,35 @@ mod tests {
         let resolved = resolve_image_path(json_path, "/absolute/path/image.jpg");
         assert_eq!(resolved, PathBuf::from("/absolute/path/image.jpg"));
     }
+
+    #[test]
+    fn test_create_fold_datasets() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let base = temp_dir.path();
+        let mut label_map = std::collections::HashMap::new();
+        label_map.insert("defect".to_string(), 0);
+
+        let fold_images = vec![
+            (0, base.join("a.jpg")),
+            (1, base.join("b.jpg")),
+            (1, base.join("c.jpg")),
+        ];
+        let mut train_copies = std::collections::HashMap::new();
+        train_copies.insert(base.join("a.jpg"), 3);
+        create_fold_datasets(base, &label_map, false, 2, &fold_images, &train_copies).unwrap();
+
+        let fold2 = base.join("folds").join("fold_2");
+        let val = fs::read_to_string(fold2.join("val.txt")).unwrap();
+        let train = fs::read_to_string(fold2.join("train.txt")).unwrap();
+        assert_eq!(val.lines().count(), 2);
+        assert_eq!(train.lines().count(), 3);
+        assert!(train.contains("a.jpg"));
+
+        let yaml = fs::read_to_string(fold2.join("dataset.yaml")).unwrap();
+        assert!(yaml.contains("train: folds/fold_2/train.txt"));
+        assert!(yaml.contains("val: folds/fold_2/val.txt"));
+        assert!(yaml.contains("0: defect"));
+    }
 }
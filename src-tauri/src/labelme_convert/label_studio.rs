@@ -0,0 +1,1105 @@
+//! Label Studio JSON task export and import
+//!
+//! Export writes `tasks.json`, a matching `label_config.xml` and an `images/`
+//! directory. Tasks reference images through Label Studio's local-files
+//! storage (`/data/local-files/?d=<dataset>/images/<file>`), so the document
+//! root should be the conversion output directory. Shape mapping:
+//! - rectangle → `rectanglelabels`
+//! - polygon → `polygonlabels`
+//! - point → `keypointlabels`
+//! - circle → `ellipselabels` with equal radii
+//!
+//! Coordinates are percentages of the image size. A shape's `description` is
+//! carried as a per-region `textarea` result and its true `flags` as a
+//! per-region `choices` result sharing the region id. True image-level flags
+//! become a task-level `choices` result.
+//!
+//! The importer reverses the mapping so annotations made in Label Studio can
+//! go through the regular labelme_convert pipelines.
+
+use crate::labelme_convert::config::ConversionConfig;
+use crate::labelme_convert::io::{
+    copy_image, escape_xml, extract_embedded_image, find_background_images, find_json_files,
+    generate_unique_path, read_labelme_json, resolve_image_path, setup_label_studio_directories,
+    write_file, write_labelme_json,
+};
+use crate::labelme_convert::pipeline::{
+    ConversionPipeline, FileType, OutputDirectories, ProcessedFileResult, ProcessingContext, Split,
+};
+use crate::labelme_convert::types::{
+    ConversionResult, InvalidAnnotation, InvalidReason, LabelMeAnnotation, Shape,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Name of the task file inside the exported dataset
+pub const LABEL_STUDIO_TASKS_FILE: &str = "tasks.json";
+
+/// Name of the labeling config inside the exported dataset
+pub const LABEL_STUDIO_CONFIG_FILE: &str = "label_config.xml";
+
+/// URL prefix of Label Studio's local-files storage
+const LOCAL_FILES_PREFIX: &str = "/data/local-files/?d=";
+
+// Control tag names used in the generated labeling config
+const IMAGE_NAME: &str = "image";
+const RECTANGLE_NAME: &str = "label";
+const POLYGON_NAME: &str = "polygon";
+const KEYPOINT_NAME: &str = "keypoint";
+const ELLIPSE_NAME: &str = "ellipse";
+const DESCRIPTION_NAME: &str = "description";
+const FLAGS_NAME: &str = "flags";
+const IMAGE_FLAGS_NAME: &str = "image_flags";
+
+// ============================================================================
+// Label Studio data structures
+// ============================================================================
+
+/// One Label Studio task
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelStudioTask {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<u64>,
+    /// Task data; the image URL is under `image`
+    pub data: HashMap<String, Value>,
+    #[serde(default)]
+    pub annotations: Vec<LabelStudioAnnotation>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub predictions: Vec<LabelStudioAnnotation>,
+}
+
+/// One annotation (or prediction) of a task
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LabelStudioAnnotation {
+    #[serde(default)]
+    pub result: Vec<LabelStudioResult>,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub was_cancelled: bool,
+}
+
+/// One result item (region or per-region/task-level control value)
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LabelStudioResult {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub result_type: String,
+    #[serde(default)]
+    pub from_name: String,
+    #[serde(default)]
+    pub to_name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_width: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub original_height: Option<u32>,
+    #[serde(default)]
+    pub value: Value,
+}
+
+impl LabelStudioResult {
+    fn new(id: Option<String>, result_type: &str, from_name: &str, value: Value) -> Self {
+        Self {
+            id,
+            result_type: result_type.to_string(),
+            from_name: from_name.to_string(),
+            to_name: IMAGE_NAME.to_string(),
+            original_width: None,
+            original_height: None,
+            value,
+        }
+    }
+}
+
+// ============================================================================
+// LabelMe → Label Studio
+// ============================================================================
+
+fn to_percent(value: f64, size: u32) -> f64 {
+    value / size as f64 * 100.0
+}
+
+fn from_percent(value: f64, size: u32) -> f64 {
+    value * size as f64 / 100.0
+}
+
+/// Convert a LabelMe shape to Label Studio results
+///
+/// The first result is the region; `textarea` and `choices` results for the
+/// description and flags follow with the same id.
+pub fn shape_to_results(
+    shape: &Shape,
+    region_id: &str,
+    width: u32,
+    height: u32,
+) -> Result<Vec<LabelStudioResult>, InvalidReason> {
+    if shape.points.is_empty() {
+        return Err(InvalidReason::EmptyPoints);
+    }
+    if width == 0 || height == 0 {
+        return Err(InvalidReason::ZeroArea);
+    }
+
+    let px = |(x, y): (f64, f64)| (to_percent(x, width), to_percent(y, height));
+    let labels = json!([shape.label]);
+
+    let (result_type, from_name, value) = match shape.shape_type.as_str() {
+        "rectangle" => {
+            if shape.points.len() != 2 {
+                return Err(InvalidReason::InsufficientPoints);
+            }
+            let (x1, y1) = px(shape.points[0]);
+            let (x2, y2) = px(shape.points[1]);
+            let value = json!({
+                "x": x1.min(x2),
+                "y": y1.min(y2),
+                "width": (x2 - x1).abs(),
+                "height": (y2 - y1).abs(),
+                "rotation": 0,
+                "rectanglelabels": labels,
+            });
+            ("rectanglelabels", RECTANGLE_NAME, value)
+        }
+        "polygon" => {
+            if shape.points.len() < 3 {
+                return Err(InvalidReason::InsufficientPoints);
+            }
+            let points: Vec<[f64; 2]> = shape
+                .points
+                .iter()
+                .map(|&p| {
+                    let (x, y) = px(p);
+                    [x, y]
+                })
+                .collect();
+            let value = json!({ "points": points, "polygonlabels": labels });
+            ("polygonlabels", POLYGON_NAME, value)
+        }
+        "point" => {
+            let (x, y) = px(shape.points[0]);
+            let value = json!({ "x": x, "y": y, "width": 0.5, "keypointlabels": labels });
+            ("keypointlabels", KEYPOINT_NAME, value)
+        }
+        "circle" => {
+            if shape.points.len() < 2 {
+                return Err(InvalidReason::InsufficientPoints);
+            }
+            let (cx, cy) = shape.points[0];
+            let (ex, ey) = shape.points[1];
+            let radius = ((ex - cx).powi(2) + (ey - cy).powi(2)).sqrt();
+            let (x, y) = px((cx, cy));
+            let value = json!({
+                "x": x,
+                "y": y,
+                "radiusX": to_percent(radius, width),
+                "radiusY": to_percent(radius, height),
+                "rotation": 0,
+                "ellipselabels": labels,
+            });
+            ("ellipselabels", ELLIPSE_NAME, value)
+        }
+        _ => return Err(InvalidReason::UnsupportedShape),
+    };
+
+    let mut region =
+        LabelStudioResult::new(Some(region_id.to_string()), result_type, from_name, value);
+    region.original_width = Some(width);
+    region.original_height = Some(height);
+    let mut results = vec![region];
+
+    if let Some(description) = shape.description.as_ref().filter(|d| !d.is_empty()) {
+        results.push(LabelStudioResult::new(
+            Some(region_id.to_string()),
+            "textarea",
+            DESCRIPTION_NAME,
+            json!({ "text": [description] }),
+        ));
+    }
+
+    let choices = true_flags(shape.flags.as_ref());
+    if !choices.is_empty() {
+        results.push(LabelStudioResult::new(
+            Some(region_id.to_string()),
+            "choices",
+            FLAGS_NAME,
+            json!({ "choices": choices }),
+        ));
+    }
+
+    Ok(results)
+}
+
+/// Names of the flags set to true, sorted
+fn true_flags(flags: Option<&HashMap<String, bool>>) -> Vec<String> {
+    let mut names: Vec<String> = flags
+        .into_iter()
+        .flatten()
+        .filter(|(_, value)| **value)
+        .map(|(name, _)| name.clone())
+        .collect();
+    names.sort();
+    names
+}
+
+/// Percent-encode a path for the `d` query parameter
+fn encode_path(path: &str) -> String {
+    let mut encoded = String::with_capacity(path.len());
+    for byte in path.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+/// Decode `%XX` escapes in a URL component
+fn decode_path(path: &str) -> String {
+    let bytes = path.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        let hex = bytes
+            .get(i + 1..i + 3)
+            .and_then(|h| std::str::from_utf8(h).ok())
+            .and_then(|h| u8::from_str_radix(h, 16).ok());
+        match (bytes[i], hex) {
+            (b'%', Some(byte)) => {
+                decoded.push(byte);
+                i += 3;
+            }
+            (byte, _) => {
+                decoded.push(byte);
+                i += 1;
+            }
+        }
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
+
+/// Render a labeling config matching the exported tasks
+pub fn write_label_config(
+    labels: &[String],
+    shape_flags: &[String],
+    image_flags: &[String],
+) -> String {
+    let label_tags: String = labels
+        .iter()
+        .map(|l| format!("    <Label value=\"{}\"/>\n", escape_xml(l)))
+        .collect();
+    let choice_tags = |names: &[String]| -> String {
+        names
+            .iter()
+            .map(|n| format!("    <Choice value=\"{}\"/>\n", escape_xml(n)))
+            .collect()
+    };
+
+    let mut xml = String::from("<View>\n");
+    xml.push_str(&format!(
+        "  <Image name=\"{}\" value=\"${}\"/>\n",
+        IMAGE_NAME, IMAGE_NAME
+    ));
+    for (tag, name) in [
+        ("RectangleLabels", RECTANGLE_NAME),
+        ("PolygonLabels", POLYGON_NAME),
+        ("KeyPointLabels", KEYPOINT_NAME),
+        ("EllipseLabels", ELLIPSE_NAME),
+    ] {
+        xml.push_str(&format!(
+            "  <{} name=\"{}\" toName=\"{}\">\n",
+            tag, name, IMAGE_NAME
+        ));
+        xml.push_str(&label_tags);
+        xml.push_str(&format!("  </{}>\n", tag));
+    }
+    xml.push_str(&format!(
+        "  <TextArea name=\"{}\" toName=\"{}\" perRegion=\"true\" editable=\"true\"/>\n",
+        DESCRIPTION_NAME, IMAGE_NAME
+    ));
+    if !shape_flags.is_empty() {
+        xml.push_str(&format!(
+            "  <Choices name=\"{}\" toName=\"{}\" perRegion=\"true\" choice=\"multiple\">\n",
+            FLAGS_NAME, IMAGE_NAME
+        ));
+        xml.push_str(&choice_tags(shape_flags));
+        xml.push_str("  </Choices>\n");
+    }
+    if !image_flags.is_empty() {
+        xml.push_str(&format!(
+            "  <Choices name=\"{}\" toName=\"{}\" choice=\"multiple\">\n",
+            IMAGE_FLAGS_NAME, IMAGE_NAME
+        ));
+        xml.push_str(&choice_tags(image_flags));
+        xml.push_str("  </Choices>\n");
+    }
+    xml.push_str("</View>\n");
+    xml
+}
+
+// ============================================================================
+// Label Studio → LabelMe
+// ============================================================================
+
+fn value_f64(value: &Value, key: &str) -> Option<f64> {
+    value.get(key).and_then(Value::as_f64)
+}
+
+fn value_strings(value: &Value, key: &str) -> Vec<String> {
+    value
+        .get(key)
+        .and_then(Value::as_array)
+        .map(|items| {
+            items
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Convert a region result to a LabelMe shape (None for unsupported types)
+fn region_to_shape(result: &LabelStudioResult, width: u32, height: u32) -> Option<Shape> {
+    let value = &result.value;
+    let label = value_strings(value, &result.result_type)
+        .into_iter()
+        .next()?;
+    let pt = |x: f64, y: f64| (from_percent(x, width), from_percent(y, height));
+
+    let (shape_type, points) = match result.result_type.as_str() {
+        "rectanglelabels" => {
+            let x = value_f64(value, "x")?;
+            let y = value_f64(value, "y")?;
+            let w = value_f64(value, "width")?;
+            let h = value_f64(value, "height")?;
+            let rotation = value_f64(value, "rotation").unwrap_or(0.0);
+            if rotation.abs() < 1e-6 {
+                ("rectangle", vec![pt(x, y), pt(x + w, y + h)])
+            } else {
+                // Label Studio rotates around the top-left corner
+                let origin = pt(x, y);
+                let (sin_r, cos_r) = rotation.to_radians().sin_cos();
+                let (w, h) = (from_percent(w, width), from_percent(h, height));
+                let corners = [(0.0, 0.0), (w, 0.0), (w, h), (0.0, h)]
+                    .iter()
+                    .map(|(dx, dy)| {
+                        (
+                            origin.0 + dx * cos_r - dy * sin_r,
+                            origin.1 + dx * sin_r + dy * cos_r,
+                        )
+                    })
+                    .collect();
+                ("polygon", corners)
+            }
+        }
+        "polygonlabels" => {
+            let points = value
+                .get("points")?
+                .as_array()?
+                .iter()
+                .filter_map(|p| {
+                    let p = p.as_array()?;
+                    Some(pt(p.first()?.as_f64()?, p.get(1)?.as_f64()?))
+                })
+                .collect();
+            ("polygon", points)
+        }
+        "keypointlabels" => (
+            "point",
+            vec![pt(value_f64(value, "x")?, value_f64(value, "y")?)],
+        ),
+        "ellipselabels" => {
+            let center = pt(value_f64(value, "x")?, value_f64(value, "y")?);
+            let radius = from_percent(value_f64(value, "radiusX")?, width);
+            ("circle", vec![center, (center.0 + radius, center.1)])
+        }
+        _ => return None,
+    };
+
+    Some(Shape {
+        label,
+        points,
+        group_id: None,
+        shape_type: shape_type.to_string(),
+        description: None,
+        mask: None,
+        flags: Some(HashMap::new()),
+    })
+}
+
+/// Result types that describe a region
+fn is_region(result_type: &str) -> bool {
+    result_type.ends_with("labels")
+}
+
+/// Convert the results of one annotation to LabelMe shapes and image flags
+///
+/// Returns (shapes, image flags, unsupported region count).
+pub fn results_to_shapes(
+    results: &[LabelStudioResult],
+    width: u32,
+    height: u32,
+) -> (Vec<Shape>, HashMap<String, bool>, usize) {
+    let mut shapes: Vec<Shape> = Vec::new();
+    let mut region_index: HashMap<&str, usize> = HashMap::new();
+    let mut unsupported = 0;
+
+    for result in results.iter().filter(|r| is_region(&r.result_type)) {
+        match region_to_shape(result, width, height) {
+            Some(shape) => {
+                if let Some(id) = result.id.as_deref() {
+                    region_index.insert(id, shapes.len());
+                }
+                shapes.push(shape);
+            }
+            None => unsupported += 1,
+        }
+    }
+
+    let mut image_flags = HashMap::new();
+    for result in results.iter().filter(|r| !is_region(&r.result_type)) {
+        let region = result
+            .id
+            .as_deref()
+            .and_then(|id| region_index.get(id))
+            .map(|&i| &mut shapes[i]);
+
+        match (result.result_type.as_str(), region) {
+            ("textarea", Some(shape)) => {
+                let text = value_strings(&result.value, "text").join("\n");
+                shape.description = Some(text).filter(|t| !t.is_empty());
+            }
+            ("choices", Some(shape)) => {
+                let flags = shape.flags.get_or_insert_with(HashMap::new);
+                for choice in value_strings(&result.value, "choices") {
+                    flags.insert(choice, true);
+                }
+            }
+            ("choices", None) => {
+                for choice in value_strings(&result.value, "choices") {
+                    image_flags.insert(choice, true);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    (shapes, image_flags, unsupported)
+}
+
+/// Image reference of a task, with the local-files prefix removed
+fn task_image_path(task: &LabelStudioTask) -> Option<String> {
+    let url = task
+        .data
+        .get(IMAGE_NAME)
+        .or_else(|| task.data.values().find(|v| v.is_string()))?
+        .as_str()?;
+
+    let path = match url.find(LOCAL_FILES_PREFIX) {
+        Some(pos) => &url[pos + LOCAL_FILES_PREFIX.len()..],
+        None => url,
+    };
+    Some(decode_path(path))
+}
+
+// ============================================================================
+// LabelStudioPipeline: ConversionPipeline trait implementation
+// ============================================================================
+
+/// Label Studio task export pipeline
+///
+/// Tasks are collected while processing files and written in `finalize`.
+#[derive(Default)]
+pub struct LabelStudioPipeline {
+    tasks: Mutex<Vec<LabelStudioTask>>,
+}
+
+impl LabelStudioPipeline {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn push_task(&self, image_url: String, results: Vec<LabelStudioResult>) {
+        let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let id = tasks.len() as u64 + 1;
+        tasks.push(LabelStudioTask {
+            id: Some(id),
+            data: HashMap::from([(IMAGE_NAME.to_string(), Value::String(image_url))]),
+            annotations: vec![LabelStudioAnnotation {
+                result: results,
+                was_cancelled: false,
+            }],
+            predictions: Vec::new(),
+        });
+    }
+
+    /// Local-files URL of an exported image
+    fn image_url(config: &ConversionConfig, output_image: &Path) -> String {
+        let relative = output_image
+            .strip_prefix(config.get_output_dir())
+            .unwrap_or(output_image);
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        format!("{}{}", LOCAL_FILES_PREFIX, encode_path(&relative))
+    }
+
+    /// Add images without annotation files as tasks without results
+    fn process_background_images(
+        &self,
+        config: &ConversionConfig,
+        output_dirs: &dyn OutputDirectories,
+        context: &ProcessingContext,
+    ) -> Vec<String> {
+        let bg_images = find_background_images(&config.input_dir, &context.processed_images);
+        let images_dir = output_dirs.get_output_dir(Split::None, FileType::Image);
+        let mut bg_files = Vec::new();
+
+        for image_path in bg_images {
+            let output_image = match copy_image(&image_path, images_dir) {
+                Ok(path) => path,
+                Err(e) => {
+                    eprintln!(
+                        "Failed to copy background image {}: {}",
+                        image_path.display(),
+                        e
+                    );
+                    continue;
+                }
+            };
+
+            self.push_task(Self::image_url(config, &output_image), Vec::new());
+            bg_files.push(file_name_of(&image_path));
+        }
+
+        bg_files
+    }
+}
+
+fn file_name_of(path: &Path) -> String {
+    path.file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+impl ConversionPipeline for LabelStudioPipeline {
+    fn needs_split(&self) -> bool {
+        false
+    }
+
+    fn setup_output_dirs(
+        &self,
+        config: &ConversionConfig,
+    ) -> Result<Box<dyn OutputDirectories>, String> {
+        let dirs = setup_label_studio_directories(config)
+            .map_err(|e| format!("Failed to create output directories: {}", e))?;
+        Ok(Box::new(dirs))
+    }
+
+    fn process_file(
+        &self,
+        json_path: &Path,
+        config: &ConversionConfig,
+        output_dirs: &dyn OutputDirectories,
+        context: &mut ProcessingContext,
+    ) -> Result<ProcessedFileResult, String> {
+        let annotation = read_labelme_json(json_path)?;
+
+        let image_path = resolve_image_path(json_path, &annotation.image_path);
+        let image_key = image_path.to_string_lossy().to_string();
+
+        if context.is_image_processed(&image_key) {
+            return Ok(ProcessedFileResult::default());
+        }
+        context.mark_image_processed(image_key);
+
+        if config.label_list.is_empty() && !config.deterministic_labels {
+            for shape in &annotation.shapes {
+                context.ensure_label(&shape.label);
+            }
+        }
+
+        // Copy or extract image
+        let images_dir = output_dirs.get_output_dir(Split::None, FileType::Image);
+        let output_image = if let Some(image_data) = &annotation.image_data {
+            let mut dest_path = images_dir.join(file_name_of(&image_path));
+            if dest_path.exists() {
+                dest_path = generate_unique_path(&dest_path);
+            }
+            extract_embedded_image(image_data, &dest_path)?;
+            dest_path
+        } else if image_path.exists() {
+            copy_image(&image_path, images_dir)
+                .map_err(|e| format!("Failed to copy image: {}", e))?
+        } else {
+            return Err(format!("Image file not found: {}", image_path.display()));
+        };
+
+        let json_file_name = file_name_of(json_path);
+        let task_index = self.tasks.lock().map(|t| t.len()).unwrap_or_default();
+        let mut results = Vec::new();
+        let mut annotations_processed = 0;
+        let mut skipped_count = 0;
+        let mut invalid_annotations = Vec::new();
+
+        for (index, shape) in annotation.shapes.iter().enumerate() {
+            if !context.label_map.contains_key(&shape.label) {
+                context.add_skipped_label(&shape.label);
+                skipped_count += 1;
+                continue;
+            }
+            let region_id = format!("t{}_r{}", task_index + 1, index);
+            match shape_to_results(
+                shape,
+                &region_id,
+                annotation.image_width,
+                annotation.image_height,
+            ) {
+                Ok(shape_results) => {
+                    results.extend(shape_results);
+                    annotations_processed += 1;
+                }
+                Err(reason) => {
+                    invalid_annotations.push(InvalidAnnotation {
+                        file: json_file_name.clone(),
+                        label: shape.label.clone(),
+                        reason: reason.as_str(),
+                        shape_type: shape.shape_type.clone(),
+                        points_count: shape.points.len(),
+                    });
+                    skipped_count += 1;
+                }
+            }
+        }
+
+        let image_flags = true_flags(annotation.flags.as_ref());
+        if !image_flags.is_empty() {
+            results.push(LabelStudioResult::new(
+                None,
+                "choices",
+                IMAGE_FLAGS_NAME,
+                json!({ "choices": image_flags }),
+            ));
+        }
+
+        self.push_task(Self::image_url(config, &output_image), results);
+
+        let is_filtered_empty = annotations_processed == 0
+            && !annotation.shapes.is_empty()
+            && !config.label_list.is_empty();
+
+        Ok(ProcessedFileResult {
+            annotations_processed,
+            annotations_skipped: skipped_count,
+            invalid_annotations,
+            is_filtered_empty,
+            filtered_empty_file_name: if is_filtered_empty {
+                Some(json_file_name)
+            } else {
+                None
+            },
+        })
+    }
+
+    fn finalize(
+        &self,
+        _config: &ConversionConfig,
+        output_dirs: &dyn OutputDirectories,
+        context: &ProcessingContext,
+    ) -> Result<(), String> {
+        let tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let base_dir = output_dirs.get_output_dir(Split::None, FileType::Annotation);
+
+        let json = serde_json::to_string_pretty(&*tasks)
+            .map_err(|e| format!("Failed to serialize Label Studio tasks: {}", e))?;
+        write_file(&base_dir.join(LABEL_STUDIO_TASKS_FILE), &json)
+            .map_err(|e| format!("Failed to write {}: {}", LABEL_STUDIO_TASKS_FILE, e))?;
+
+        // Collect the choices used so the labeling config can show them
+        let mut shape_flags = BTreeSet::new();
+        let mut image_flags = BTreeSet::new();
+        for result in tasks
+            .iter()
+            .flat_map(|t| &t.annotations)
+            .flat_map(|a| &a.result)
+            .filter(|r| r.result_type == "choices")
+        {
+            let target = if result.from_name == IMAGE_FLAGS_NAME {
+                &mut image_flags
+            } else {
+                &mut shape_flags
+            };
+            target.extend(value_strings(&result.value, "choices"));
+        }
+
+        let mut labels: Vec<_> = context.label_map.iter().collect();
+        labels.sort_by_key(|(_, id)| *id);
+        let labels: Vec<String> = labels.into_iter().map(|(l, _)| l.clone()).collect();
+
+        let config_xml = write_label_config(
+            &labels,
+            &shape_flags.into_iter().collect::<Vec<_>>(),
+            &image_flags.into_iter().collect::<Vec<_>>(),
+        );
+        write_file(&base_dir.join(LABEL_STUDIO_CONFIG_FILE), &config_xml)
+            .map_err(|e| format!("Failed to write {}: {}", LABEL_STUDIO_CONFIG_FILE, e))
+    }
+}
+
+// ============================================================================
+// Public conversion function
+// ============================================================================
+
+/// Main Label Studio export function
+pub fn convert_to_label_studio(config: &ConversionConfig) -> ConversionResult {
+    if let Err(e) = config.validate() {
+        return ConversionResult::failure(vec![e]);
+    }
+
+    let pipeline = LabelStudioPipeline::new();
+
+    let output_dirs = match pipeline.setup_output_dirs(config) {
+        Ok(dirs) => dirs,
+        Err(e) => return ConversionResult::failure(vec![e]),
+    };
+
+    let mut context = if config.label_list.is_empty() {
+        ProcessingContext::new()
+    } else {
+        ProcessingContext::with_labels(&config.label_list)
+    };
+
+    let json_files = find_json_files(&config.input_dir);
+    context.stats.total_files = json_files.len();
+
+    if config.deterministic_labels && config.label_list.is_empty() {
+        pipeline.gather_labels(&json_files, &mut context);
+    }
+
+    for json_path in &json_files {
+        match pipeline.process_file(json_path, config, output_dirs.as_ref(), &mut context) {
+            Ok(result) => {
+                context.stats.increment_processed();
+                context.stats.add_annotations(result.annotations_processed);
+                context
+                    .stats
+                    .add_skipped_annotations(result.annotations_skipped);
+                for invalid in result.invalid_annotations {
+                    context.stats.add_invalid_annotation(invalid);
+                }
+                if let Some(file_name) = result.filtered_empty_file_name {
+                    context.stats.add_filtered_empty_file(file_name);
+                }
+            }
+            Err(e) => {
+                context.stats.increment_failed();
+                context.add_error(format!("{}: {}", json_path.display(), e));
+            }
+        }
+    }
+
+    if config.include_background {
+        let bg_files = pipeline.process_background_images(config, output_dirs.as_ref(), &context);
+        for file_name in bg_files {
+            context.stats.add_background_file(file_name);
+        }
+    }
+
+    for label in context.label_map.keys() {
+        context.stats.add_label(label.clone());
+    }
+    for label in &context.skipped_labels {
+        context.stats.add_skipped_label(label.clone());
+    }
+
+    if let Err(e) = pipeline.finalize(config, output_dirs.as_ref(), &context) {
+        context.add_error(e);
+    }
+
+    let mut result = ConversionResult::success(
+        output_dirs.base_dir().to_string_lossy().to_string(),
+        context.stats,
+    );
+    result.errors = context.errors;
+    result
+}
+
+// ============================================================================
+// Label Studio → LabelMe import
+// ============================================================================
+
+/// Result of importing a Label Studio export
+#[derive(Debug, Clone, Serialize)]
+pub struct LabelStudioImportResult {
+    pub output_dir: String,
+    /// LabelMe JSON files written
+    pub images: usize,
+    /// LabelMe shapes written
+    pub shapes: usize,
+    /// Regions LabelMe cannot represent (brush, text regions, ...)
+    pub skipped_shapes: usize,
+    /// Tasks without an image reference or without a known image size
+    pub skipped_tasks: usize,
+    /// Images referenced by tasks but not found below the images root
+    pub missing_images: Vec<String>,
+    pub labels: Vec<String>,
+}
+
+/// Convert a Label Studio JSON export into LabelMe JSON files
+///
+/// Uses the first annotation that was not cancelled, falling back to the
+/// first prediction. When `images_root` (the local-files document root) is
+/// given, images are copied next to their JSON (renamed after it when another
+/// task already used the file name) and used to read the image size for
+/// tasks without any region.
+pub fn import_label_studio(
+    tasks_path: &Path,
+    images_root: Option<&Path>,
+    output_dir: &Path,
+) -> Result<LabelStudioImportResult, String> {
+    let content = fs::read_to_string(tasks_path)
+        .map_err(|e| format!("Failed to read {}: {}", tasks_path.display(), e))?;
+    let value: Value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", tasks_path.display(), e))?;
+    // Exports are a task array; a single task object is accepted as well
+    let tasks: Vec<LabelStudioTask> = match value {
+        Value::Array(_) => serde_json::from_value(value),
+        _ => serde_json::from_value(value).map(|task| vec![task]),
+    }
+    .map_err(|e| format!("Invalid Label Studio tasks: {}", e))?;
+
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+
+    let mut result = LabelStudioImportResult {
+        output_dir: output_dir.to_string_lossy().to_string(),
+        images: 0,
+        shapes: 0,
+        skipped_shapes: 0,
+        skipped_tasks: 0,
+        missing_images: Vec::new(),
+        labels: Vec::new(),
+    };
+    let mut labels = BTreeSet::new();
+    let mut used_stems = HashSet::new();
+
+    for task in &tasks {
+        let Some(image_ref) = task_image_path(task) else {
+            result.skipped_tasks += 1;
+            continue;
+        };
+        let file_name = file_name_of(Path::new(&image_ref));
+
+        let source: Option<PathBuf> = images_root.and_then(|root| {
+            [root.join(&image_ref), root.join(&file_name)]
+                .into_iter()
+                .find(|p| p.is_file())
+        });
+        if images_root.is_some() && source.is_none() {
+            result.missing_images.push(image_ref.clone());
+        }
+
+        let annotation = task
+            .annotations
+            .iter()
+            .find(|a| !a.was_cancelled)
+            .or_else(|| task.predictions.first());
+        let results = annotation.map(|a| a.result.as_slice()).unwrap_or_default();
+
+        // Image size comes from the results, or from the image itself
+        let size = results
+            .iter()
+            .find_map(|r| Some((r.original_width?, r.original_height?)))
+            .or_else(|| {
+                let size = imagesize::size(source.as_ref()?).ok()?;
+                Some((size.width as u32, size.height as u32))
+            });
+        let Some((width, height)) = size else {
+            result.skipped_tasks += 1;
+            continue;
+        };
+
+        let stem = Path::new(&file_name)
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "image".to_string());
+        let mut json_stem = stem.clone();
+        let mut counter = 1;
+        while !used_stems.insert(json_stem.clone()) {
+            json_stem = format!("{}_{}", stem, counter);
+            counter += 1;
+        }
+
+        // Copied images share the JSON stem so same-named images from
+        // different task folders do not overwrite each other
+        let mut image_name = file_name;
+        if let Some(source) = &source {
+            if json_stem != stem {
+                image_name = match Path::new(&image_name).extension() {
+                    Some(ext) => format!("{}.{}", json_stem, ext.to_string_lossy()),
+                    None => json_stem.clone(),
+                };
+            }
+            fs::copy(source, output_dir.join(&image_name))
+                .map_err(|e| format!("Failed to copy image {}: {}", source.display(), e))?;
+        }
+
+        let (shapes, image_flags, unsupported) = results_to_shapes(results, width, height);
+        labels.extend(shapes.iter().map(|s| s.label.clone()));
+        result.shapes += shapes.len();
+        result.skipped_shapes += unsupported;
+
+        let annotation = LabelMeAnnotation {
+            version: "5.0.1".to_string(),
+            flags: Some(image_flags),
+            shapes,
+            image_path: image_name,
+            image_data: None,
+            image_height: height,
+            image_width: width,
+        };
+        write_labelme_json(&output_dir.join(format!("{}.json", json_stem)), &annotation)?;
+        result.images += 1;
+    }
+
+    result.labels = labels.into_iter().collect();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(label: &str, shape_type: &str, points: Vec<(f64, f64)>) -> Shape {
+        Shape {
+            label: label.to_string(),
+            points,
+            group_id: None,
+            shape_type: shape_type.to_string(),
+            description: None,
+            mask: None,
+            flags: None,
+        }
+    }
+
+    #[test]
+    fn test_rectangle_percent_coordinates() {
+        let rect = shape("car", "rectangle", vec![(300.0, 100.0), (100.0, 50.0)]);
+        let results = shape_to_results(&rect, "r1", 400, 200).unwrap();
+        assert_eq!(results.len(), 1);
+
+        let value = &results[0].value;
+        assert_eq!(results[0].result_type, "rectanglelabels");
+        assert_eq!(value_f64(value, "x"), Some(25.0));
+        assert_eq!(value_f64(value, "y"), Some(25.0));
+        assert_eq!(value_f64(value, "width"), Some(50.0));
+        assert_eq!(value_f64(value, "height"), Some(25.0));
+
+        let line = shape("lane", "line", vec![(0.0, 0.0), (1.0, 1.0)]);
+        assert!(matches!(
+            shape_to_results(&line, "r2", 400, 200),
+            Err(InvalidReason::UnsupportedShape)
+        ));
+    }
+
+    #[test]
+    fn test_round_trip_preserves_description_and_flags() {
+        let mut polygon = shape(
+            "person",
+            "polygon",
+            vec![(40.0, 20.0), (200.0, 20.0), (200.0, 100.0)],
+        );
+        polygon.description = Some("partly hidden".to_string());
+        polygon.flags = Some(HashMap::from([
+            ("truncated".to_string(), true),
+            ("difficult".to_string(), false),
+        ]));
+        let point = shape("head", "point", vec![(100.0, 50.0)]);
+
+        let mut results = shape_to_results(&polygon, "a", 400, 200).unwrap();
+        results.extend(shape_to_results(&point, "b", 400, 200).unwrap());
+        results.push(LabelStudioResult::new(
+            None,
+            "choices",
+            IMAGE_FLAGS_NAME,
+            json!({ "choices": ["night"] }),
+        ));
+
+        // Serialize like a real export file
+        let json = serde_json::to_string(&results).unwrap();
+        let results: Vec<LabelStudioResult> = serde_json::from_str(&json).unwrap();
+
+        let (shapes, image_flags, unsupported) = results_to_shapes(&results, 400, 200);
+        assert_eq!(unsupported, 0);
+        assert_eq!(shapes.len(), 2);
+        assert_eq!(shapes[0].label, "person");
+        assert_eq!(shapes[0].shape_type, "polygon");
+        for (actual, expected) in shapes[0].points.iter().zip(&polygon.points) {
+            assert!((actual.0 - expected.0).abs() < 1e-9);
+            assert!((actual.1 - expected.1).abs() < 1e-9);
+        }
+        assert_eq!(shapes[0].description.as_deref(), Some("partly hidden"));
+        assert_eq!(
+            shapes[0].flags.as_ref().unwrap().get("truncated"),
+            Some(&true)
+        );
+        assert_eq!(shapes[1].shape_type, "point");
+        assert_eq!(image_flags.get("night"), Some(&true));
+    }
+
+    #[test]
+    fn test_local_files_path() {
+        let task: LabelStudioTask = serde_json::from_value(json!({
+            "data": { "image": "/data/local-files/?d=set%201/images/a%2Bb.jpg" }
+        }))
+        .unwrap();
+        assert_eq!(
+            task_image_path(&task).as_deref(),
+            Some("set 1/images/a+b.jpg")
+        );
+        assert_eq!(
+            encode_path("set 1/images/a+b.jpg"),
+            "set%201/images/a%2Bb.jpg"
+        );
+    }
+
+    #[test]
+    fn test_import_same_image_names_from_different_folders() {
+        let input = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        for folder in ["day", "night"] {
+            fs::create_dir_all(input.path().join(folder)).unwrap();
+            fs::write(input.path().join(folder).join("frame.jpg"), folder).unwrap();
+        }
+        let region = json!({
+            "type": "rectanglelabels",
+            "original_width": 100,
+            "original_height": 50,
+            "value": { "x": 10, "y": 10, "width": 20, "height": 20, "rectanglelabels": ["car"] }
+        });
+        let tasks = json!([
+            {
+                "data": { "image": "/data/local-files/?d=day/frame.jpg" },
+                "annotations": [{ "result": [region.clone()] }]
+            },
+            {
+                "data": { "image": "/data/local-files/?d=night/frame.jpg" },
+                "annotations": [{ "result": [region] }]
+            }
+        ]);
+        let tasks_path = input.path().join("tasks.json");
+        fs::write(&tasks_path, tasks.to_string()).unwrap();
+
+        let result = import_label_studio(&tasks_path, Some(input.path()), output.path()).unwrap();
+        assert_eq!(result.images, 2);
+        assert!(result.missing_images.is_empty());
+
+        for (json_name, content) in [("frame.json", "day"), ("frame_1.json", "night")] {
+            let annotation = read_labelme_json(&output.path().join(json_name)).unwrap();
+            let image = fs::read_to_string(output.path().join(&annotation.image_path)).unwrap();
+            assert_eq!(image, content, "{}", json_name);
+        }
+    }
+}
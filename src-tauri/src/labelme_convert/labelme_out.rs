@@ -11,8 +11,8 @@
 //! Unlike YOLO/COCO conversion, this does NOT split the dataset into
 //! train/val/test sets.
 
-use crate::labelme_convert::config::{ConversionConfig, LabelMeOutputFormat};
-use crate::labelme_convert::detection::validate_shape_points;
+use crate::labelme_convert::config::{ConversionConfig, LabelMeOutputFormat, ShapeOptions};
+use crate::labelme_convert::geometry::shape_geometry;
 use crate::labelme_convert::io::{
     copy_image, find_background_images, find_json_files, read_labelme_json, resolve_image_path,
     setup_labelme_directories, write_labelme_json,
@@ -21,7 +21,7 @@ use crate::labelme_convert::pipeline::{
     ConversionPipeline, FileType, OutputDirectories, ProcessedFileResult, ProcessingContext, Split,
 };
 use crate::labelme_convert::types::{
-    ConversionResult, InputAnnotationFormat, InvalidAnnotation, Shape,
+    ConversionResult, InputAnnotationFormat, InvalidAnnotation, Shape, ShapeKind,
 };
 use std::collections::HashSet;
 use std::path::Path;
@@ -254,6 +254,7 @@ impl ConversionPipeline for LabelMePipeline {
             &config.label_list,
             input_format,
             config.labelme_output_format,
+            &config.shape_options,
             &json_filename,
             context,
         );
@@ -374,6 +375,7 @@ fn filter_and_validate_shapes(
     label_list: &[String],
     input_format: InputAnnotationFormat,
     output_format: LabelMeOutputFormat,
+    shape_options: &ShapeOptions,
     file_name: &str,
     context: &mut ProcessingContext,
 ) -> (Vec<Shape>, usize, Vec<InvalidAnnotation>) {
@@ -401,21 +403,45 @@ fn filter_and_validate_shapes(
             continue;
         }
 
-        // Validate points count based on detected input format
-        if let Err(reason) = validate_shape_points(shape, input_format) {
-            invalid_annotations.push(InvalidAnnotation {
-                file: file_name.to_string(),
-                label: shape.label.clone(),
-                reason: reason.as_str(),
-                shape_type: shape.shape_type.clone(),
-                points_count: shape.points.len(),
-            });
-            skipped += 1;
-            continue;
-        }
+        // Validate points count based on shape type and detected input format
+        let geometry = match shape_geometry(shape, input_format, shape_options) {
+            Ok(geometry) => geometry,
+            Err(reason) => {
+                invalid_annotations.push(InvalidAnnotation {
+                    file: file_name.to_string(),
+                    label: shape.label.clone(),
+                    reason: reason.as_str(),
+                    shape_type: shape.shape_type.clone(),
+                    points_count: shape.points.len(),
+                });
+                skipped += 1;
+                continue;
+            }
+        };
+
+        // Bbox output: circles, lines, points and masks are boxed by their outline
+        let outlined;
+        let source = match ShapeKind::of(shape) {
+            ShapeKind::Polygon | ShapeKind::Rectangle | ShapeKind::Other => shape,
+            _ if output_format == LabelMeOutputFormat::Original => shape,
+            _ => match geometry.outline() {
+                Some(outline) => {
+                    let mut polygon = shape.with_new_geometry(outline.to_vec(), "polygon");
+                    polygon.mask = None;
+                    outlined = polygon;
+                    &outlined
+                }
+                None => {
+                    context.stats.shape_types.record_skipped(&shape.shape_type);
+                    skipped += 1;
+                    continue;
+                }
+            },
+        };
 
         // Transform shape based on output format
-        let transformed_shape = transform_shape_for_output(shape, output_format);
+        let transformed_shape = transform_shape_for_output(source, output_format);
+        context.stats.shape_types.record_converted(&shape.shape_type);
 
         // Shape passed all checks, add it
         context.ensure_label(&transformed_shape.label);
@@ -578,6 +604,7 @@ mod tests {
             &[],
             InputAnnotationFormat::Bbox4Point,
             LabelMeOutputFormat::Original,
+            &ShapeOptions::default(),
             "test.json",
             &mut context,
         );
@@ -600,6 +627,7 @@ mod tests {
             &label_list,
             InputAnnotationFormat::Bbox4Point,
             LabelMeOutputFormat::Original,
+            &ShapeOptions::default(),
             "test.json",
             &mut context,
         );
@@ -626,6 +654,7 @@ mod tests {
             &[],
             InputAnnotationFormat::Bbox4Point,
             LabelMeOutputFormat::Original,
+            &ShapeOptions::default(),
             "test.json",
             &mut context,
         );
@@ -801,6 +830,7 @@ mod tests {
             &[],
             InputAnnotationFormat::Bbox2Point,
             LabelMeOutputFormat::Bbox4Point,
+            &ShapeOptions::default(),
             "test.json",
             &mut context,
         );
@@ -836,6 +866,7 @@ mod tests {
             &[],
             InputAnnotationFormat::Bbox4Point,
             LabelMeOutputFormat::Bbox2Point,
+            &ShapeOptions::default(),
             "test.json",
             &mut context,
         );
@@ -851,6 +882,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_filter_shapes_circle_and_line_to_bbox() {
+        let mut circle = create_rectangle_shape("ball", 50.0, 50.0, 60.0, 50.0);
+        circle.shape_type = "circle".to_string();
+        let mut line = create_rectangle_shape("rope", 0.0, 0.0, 10.0, 0.0);
+        line.shape_type = "line".to_string();
+        let shapes = vec![circle, line];
+
+        let mut context = ProcessingContext::new();
+        let (filtered, skipped, invalid) = filter_and_validate_shapes(
+            &shapes,
+            &[],
+            InputAnnotationFormat::Polygon,
+            LabelMeOutputFormat::Bbox2Point,
+            &ShapeOptions::default(),
+            "test.json",
+            &mut context,
+        );
+
+        // Circle boxed by center ± radius, line skipped by default
+        assert_eq!(filtered.len(), 1);
+        assert_eq!(skipped, 1);
+        assert_eq!(invalid.len(), 0);
+        assert_eq!(filtered[0].shape_type, "rectangle");
+        let (min, max) = (filtered[0].points[0], filtered[0].points[1]);
+        assert!((min.0 - 40.0).abs() < 1e-9 && (min.1 - 40.0).abs() < 1e-9);
+        assert!((max.0 - 60.0).abs() < 1e-9 && (max.1 - 60.0).abs() < 1e-9);
+        assert_eq!(context.stats.shape_types.skipped.get("line"), Some(&1));
+        assert_eq!(context.stats.shape_types.converted.get("circle"), Some(&1));
+    }
+
     // ========================================================================
     // Polygon to Bbox Tests
     // ========================================================================
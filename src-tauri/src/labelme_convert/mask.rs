@@ -0,0 +1,981 @@
+//! Segmentation mask export
+//!
+//! Two mask layouts are supported, both reusing the YOLO/COCO split planning:
+//!
+//! Semantic masks (`OutputFormat::SemanticMask`) are single-channel PNGs whose
+//! pixel values are class indices, the input format of DeepLab/SegFormer
+//! style trainers:
+//! - `images/{split}/`: copied images
+//! - `masks/{split}/`: class-index masks (`0` = background, classes from `1`)
+//! - `visualizations/{split}/`: masks rendered with the Pascal VOC palette
+//! - `classes.json`: index, name and palette color of every class
+//!
+//! Labels listed in `mask_ignore_labels` are painted with `mask_ignore_index`
+//! instead of a class index.
+//!
+//! Instance masks (`OutputFormat::InstanceMask`) are 16-bit PNGs whose pixel
+//! values are per-image instance IDs (`0` = background). Shapes sharing a
+//! label and `group_id` form one instance; `instances/{split}/{stem}.json`
+//! maps every instance ID to its class and group. `classes.json` and the
+//! visualizations are written as for semantic masks; ignored labels are left
+//! out of the instance masks.
+//!
+//! In both layouts polygons, rectangles, circles and masks are painted; lines
+//! and points cover no area and are reported as invalid. Overlaps are
+//! resolved by `mask_draw_order`.
+
+use crate::labelme_convert::config::{ConversionConfig, MaskDrawOrder};
+use crate::labelme_convert::io::{
+    copy_image, extract_embedded_image, find_background_images, find_json_files,
+    read_labelme_json, resolve_image_path, setup_mask_directories, write_file,
+};
+use crate::labelme_convert::pipeline::{
+    ConversionPipeline, FileType, OutputDirectories, ProcessedFileResult, ProcessingContext, Split,
+};
+use crate::labelme_convert::raster::{
+    colorize, colorize_instances, paint_order, palette_color, shape_pixels, InstanceImage,
+};
+use crate::labelme_convert::split::{plan_splits, resolve_split};
+use crate::labelme_convert::types::{
+    ConversionResult, InvalidAnnotation, InvalidReason, LabelMeAnnotation, Shape,
+};
+use image::{GrayImage, Luma};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+/// Name of the class table inside the exported dataset
+pub const MASK_CLASSES_FILE: &str = "classes.json";
+
+/// Folder holding the palette renderings of the masks
+const VISUALIZATION_DIR: &str = "visualizations";
+
+/// Folder holding the per-image instance tables of instance masks
+const INSTANCES_DIR: &str = "instances";
+
+/// Name of the background class (index 0), as used by labelme2voc
+pub const BACKGROUND_NAME: &str = "_background_";
+
+// ============================================================================
+// classes.json
+// ============================================================================
+
+/// One entry of `classes.json`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaskClass {
+    pub index: u8,
+    pub name: String,
+    /// RGB color used in the visualizations
+    pub color: [u8; 3],
+}
+
+/// Content of `classes.json`
+#[derive(Debug, Clone, Serialize)]
+pub struct MaskClasses {
+    pub background_index: u8,
+    pub ignore_index: u8,
+    pub ignore_labels: Vec<String>,
+    pub draw_order: MaskDrawOrder,
+    /// Background first, then classes by index
+    pub classes: Vec<MaskClass>,
+}
+
+/// Build the class table for a label map
+pub fn build_mask_classes(
+    label_map: &HashMap<String, usize>,
+    config: &ConversionConfig,
+) -> MaskClasses {
+    let mut sorted_labels: Vec<_> = label_map.iter().collect();
+    sorted_labels.sort_by_key(|(_, id)| *id);
+
+    let mut classes = vec![MaskClass {
+        index: 0,
+        name: BACKGROUND_NAME.to_string(),
+        color: palette_color(0),
+    }];
+    classes.extend(sorted_labels.into_iter().filter_map(|(name, id)| {
+        let index = class_value(*id, config.mask_ignore_index).ok()?;
+        Some(MaskClass {
+            index,
+            name: name.clone(),
+            color: palette_color(index),
+        })
+    }));
+
+    MaskClasses {
+        background_index: 0,
+        ignore_index: config.mask_ignore_index,
+        ignore_labels: config.mask_ignore_labels.clone(),
+        draw_order: config.mask_draw_order,
+        classes,
+    }
+}
+
+/// Pixel value of a label ID (shifted by one for the background)
+///
+/// Fails when the value does not fit into 8 bits or collides with the
+/// ignore index.
+pub fn class_value(class_id: usize, ignore_index: u8) -> Result<u8, String> {
+    match u8::try_from(class_id + 1) {
+        Ok(value) if value != ignore_index => Ok(value),
+        _ => Err(format!(
+            "Class index {} does not fit into an 8-bit mask with ignore index {}",
+            class_id + 1,
+            ignore_index
+        )),
+    }
+}
+
+/// Remove ignored labels from the label map and close the ID gaps they leave
+pub fn exclude_ignore_labels(label_map: &mut HashMap<String, usize>, ignore_labels: &[String]) {
+    if !ignore_labels.iter().any(|label| label_map.contains_key(label)) {
+        return;
+    }
+
+    let mut sorted_labels: Vec<(String, usize)> = label_map
+        .drain()
+        .filter(|(label, _)| !ignore_labels.contains(label))
+        .collect();
+    sorted_labels.sort_by_key(|(_, id)| *id);
+
+    for (id, (label, _)) in sorted_labels.into_iter().enumerate() {
+        label_map.insert(label, id);
+    }
+}
+
+// ============================================================================
+// Instance tables
+// ============================================================================
+
+/// One instance of an instance mask
+#[derive(Debug, Clone, Serialize)]
+pub struct MaskInstance {
+    /// Pixel value in the instance mask
+    pub id: u16,
+    pub label: String,
+    /// Class index as listed in `classes.json`
+    pub class_index: u8,
+    pub group_id: Option<i64>,
+    /// Number of LabelMe shapes merged into this instance
+    pub shape_count: usize,
+    /// Visible pixels after overlaps are resolved
+    pub area: usize,
+}
+
+/// Content of `instances/{split}/{stem}.json`
+#[derive(Debug, Clone, Serialize)]
+pub struct MaskInstances {
+    pub mask: String,
+    pub width: u32,
+    pub height: u32,
+    pub instances: Vec<MaskInstance>,
+}
+
+/// Group shapes into instances
+///
+/// Shapes sharing a label and `group_id` form one instance; shapes without a
+/// `group_id` are instances of their own. Instances are ordered by their
+/// first shape.
+pub fn group_instances(shapes: &[Shape]) -> Vec<Vec<usize>> {
+    let mut instances: Vec<Vec<usize>> = Vec::new();
+    let mut groups: HashMap<(&str, i64), usize> = HashMap::new();
+
+    for (index, shape) in shapes.iter().enumerate() {
+        match shape.group_id {
+            Some(group_id) => {
+                let slot = *groups
+                    .entry((shape.label.as_str(), group_id))
+                    .or_insert_with(|| {
+                        instances.push(Vec::new());
+                        instances.len() - 1
+                    });
+                instances[slot].push(index);
+            }
+            None => instances.push(vec![index]),
+        }
+    }
+    instances
+}
+
+// ============================================================================
+// Shared helpers
+// ============================================================================
+
+/// Image whose annotation is ready to be rasterized
+struct MaskSource {
+    annotation: LabelMeAnnotation,
+    split: Split,
+    image_stem: String,
+    /// JSON file name for error reporting
+    file_name: String,
+}
+
+/// Read an annotation, copy its image and resolve the split
+///
+/// Returns `None` for images that were already exported.
+fn prepare_source(
+    json_path: &Path,
+    config: &ConversionConfig,
+    output_dirs: &dyn OutputDirectories,
+    context: &mut ProcessingContext,
+) -> Result<Option<MaskSource>, String> {
+    let annotation = read_labelme_json(json_path)?;
+
+    let image_path = resolve_image_path(json_path, &annotation.image_path);
+    let image_key = image_path.to_string_lossy().to_string();
+
+    if context.is_image_processed(&image_key) {
+        return Ok(None);
+    }
+    context.mark_image_processed(image_key.clone());
+
+    let split = resolve_split(config, context.split_plan.as_ref(), &image_key);
+    let images_dir = output_dirs.get_output_dir(split, FileType::Image);
+
+    // Ignored labels are not classes and never get an ID
+    if config.label_list.is_empty() && !config.deterministic_labels {
+        for shape in &annotation.shapes {
+            if !config.mask_ignore_labels.contains(&shape.label) {
+                context.ensure_label(&shape.label);
+            }
+        }
+    }
+
+    if annotation.image_width == 0 || annotation.image_height == 0 {
+        return Err("Image size is missing from the annotation".to_string());
+    }
+
+    // Copy or extract image
+    let image_stem = image_path
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    let output_image = if let Some(image_data) = &annotation.image_data {
+        let ext = image_path
+            .extension()
+            .map(|e| e.to_string_lossy().to_string())
+            .unwrap_or_else(|| "png".to_string());
+        let dest_path = images_dir.join(format!("{}.{}", image_stem, ext));
+        extract_embedded_image(image_data, &dest_path)?;
+        dest_path
+    } else if image_path.exists() {
+        copy_image(&image_path, images_dir).map_err(|e| format!("Failed to copy image: {}", e))?
+    } else {
+        return Err(format!("Image file not found: {}", image_path.display()));
+    };
+
+    context.record_output_image(&image_key, split, output_image);
+
+    let file_name = json_path
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "unknown".to_string());
+
+    Ok(Some(MaskSource {
+        annotation,
+        split,
+        image_stem,
+        file_name,
+    }))
+}
+
+/// Pixels of a shape, or the invalid-annotation record if it covers none
+fn rasterize(
+    shape: &Shape,
+    width: u32,
+    height: u32,
+    file_name: &str,
+) -> Result<Vec<usize>, InvalidAnnotation> {
+    let pixels = shape_pixels(shape, width, height);
+    if !pixels.is_empty() {
+        return Ok(pixels);
+    }
+
+    let reason = match shape.shape_type.as_str() {
+        "polygon" | "rectangle" | "circle" | "mask" => InvalidReason::ZeroArea,
+        _ => InvalidReason::UnsupportedShape,
+    };
+    Err(InvalidAnnotation {
+        file: file_name.to_string(),
+        label: shape.label.clone(),
+        reason: reason.as_str(),
+        shape_type: shape.shape_type.clone(),
+        points_count: shape.points.len(),
+    })
+}
+
+/// Folder for a split next to the split's mask folder (e.g. `visualizations/val`)
+fn split_dir(output_dirs: &dyn OutputDirectories, split: Split, folder: &str) -> PathBuf {
+    let masks_dir = output_dirs.get_output_dir(split, FileType::Label);
+    let split_name = masks_dir.file_name().unwrap_or_default();
+    output_dirs.base_dir().join(folder).join(split_name)
+}
+
+/// Write a class-index mask and its palette rendering as `{stem}.png`
+fn write_semantic_mask(
+    mask: &GrayImage,
+    stem: &str,
+    output_dirs: &dyn OutputDirectories,
+    split: Split,
+) -> Result<(), String> {
+    let file_name = format!("{}.png", stem);
+    let masks_dir = output_dirs.get_output_dir(split, FileType::Label);
+
+    mask.save(masks_dir.join(&file_name))
+        .map_err(|e| format!("Failed to write mask {}: {}", file_name, e))?;
+    colorize(mask)
+        .save(split_dir(output_dirs, split, VISUALIZATION_DIR).join(&file_name))
+        .map_err(|e| format!("Failed to write visualization {}: {}", file_name, e))
+}
+
+/// Write an instance mask, its palette rendering and its instance table
+fn write_instance_mask(
+    mask: &InstanceImage,
+    instances: Vec<MaskInstance>,
+    stem: &str,
+    output_dirs: &dyn OutputDirectories,
+    split: Split,
+) -> Result<(), String> {
+    let file_name = format!("{}.png", stem);
+    let masks_dir = output_dirs.get_output_dir(split, FileType::Label);
+
+    mask.save(masks_dir.join(&file_name))
+        .map_err(|e| format!("Failed to write mask {}: {}", file_name, e))?;
+    colorize_instances(mask)
+        .save(split_dir(output_dirs, split, VISUALIZATION_DIR).join(&file_name))
+        .map_err(|e| format!("Failed to write visualization {}: {}", file_name, e))?;
+
+    let table = MaskInstances {
+        mask: file_name,
+        width: mask.width(),
+        height: mask.height(),
+        instances,
+    };
+    let content = serde_json::to_string_pretty(&table)
+        .map_err(|e| format!("Failed to serialize instances of {}: {}", stem, e))?;
+    let path = split_dir(output_dirs, split, INSTANCES_DIR).join(format!("{}.json", stem));
+    write_file(&path, &content).map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Write `classes.json` into the dataset root
+fn write_mask_classes(
+    config: &ConversionConfig,
+    output_dirs: &dyn OutputDirectories,
+    context: &ProcessingContext,
+) -> Result<(), String> {
+    let classes = build_mask_classes(&context.label_map, config);
+    let content = serde_json::to_string_pretty(&classes)
+        .map_err(|e| format!("Failed to serialize {}: {}", MASK_CLASSES_FILE, e))?;
+    let path = output_dirs
+        .get_output_dir(Split::None, FileType::Annotation)
+        .join(MASK_CLASSES_FILE);
+    write_file(&path, &content).map_err(|e| format!("Failed to write {}: {}", MASK_CLASSES_FILE, e))
+}
+
+/// Build the per-file result once the shapes have been painted
+fn file_result(
+    source: &MaskSource,
+    config: &ConversionConfig,
+    annotations_processed: usize,
+    annotations_skipped: usize,
+    invalid_annotations: Vec<InvalidAnnotation>,
+) -> ProcessedFileResult {
+    let is_filtered_empty = annotations_processed == 0
+        && !source.annotation.shapes.is_empty()
+        && !config.label_list.is_empty();
+
+    ProcessedFileResult {
+        annotations_processed,
+        annotations_skipped,
+        invalid_annotations,
+        is_filtered_empty,
+        filtered_empty_file_name: if is_filtered_empty {
+            Some(source.file_name.clone())
+        } else {
+            None
+        },
+    }
+}
+
+/// Mask pipelines differ only in how shapes are painted and how an empty
+/// (background) mask is written
+trait MaskPipeline: ConversionPipeline {
+    /// Folders created per split next to `images/` and `masks/`
+    fn extra_dirs(&self) -> &'static [&'static str];
+
+    /// Write the mask files of an image without annotations
+    fn write_background(
+        &self,
+        width: u32,
+        height: u32,
+        stem: &str,
+        output_dirs: &dyn OutputDirectories,
+        split: Split,
+    ) -> Result<(), String>;
+}
+
+// ============================================================================
+// SemanticMaskPipeline: ConversionPipeline trait implementation
+// ============================================================================
+
+/// Semantic segmentation mask conversion pipeline
+pub struct SemanticMaskPipeline;
+
+impl MaskPipeline for SemanticMaskPipeline {
+    fn extra_dirs(&self) -> &'static [&'static str] {
+        &[VISUALIZATION_DIR]
+    }
+
+    fn write_background(
+        &self,
+        width: u32,
+        height: u32,
+        stem: &str,
+        output_dirs: &dyn OutputDirectories,
+        split: Split,
+    ) -> Result<(), String> {
+        let mask = GrayImage::from_pixel(width, height, Luma([0]));
+        write_semantic_mask(&mask, stem, output_dirs, split)
+    }
+}
+
+impl ConversionPipeline for SemanticMaskPipeline {
+    fn needs_split(&self) -> bool {
+        true
+    }
+
+    fn setup_output_dirs(
+        &self,
+        config: &ConversionConfig,
+    ) -> Result<Box<dyn OutputDirectories>, String> {
+        let dirs = setup_mask_directories(config, self.extra_dirs())
+            .map_err(|e| format!("Failed to create output directories: {}", e))?;
+        Ok(Box::new(dirs))
+    }
+
+    fn process_file(
+        &self,
+        json_path: &Path,
+        config: &ConversionConfig,
+        output_dirs: &dyn OutputDirectories,
+        context: &mut ProcessingContext,
+    ) -> Result<ProcessedFileResult, String> {
+        let Some(source) = prepare_source(json_path, config, output_dirs, context)? else {
+            return Ok(ProcessedFileResult::default());
+        };
+        let (width, height) = (source.annotation.image_width, source.annotation.image_height);
+
+        // Rasterize every shape first so the draw order can use their areas
+        let mut painted: Vec<(Vec<usize>, u8)> = Vec::new();
+        let mut skipped_count = 0;
+        let mut invalid_annotations = Vec::new();
+
+        for shape in &source.annotation.shapes {
+            let value = if config.mask_ignore_labels.contains(&shape.label) {
+                config.mask_ignore_index
+            } else if let Some(&class_id) = context.label_map.get(&shape.label) {
+                class_value(class_id, config.mask_ignore_index)?
+            } else {
+                context.add_skipped_label(&shape.label);
+                skipped_count += 1;
+                continue;
+            };
+
+            match rasterize(shape, width, height, &source.file_name) {
+                Ok(pixels) => painted.push((pixels, value)),
+                Err(invalid) => {
+                    invalid_annotations.push(invalid);
+                    skipped_count += 1;
+                }
+            }
+        }
+
+        let areas: Vec<usize> = painted.iter().map(|(pixels, _)| pixels.len()).collect();
+        let classes: Vec<usize> = painted.iter().map(|(_, value)| *value as usize).collect();
+
+        let mut mask = GrayImage::new(width, height);
+        for index in paint_order(&areas, &classes, config.mask_draw_order) {
+            let (pixels, value) = &painted[index];
+            let buffer: &mut [u8] = &mut mask;
+            for &pixel in pixels {
+                buffer[pixel] = *value;
+            }
+        }
+        write_semantic_mask(&mask, &source.image_stem, output_dirs, source.split)?;
+
+        Ok(file_result(
+            &source,
+            config,
+            painted.len(),
+            skipped_count,
+            invalid_annotations,
+        ))
+    }
+
+    fn finalize(
+        &self,
+        config: &ConversionConfig,
+        output_dirs: &dyn OutputDirectories,
+        context: &ProcessingContext,
+    ) -> Result<(), String> {
+        write_mask_classes(config, output_dirs, context)
+    }
+}
+
+// ============================================================================
+// InstanceMaskPipeline: ConversionPipeline trait implementation
+// ============================================================================
+
+/// Instance-ID mask conversion pipeline
+pub struct InstanceMaskPipeline;
+
+impl MaskPipeline for InstanceMaskPipeline {
+    fn extra_dirs(&self) -> &'static [&'static str] {
+        &[VISUALIZATION_DIR, INSTANCES_DIR]
+    }
+
+    fn write_background(
+        &self,
+        width: u32,
+        height: u32,
+        stem: &str,
+        output_dirs: &dyn OutputDirectories,
+        split: Split,
+    ) -> Result<(), String> {
+        let mask = InstanceImage::new(width, height);
+        write_instance_mask(&mask, Vec::new(), stem, output_dirs, split)
+    }
+}
+
+impl ConversionPipeline for InstanceMaskPipeline {
+    fn needs_split(&self) -> bool {
+        true
+    }
+
+    fn setup_output_dirs(
+        &self,
+        config: &ConversionConfig,
+    ) -> Result<Box<dyn OutputDirectories>, String> {
+        let dirs = setup_mask_directories(config, self.extra_dirs())
+            .map_err(|e| format!("Failed to create output directories: {}", e))?;
+        Ok(Box::new(dirs))
+    }
+
+    fn process_file(
+        &self,
+        json_path: &Path,
+        config: &ConversionConfig,
+        output_dirs: &dyn OutputDirectories,
+        context: &mut ProcessingContext,
+    ) -> Result<ProcessedFileResult, String> {
+        let Some(source) = prepare_source(json_path, config, output_dirs, context)? else {
+            return Ok(ProcessedFileResult::default());
+        };
+        let shapes = &source.annotation.shapes;
+        let (width, height) = (source.annotation.image_width, source.annotation.image_height);
+
+        // Union of the shape pixels of every instance
+        let mut instances: Vec<(MaskInstance, Vec<usize>)> = Vec::new();
+        let mut skipped_count = 0;
+        let mut invalid_annotations = Vec::new();
+
+        for members in group_instances(shapes) {
+            let first = &shapes[members[0]];
+            let class_index = if config.mask_ignore_labels.contains(&first.label) {
+                skipped_count += members.len();
+                continue;
+            } else if let Some(&class_id) = context.label_map.get(&first.label) {
+                class_value(class_id, config.mask_ignore_index)?
+            } else {
+                context.add_skipped_label(&first.label);
+                skipped_count += members.len();
+                continue;
+            };
+
+            let mut pixels = Vec::new();
+            let mut shape_count = 0;
+            for &member in &members {
+                match rasterize(&shapes[member], width, height, &source.file_name) {
+                    Ok(shape_pixels) => {
+                        pixels.extend(shape_pixels);
+                        shape_count += 1;
+                    }
+                    Err(invalid) => {
+                        invalid_annotations.push(invalid);
+                        skipped_count += 1;
+                    }
+                }
+            }
+            if pixels.is_empty() {
+                continue;
+            }
+            pixels.sort_unstable();
+            pixels.dedup();
+
+            let id = u16::try_from(instances.len() + 1)
+                .map_err(|_| format!("More than {} instances in one image", u16::MAX))?;
+            instances.push((
+                MaskInstance {
+                    id,
+                    label: first.label.clone(),
+                    class_index,
+                    group_id: first.group_id,
+                    shape_count,
+                    area: 0,
+                },
+                pixels,
+            ));
+        }
+
+        let areas: Vec<usize> = instances.iter().map(|(_, pixels)| pixels.len()).collect();
+        let classes: Vec<usize> = instances
+            .iter()
+            .map(|(instance, _)| instance.class_index as usize)
+            .collect();
+
+        let mut mask = InstanceImage::new(width, height);
+        for index in paint_order(&areas, &classes, config.mask_draw_order) {
+            let (instance, pixels) = &instances[index];
+            let buffer: &mut [u16] = &mut mask;
+            for &pixel in pixels {
+                buffer[pixel] = instance.id;
+            }
+        }
+
+        // Report the area left visible after overlaps
+        let mut visible = vec![0usize; instances.len() + 1];
+        for value in mask.iter() {
+            visible[*value as usize] += 1;
+        }
+        let annotations_processed = instances.iter().map(|(i, _)| i.shape_count).sum();
+        let table: Vec<MaskInstance> = instances
+            .into_iter()
+            .map(|(mut instance, _)| {
+                instance.area = visible[instance.id as usize];
+                instance
+            })
+            .collect();
+
+        write_instance_mask(&mask, table, &source.image_stem, output_dirs, source.split)?;
+
+        Ok(file_result(
+            &source,
+            config,
+            annotations_processed,
+            skipped_count,
+            invalid_annotations,
+        ))
+    }
+
+    fn finalize(
+        &self,
+        config: &ConversionConfig,
+        output_dirs: &dyn OutputDirectories,
+        context: &ProcessingContext,
+    ) -> Result<(), String> {
+        write_mask_classes(config, output_dirs, context)
+    }
+}
+
+// ============================================================================
+// Public conversion functions
+// ============================================================================
+
+/// Main semantic mask export function
+pub fn convert_to_semantic_mask(config: &ConversionConfig) -> ConversionResult {
+    convert_to_masks(&SemanticMaskPipeline, config)
+}
+
+/// Main instance mask export function
+pub fn convert_to_instance_mask(config: &ConversionConfig) -> ConversionResult {
+    convert_to_masks(&InstanceMaskPipeline, config)
+}
+
+fn convert_to_masks(pipeline: &dyn MaskPipeline, config: &ConversionConfig) -> ConversionResult {
+    if let Err(e) = config.validate() {
+        return ConversionResult::failure(vec![e]);
+    }
+
+    let output_dirs = match pipeline.setup_output_dirs(config) {
+        Ok(dirs) => dirs,
+        Err(e) => return ConversionResult::failure(vec![e]),
+    };
+
+    let mut context = if config.label_list.is_empty() {
+        ProcessingContext::new()
+    } else {
+        ProcessingContext::with_labels(&config.label_list)
+    };
+
+    let json_files = find_json_files(&config.input_dir);
+    context.stats.total_files = json_files.len();
+
+    if config.deterministic_labels && config.label_list.is_empty() {
+        pipeline.gather_labels(&json_files, &mut context);
+    }
+    exclude_ignore_labels(&mut context.label_map, &config.mask_ignore_labels);
+
+    context.split_plan = plan_splits(config, &json_files);
+    context.stats.split_report = context.split_plan.as_ref().map(|p| p.report.clone());
+
+    for json_path in &json_files {
+        match pipeline.process_file(json_path, config, output_dirs.as_ref(), &mut context) {
+            Ok(result) => {
+                context.stats.increment_processed();
+                context.stats.add_annotations(result.annotations_processed);
+                context
+                    .stats
+                    .add_skipped_annotations(result.annotations_skipped);
+                for invalid in result.invalid_annotations {
+                    context.stats.add_invalid_annotation(invalid);
+                }
+                if let Some(file_name) = result.filtered_empty_file_name {
+                    context.stats.add_filtered_empty_file(file_name);
+                }
+            }
+            Err(e) => {
+                context.stats.increment_failed();
+                context.add_error(format!("{}: {}", json_path.display(), e));
+            }
+        }
+    }
+
+    if config.include_background {
+        let bg_files =
+            process_background_images(pipeline, config, output_dirs.as_ref(), &mut context);
+        for file_name in bg_files {
+            context.stats.add_background_file(file_name);
+        }
+    }
+
+    for label in context.label_map.keys() {
+        context.stats.add_label(label.clone());
+    }
+    for label in &context.skipped_labels {
+        context.stats.add_skipped_label(label.clone());
+    }
+
+    if let Err(e) = pipeline.finalize(config, output_dirs.as_ref(), &context) {
+        context.add_error(e);
+    }
+
+    let mut result = ConversionResult::success(
+        output_dirs.base_dir().to_string_lossy().to_string(),
+        context.stats,
+    );
+    result.errors = context.errors;
+    result
+}
+
+// ============================================================================
+// Helper functions
+// ============================================================================
+
+/// Export background images with empty masks
+/// Returns the list of background image file names
+fn process_background_images(
+    pipeline: &dyn MaskPipeline,
+    config: &ConversionConfig,
+    output_dirs: &dyn OutputDirectories,
+    context: &mut ProcessingContext,
+) -> Vec<String> {
+    let bg_images = find_background_images(&config.input_dir, &context.processed_images);
+    let mut bg_files = Vec::new();
+
+    for image_path in bg_images {
+        let image_key = image_path.to_string_lossy().to_string();
+        let split = resolve_split(config, context.split_plan.as_ref(), &image_key);
+        let images_dir = output_dirs.get_output_dir(split, FileType::Image);
+
+        let Ok((width, height)) = image::image_dimensions(&image_path) else {
+            eprintln!("Failed to read background image size: {}", image_path.display());
+            continue;
+        };
+
+        let output_image = match copy_image(&image_path, images_dir) {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!(
+                    "Failed to copy background image {}: {}",
+                    image_path.display(),
+                    e
+                );
+                continue;
+            }
+        };
+        context.record_output_image(&image_key, split, output_image);
+
+        let image_stem = image_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        if let Err(e) = pipeline.write_background(width, height, &image_stem, output_dirs, split) {
+            eprintln!("{}", e);
+            continue;
+        }
+
+        let file_name = image_path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| "unknown".to_string());
+        bg_files.push(file_name);
+    }
+
+    bg_files
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::labelme_convert::config::OutputFormat;
+    use crate::labelme_convert::io::write_labelme_json;
+
+    fn shape(label: &str, shape_type: &str, points: Vec<(f64, f64)>) -> Shape {
+        Shape {
+            label: label.to_string(),
+            points,
+            group_id: None,
+            shape_type: shape_type.to_string(),
+            description: None,
+            mask: None,
+            flags: None,
+        }
+    }
+
+    /// Write an 8x8 image with its annotation and return the export config
+    fn write_dataset(
+        input: &Path,
+        output: &Path,
+        shapes: Vec<Shape>,
+        format: OutputFormat,
+    ) -> ConversionConfig {
+        GrayImage::new(8, 8).save(input.join("a.png")).unwrap();
+        let annotation = LabelMeAnnotation {
+            version: "5.0.1".to_string(),
+            flags: None,
+            shapes,
+            image_path: "a.png".to_string(),
+            image_data: None,
+            image_height: 8,
+            image_width: 8,
+        };
+        write_labelme_json(&input.join("a.json"), &annotation).unwrap();
+
+        let mut config = ConversionConfig::new(input.to_path_buf())
+            .with_output_format(format)
+            .with_output_dir(output.to_path_buf())
+            .with_custom_name(Some("masks".to_string()))
+            .with_val_size(0.0);
+        config.deterministic_labels = true;
+        config
+    }
+
+    #[test]
+    fn test_class_values_and_ignore_labels() {
+        assert_eq!(class_value(0, 255), Ok(1));
+        assert!(class_value(253, 255).is_ok());
+        assert!(class_value(254, 255).is_err());
+        assert!(class_value(255, 0).is_err());
+
+        let mut label_map: HashMap<String, usize> =
+            [("cat", 0), ("void", 1), ("dog", 2)]
+                .into_iter()
+                .map(|(label, id)| (label.to_string(), id))
+                .collect();
+        exclude_ignore_labels(&mut label_map, &["void".to_string()]);
+        assert_eq!(label_map.len(), 2);
+        assert_eq!(label_map["cat"], 0);
+        assert_eq!(label_map["dog"], 1);
+
+        let config = ConversionConfig::default();
+        let classes = build_mask_classes(&label_map, &config);
+        let names: Vec<_> = classes.classes.iter().map(|c| (c.index, c.name.as_str())).collect();
+        assert_eq!(names, vec![(0, BACKGROUND_NAME), (1, "cat"), (2, "dog")]);
+        assert_eq!(classes.ignore_index, 255);
+    }
+
+    #[test]
+    fn test_convert_to_semantic_mask() {
+        let input = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        let shapes = vec![
+            shape("road", "rectangle", vec![(0.0, 0.0), (8.0, 8.0)]),
+            shape("car", "rectangle", vec![(2.0, 2.0), (4.0, 4.0)]),
+            shape("void", "rectangle", vec![(6.0, 6.0), (8.0, 8.0)]),
+            shape("car", "point", vec![(1.0, 1.0)]),
+        ];
+        let mut config =
+            write_dataset(input.path(), output.path(), shapes, OutputFormat::SemanticMask);
+        config.mask_ignore_labels = vec!["void".to_string()];
+
+        let result = convert_to_semantic_mask(&config);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.stats.total_annotations, 3);
+        assert_eq!(result.stats.invalid_annotations.len(), 1);
+
+        // Labels sorted: car = 1, road = 2; the later car rectangle is on top
+        let base = output.path().join("masks");
+        let mask = image::open(base.join("masks/train/a.png")).unwrap().to_luma8();
+        assert_eq!(mask.get_pixel(0, 0).0[0], 2);
+        assert_eq!(mask.get_pixel(3, 3).0[0], 1);
+        assert_eq!(mask.get_pixel(7, 7).0[0], 255);
+        assert!(base.join("visualizations/train/a.png").exists());
+        assert!(base.join(MASK_CLASSES_FILE).exists());
+    }
+
+    #[test]
+    fn test_group_instances() {
+        let mut shapes = vec![
+            shape("car", "polygon", Vec::new()),
+            shape("car", "polygon", Vec::new()),
+            shape("person", "polygon", Vec::new()),
+            shape("car", "polygon", Vec::new()),
+        ];
+        shapes[0].group_id = Some(1);
+        shapes[2].group_id = Some(1);
+        shapes[3].group_id = Some(1);
+
+        // Same group_id but a different label stays a separate instance
+        assert_eq!(group_instances(&shapes), vec![vec![0, 3], vec![1], vec![2]]);
+    }
+
+    #[test]
+    fn test_convert_to_instance_mask() {
+        let input = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        let mut shapes = vec![
+            shape("car", "rectangle", vec![(0.0, 0.0), (2.0, 2.0)]),
+            shape("car", "rectangle", vec![(0.0, 0.0), (6.0, 6.0)]),
+            shape("car", "rectangle", vec![(6.0, 6.0), (8.0, 8.0)]),
+        ];
+        shapes[1].group_id = Some(7);
+        shapes[2].group_id = Some(7);
+        let mut config =
+            write_dataset(input.path(), output.path(), shapes, OutputFormat::InstanceMask);
+        config.mask_draw_order = MaskDrawOrder::AreaDescending;
+
+        let result = convert_to_instance_mask(&config);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        assert_eq!(result.stats.total_annotations, 3);
+
+        // The small ungrouped box (ID 1) is painted over the grouped one (ID 2)
+        let base = output.path().join("masks");
+        let mask = image::open(base.join("masks/train/a.png")).unwrap().into_luma16();
+        assert_eq!(mask.get_pixel(1, 1).0[0], 1);
+        assert_eq!(mask.get_pixel(4, 4).0[0], 2);
+        assert_eq!(mask.get_pixel(7, 7).0[0], 2);
+        assert_eq!(mask.get_pixel(7, 0).0[0], 0);
+
+        let table: serde_json::Value = serde_json::from_str(
+            &std::fs::read_to_string(base.join("instances/train/a.json")).unwrap(),
+        )
+        .unwrap();
+        let instances = table["instances"].as_array().unwrap();
+        assert_eq!(instances.len(), 2);
+        assert_eq!(instances[1]["group_id"], 7);
+        assert_eq!(instances[1]["shape_count"], 2);
+        assert_eq!(instances[1]["area"], 36);
+        assert_eq!(instances[0]["area"], 4);
+    }
+}
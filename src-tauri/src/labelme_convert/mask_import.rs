@@ -0,0 +1,679 @@
+//! Segmentation mask → LabelMe import
+//!
+//! Turns mask-only datasets into LabelMe polygons. Masks are matched to
+//! images by file stem; every labeled region is traced with OpenCV
+//! `find_contours`, simplified with Douglas–Peucker (`approx_poly_dp`) and
+//! written as a `polygon` shape. Supported encodings:
+//! - binary: every non-zero pixel belongs to one label
+//! - class index: pixel values are class indices (e.g. our semantic export)
+//! - color: RGB palette colors (e.g. our mask visualizations)
+//!
+//! Labels come from a class table (`classes.json` of the mask export or an
+//! inline list); unknown values fall back to `class_{value}` /
+//! `color_{rrggbb}`. Value `0` (black) is background and never imported.
+//!
+//! LabelMe polygons cannot have holes, so holes are either filled, bridged
+//! into the outer polygon with a zero-width cut, or written as separate
+//! polygons flagged `hole`.
+
+use crate::labelme_convert::io::{find_image_files, write_labelme_json};
+use crate::labelme_convert::mask::{MaskClass, BACKGROUND_NAME};
+use crate::labelme_convert::raster::palette_color;
+use crate::labelme_convert::types::{LabelMeAnnotation, Shape};
+use opencv::core::{Mat, Point, Scalar, Vec4i, Vector, CV_8UC1};
+use opencv::prelude::*;
+use opencv::{imgcodecs, imgproc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Shape flag marking polygons that were holes in the mask
+pub const HOLE_FLAG: &str = "hole";
+
+/// How mask pixel values map to labels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MaskEncoding {
+    /// Non-zero pixels are foreground of a single label
+    #[default]
+    Binary,
+    /// Pixel values are class indices
+    ClassIndex,
+    /// RGB colors identify the classes
+    Color,
+}
+
+/// What to do with holes inside a region
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum HoleMode {
+    /// Drop holes; the outer boundary is imported as is
+    #[default]
+    Fill,
+    /// Join each hole into its outer polygon through a zero-width cut
+    Bridge,
+    /// Import holes as separate polygons with the `hole` flag set
+    Separate,
+}
+
+/// Options for importing segmentation masks
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MaskImportConfig {
+    /// Directory with the mask images
+    pub masks_dir: PathBuf,
+
+    /// Directory with the images the masks belong to (matched by file stem)
+    pub images_dir: PathBuf,
+
+    /// Directory receiving the LabelMe JSON files and image copies
+    pub output_dir: PathBuf,
+
+    /// Pixel encoding of the masks
+    #[serde(default)]
+    pub encoding: MaskEncoding,
+
+    /// Class table file in the `classes.json` format of the mask export
+    #[serde(default)]
+    pub classes_file: Option<PathBuf>,
+
+    /// Inline class table (overrides entries of `classes_file`)
+    #[serde(default)]
+    pub classes: Vec<MaskClass>,
+
+    /// Label of the foreground in binary masks
+    #[serde(default = "default_binary_label")]
+    pub binary_label: String,
+
+    /// Class index treated as unlabeled and skipped (None = import every value;
+    /// not applied to binary masks)
+    #[serde(default = "default_ignore_index")]
+    pub ignore_index: Option<u8>,
+
+    /// Regions and holes smaller than this many pixels are dropped
+    #[serde(default = "default_min_area")]
+    pub min_area: f64,
+
+    /// Douglas–Peucker tolerance in pixels (0 = keep every contour point)
+    #[serde(default = "default_epsilon")]
+    pub epsilon: f64,
+
+    /// Handling of holes inside regions
+    #[serde(default)]
+    pub holes: HoleMode,
+}
+
+fn default_binary_label() -> String {
+    "object".to_string()
+}
+
+fn default_ignore_index() -> Option<u8> {
+    Some(255)
+}
+
+fn default_min_area() -> f64 {
+    10.0
+}
+
+fn default_epsilon() -> f64 {
+    1.0
+}
+
+impl MaskImportConfig {
+    /// Create an import config with default options
+    pub fn new(masks_dir: PathBuf, images_dir: PathBuf, output_dir: PathBuf) -> Self {
+        Self {
+            masks_dir,
+            images_dir,
+            output_dir,
+            encoding: MaskEncoding::default(),
+            classes_file: None,
+            classes: Vec::new(),
+            binary_label: default_binary_label(),
+            ignore_index: default_ignore_index(),
+            min_area: default_min_area(),
+            epsilon: default_epsilon(),
+            holes: HoleMode::default(),
+        }
+    }
+
+    /// Validate the configuration
+    pub fn validate(&self) -> Result<(), String> {
+        if !self.masks_dir.is_dir() {
+            return Err(format!(
+                "Mask directory does not exist: {}",
+                self.masks_dir.display()
+            ));
+        }
+        if !self.images_dir.is_dir() {
+            return Err(format!(
+                "Image directory does not exist: {}",
+                self.images_dir.display()
+            ));
+        }
+        if self.min_area < 0.0 || self.epsilon < 0.0 {
+            return Err("min_area and epsilon must not be negative".to_string());
+        }
+        if self.encoding == MaskEncoding::Binary && self.binary_label.trim().is_empty() {
+            return Err("binary_label must not be empty".to_string());
+        }
+        Ok(())
+    }
+}
+
+/// Result of a mask import
+#[derive(Debug, Clone, Serialize)]
+pub struct MaskImportResult {
+    pub output_dir: String,
+    /// LabelMe JSON files written
+    pub images: usize,
+    /// Polygon shapes written
+    pub shapes: usize,
+    /// Regions and holes dropped by `min_area`
+    pub small_regions: usize,
+    /// Masks without a matching image
+    pub missing_images: Vec<String>,
+    /// Mask values without an entry in the class table
+    pub unknown_values: Vec<String>,
+    pub labels: Vec<String>,
+    /// Masks that could not be read or traced
+    pub errors: Vec<String>,
+}
+
+/// Class content of a `classes.json` file
+#[derive(Debug, Deserialize)]
+struct ClassesFile {
+    classes: Vec<MaskClass>,
+}
+
+// ============================================================================
+// Label resolution
+// ============================================================================
+
+/// Mask value key: the class index, or `0xRRGGBB` in color mode
+type MaskKey = u32;
+
+fn color_key(color: [u8; 3]) -> MaskKey {
+    ((color[0] as u32) << 16) | ((color[1] as u32) << 8) | color[2] as u32
+}
+
+/// Maps mask values to labels
+#[derive(Debug, Clone)]
+pub struct MaskLabels {
+    encoding: MaskEncoding,
+    binary_label: String,
+    names: HashMap<MaskKey, String>,
+    /// Keys that are never imported (background and ignore)
+    skipped: Vec<MaskKey>,
+}
+
+impl MaskLabels {
+    /// Build the value → label table for an encoding
+    pub fn new(
+        encoding: MaskEncoding,
+        classes: &[MaskClass],
+        binary_label: &str,
+        ignore_index: Option<u8>,
+    ) -> Self {
+        let key_of = |class: &MaskClass| match encoding {
+            MaskEncoding::Color => color_key(class.color),
+            _ => class.index as MaskKey,
+        };
+
+        let mut skipped = vec![0];
+        skipped.extend(classes.iter().filter(|c| c.name == BACKGROUND_NAME).map(key_of));
+        // Binary masks are commonly 0/255, so the ignore value only applies to class tables
+        match (encoding, ignore_index) {
+            (MaskEncoding::Binary, _) | (_, None) => {}
+            (MaskEncoding::Color, Some(ignore)) => skipped.push(color_key(palette_color(ignore))),
+            (MaskEncoding::ClassIndex, Some(ignore)) => skipped.push(ignore as MaskKey),
+        }
+
+        Self {
+            encoding,
+            binary_label: binary_label.to_string(),
+            names: classes.iter().map(|c| (key_of(c), c.name.clone())).collect(),
+            skipped,
+        }
+    }
+
+    /// Key of a pixel (`[value]` or `[r, g, b]`); binary masks collapse to `1`
+    pub fn key(&self, pixel: &[u8]) -> MaskKey {
+        match self.encoding {
+            MaskEncoding::Binary if self.skipped.contains(&(pixel[0] as MaskKey)) => 0,
+            MaskEncoding::Binary => 1,
+            MaskEncoding::ClassIndex => pixel[0] as MaskKey,
+            MaskEncoding::Color => color_key([pixel[0], pixel[1], pixel[2]]),
+        }
+    }
+
+    /// Whether pixels with this key are imported
+    pub fn is_labeled(&self, key: MaskKey) -> bool {
+        !self.skipped.contains(&key)
+    }
+
+    /// Label of a key and whether it was found in the class table
+    pub fn label(&self, key: MaskKey) -> (String, bool) {
+        if self.encoding == MaskEncoding::Binary {
+            return (self.binary_label.clone(), true);
+        }
+        match self.names.get(&key) {
+            Some(name) => (name.clone(), true),
+            None if self.encoding == MaskEncoding::Color => (format!("color_{:06x}", key), false),
+            None => (format!("class_{}", key), false),
+        }
+    }
+}
+
+/// Load the class table from the config (file entries first, inline entries win)
+fn load_classes(config: &MaskImportConfig) -> Result<Vec<MaskClass>, String> {
+    let mut classes = Vec::new();
+    if let Some(path) = &config.classes_file {
+        let content = fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        let file: ClassesFile = serde_json::from_str(&content)
+            .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+        classes = file.classes;
+    }
+    for class in &config.classes {
+        classes.retain(|c: &MaskClass| c.index != class.index);
+        classes.push(class.clone());
+    }
+    Ok(classes)
+}
+
+// ============================================================================
+// Geometry
+// ============================================================================
+
+/// Join a hole into its outer ring through the closest vertex pair
+///
+/// The ring walks the outer boundary to the bridge vertex, around the hole
+/// and back, so the area between the two rings is enclosed while the hole
+/// stays open.
+pub fn bridge_hole(outer: &[(f64, f64)], hole: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    if outer.is_empty() || hole.is_empty() {
+        return outer.to_vec();
+    }
+
+    let distance = |a: (f64, f64), b: (f64, f64)| (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2);
+    let (mut best_outer, mut best_hole, mut best) = (0, 0, f64::MAX);
+    for (i, &o) in outer.iter().enumerate() {
+        for (j, &h) in hole.iter().enumerate() {
+            let d = distance(o, h);
+            if d < best {
+                (best_outer, best_hole, best) = (i, j, d);
+            }
+        }
+    }
+
+    let mut ring = Vec::with_capacity(outer.len() + hole.len() + 2);
+    ring.extend_from_slice(&outer[..=best_outer]);
+    ring.extend_from_slice(&hole[best_hole..]);
+    ring.extend_from_slice(&hole[..=best_hole]);
+    ring.extend_from_slice(&outer[best_outer..]);
+    ring
+}
+
+fn polygon_shape(label: &str, points: Vec<(f64, f64)>, hole: bool) -> Shape {
+    Shape {
+        label: label.to_string(),
+        points,
+        group_id: None,
+        shape_type: "polygon".to_string(),
+        description: None,
+        mask: None,
+        flags: Some(if hole {
+            HashMap::from([(HOLE_FLAG.to_string(), true)])
+        } else {
+            HashMap::new()
+        }),
+    }
+}
+
+// ============================================================================
+// Contour extraction (OpenCV)
+// ============================================================================
+
+/// Outer ring of a region and its holes
+struct Region {
+    outer: Vec<(f64, f64)>,
+    holes: Vec<Vec<(f64, f64)>>,
+}
+
+fn cv_error(e: opencv::Error) -> String {
+    format!("OpenCV error: {}", e)
+}
+
+/// Simplify a contour; returns `None` when it is below `min_area` or degenerate
+fn simplify_contour(
+    contour: &Vector<Point>,
+    min_area: f64,
+    epsilon: f64,
+) -> Result<Option<Vec<(f64, f64)>>, String> {
+    if imgproc::contour_area(contour, false).map_err(cv_error)? < min_area {
+        return Ok(None);
+    }
+
+    let mut approx = Vector::<Point>::new();
+    let points = if epsilon > 0.0 {
+        imgproc::approx_poly_dp(contour, &mut approx, epsilon, true).map_err(cv_error)?;
+        &approx
+    } else {
+        contour
+    };
+    if points.len() < 3 {
+        return Ok(None);
+    }
+    Ok(Some(points.iter().map(|p| (p.x as f64, p.y as f64)).collect()))
+}
+
+/// Trace the regions of a binary (0/255) mask
+///
+/// Returns the regions and the number of regions and holes below `min_area`.
+fn trace_regions(
+    binary: &Mat,
+    min_area: f64,
+    epsilon: f64,
+) -> Result<(Vec<Region>, usize), String> {
+    let mut contours = Vector::<Vector<Point>>::new();
+    let mut hierarchy = Vector::<Vec4i>::new();
+    // RETR_CCOMP: top level = outer boundaries, second level = their holes
+    imgproc::find_contours_with_hierarchy(
+        binary,
+        &mut contours,
+        &mut hierarchy,
+        imgproc::RETR_CCOMP,
+        imgproc::CHAIN_APPROX_SIMPLE,
+        Point::new(0, 0),
+    )
+    .map_err(cv_error)?;
+
+    let mut regions = Vec::new();
+    let mut small = 0;
+    for index in 0..contours.len() {
+        // hierarchy entry: [next, previous, first child, parent]
+        let links = hierarchy.get(index).map_err(cv_error)?.0;
+        if links[3] >= 0 {
+            continue;
+        }
+
+        let contour = contours.get(index).map_err(cv_error)?;
+        let Some(outer) = simplify_contour(&contour, min_area, epsilon)? else {
+            small += 1;
+            continue;
+        };
+
+        let mut holes = Vec::new();
+        let mut child = links[2];
+        while child >= 0 {
+            let hole = contours.get(child as usize).map_err(cv_error)?;
+            match simplify_contour(&hole, min_area, epsilon)? {
+                Some(points) => holes.push(points),
+                None => small += 1,
+            }
+            child = hierarchy.get(child as usize).map_err(cv_error)?.0[0];
+        }
+        regions.push(Region { outer, holes });
+    }
+    Ok((regions, small))
+}
+
+/// Polygons of one mask file, with the number of dropped small regions
+fn mask_to_shapes(
+    mask_path: &Path,
+    labels: &MaskLabels,
+    config: &MaskImportConfig,
+    unknown: &mut BTreeSet<String>,
+) -> Result<(Vec<Shape>, u32, u32, usize), String> {
+    let path_str = mask_path.to_string_lossy();
+    let flags = match config.encoding {
+        MaskEncoding::Color => imgcodecs::IMREAD_COLOR,
+        _ => imgcodecs::IMREAD_GRAYSCALE,
+    };
+    let mask = imgcodecs::imread(&path_str, flags).map_err(cv_error)?;
+    if mask.empty() {
+        return Err(format!("Failed to read mask {}", mask_path.display()));
+    }
+    let (rows, cols) = (mask.rows(), mask.cols());
+    let channels = mask.channels() as usize;
+
+    // Key of every pixel (OpenCV stores color as BGR)
+    let keys: Vec<MaskKey> = mask
+        .data_bytes()
+        .map_err(cv_error)?
+        .chunks_exact(channels)
+        .map(|pixel| match channels {
+            3 => labels.key(&[pixel[2], pixel[1], pixel[0]]),
+            _ => labels.key(pixel),
+        })
+        .collect();
+    let present: BTreeSet<MaskKey> = keys.iter().copied().filter(|k| labels.is_labeled(*k)).collect();
+
+    let mut shapes = Vec::new();
+    let mut small = 0;
+    for key in present {
+        let (label, known) = labels.label(key);
+        if !known {
+            unknown.insert(label.clone());
+        }
+
+        let mut binary =
+            Mat::new_rows_cols_with_default(rows, cols, CV_8UC1, Scalar::all(0.0))
+                .map_err(cv_error)?;
+        for (value, pixel_key) in binary.data_bytes_mut().map_err(cv_error)?.iter_mut().zip(&keys) {
+            if *pixel_key == key {
+                *value = 255;
+            }
+        }
+
+        let (regions, dropped) = trace_regions(&binary, config.min_area, config.epsilon)?;
+        small += dropped;
+        for region in regions {
+            match config.holes {
+                HoleMode::Fill => shapes.push(polygon_shape(&label, region.outer, false)),
+                HoleMode::Bridge => {
+                    let ring = region
+                        .holes
+                        .iter()
+                        .fold(region.outer, |ring, hole| bridge_hole(&ring, hole));
+                    shapes.push(polygon_shape(&label, ring, false));
+                }
+                HoleMode::Separate => {
+                    shapes.push(polygon_shape(&label, region.outer, false));
+                    for hole in region.holes {
+                        shapes.push(polygon_shape(&label, hole, true));
+                    }
+                }
+            }
+        }
+    }
+
+    Ok((shapes, cols as u32, rows as u32, small))
+}
+
+// ============================================================================
+// Import
+// ============================================================================
+
+/// Import a folder of masks as LabelMe polygon annotations
+///
+/// One JSON is written per mask into `output_dir`, next to a copy of the
+/// matching image.
+pub fn import_masks(config: &MaskImportConfig) -> Result<MaskImportResult, String> {
+    config.validate()?;
+    let classes = load_classes(config)?;
+    let labels = MaskLabels::new(
+        config.encoding,
+        &classes,
+        &config.binary_label,
+        config.ignore_index,
+    );
+
+    fs::create_dir_all(&config.output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+
+    // Image stem → image path (first match wins)
+    let mut images: BTreeMap<String, PathBuf> = BTreeMap::new();
+    let mut image_files = find_image_files(&config.images_dir);
+    image_files.sort();
+    for path in image_files {
+        if let Some(stem) = path.file_stem() {
+            images.entry(stem.to_string_lossy().to_string()).or_insert(path);
+        }
+    }
+
+    let mut result = MaskImportResult {
+        output_dir: config.output_dir.to_string_lossy().to_string(),
+        images: 0,
+        shapes: 0,
+        small_regions: 0,
+        missing_images: Vec::new(),
+        unknown_values: Vec::new(),
+        labels: Vec::new(),
+        errors: Vec::new(),
+    };
+    let mut found_labels = BTreeSet::new();
+    let mut unknown = BTreeSet::new();
+
+    let mut mask_files = find_image_files(&config.masks_dir);
+    mask_files.sort();
+    for mask_path in mask_files {
+        let stem = mask_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let Some(image_path) = images.get(&stem) else {
+            result.missing_images.push(stem);
+            continue;
+        };
+
+        let (shapes, width, height, small) =
+            match mask_to_shapes(&mask_path, &labels, config, &mut unknown) {
+                Ok(traced) => traced,
+                Err(e) => {
+                    result.errors.push(format!("{}: {}", mask_path.display(), e));
+                    continue;
+                }
+            };
+
+        let file_name = image_path
+            .file_name()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_else(|| format!("{}.png", stem));
+        fs::copy(image_path, config.output_dir.join(&file_name))
+            .map_err(|e| format!("Failed to copy image {}: {}", image_path.display(), e))?;
+
+        found_labels.extend(shapes.iter().map(|s| s.label.clone()));
+        result.shapes += shapes.len();
+        result.small_regions += small;
+
+        let annotation = LabelMeAnnotation {
+            version: "5.0.1".to_string(),
+            flags: Some(HashMap::new()),
+            shapes,
+            image_path: file_name,
+            image_data: None,
+            image_height: height,
+            image_width: width,
+        };
+        write_labelme_json(&config.output_dir.join(format!("{}.json", stem)), &annotation)?;
+        result.images += 1;
+    }
+
+    result.labels = found_labels.into_iter().collect();
+    result.unknown_values = unknown.into_iter().collect();
+    Ok(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn class(index: u8, name: &str) -> MaskClass {
+        MaskClass {
+            index,
+            name: name.to_string(),
+            color: palette_color(index),
+        }
+    }
+
+    #[test]
+    fn test_mask_labels() {
+        let classes = vec![class(0, BACKGROUND_NAME), class(1, "road"), class(2, "car")];
+
+        let labels = MaskLabels::new(MaskEncoding::ClassIndex, &classes, "object", Some(255));
+        assert!(!labels.is_labeled(labels.key(&[0])));
+        assert!(!labels.is_labeled(labels.key(&[255])));
+        assert_eq!(labels.label(labels.key(&[2])), ("car".to_string(), true));
+        assert_eq!(labels.label(labels.key(&[7])), ("class_7".to_string(), false));
+
+        let labels = MaskLabels::new(MaskEncoding::Color, &classes, "object", Some(255));
+        assert_eq!(labels.label(labels.key(&palette_color(1))), ("road".to_string(), true));
+        assert!(!labels.is_labeled(labels.key(&palette_color(255))));
+        assert_eq!(labels.label(labels.key(&[1, 2, 3])).0, "color_010203");
+
+        // ignore_index does not apply to binary masks (0/255 is the usual encoding)
+        let labels = MaskLabels::new(MaskEncoding::Binary, &[], "defect", Some(255));
+        assert_eq!(labels.key(&[1]), labels.key(&[200]));
+        assert!(labels.is_labeled(labels.key(&[255])));
+        assert!(!labels.is_labeled(labels.key(&[0])));
+        assert_eq!(labels.label(labels.key(&[255])).0, "defect");
+    }
+
+    #[test]
+    fn test_import_binary_mask() {
+        let masks = tempfile::tempdir().unwrap();
+        let images = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+
+        // 0/255 mask with a painted 10x10 square at (5, 5)
+        let mut mask = image::GrayImage::new(20, 20);
+        for y in 5..15 {
+            for x in 5..15 {
+                mask.put_pixel(x, y, image::Luma([255]));
+            }
+        }
+        mask.save(masks.path().join("a.png")).unwrap();
+        image::RgbImage::new(20, 20).save(images.path().join("a.png")).unwrap();
+        fs::write(masks.path().join("broken.png"), b"not a png").unwrap();
+        image::RgbImage::new(20, 20).save(images.path().join("broken.png")).unwrap();
+
+        let mut config = MaskImportConfig::new(
+            masks.path().to_path_buf(),
+            images.path().to_path_buf(),
+            output.path().to_path_buf(),
+        );
+        config.binary_label = "defect".to_string();
+        let result = import_masks(&config).unwrap();
+
+        // The unreadable mask is reported without aborting the import
+        assert_eq!(result.images, 1);
+        assert_eq!(result.errors.len(), 1);
+        assert!(result.errors[0].contains("broken.png"));
+        assert_eq!(result.labels, vec!["defect".to_string()]);
+
+        let content = fs::read_to_string(output.path().join("a.json")).unwrap();
+        let annotation: LabelMeAnnotation = serde_json::from_str(&content).unwrap();
+        assert_eq!((annotation.image_width, annotation.image_height), (20, 20));
+        assert_eq!(annotation.shapes.len(), 1);
+        let shape = &annotation.shapes[0];
+        assert_eq!(shape.shape_type, "polygon");
+        let mut corners = shape.points.clone();
+        corners.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(corners, vec![(5.0, 5.0), (5.0, 14.0), (14.0, 5.0), (14.0, 14.0)]);
+        assert!(output.path().join("a.png").is_file());
+    }
+
+    #[test]
+    fn test_bridge_hole() {
+        let outer = vec![(0.0, 0.0), (10.0, 0.0), (10.0, 10.0), (0.0, 10.0)];
+        let hole = vec![(4.0, 4.0), (4.0, 6.0), (6.0, 6.0), (6.0, 4.0)];
+
+        let ring = bridge_hole(&outer, &hole);
+        assert_eq!(ring.len(), outer.len() + hole.len() + 2);
+        // Cut runs from the outer corner (0,0) to the nearest hole corner (4,4)
+        assert_eq!(&ring[..3], &[(0.0, 0.0), (4.0, 4.0), (4.0, 6.0)]);
+        assert_eq!(&ring[5..8], &[(4.0, 4.0), (0.0, 0.0), (10.0, 0.0)]);
+    }
+}
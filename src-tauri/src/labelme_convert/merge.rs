@@ -0,0 +1,571 @@
+//! Merge several LabelMe datasets into one
+//!
+//! Annotator batches often spell the same class differently ("Scratch",
+//! "scratch ", "scratch"). The merge first scans every source for its label
+//! counts, groups labels that only differ in case or whitespace and proposes a
+//! single spelling per group. Explicit mappings override the proposal.
+//!
+//! The output is a flat LabelMe dataset: every JSON sits next to its image,
+//! `imagePath` is rewritten to the new file name, and `merge_provenance.json`
+//! records where each file came from.
+
+use crate::labelme_convert::io::{
+    extract_embedded_image, find_json_files, read_labelme_json, resolve_image_path,
+    sanitize_filename, write_file, write_labelme_json,
+};
+use crate::labelme_convert::progress::ProgressEmitter;
+use crate::labelme_convert::scanner::count_scanner::scan_counts_blocking;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Name of the provenance file written into the merged dataset
+pub const PROVENANCE_FILE: &str = "merge_provenance.json";
+
+/// Options for merging LabelMe datasets
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeConfig {
+    /// Source dataset directories (earlier sources keep their file names on collision)
+    pub sources: Vec<PathBuf>,
+
+    /// Directory receiving the merged dataset
+    pub output_dir: PathBuf,
+
+    /// Explicit label renames applied before conflict resolution (source label → target label)
+    #[serde(default)]
+    pub label_mapping: HashMap<String, String>,
+
+    /// Merge labels that only differ in case or whitespace
+    #[serde(default = "default_true")]
+    pub normalize_labels: bool,
+
+    /// Remove imageData from the merged JSON files
+    #[serde(default)]
+    pub remove_image_data: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+impl MergeConfig {
+    /// Create a merge config with default options
+    pub fn new(sources: Vec<PathBuf>, output_dir: PathBuf) -> Self {
+        Self {
+            sources,
+            output_dir,
+            label_mapping: HashMap::new(),
+            normalize_labels: true,
+            remove_image_data: false,
+        }
+    }
+
+    /// Validate the configuration
+    pub fn validate(&self) -> Result<(), String> {
+        if self.sources.is_empty() {
+            return Err("At least one source directory is required".to_string());
+        }
+
+        let output_dir = canonical_path(&self.output_dir);
+        for source in &self.sources {
+            if !source.is_dir() {
+                return Err(format!(
+                    "Source directory does not exist: {}",
+                    source.display()
+                ));
+            }
+            let source_dir = canonical_path(source);
+            if output_dir == source_dir {
+                return Err(format!(
+                    "Output directory must differ from source directory: {}",
+                    source.display()
+                ));
+            }
+            // The merge would pick up its own output on the next run
+            if output_dir.starts_with(&source_dir) {
+                return Err(format!(
+                    "Output directory must not be inside source directory: {}",
+                    source.display()
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// One spelling of a label in one source
+#[derive(Debug, Clone, Serialize)]
+pub struct LabelVariant {
+    pub label: String,
+    pub source_index: usize,
+    pub count: usize,
+}
+
+/// Labels that differ only in case/whitespace, or that need a mapping
+#[derive(Debug, Clone, Serialize)]
+pub struct LabelConflict {
+    /// Normalized form shared by all variants
+    pub normalized: String,
+    pub variants: Vec<LabelVariant>,
+    /// Label all variants are merged into
+    pub resolved_to: String,
+}
+
+/// Result of scanning the sources before a merge
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeAnalysis {
+    /// Label counts per source (same order as `MergeConfig::sources`)
+    pub source_labels: Vec<HashMap<String, usize>>,
+    /// JSON file count per source
+    pub source_files: Vec<usize>,
+    pub conflicts: Vec<LabelConflict>,
+    /// Final label name for every source label
+    pub label_map: BTreeMap<String, String>,
+    /// Number of files that will be renamed because of name collisions
+    pub file_collisions: usize,
+}
+
+/// Origin of one merged file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvenanceEntry {
+    pub output_json: String,
+    pub output_image: String,
+    pub source_dir: String,
+    pub source_json: String,
+    pub source_image: String,
+    /// Labels renamed in this file (source label → merged label)
+    #[serde(default)]
+    pub renamed_labels: BTreeMap<String, String>,
+}
+
+/// Contents of the provenance file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MergeProvenance {
+    pub created_at: String,
+    pub sources: Vec<String>,
+    pub label_map: BTreeMap<String, String>,
+    pub files: Vec<ProvenanceEntry>,
+}
+
+/// Summary of a finished merge
+#[derive(Debug, Clone, Serialize)]
+pub struct MergeResult {
+    pub output_dir: String,
+    pub merged_files: usize,
+    pub renamed_files: usize,
+    pub renamed_labels: usize,
+    pub labels: Vec<String>,
+    pub conflicts: Vec<LabelConflict>,
+    pub errors: Vec<String>,
+}
+
+/// Normalize a label for conflict detection (trim, collapse whitespace, lowercase)
+pub fn normalize_label(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Group label spellings across sources and pick one name per group
+///
+/// Explicit mappings are applied first. Remaining spellings that share a
+/// normalized form are resolved to the most frequent spelling (ties go to the
+/// alphabetically first one). Returns the conflicts and the final label map.
+pub fn resolve_labels(
+    source_labels: &[HashMap<String, usize>],
+    label_mapping: &HashMap<String, String>,
+    normalize: bool,
+) -> (Vec<LabelConflict>, BTreeMap<String, String>) {
+    let mut groups: BTreeMap<String, Vec<LabelVariant>> = BTreeMap::new();
+
+    for (source_index, labels) in source_labels.iter().enumerate() {
+        for (label, count) in labels {
+            let mapped = label_mapping.get(label).unwrap_or(label);
+            let key = if normalize {
+                normalize_label(mapped)
+            } else {
+                mapped.clone()
+            };
+            groups.entry(key).or_default().push(LabelVariant {
+                label: label.clone(),
+                source_index,
+                count: *count,
+            });
+        }
+    }
+
+    let mut conflicts = Vec::new();
+    let mut label_map = BTreeMap::new();
+
+    for (normalized, variants) in groups {
+        // Count each candidate spelling after explicit mapping
+        let mut totals: BTreeMap<&str, usize> = BTreeMap::new();
+        for variant in &variants {
+            let mapped = label_mapping.get(&variant.label).unwrap_or(&variant.label);
+            *totals.entry(mapped.as_str()).or_insert(0) += variant.count;
+        }
+
+        // BTreeMap iteration is alphabetical, so max_by_key keeps the last
+        // maximum; reverse to prefer the alphabetically first spelling
+        let resolved_to = totals
+            .iter()
+            .rev()
+            .max_by_key(|(_, count)| **count)
+            .map(|(label, _)| label.trim().to_string())
+            .unwrap_or_else(|| normalized.clone());
+
+        let distinct: HashSet<&str> = variants.iter().map(|v| v.label.as_str()).collect();
+        for label in &distinct {
+            label_map.insert(label.to_string(), resolved_to.clone());
+        }
+
+        if distinct.len() > 1 || distinct.iter().any(|l| *l != resolved_to) {
+            conflicts.push(LabelConflict {
+                normalized,
+                variants,
+                resolved_to,
+            });
+        }
+    }
+
+    (conflicts, label_map)
+}
+
+/// Scan the sources and report label conflicts and file name collisions
+pub fn analyze_merge(config: &MergeConfig) -> Result<MergeAnalysis, String> {
+    config.validate()?;
+
+    let mut source_labels = Vec::new();
+    let mut source_files = Vec::new();
+    let mut used_names = HashSet::new();
+    let mut file_collisions = 0;
+
+    for source in &config.sources {
+        source_labels.push(scan_counts_blocking(source.clone(), None)?);
+
+        let json_files = find_json_files(source);
+        for json_path in &json_files {
+            let stem = file_stem(json_path);
+            if !used_names.insert(stem.to_lowercase()) {
+                file_collisions += 1;
+            }
+        }
+        source_files.push(json_files.len());
+    }
+
+    let (conflicts, label_map) = resolve_labels(
+        &source_labels,
+        &config.label_mapping,
+        config.normalize_labels,
+    );
+
+    Ok(MergeAnalysis {
+        source_labels,
+        source_files,
+        conflicts,
+        label_map,
+        file_collisions,
+    })
+}
+
+/// Merge all sources into `config.output_dir`
+pub fn merge_datasets(
+    config: &MergeConfig,
+    progress: Option<&ProgressEmitter>,
+) -> Result<MergeResult, String> {
+    let analysis = analyze_merge(config)?;
+    fs::create_dir_all(&config.output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+
+    let total: usize = analysis.source_files.iter().sum();
+    if let Some(p) = progress {
+        p.emit(0, total, "開始合併資料集...");
+    }
+
+    let mut used_stems: HashSet<String> = HashSet::new();
+    let mut files = Vec::new();
+    let mut errors = Vec::new();
+    let mut renamed_files = 0;
+    let mut renamed_labels = 0;
+    let mut done = 0;
+
+    for source in &config.sources {
+        let source_name = source
+            .file_name()
+            .map(|s| sanitize_filename(&s.to_string_lossy()))
+            .unwrap_or_else(|| "source".to_string());
+
+        for json_path in find_json_files(source) {
+            done += 1;
+            match merge_file(
+                &json_path,
+                source,
+                &source_name,
+                config,
+                &analysis.label_map,
+                &mut used_stems,
+            ) {
+                Ok((entry, renamed)) => {
+                    if renamed {
+                        renamed_files += 1;
+                    }
+                    renamed_labels += entry.renamed_labels.len();
+                    files.push(entry);
+                }
+                Err(e) => errors.push(format!("{}: {}", json_path.display(), e)),
+            }
+
+            if let Some(p) = progress.filter(|_| done % 100 == 0 || done == total) {
+                p.emit(done, total, format!("已合併 {} / {} 個檔案", done, total));
+            }
+        }
+    }
+
+    let merged_files = files.len();
+
+    let record = MergeProvenance {
+        created_at: chrono::Local::now().to_rfc3339(),
+        sources: config
+            .sources
+            .iter()
+            .map(|s| s.to_string_lossy().to_string())
+            .collect(),
+        label_map: analysis.label_map.clone(),
+        files,
+    };
+    let json = serde_json::to_string_pretty(&record)
+        .map_err(|e| format!("Failed to serialize provenance: {}", e))?;
+    write_file(&config.output_dir.join(PROVENANCE_FILE), &json)
+        .map_err(|e| format!("Failed to write provenance: {}", e))?;
+
+    // Labels present in the merged output
+    let mut labels: Vec<String> = analysis.label_map.values().cloned().collect();
+    labels.sort();
+    labels.dedup();
+
+    if let Some(p) = progress {
+        p.complete(format!("合併完成，共 {} 個檔案", merged_files));
+    }
+
+    Ok(MergeResult {
+        output_dir: config.output_dir.to_string_lossy().to_string(),
+        merged_files,
+        renamed_files,
+        renamed_labels,
+        labels,
+        conflicts: analysis.conflicts,
+        errors,
+    })
+}
+
+/// Copy one annotation and its image into the merged dataset
+///
+/// Returns the provenance entry and whether the file had to be renamed.
+fn merge_file(
+    json_path: &Path,
+    source_dir: &Path,
+    source_name: &str,
+    config: &MergeConfig,
+    label_map: &BTreeMap<String, String>,
+    used_stems: &mut HashSet<String>,
+) -> Result<(ProvenanceEntry, bool), String> {
+    let mut annotation = read_labelme_json(json_path)?;
+    let image_path = resolve_image_path(json_path, &annotation.image_path);
+
+    let stem = file_stem(json_path);
+    let (out_stem, renamed) = unique_stem(&stem, source_name, used_stems);
+
+    let ext = image_path
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_else(|| "png".to_string());
+    let out_image_name = format!("{}.{}", out_stem, ext);
+    let out_image = config.output_dir.join(&out_image_name);
+
+    if image_path.exists() {
+        fs::copy(&image_path, &out_image).map_err(|e| format!("Failed to copy image: {}", e))?;
+    } else if let Some(image_data) = &annotation.image_data {
+        extract_embedded_image(image_data, &out_image)?;
+    } else {
+        return Err(format!("Image file not found: {}", image_path.display()));
+    }
+
+    let mut renamed_labels = BTreeMap::new();
+    for shape in &mut annotation.shapes {
+        let Some(target) = label_map.get(&shape.label) else {
+            continue;
+        };
+        if *target != shape.label {
+            renamed_labels.insert(shape.label.clone(), target.clone());
+            shape.label = target.clone();
+        }
+    }
+
+    annotation.image_path = out_image_name.clone();
+    if config.remove_image_data {
+        annotation.image_data = None;
+    }
+
+    let out_json_name = format!("{}.json", out_stem);
+    write_labelme_json(&config.output_dir.join(&out_json_name), &annotation)?;
+
+    Ok((
+        ProvenanceEntry {
+            output_json: out_json_name,
+            output_image: out_image_name,
+            source_dir: source_dir.to_string_lossy().to_string(),
+            source_json: json_path.to_string_lossy().to_string(),
+            source_image: image_path.to_string_lossy().to_string(),
+            renamed_labels,
+        },
+        renamed,
+    ))
+}
+
+/// Pick an unused output stem: the original name, then `{stem}_{source}`,
+/// then `{stem}_{source}_{n}` (compared case-insensitively)
+fn unique_stem(stem: &str, source_name: &str, used: &mut HashSet<String>) -> (String, bool) {
+    if used.insert(stem.to_lowercase()) {
+        return (stem.to_string(), false);
+    }
+
+    let mut candidate = format!("{}_{}", stem, source_name);
+    let mut counter = 1;
+    while !used.insert(candidate.to_lowercase()) {
+        candidate = format!("{}_{}_{}", stem, source_name, counter);
+        counter += 1;
+    }
+    (candidate, true)
+}
+
+fn file_stem(path: &Path) -> String {
+    path.file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// Canonical form of `path`, which may not exist yet (e.g. a new output directory)
+fn canonical_path(path: &Path) -> PathBuf {
+    if let Ok(canonical) = fs::canonicalize(path) {
+        return canonical;
+    }
+    match (path.parent(), path.file_name()) {
+        (Some(parent), Some(name)) if !parent.as_os_str().is_empty() => {
+            canonical_path(parent).join(name)
+        }
+        _ => path.to_path_buf(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counts(pairs: &[(&str, usize)]) -> HashMap<String, usize> {
+        pairs.iter().map(|(l, c)| (l.to_string(), *c)).collect()
+    }
+
+    #[test]
+    fn test_normalize_label() {
+        assert_eq!(normalize_label("  Scratch  Mark "), "scratch mark");
+        assert_eq!(normalize_label("dent"), "dent");
+    }
+
+    #[test]
+    fn test_resolve_labels_merges_variants() {
+        let sources = vec![
+            counts(&[("scratch", 10), ("dent", 4)]),
+            counts(&[("Scratch ", 3), ("dent", 2)]),
+        ];
+        let (conflicts, label_map) = resolve_labels(&sources, &HashMap::new(), true);
+
+        assert_eq!(label_map["Scratch "], "scratch");
+        assert_eq!(label_map["scratch"], "scratch");
+        assert_eq!(label_map["dent"], "dent");
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].resolved_to, "scratch");
+        assert_eq!(conflicts[0].variants.len(), 2);
+    }
+
+    #[test]
+    fn test_resolve_labels_explicit_mapping() {
+        let sources = vec![counts(&[("car", 5)]), counts(&[("vehicle", 1)])];
+        let mut mapping = HashMap::new();
+        mapping.insert("vehicle".to_string(), "car".to_string());
+
+        let (conflicts, label_map) = resolve_labels(&sources, &mapping, false);
+        assert_eq!(label_map["vehicle"], "car");
+        assert_eq!(label_map["car"], "car");
+        assert_eq!(conflicts.len(), 1);
+    }
+
+    #[test]
+    fn test_unique_stem() {
+        let mut used = HashSet::new();
+        assert_eq!(
+            unique_stem("img", "batch1", &mut used),
+            ("img".to_string(), false)
+        );
+        assert_eq!(
+            unique_stem("IMG", "batch2", &mut used),
+            ("IMG_batch2".to_string(), true)
+        );
+        assert_eq!(
+            unique_stem("img", "batch2", &mut used),
+            ("img_batch2_1".to_string(), true)
+        );
+    }
+
+    #[test]
+    fn test_merge_datasets() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        for (batch, label) in [("batch1", "scratch"), ("batch2", "Scratch")] {
+            let dir = root.join(batch);
+            fs::create_dir_all(&dir).unwrap();
+            fs::write(dir.join("img.jpg"), b"fake").unwrap();
+            let json = format!(
+                r#"{{"version":"5.0.1","flags":{{}},"shapes":[{{"label":"{}","points":[[1,1],[5,5]],"group_id":null,"shape_type":"rectangle"}}],"imagePath":"img.jpg","imageData":null,"imageHeight":10,"imageWidth":10}}"#,
+                label
+            );
+            fs::write(dir.join("img.json"), json).unwrap();
+        }
+
+        let config = MergeConfig::new(
+            vec![root.join("batch1"), root.join("batch2")],
+            root.join("merged"),
+        );
+        let result = merge_datasets(&config, None).unwrap();
+
+        assert_eq!(result.merged_files, 2);
+        assert_eq!(result.renamed_files, 1);
+        assert_eq!(result.labels.len(), 1);
+
+        let merged = read_labelme_json(&root.join("merged").join("img_batch2.json")).unwrap();
+        assert_eq!(merged.image_path, "img_batch2.jpg");
+        assert_eq!(merged.shapes[0].label, result.labels[0]);
+        assert!(root.join("merged").join(PROVENANCE_FILE).exists());
+    }
+
+    #[test]
+    fn test_validate_rejects_nested_output() {
+        use tempfile::TempDir;
+
+        let temp_dir = TempDir::new().unwrap();
+        let source = temp_dir.path().join("batch1");
+        fs::create_dir_all(&source).unwrap();
+
+        let nested = MergeConfig::new(vec![source.clone()], source.join("out").join("merged"));
+        assert!(nested.validate().is_err());
+        let same = MergeConfig::new(vec![source.clone()], source.join("."));
+        assert!(same.validate().is_err());
+        let sibling = MergeConfig::new(vec![source], temp_dir.path().join("merged"));
+        assert!(sibling.validate().is_ok());
+    }
+}
@@ -10,6 +10,13 @@
 //! This module provides functionality to convert LabelMe JSON annotations to:
 //! - YOLO format (for object detection and segmentation)
 //! - COCO format (for instance segmentation and object detection)
+//! - CVAT for images 1.1 XML
+//! - Label Studio JSON tasks
+//! - Class-index PNG masks (semantic segmentation)
+//! - Instance-ID PNG masks with per-image instance tables (panoptic)
+//!
+//! Pascal VOC XML datasets are accepted as input as well (see `voc`), and
+//! mask-only datasets can be imported as LabelMe polygons (see `mask_import`).
 //!
 //! # Example
 //!
@@ -25,14 +32,29 @@
 //! let result = convert(&config);
 //! ```
 
+pub mod augment;
+pub mod balance;
 pub mod coco;
 pub mod config;
 pub mod conversion;
+pub mod cvat;
 pub mod detection;
+pub mod diff;
+pub mod geometry;
 pub mod io;
+pub mod label_studio;
 pub mod labelme_out;
+pub mod mask;
+pub mod mask_import;
+pub mod merge;
 pub mod pipeline;
+pub mod raster;
+pub mod render;
+pub mod sample;
+pub mod split;
+pub mod statistics;
 pub mod types;
+pub mod voc;
 pub mod yolo;
 
 // 🆕 Async scanner module with progress reporting
@@ -40,18 +62,35 @@ pub mod progress;
 pub mod scanner;
 
 // Re-export commonly used types for convenience
+pub use augment::{AugmentConfig, AugmentReport};
+pub use balance::{BalanceConfig, BalanceReport};
 pub use config::{
-    AnnotationFormat, ConversionConfig, LabelMeOutputFormat, OutputFormat, SegmentationMode,
+    AnnotationFormat, ConversionConfig, LabelMeOutputFormat, LineShapeMode, MaskDrawOrder,
+    OutputFormat, PointShapeMode, PolygonOptions, SegmentationMode, ShapeOptions,
 };
 pub use detection::{analyze_dataset, DatasetAnalysis};
+pub use diff::{diff_datasets, DatasetDiff, DiffConfig};
+pub use merge::{merge_datasets, MergeConfig, MergeResult};
 pub use pipeline::{ConversionPipeline, ProcessingContext, Split};
-pub use types::{ConversionResult, InputAnnotationFormat};
+pub use render::{render_file, PreviewConfig, RenderOptions};
+pub use sample::{sample_annotated_images, ImageSample, SampleOptions};
+pub use split::{SplitGroupKey, SplitPlan, SplitReport};
+pub use statistics::{compute_statistics, DatasetStatistics};
+pub use types::{
+    ConversionResult, DatasetSourceFormat, InputAnnotationFormat, ShapeTypeReport, VertexReport,
+};
+pub use voc::{import_voc, VocImportResult};
 
 // Re-export pipeline implementations
 pub use coco::CocoPipeline;
+pub use cvat::CvatPipeline;
+pub use label_studio::LabelStudioPipeline;
 pub use labelme_out::LabelMePipeline;
+pub use mask::{InstanceMaskPipeline, SemanticMaskPipeline};
 pub use yolo::YoloPipeline;
 
+use std::path::Path;
+
 /// Main conversion function that dispatches to the appropriate converter
 /// based on the output format specified in the configuration.
 ///
@@ -62,7 +101,7 @@ pub use yolo::YoloPipeline;
 /// # Returns
 ///
 /// A `ConversionResult` containing the output directory path, processing statistics,
-/// and any errors that occurred during conversion.
+/// any errors that occurred during conversion and the rendered previews.
 ///
 /// # Example
 ///
@@ -98,11 +137,52 @@ pub fn convert(config: &ConversionConfig) -> ConversionResult {
         config.detected_input_format = Some(analysis.input_format);
     }
 
-    match config.output_format {
+    // VOC input is staged as LabelMe JSON and converted from there
+    let voc_root = match config.source_format {
+        Some(DatasetSourceFormat::LabelMe) => None,
+        Some(DatasetSourceFormat::PascalVoc) => match voc::find_voc_root(&config.input_dir) {
+            Some(root) => Some(root),
+            None => {
+                return ConversionResult::failure(vec![format!(
+                    "No Pascal VOC annotations found in {}",
+                    config.input_dir.display()
+                )]);
+            }
+        },
+        None => voc::find_voc_input(&config.input_dir),
+    };
+    if let Some(voc_root) = voc_root {
+        return voc::convert_voc(&config, &voc_root);
+    }
+
+    let mut result = match config.output_format {
         OutputFormat::Yolo => yolo::convert_to_yolo(&config),
         OutputFormat::Coco => coco::convert_to_coco(&config),
         OutputFormat::LabelMe => labelme_out::convert_to_labelme(&config),
+        OutputFormat::Cvat => cvat::convert_to_cvat(&config),
+        OutputFormat::LabelStudio => label_studio::convert_to_label_studio(&config),
+        OutputFormat::SemanticMask => mask::convert_to_semantic_mask(&config),
+        OutputFormat::InstanceMask => mask::convert_to_instance_mask(&config),
+    };
+
+    // Rendered samples of the source annotations for the export report
+    if let Some(previews) = config.previews.as_ref().filter(|_| result.success) {
+        let preview_dir = Path::new(&result.output_dir).join(render::PREVIEW_DIR);
+        match render::render_previews(&config.input_dir, &preview_dir, previews, config.seed) {
+            Ok(paths) => {
+                result.previews = paths
+                    .iter()
+                    .map(|p| p.to_string_lossy().to_string())
+                    .collect();
+            }
+            Err(e) => {
+                let message = format!("Failed to render previews: {}", e);
+                result.errors.push(message);
+            }
+        }
     }
+
+    result
 }
 
 #[cfg(test)]
@@ -8,7 +8,10 @@
 //! - Unified file processing flow
 //! - Easy addition of new output formats
 
+use crate::labelme_convert::augment::Augmenter;
+use crate::labelme_convert::balance::BalancePlan;
 use crate::labelme_convert::config::ConversionConfig;
+use crate::labelme_convert::split::SplitPlan;
 use crate::labelme_convert::types::{InvalidAnnotation, ProcessingStats};
 use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
@@ -72,6 +75,16 @@ pub struct ProcessingContext {
     pub skipped_labels: HashSet<String>,
     /// Accumulated errors
     pub errors: Vec<String>,
+    /// Group-aware split assignment (None = hash each image path)
+    pub split_plan: Option<SplitPlan>,
+    /// Output image paths with their cross-validation fold (k-fold mode only)
+    pub fold_images: Vec<(usize, PathBuf)>,
+    /// Train split balancing decisions (None = export as-is)
+    pub balance_plan: Option<BalancePlan>,
+    /// Train output image paths with their reference count (balancing only)
+    pub balanced_train: Vec<(PathBuf, usize)>,
+    /// Augmented copy generator for train images (None = no augmentation)
+    pub augmenter: Option<Augmenter>,
 }
 
 impl ProcessingContext {
@@ -83,6 +96,11 @@ impl ProcessingContext {
             processed_images: HashSet::new(),
             skipped_labels: HashSet::new(),
             errors: Vec::new(),
+            split_plan: None,
+            fold_images: Vec::new(),
+            balance_plan: None,
+            balanced_train: Vec::new(),
+            augmenter: None,
         }
     }
 
@@ -125,6 +143,16 @@ impl ProcessingContext {
     pub fn mark_image_processed(&mut self, image_key: String) {
         self.processed_images.insert(image_key);
     }
+
+    /// Track an exported image for the fold lists and the balanced train list
+    pub fn record_output_image(&mut self, image_key: &str, split: Split, output_image: PathBuf) {
+        if let Some(fold) = self.split_plan.as_ref().and_then(|p| p.fold_for(image_key)) {
+            self.fold_images.push((fold, output_image.clone()));
+        }
+        if let Some(plan) = self.balance_plan.as_ref().filter(|_| split == Split::Train) {
+            self.balanced_train.push((output_image, plan.copies(image_key)));
+        }
+    }
 }
 
 impl Default for ProcessingContext {
@@ -0,0 +1,330 @@
+//! Shape rasterization for mask exports
+//!
+//! Turns LabelMe shapes into the set of pixels they cover so that mask
+//! pipelines can paint class or instance indices into an image buffer:
+//! - polygon / rectangle: scanline fill sampled at pixel centers
+//! - circle: pixels whose center lies inside the circle
+//! - mask: non-zero pixels of the embedded PNG, placed at the first point
+//!
+//! Lines, line strips and points cover no area and yield no pixels.
+//! [`mask_contours`] traces mask bitmaps for exporters and renderers that
+//! need an outline instead of pixels.
+
+use crate::labelme_convert::config::MaskDrawOrder;
+use crate::labelme_convert::conversion::rectangle_to_polygon;
+use crate::labelme_convert::types::Shape;
+use image::{GrayImage, ImageBuffer, Luma, Rgb, RgbImage};
+use imageproc::contours::BorderType;
+
+/// 16-bit single-channel image holding instance IDs
+pub type InstanceImage = ImageBuffer<Luma<u16>, Vec<u16>>;
+
+/// Indices (`y * width + x`) of the pixels covered by a shape
+pub fn shape_pixels(shape: &Shape, width: u32, height: u32) -> Vec<usize> {
+    match shape.shape_type.as_str() {
+        "polygon" if shape.points.len() >= 3 => fill_polygon(&shape.points, width, height),
+        "rectangle" if shape.points.len() == 2 => {
+            fill_polygon(&rectangle_to_polygon(&shape.points), width, height)
+        }
+        "rectangle" if shape.points.len() >= 3 => fill_polygon(&shape.points, width, height),
+        "circle" if shape.points.len() >= 2 => fill_circle(&shape.points, width, height),
+        "mask" => shape
+            .mask
+            .as_deref()
+            .and_then(|data| fill_mask(data, &shape.points, width, height))
+            .unwrap_or_default(),
+        _ => Vec::new(),
+    }
+}
+
+/// Even-odd scanline fill of a polygon
+fn fill_polygon(points: &[(f64, f64)], width: u32, height: u32) -> Vec<usize> {
+    let mut pixels = Vec::new();
+    let min_y = points.iter().map(|p| p.1).fold(f64::MAX, f64::min);
+    let max_y = points.iter().map(|p| p.1).fold(f64::MIN, f64::max);
+    let row_start = (min_y - 0.5).ceil().max(0.0) as u32;
+    let row_end = ((max_y - 0.5).floor() + 1.0).clamp(0.0, height as f64) as u32;
+
+    let mut crossings: Vec<f64> = Vec::new();
+    for y in row_start..row_end {
+        let yc = y as f64 + 0.5;
+        crossings.clear();
+        for (i, &(x0, y0)) in points.iter().enumerate() {
+            let (x1, y1) = points[(i + 1) % points.len()];
+            if (y0 <= yc && y1 > yc) || (y1 <= yc && y0 > yc) {
+                crossings.push(x0 + (yc - y0) * (x1 - x0) / (y1 - y0));
+            }
+        }
+        crossings.sort_by(|a, b| a.total_cmp(b));
+
+        for span in crossings.chunks_exact(2) {
+            let x_start = (span[0] - 0.5).ceil().max(0.0) as u32;
+            let x_end = (span[1] - 0.5).ceil().clamp(0.0, width as f64) as u32;
+            let row = y as usize * width as usize;
+            pixels.extend((x_start..x_end).map(|x| row + x as usize));
+        }
+    }
+    pixels
+}
+
+/// Fill a circle given as center and a point on the circumference
+fn fill_circle(points: &[(f64, f64)], width: u32, height: u32) -> Vec<usize> {
+    let (cx, cy) = points[0];
+    let (px, py) = points[1];
+    let radius_sq = (px - cx).powi(2) + (py - cy).powi(2);
+    let radius = radius_sq.sqrt();
+
+    let x_start = (cx - radius).floor().max(0.0) as u32;
+    let x_end = ((cx + radius).ceil() + 1.0).clamp(0.0, width as f64) as u32;
+    let y_start = (cy - radius).floor().max(0.0) as u32;
+    let y_end = ((cy + radius).ceil() + 1.0).clamp(0.0, height as f64) as u32;
+
+    let mut pixels = Vec::new();
+    for y in y_start..y_end {
+        for x in x_start..x_end {
+            let dx = x as f64 + 0.5 - cx;
+            let dy = y as f64 + 0.5 - cy;
+            if dx * dx + dy * dy <= radius_sq {
+                pixels.push(y as usize * width as usize + x as usize);
+            }
+        }
+    }
+    pixels
+}
+
+/// Decoded bitmap of a base64 PNG mask and the pixel of its top-left corner
+fn decode_mask(data: &str, points: &[(f64, f64)]) -> Option<(GrayImage, i64, i64)> {
+    use base64::Engine;
+
+    let origin_x = points.iter().map(|p| p.0).fold(f64::MAX, f64::min).round();
+    let origin_y = points.iter().map(|p| p.1).fold(f64::MAX, f64::min).round();
+    if !origin_x.is_finite() || !origin_y.is_finite() {
+        return None;
+    }
+
+    let base64_data = data.find(',').map_or(data, |pos| &data[pos + 1..]);
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(base64_data)
+        .ok()?;
+    let mask = image::load_from_memory(&bytes).ok()?.to_luma8();
+    Some((mask, origin_x as i64, origin_y as i64))
+}
+
+/// Non-zero pixels of a base64 PNG mask anchored at the top-left point
+fn fill_mask(data: &str, points: &[(f64, f64)], width: u32, height: u32) -> Option<Vec<usize>> {
+    let (mask, origin_x, origin_y) = decode_mask(data, points)?;
+
+    let mut pixels = Vec::new();
+    for (mx, my, value) in mask.enumerate_pixels() {
+        let x = origin_x + mx as i64;
+        let y = origin_y + my as i64;
+        if value.0[0] > 0 && x >= 0 && y >= 0 && x < width as i64 && y < height as i64 {
+            pixels.push(y as usize * width as usize + x as usize);
+        }
+    }
+    Some(pixels)
+}
+
+/// Outer contours of a mask shape in image coordinates, largest area first
+///
+/// Contours run through the centers of the boundary pixels, with runs of
+/// collinear pixels reduced to their end points. Returns nothing when the
+/// shape has no decodable bitmap.
+pub fn mask_contours(shape: &Shape) -> Vec<Vec<(f64, f64)>> {
+    let Some((mask, origin_x, origin_y)) = shape
+        .mask
+        .as_deref()
+        .and_then(|data| decode_mask(data, &shape.points))
+    else {
+        return Vec::new();
+    };
+
+    // The tracer skips regions touching the image edge, so trace inside a 1 pixel frame
+    let mut framed = GrayImage::new(mask.width() + 2, mask.height() + 2);
+    image::imageops::replace(&mut framed, &mask, 1, 1);
+
+    let mut contours: Vec<Vec<(f64, f64)>> = imageproc::contours::find_contours::<i64>(&framed)
+        .into_iter()
+        .filter(|contour| contour.border_type == BorderType::Outer)
+        .map(|contour| {
+            let points: Vec<(f64, f64)> = contour
+                .points
+                .iter()
+                .map(|p| ((origin_x + p.x - 1) as f64, (origin_y + p.y - 1) as f64))
+                .collect();
+            drop_collinear(&points)
+        })
+        .collect();
+    contours.sort_by(|a, b| ring_area(b).total_cmp(&ring_area(a)));
+    contours
+}
+
+/// Remove the vertices of a closed ring that lie on a straight run
+fn drop_collinear(ring: &[(f64, f64)]) -> Vec<(f64, f64)> {
+    let n = ring.len();
+    if n < 3 {
+        return ring.to_vec();
+    }
+    (0..n)
+        .filter(|&i| {
+            let (a, b, c) = (ring[(i + n - 1) % n], ring[i], ring[(i + 1) % n]);
+            let (u, v) = ((b.0 - a.0, b.1 - a.1), (c.0 - b.0, c.1 - b.1));
+            // Keep turns and the tips of one pixel wide spikes
+            u.0 * v.1 != u.1 * v.0 || u.0 * v.0 + u.1 * v.1 <= 0.0
+        })
+        .map(|i| ring[i])
+        .collect()
+}
+
+/// Unsigned area of a closed ring
+fn ring_area(ring: &[(f64, f64)]) -> f64 {
+    let twice: f64 = ring
+        .iter()
+        .zip(ring.iter().cycle().skip(1))
+        .map(|(a, b)| a.0 * b.1 - b.0 * a.1)
+        .sum();
+    twice.abs() / 2.0
+}
+
+/// Order in which shapes are painted (later shapes overwrite earlier ones)
+///
+/// `areas` and `classes` are per shape; sorting is stable so ties keep file
+/// order.
+pub fn paint_order(areas: &[usize], classes: &[usize], order: MaskDrawOrder) -> Vec<usize> {
+    let mut indices: Vec<usize> = (0..areas.len()).collect();
+    match order {
+        MaskDrawOrder::FileOrder => {}
+        // Large shapes first so small objects stay visible on top
+        MaskDrawOrder::AreaDescending => indices.sort_by(|&a, &b| areas[b].cmp(&areas[a])),
+        MaskDrawOrder::ClassIndex => indices.sort_by_key(|&i| classes[i]),
+    }
+    indices
+}
+
+/// Color of an index in the Pascal VOC palette
+pub fn palette_color(index: u8) -> [u8; 3] {
+    let mut color = [0u8; 3];
+    let mut value = index;
+    for bit in 0..8 {
+        for (channel, c) in color.iter_mut().enumerate() {
+            *c |= ((value >> channel) & 1) << (7 - bit);
+        }
+        value >>= 3;
+    }
+    color
+}
+
+/// Render a class-index mask with the VOC palette
+pub fn colorize(mask: &GrayImage) -> RgbImage {
+    RgbImage::from_fn(mask.width(), mask.height(), |x, y| {
+        Rgb(palette_color(mask.get_pixel(x, y).0[0]))
+    })
+}
+
+/// Render an instance-ID mask, cycling the VOC palette past 255 instances
+pub fn colorize_instances(mask: &InstanceImage) -> RgbImage {
+    RgbImage::from_fn(mask.width(), mask.height(), |x, y| {
+        let id = mask.get_pixel(x, y).0[0];
+        let index = if id == 0 { 0 } else { (id - 1) % 255 + 1 };
+        Rgb(palette_color(index as u8))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(shape_type: &str, points: Vec<(f64, f64)>) -> Shape {
+        Shape {
+            label: "a".to_string(),
+            points,
+            group_id: None,
+            shape_type: shape_type.to_string(),
+            description: None,
+            mask: None,
+            flags: None,
+        }
+    }
+
+    #[test]
+    fn test_shape_pixels() {
+        let rect = shape("rectangle", vec![(1.0, 1.0), (3.0, 4.0)]);
+        assert_eq!(shape_pixels(&rect, 10, 10).len(), 6);
+
+        // Clipped at the image border
+        let rect = shape("rectangle", vec![(-5.0, -5.0), (2.0, 2.0)]);
+        assert_eq!(shape_pixels(&rect, 10, 10).len(), 4);
+
+        let triangle = shape("polygon", vec![(0.0, 0.0), (4.0, 0.0), (0.0, 4.0)]);
+        assert_eq!(shape_pixels(&triangle, 10, 10).len(), 6);
+
+        let circle = shape("circle", vec![(5.0, 5.0), (7.0, 5.0)]);
+        assert_eq!(shape_pixels(&circle, 10, 10).len(), 12);
+
+        let line = shape("line", vec![(0.0, 0.0), (5.0, 5.0)]);
+        assert!(shape_pixels(&line, 10, 10).is_empty());
+    }
+
+    #[test]
+    fn test_mask_pixels() {
+        use base64::Engine;
+        use std::io::Cursor;
+
+        let mut mask = GrayImage::new(2, 2);
+        mask.put_pixel(1, 1, image::Luma([1]));
+        let mut png = Vec::new();
+        mask.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let mut shape = shape("mask", vec![(3.0, 2.0), (4.0, 3.0)]);
+        shape.mask = Some(base64::engine::general_purpose::STANDARD.encode(png));
+        assert_eq!(shape_pixels(&shape, 10, 10), vec![3 * 10 + 4]);
+    }
+
+    #[test]
+    fn test_mask_contours() {
+        use base64::Engine;
+        use std::io::Cursor;
+
+        // 3x2 block on the bitmap edge and a single stray pixel
+        let mut mask = GrayImage::new(6, 6);
+        for (x, y) in [(0, 0), (1, 0), (2, 0), (0, 1), (1, 1), (2, 1), (5, 5)] {
+            mask.put_pixel(x, y, image::Luma([255]));
+        }
+        let mut png = Vec::new();
+        mask.write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+
+        let mut shape = shape("mask", vec![(10.0, 20.0), (16.0, 26.0)]);
+        shape.mask = Some(base64::engine::general_purpose::STANDARD.encode(png));
+        let contours = mask_contours(&shape);
+        assert_eq!(contours.len(), 2);
+        let mut corners = contours[0].clone();
+        corners.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(corners, vec![(10.0, 20.0), (10.0, 21.0), (12.0, 20.0), (12.0, 21.0)]);
+        assert_eq!(contours[1], vec![(15.0, 25.0)]);
+
+        shape.mask = None;
+        assert!(mask_contours(&shape).is_empty());
+    }
+
+    #[test]
+    fn test_paint_order_and_palette() {
+        let areas = [5, 50, 5];
+        let classes = [2, 0, 1];
+        assert_eq!(paint_order(&areas, &classes, MaskDrawOrder::FileOrder), vec![0, 1, 2]);
+        assert_eq!(paint_order(&areas, &classes, MaskDrawOrder::AreaDescending), vec![1, 0, 2]);
+        assert_eq!(paint_order(&areas, &classes, MaskDrawOrder::ClassIndex), vec![1, 2, 0]);
+
+        assert_eq!(palette_color(0), [0, 0, 0]);
+        assert_eq!(palette_color(1), [128, 0, 0]);
+        assert_eq!(palette_color(15), [192, 128, 128]);
+        assert_eq!(palette_color(255), [224, 224, 192]);
+
+        let mut instances = InstanceImage::new(2, 1);
+        instances.put_pixel(1, 0, Luma([256]));
+        let colors = colorize_instances(&instances);
+        assert_eq!(colors.get_pixel(0, 0).0, [0, 0, 0]);
+        assert_eq!(colors.get_pixel(1, 0).0, palette_color(1));
+    }
+}
@@ -0,0 +1,816 @@
+//! Annotation rendering for previews, visualizations and export reports
+//!
+//! Draws every LabelMe shape type onto its image in one color per class:
+//! - polygon / rectangle / circle / mask: filled with `fill_alpha`, then outlined
+//! - line / linestrip: open polylines
+//! - point / points: dots
+//!
+//! Class colors are derived from a hash of the label, so a class keeps its
+//! color across images and datasets unless the palette overrides it. Shapes
+//! sharing a `group_id` get a common outline, and an optional legend lists
+//! the classes of the image.
+
+use crate::labelme_convert::conversion::rectangle_to_polygon;
+use crate::labelme_convert::io::{read_labelme_json, resolve_image_path};
+use crate::labelme_convert::pipeline::hash_string;
+use crate::labelme_convert::raster::{mask_contours, shape_pixels};
+use crate::labelme_convert::sample::{sample_annotated_images, SampleOptions};
+use crate::labelme_convert::types::{LabelMeAnnotation, Shape, ShapeKind};
+use opencv::core::{Mat, Point, Rect, Scalar, Vector};
+use opencv::prelude::*;
+use opencv::{imgcodecs, imgproc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Folder of rendered previews inside an exported dataset
+pub const PREVIEW_DIR: &str = "previews";
+
+/// Space between label text and the edge of its background
+const TEXT_PADDING: i32 = 3;
+
+/// Gap between a group outline and the shapes it encloses
+const GROUP_MARGIN: f64 = 6.0;
+
+/// Drawing options shared by previews, visualizations and export reports
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderOptions {
+    /// Opacity of shape fills (0.0 = outlines only)
+    pub fill_alpha: f32,
+    /// Outline thickness in pixels
+    pub line_thickness: i32,
+    /// Draw every shape except points as its bounding box
+    pub boxes_only: bool,
+    /// Draw the label of every shape on a background in its class color
+    pub show_labels: bool,
+    /// Scale of the label font
+    pub font_scale: f64,
+    /// Outline shapes sharing a group_id
+    pub group_outlines: bool,
+    /// List the classes of the image with their shape counts
+    pub legend: bool,
+    /// Fixed RGB colors per class, overriding the derived colors
+    pub palette: HashMap<String, [u8; 3]>,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            fill_alpha: 0.3,
+            line_thickness: 2,
+            boxes_only: false,
+            show_labels: true,
+            font_scale: 0.5,
+            group_outlines: true,
+            legend: false,
+            palette: HashMap::new(),
+        }
+    }
+}
+
+impl RenderOptions {
+    /// Validate the render options
+    pub fn validate(&self) -> Result<(), String> {
+        if !(0.0..=1.0).contains(&self.fill_alpha) {
+            return Err(format!(
+                "fill_alpha must be between 0.0 and 1.0, got {}",
+                self.fill_alpha
+            ));
+        }
+        if self.line_thickness < 1 {
+            return Err("line_thickness must be at least 1".to_string());
+        }
+        if self.font_scale <= 0.0 {
+            return Err(format!(
+                "font_scale must be positive, got {}",
+                self.font_scale
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Rendered sample images written next to an export
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreviewConfig {
+    /// Number of images to render (sampled with the export seed, stratified by label)
+    pub count: usize,
+    pub render: RenderOptions,
+}
+
+impl Default for PreviewConfig {
+    fn default() -> Self {
+        Self {
+            count: 8,
+            render: RenderOptions::default(),
+        }
+    }
+}
+
+/// RGB color of a class
+pub fn class_color(label: &str, options: &RenderOptions) -> [u8; 3] {
+    if let Some(color) = options.palette.get(label) {
+        return *color;
+    }
+    let hue = (hash_string(label) % 3600) as f32 / 10.0;
+    hsv_to_rgb(hue, 0.8, 0.95)
+}
+
+fn hsv_to_rgb(hue: f32, saturation: f32, value: f32) -> [u8; 3] {
+    let chroma = value * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0) % 2.0 - 1.0).abs());
+    let (r, g, b) = match hue as u32 / 60 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = value - chroma;
+    [r, g, b].map(|c| ((c + m) * 255.0).round() as u8)
+}
+
+/// Black or white, whichever reads better on `background`
+pub fn text_color(background: [u8; 3]) -> [u8; 3] {
+    let [r, g, b] = background.map(|c| c as f32);
+    if 0.299 * r + 0.587 * g + 0.114 * b > 150.0 {
+        [0, 0, 0]
+    } else {
+        [255, 255, 255]
+    }
+}
+
+/// Shape kind, treating the legacy `bounding_box` type as a rectangle
+fn kind_of(shape: &Shape) -> ShapeKind {
+    match shape.shape_type.as_str() {
+        "bounding_box" => ShapeKind::Rectangle,
+        _ => ShapeKind::of(shape),
+    }
+}
+
+/// Bounding box of a shape as `[x1, y1, x2, y2]` (circles use their radius)
+pub fn shape_bounds(shape: &Shape) -> Option<[f64; 4]> {
+    if kind_of(shape) == ShapeKind::Circle && shape.points.len() >= 2 {
+        let (cx, cy) = shape.points[0];
+        let (px, py) = shape.points[1];
+        let radius = (px - cx).hypot(py - cy);
+        return Some([cx - radius, cy - radius, cx + radius, cy + radius]);
+    }
+    let (first, rest) = shape.points.split_first()?;
+    Some(rest.iter().fold(
+        [first.0, first.1, first.0, first.1],
+        |[x1, y1, x2, y2], &(x, y)| [x1.min(x), y1.min(y), x2.max(x), y2.max(y)],
+    ))
+}
+
+/// Outline of every group with at least two shapes, sorted by group_id
+///
+/// The outline encloses the bounds of all member shapes plus a small margin.
+pub fn group_bounds(shapes: &[Shape]) -> Vec<(i64, [f64; 4])> {
+    let mut groups: BTreeMap<i64, (usize, [f64; 4])> = BTreeMap::new();
+    for shape in shapes {
+        let (Some(group_id), Some(bounds)) = (shape.group_id, shape_bounds(shape)) else {
+            continue;
+        };
+        let entry = groups.entry(group_id).or_insert((0, bounds));
+        entry.0 += 1;
+        entry.1 = [
+            entry.1[0].min(bounds[0]),
+            entry.1[1].min(bounds[1]),
+            entry.1[2].max(bounds[2]),
+            entry.1[3].max(bounds[3]),
+        ];
+    }
+    groups
+        .into_iter()
+        .filter(|(_, (members, _))| *members >= 2)
+        .map(|(group_id, (_, [x1, y1, x2, y2]))| {
+            (
+                group_id,
+                [
+                    x1 - GROUP_MARGIN,
+                    y1 - GROUP_MARGIN,
+                    x2 + GROUP_MARGIN,
+                    y2 + GROUP_MARGIN,
+                ],
+            )
+        })
+        .collect()
+}
+
+/// Classes of the shapes with their shape counts, sorted by label
+pub fn legend_entries(shapes: &[Shape]) -> Vec<(String, usize)> {
+    let mut counts: BTreeMap<&str, usize> = BTreeMap::new();
+    for shape in shapes {
+        *counts.entry(shape.label.as_str()).or_insert(0) += 1;
+    }
+    counts
+        .into_iter()
+        .map(|(label, count)| (label.to_string(), count))
+        .collect()
+}
+
+fn cv_error(e: opencv::Error) -> String {
+    format!("OpenCV error: {}", e)
+}
+
+/// OpenCV color (BGR) of an RGB color
+fn bgr(color: [u8; 3]) -> Scalar {
+    Scalar::new(color[2] as f64, color[1] as f64, color[0] as f64, 0.0)
+}
+
+fn to_point(&(x, y): &(f64, f64)) -> Point {
+    Point::new(x.round() as i32, y.round() as i32)
+}
+
+fn bounds_rect([x1, y1, x2, y2]: [f64; 4]) -> Rect {
+    let (left, top) = (x1.round() as i32, y1.round() as i32);
+    Rect::new(
+        left,
+        top,
+        (x2.round() as i32 - left).max(1),
+        (y2.round() as i32 - top).max(1),
+    )
+}
+
+/// Blend the fills of all area shapes into a BGR image
+fn fill_shapes(image: &mut Mat, shapes: &[Shape], options: &RenderOptions) -> Result<(), String> {
+    if image.channels() != 3 {
+        return Err("Annotations can only be rendered on color images".to_string());
+    }
+    let (width, height) = (image.cols() as u32, image.rows() as u32);
+    let alpha = options.fill_alpha;
+    let pixels = image.data_bytes_mut().map_err(cv_error)?;
+
+    for shape in shapes {
+        let kind = kind_of(shape);
+        if matches!(
+            kind,
+            ShapeKind::Line | ShapeKind::LineStrip | ShapeKind::Point
+        ) {
+            continue;
+        }
+        let area = match (options.boxes_only, shape_bounds(shape)) {
+            (true, Some([x1, y1, x2, y2])) => Shape {
+                points: vec![(x1, y1), (x2, y2)],
+                shape_type: "rectangle".to_string(),
+                mask: None,
+                ..shape.clone()
+            },
+            _ if kind == ShapeKind::Rectangle => Shape {
+                shape_type: "rectangle".to_string(),
+                ..shape.clone()
+            },
+            _ if kind == ShapeKind::Other => Shape {
+                shape_type: "polygon".to_string(),
+                ..shape.clone()
+            },
+            _ => shape.clone(),
+        };
+
+        let [r, g, b] = class_color(&shape.label, options).map(|c| c as f32);
+        for index in shape_pixels(&area, width, height) {
+            let pixel = &mut pixels[index * 3..index * 3 + 3];
+            for (channel, value) in pixel.iter_mut().zip([b, g, r]) {
+                *channel = (*channel as f32 * (1.0 - alpha) + value * alpha).round() as u8;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Outline of one shape
+fn draw_shape(image: &mut Mat, shape: &Shape, options: &RenderOptions) -> Result<(), String> {
+    let color = bgr(class_color(&shape.label, options));
+    let thickness = options.line_thickness;
+    let kind = kind_of(shape);
+
+    if kind == ShapeKind::Point {
+        let radius = (thickness * 2).max(3);
+        for point in &shape.points {
+            imgproc::circle(
+                image,
+                to_point(point),
+                radius,
+                color,
+                imgproc::FILLED,
+                imgproc::LINE_AA,
+                0,
+            )
+            .map_err(cv_error)?;
+        }
+        return Ok(());
+    }
+
+    if options.boxes_only {
+        if let Some(bounds) = shape_bounds(shape) {
+            imgproc::rectangle(
+                image,
+                bounds_rect(bounds),
+                color,
+                thickness,
+                imgproc::LINE_AA,
+                0,
+            )
+            .map_err(cv_error)?;
+        }
+        return Ok(());
+    }
+
+    if kind == ShapeKind::Circle && shape.points.len() >= 2 {
+        let (cx, cy) = shape.points[0];
+        let (px, py) = shape.points[1];
+        imgproc::circle(
+            image,
+            to_point(&(cx, cy)),
+            (px - cx).hypot(py - cy).round() as i32,
+            color,
+            thickness,
+            imgproc::LINE_AA,
+            0,
+        )
+        .map_err(cv_error)?;
+        return Ok(());
+    }
+
+    let (rings, closed) = match kind {
+        ShapeKind::Rectangle => (vec![rectangle_to_polygon(&shape.points)], true),
+        // Every region of the bitmap; masks that cannot be decoded show their box
+        ShapeKind::Mask => match mask_contours(shape) {
+            contours if contours.is_empty() => (vec![rectangle_to_polygon(&shape.points)], true),
+            contours => (contours, true),
+        },
+        ShapeKind::Line | ShapeKind::LineStrip => (vec![shape.points.clone()], false),
+        _ => (vec![shape.points.clone()], true),
+    };
+    let outlines: Vector<Vector<Point>> = rings
+        .iter()
+        .filter(|ring| ring.len() >= 2)
+        .map(|ring| ring.iter().map(to_point).collect())
+        .collect();
+    if outlines.is_empty() {
+        return Ok(());
+    }
+    imgproc::polylines(
+        image,
+        &outlines,
+        closed,
+        color,
+        thickness,
+        imgproc::LINE_AA,
+        0,
+    )
+    .map_err(cv_error)
+}
+
+/// Text on a filled background whose top-left corner is `anchor`
+///
+/// The box is moved inside the image when it would leave it.
+fn draw_tag(
+    image: &mut Mat,
+    text: &str,
+    anchor: Point,
+    background: [u8; 3],
+    options: &RenderOptions,
+) -> Result<(), String> {
+    let mut baseline = 0;
+    let size = imgproc::get_text_size(
+        text,
+        imgproc::FONT_HERSHEY_SIMPLEX,
+        options.font_scale,
+        1,
+        &mut baseline,
+    )
+    .map_err(cv_error)?;
+    let width = size.width + 2 * TEXT_PADDING;
+    let height = size.height + baseline + 2 * TEXT_PADDING;
+    let x = anchor.x.min(image.cols() - width).max(0);
+    let y = anchor.y.min(image.rows() - height).max(0);
+
+    imgproc::rectangle(
+        image,
+        Rect::new(x, y, width, height),
+        bgr(background),
+        imgproc::FILLED,
+        imgproc::LINE_8,
+        0,
+    )
+    .map_err(cv_error)?;
+    imgproc::put_text(
+        image,
+        text,
+        Point::new(x + TEXT_PADDING, y + TEXT_PADDING + size.height),
+        imgproc::FONT_HERSHEY_SIMPLEX,
+        options.font_scale,
+        bgr(text_color(background)),
+        1,
+        imgproc::LINE_AA,
+        false,
+    )
+    .map_err(cv_error)
+}
+
+/// Label of a shape, placed above its bounds (inside when there is no room)
+fn draw_label(image: &mut Mat, shape: &Shape, options: &RenderOptions) -> Result<(), String> {
+    let Some([x1, y1, _, _]) = shape_bounds(shape) else {
+        return Ok(());
+    };
+    let mut baseline = 0;
+    let size = imgproc::get_text_size(
+        &shape.label,
+        imgproc::FONT_HERSHEY_SIMPLEX,
+        options.font_scale,
+        1,
+        &mut baseline,
+    )
+    .map_err(cv_error)?;
+    let tag_height = size.height + baseline + 2 * TEXT_PADDING;
+    let (x, y) = (x1.round() as i32, y1.round() as i32);
+    let top = if y >= tag_height { y - tag_height } else { y };
+    draw_tag(
+        image,
+        &shape.label,
+        Point::new(x, top),
+        class_color(&shape.label, options),
+        options,
+    )
+}
+
+/// Legend panel in the top-left corner with a swatch per class
+fn draw_legend(
+    image: &mut Mat,
+    entries: &[(String, usize)],
+    options: &RenderOptions,
+) -> Result<(), String> {
+    if entries.is_empty() {
+        return Ok(());
+    }
+    let lines: Vec<String> = entries
+        .iter()
+        .map(|(label, count)| format!("{} ({})", label, count))
+        .collect();
+
+    let mut baseline = 0;
+    let mut text_width = 0;
+    let mut text_height = 0;
+    for line in &lines {
+        let size = imgproc::get_text_size(
+            line,
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            options.font_scale,
+            1,
+            &mut baseline,
+        )
+        .map_err(cv_error)?;
+        text_width = text_width.max(size.width);
+        text_height = text_height.max(size.height);
+    }
+    let row_height = text_height + baseline + 2 * TEXT_PADDING;
+    let swatch = row_height - 2 * TEXT_PADDING;
+    let margin = 8;
+
+    imgproc::rectangle(
+        image,
+        Rect::new(
+            margin,
+            margin,
+            swatch + text_width + 4 * TEXT_PADDING,
+            row_height * lines.len() as i32 + 2 * TEXT_PADDING,
+        ),
+        Scalar::new(32.0, 32.0, 32.0, 0.0),
+        imgproc::FILLED,
+        imgproc::LINE_8,
+        0,
+    )
+    .map_err(cv_error)?;
+
+    for (row, ((label, _), line)) in entries.iter().zip(&lines).enumerate() {
+        let top = margin + TEXT_PADDING + row as i32 * row_height;
+        imgproc::rectangle(
+            image,
+            Rect::new(margin + TEXT_PADDING, top + TEXT_PADDING, swatch, swatch),
+            bgr(class_color(label, options)),
+            imgproc::FILLED,
+            imgproc::LINE_8,
+            0,
+        )
+        .map_err(cv_error)?;
+        imgproc::put_text(
+            image,
+            line,
+            Point::new(
+                margin + swatch + 3 * TEXT_PADDING,
+                top + TEXT_PADDING + text_height,
+            ),
+            imgproc::FONT_HERSHEY_SIMPLEX,
+            options.font_scale,
+            Scalar::new(255.0, 255.0, 255.0, 0.0),
+            1,
+            imgproc::LINE_AA,
+            false,
+        )
+        .map_err(cv_error)?;
+    }
+    Ok(())
+}
+
+/// Draw the shapes of `annotation` onto a BGR image
+///
+/// Fills go first, then outlines, group outlines, labels and the legend, so
+/// text is never covered by a shape.
+pub fn render_annotation(
+    image: &mut Mat,
+    annotation: &LabelMeAnnotation,
+    options: &RenderOptions,
+) -> Result<(), String> {
+    options.validate()?;
+    let shapes = &annotation.shapes;
+
+    if options.fill_alpha > 0.0 {
+        fill_shapes(image, shapes, options)?;
+    }
+    for shape in shapes {
+        draw_shape(image, shape, options)?;
+    }
+    if options.group_outlines {
+        for (group_id, bounds) in group_bounds(shapes) {
+            let color = class_color(&format!("group {}", group_id), options);
+            imgproc::rectangle(
+                image,
+                bounds_rect(bounds),
+                bgr(color),
+                1,
+                imgproc::LINE_AA,
+                0,
+            )
+            .map_err(cv_error)?;
+            let anchor = Point::new(bounds[0].round() as i32, bounds[3].round() as i32);
+            draw_tag(
+                image,
+                &format!("group {}", group_id),
+                anchor,
+                color,
+                options,
+            )?;
+        }
+    }
+    if options.show_labels {
+        for shape in shapes {
+            draw_label(image, shape, options)?;
+        }
+    }
+    if options.legend {
+        draw_legend(image, &legend_entries(shapes), options)?;
+    }
+    Ok(())
+}
+
+/// Read `image_path` and draw the annotation onto it
+fn rendered_image(
+    image_path: &Path,
+    annotation: &LabelMeAnnotation,
+    options: &RenderOptions,
+) -> Result<Mat, String> {
+    let mut image = imgcodecs::imread(&image_path.to_string_lossy(), imgcodecs::IMREAD_COLOR)
+        .map_err(cv_error)?;
+    if image.empty() {
+        return Err(format!("Failed to read image {}", image_path.display()));
+    }
+    render_annotation(&mut image, annotation, options)?;
+    Ok(image)
+}
+
+/// Render an annotation onto `image_path` and save the result to `output_path`
+pub fn render_image(
+    image_path: &Path,
+    annotation: &LabelMeAnnotation,
+    output_path: &Path,
+    options: &RenderOptions,
+) -> Result<(), String> {
+    let image = rendered_image(image_path, annotation, options)?;
+    let written = imgcodecs::imwrite(&output_path.to_string_lossy(), &image, &Vector::new())
+        .map_err(cv_error)?;
+    if !written {
+        return Err(format!("Failed to write {}", output_path.display()));
+    }
+    Ok(())
+}
+
+/// Render a LabelMe file onto its image and save the result to `output_path`
+pub fn render_file(
+    json_path: &Path,
+    output_path: &Path,
+    options: &RenderOptions,
+) -> Result<(), String> {
+    let annotation = read_labelme_json(json_path)?;
+    let image_path = resolve_image_path(json_path, &annotation.image_path);
+    render_image(&image_path, &annotation, output_path, options)
+}
+
+/// Render a LabelMe file onto its image and return it as JPEG bytes
+pub fn render_file_to_jpeg(json_path: &Path, options: &RenderOptions) -> Result<Vec<u8>, String> {
+    let annotation = read_labelme_json(json_path)?;
+    let image_path = resolve_image_path(json_path, &annotation.image_path);
+    let image = rendered_image(&image_path, &annotation, options)?;
+    let mut buffer: Vector<u8> = Vector::new();
+    imgcodecs::imencode(".jpg", &image, &mut buffer, &Vector::new()).map_err(cv_error)?;
+    Ok(buffer.to_vec())
+}
+
+/// Render a stratified sample of the annotated images below `input_dir`
+///
+/// Files are written to `output_dir` as `{index}_{stem}.jpg`; images that
+/// fail to render are skipped with a warning.
+pub fn render_previews(
+    input_dir: &Path,
+    output_dir: &Path,
+    config: &PreviewConfig,
+    seed: u64,
+) -> Result<Vec<PathBuf>, String> {
+    let sampling = SampleOptions {
+        seed,
+        stratify: true,
+        ..SampleOptions::default()
+    };
+    let sample = sample_annotated_images(input_dir, config.count, &sampling)?;
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create preview directory: {}", e))?;
+
+    let mut written = Vec::new();
+    for (index, image) in sample.images.iter().enumerate() {
+        let stem = image
+            .json_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let output_path = output_dir.join(format!("{:03}_{}.jpg", index, stem));
+        match render_file(&image.json_path, &output_path, &config.render) {
+            Ok(()) => written.push(output_path),
+            Err(e) => println!(
+                "Warning: Failed to render preview of {}: {}",
+                image.json_path.display(),
+                e
+            ),
+        }
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn shape(label: &str, shape_type: &str, points: Vec<(f64, f64)>, group: Option<i64>) -> Shape {
+        Shape {
+            label: label.to_string(),
+            points,
+            group_id: group,
+            shape_type: shape_type.to_string(),
+            description: None,
+            mask: None,
+            flags: None,
+        }
+    }
+
+    #[test]
+    fn test_render_annotation_pixels() {
+        use base64::Engine;
+        use opencv::core::CV_8UC3;
+        use std::io::Cursor;
+
+        // Mask bitmap: triangle of the pixels on and below the diagonal
+        let mut bitmap = image::GrayImage::new(20, 20);
+        for y in 0..20 {
+            for x in 0..=y {
+                bitmap.put_pixel(x, y, image::Luma([255]));
+            }
+        }
+        let mut png = Vec::new();
+        bitmap
+            .write_to(&mut Cursor::new(&mut png), image::ImageFormat::Png)
+            .unwrap();
+        let mut mask = shape("spill", "mask", vec![(60.0, 50.0), (80.0, 70.0)], None);
+        mask.mask = Some(base64::engine::general_purpose::STANDARD.encode(png));
+
+        let annotation = LabelMeAnnotation {
+            version: "5.0.1".to_string(),
+            flags: None,
+            shapes: vec![
+                shape("box", "rectangle", vec![(10.0, 40.0), (40.0, 80.0)], None),
+                mask,
+            ],
+            image_path: "frame.jpg".to_string(),
+            image_data: None,
+            image_height: 100,
+            image_width: 100,
+        };
+        let mut options = RenderOptions {
+            fill_alpha: 0.5,
+            line_thickness: 3,
+            ..RenderOptions::default()
+        };
+        options.palette.insert("box".to_string(), [255, 0, 0]);
+        options.palette.insert("spill".to_string(), [0, 0, 255]);
+
+        let mut image =
+            Mat::new_rows_cols_with_default(100, 100, CV_8UC3, Scalar::all(100.0)).unwrap();
+        render_annotation(&mut image, &annotation, &options).unwrap();
+        let data = image.data_bytes().unwrap();
+        let bgr = |x: usize, y: usize| {
+            let i = (y * 100 + x) * 3;
+            [data[i], data[i + 1], data[i + 2]]
+        };
+
+        // Fill: gray blended half-way with red
+        assert_eq!(bgr(25, 60), [50, 50, 178]);
+        // Outline in the full class color on top of the fill
+        let edge = bgr(10, 60);
+        assert!(edge[2] > 230 && edge[1] < 30, "{:?}", edge);
+        // Label background above the box, left of the text
+        assert_eq!(bgr(11, 38), [0, 0, 255]);
+
+        // The mask is outlined along its diagonal, not along its box
+        let diagonal = bgr(70, 60);
+        assert!(diagonal[0] > 230 && diagonal[2] < 30, "{:?}", diagonal);
+        assert_eq!(bgr(75, 50), [100, 100, 100]);
+        assert_eq!(bgr(95, 95), [100, 100, 100]);
+    }
+
+    #[test]
+    fn test_class_colors_are_stable() {
+        let options = RenderOptions::default();
+        assert_eq!(class_color("car", &options), class_color("car", &options));
+        assert_ne!(
+            class_color("car", &options),
+            class_color("person", &options)
+        );
+
+        let mut custom = RenderOptions::default();
+        custom.palette.insert("car".to_string(), [255, 0, 0]);
+        assert_eq!(class_color("car", &custom), [255, 0, 0]);
+        assert_eq!(
+            class_color("person", &custom),
+            class_color("person", &options)
+        );
+
+        assert_eq!(text_color([250, 250, 250]), [0, 0, 0]);
+        assert_eq!(text_color([20, 20, 120]), [255, 255, 255]);
+    }
+
+    #[test]
+    fn test_bounds_groups_and_legend() {
+        let shapes = vec![
+            shape(
+                "person",
+                "rectangle",
+                vec![(10.0, 10.0), (50.0, 90.0)],
+                Some(1),
+            ),
+            shape("helmet", "circle", vec![(30.0, 5.0), (30.0, 0.0)], Some(1)),
+            shape(
+                "person",
+                "polygon",
+                vec![(100.0, 10.0), (140.0, 10.0), (120.0, 80.0)],
+                Some(2),
+            ),
+            shape("hand", "point", vec![(20.0, 60.0)], None),
+        ];
+
+        assert_eq!(shape_bounds(&shapes[1]), Some([25.0, 0.0, 35.0, 10.0]));
+        assert_eq!(shape_bounds(&shapes[3]), Some([20.0, 60.0, 20.0, 60.0]));
+
+        // Group 2 has a single shape and gets no outline
+        let groups = group_bounds(&shapes);
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].0, 1);
+        assert_eq!(groups[0].1, [4.0, -6.0, 56.0, 96.0]);
+
+        assert_eq!(
+            legend_entries(&shapes),
+            vec![
+                ("hand".to_string(), 1),
+                ("helmet".to_string(), 1),
+                ("person".to_string(), 2),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_render_options_validation() {
+        assert!(RenderOptions::default().validate().is_ok());
+        let options = RenderOptions {
+            fill_alpha: 1.5,
+            ..RenderOptions::default()
+        };
+        assert!(options.validate().is_err());
+        let options = RenderOptions {
+            line_thickness: 0,
+            ..RenderOptions::default()
+        };
+        assert!(options.validate().is_err());
+    }
+}
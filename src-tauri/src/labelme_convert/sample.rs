@@ -0,0 +1,260 @@
+//! Reproducible sampling of annotated images
+//!
+//! Every LabelMe file below a directory is a candidate, not just the first
+//! page of images. The selection only depends on the seed and the files on
+//! disk:
+//! - candidates are sorted by path and shuffled with the seed
+//! - images for `must_include` labels are picked first
+//! - with `stratify`, the remaining picks go round-robin over the labels,
+//!   rarest label first, so rare classes show up even in small samples
+
+use crate::labelme_convert::io::{find_json_files, read_labelme_json, resolve_image_path};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use rayon::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// How annotated images are picked
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SampleOptions {
+    /// Seed for the shuffle; the same seed picks the same images
+    pub seed: u64,
+    /// Spread the picks evenly over the labels instead of sampling uniformly
+    pub stratify: bool,
+    /// Labels that must appear in at least one picked image
+    pub must_include: Vec<String>,
+}
+
+impl Default for SampleOptions {
+    fn default() -> Self {
+        Self {
+            seed: 42,
+            stratify: false,
+            must_include: Vec::new(),
+        }
+    }
+}
+
+/// An annotated image considered for sampling
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SampleCandidate {
+    pub json_path: PathBuf,
+    pub image_path: PathBuf,
+    /// Distinct labels of the image, sorted
+    pub labels: Vec<String>,
+}
+
+/// Picked images and the size of the pool they were picked from
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImageSample {
+    pub images: Vec<SampleCandidate>,
+    /// Annotated images found below the directory
+    pub candidates: usize,
+}
+
+/// Pick up to `count` annotated images below `dir`
+///
+/// Only files with at least one shape and an existing image are candidates.
+/// Images picked for `must_include` labels are always part of the sample,
+/// even when there are more of them than `count`.
+pub fn sample_annotated_images(
+    dir: &Path,
+    count: usize,
+    options: &SampleOptions,
+) -> Result<ImageSample, String> {
+    if !dir.exists() {
+        return Err(format!("Directory does not exist: {}", dir.display()));
+    }
+
+    let candidates: Vec<SampleCandidate> = find_json_files(dir)
+        .par_iter()
+        .filter_map(|json_path| {
+            let annotation = read_labelme_json(json_path).ok()?;
+            if annotation.shapes.is_empty() {
+                return None;
+            }
+            let image_path = resolve_image_path(json_path, &annotation.image_path);
+            if !image_path.is_file() {
+                return None;
+            }
+            let mut labels: Vec<String> =
+                annotation.shapes.iter().map(|s| s.label.clone()).collect();
+            labels.sort();
+            labels.dedup();
+            Some(SampleCandidate {
+                json_path: json_path.clone(),
+                image_path,
+                labels,
+            })
+        })
+        .collect();
+
+    sample_candidates(candidates, count, options)
+}
+
+/// Pick up to `count` of `candidates` (see the module docs for the order)
+pub fn sample_candidates(
+    mut candidates: Vec<SampleCandidate>,
+    count: usize,
+    options: &SampleOptions,
+) -> Result<ImageSample, String> {
+    // Sort first so the seeded shuffle is reproducible regardless of input order
+    candidates.sort_by(|a, b| a.json_path.cmp(&b.json_path));
+    let mut rng = StdRng::seed_from_u64(options.seed);
+    candidates.shuffle(&mut rng);
+
+    let mut picked: Vec<usize> = Vec::new();
+    let mut taken: HashSet<usize> = HashSet::new();
+
+    for label in &options.must_include {
+        let covered = picked.iter().any(|&i| candidates[i].labels.contains(label));
+        if covered {
+            continue;
+        }
+        let index = candidates
+            .iter()
+            .position(|c| c.labels.contains(label))
+            .ok_or_else(|| format!("No annotated image contains the label '{}'", label))?;
+        picked.push(index);
+        taken.insert(index);
+    }
+
+    if options.stratify {
+        // Shuffled images per label, rarest label first
+        let mut by_label: HashMap<&str, Vec<usize>> = HashMap::new();
+        for (i, candidate) in candidates.iter().enumerate() {
+            for label in &candidate.labels {
+                by_label.entry(label.as_str()).or_default().push(i);
+            }
+        }
+        let mut queues: Vec<(&str, Vec<usize>)> = by_label.into_iter().collect();
+        queues.sort_by(|a, b| a.1.len().cmp(&b.1.len()).then(a.0.cmp(b.0)));
+        let mut positions = vec![0; queues.len()];
+
+        while picked.len() < count {
+            let mut progressed = false;
+            for (queue, position) in queues.iter().zip(positions.iter_mut()) {
+                if picked.len() >= count {
+                    break;
+                }
+                while let Some(&index) = queue.1.get(*position) {
+                    *position += 1;
+                    if taken.insert(index) {
+                        picked.push(index);
+                        progressed = true;
+                        break;
+                    }
+                }
+            }
+            if !progressed {
+                break;
+            }
+        }
+    } else {
+        for index in 0..candidates.len() {
+            if picked.len() >= count {
+                break;
+            }
+            if taken.insert(index) {
+                picked.push(index);
+            }
+        }
+    }
+
+    let total = candidates.len();
+    let mut slots: Vec<Option<SampleCandidate>> = candidates.into_iter().map(Some).collect();
+    Ok(ImageSample {
+        images: picked.into_iter().filter_map(|i| slots[i].take()).collect(),
+        candidates: total,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn candidate(name: &str, labels: &[&str]) -> SampleCandidate {
+        SampleCandidate {
+            json_path: PathBuf::from(format!("/data/{}.json", name)),
+            image_path: PathBuf::from(format!("/data/{}.jpg", name)),
+            labels: labels.iter().map(|l| l.to_string()).collect(),
+        }
+    }
+
+    fn pool() -> Vec<SampleCandidate> {
+        let mut candidates: Vec<SampleCandidate> = (0..50)
+            .map(|i| candidate(&format!("car{:02}", i), &["car"]))
+            .collect();
+        candidates.push(candidate("bike", &["bike"]));
+        candidates.push(candidate("truck", &["car", "truck"]));
+        candidates
+    }
+
+    fn names(sample: &ImageSample) -> Vec<String> {
+        sample
+            .images
+            .iter()
+            .map(|c| c.json_path.display().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn test_sample_is_reproducible() {
+        let options = SampleOptions::default();
+        let first = sample_candidates(pool(), 5, &options).unwrap();
+        let mut reversed = pool();
+        reversed.reverse();
+        let second = sample_candidates(reversed, 5, &options).unwrap();
+        assert_eq!(names(&first), names(&second));
+        assert_eq!(first.images.len(), 5);
+        assert_eq!(first.candidates, 52);
+
+        let other = SampleOptions {
+            seed: 7,
+            ..SampleOptions::default()
+        };
+        let third = sample_candidates(pool(), 5, &other).unwrap();
+        assert_ne!(names(&first), names(&third));
+    }
+
+    #[test]
+    fn test_stratified_sample_covers_rare_labels() {
+        let options = SampleOptions {
+            stratify: true,
+            ..SampleOptions::default()
+        };
+        let sample = sample_candidates(pool(), 3, &options).unwrap();
+        let labels: HashSet<&str> = sample
+            .images
+            .iter()
+            .flat_map(|c| c.labels.iter().map(|l| l.as_str()))
+            .collect();
+        assert_eq!(sample.images.len(), 3);
+        assert!(labels.contains("bike") && labels.contains("truck"));
+
+        // Asking for more than there is returns every image once
+        let all = sample_candidates(pool(), 100, &options).unwrap();
+        let unique: HashSet<String> = names(&all).into_iter().collect();
+        assert_eq!(unique.len(), 52);
+    }
+
+    #[test]
+    fn test_must_include_labels() {
+        let options = SampleOptions {
+            must_include: vec!["bike".to_string()],
+            ..SampleOptions::default()
+        };
+        let sample = sample_candidates(pool(), 1, &options).unwrap();
+        assert_eq!(names(&sample), vec!["/data/bike.json".to_string()]);
+
+        let missing = SampleOptions {
+            must_include: vec!["boat".to_string()],
+            ..SampleOptions::default()
+        };
+        assert!(sample_candidates(pool(), 1, &missing).is_err());
+    }
+}
@@ -30,7 +30,7 @@ pub async fn scan_labels_with_counts_async(
 }
 
 /// Blocking implementation of label counting (uses Rayon)
-fn scan_counts_blocking(
+pub(crate) fn scan_counts_blocking(
     input_dir: PathBuf,
     progress: Option<ProgressEmitter>,
 ) -> Result<HashMap<String, usize>, String> {
@@ -0,0 +1,557 @@
+//! Group-aware split planning
+//!
+//! The default splitter assigns every image to train/val/test independently
+//! by hashing its path. That leaks near-duplicate images across splits when
+//! a dataset contains several frames of one video or several photos from one
+//! camera session.
+//!
+//! When `ConversionConfig::split_group` is set, this module reads all files up
+//! front, derives a group key for each image and assigns whole groups to a
+//! split so that the requested `val_size`/`test_size` are matched as closely
+//! as group sizes allow.
+
+use crate::labelme_convert::config::ConversionConfig;
+use crate::labelme_convert::io::{find_background_images, read_labelme_json, resolve_image_path};
+use crate::labelme_convert::pipeline::{determine_split, hash_string, Split};
+use rand::rngs::StdRng;
+use rand::seq::SliceRandom;
+use rand::SeedableRng;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+/// Source of the key used to keep related images in the same split
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", content = "value", rename_all = "snake_case")]
+pub enum SplitGroupKey {
+    /// Regex applied to the image path; the first capture group (or the whole
+    /// match if the pattern has no groups) is the group key
+    PathRegex(String),
+    /// Name of the folder containing the image
+    ParentFolder,
+    /// Name of the first enabled image-level LabelMe flag starting with this prefix
+    /// (an empty prefix matches any flag)
+    Flag(String),
+}
+
+impl SplitGroupKey {
+    /// Check that the key definition is usable (e.g. the regex compiles)
+    pub fn validate(&self) -> Result<(), String> {
+        if let SplitGroupKey::PathRegex(pattern) = self {
+            Regex::new(pattern)
+                .map_err(|e| format!("Invalid split group regex '{}': {}", pattern, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Requested and achieved split ratios of a planned split
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct SplitReport {
+    /// Number of distinct groups that were assigned
+    pub group_count: usize,
+    /// Size of the largest group (in images)
+    pub largest_group: usize,
+    pub train_images: usize,
+    pub val_images: usize,
+    pub test_images: usize,
+    pub requested_val: f32,
+    pub requested_test: f32,
+    pub achieved_train: f32,
+    pub achieved_val: f32,
+    pub achieved_test: f32,
+    /// Images per cross-validation fold (empty outside k-fold mode)
+    pub fold_images: Vec<usize>,
+}
+
+/// Precomputed split assignment for every image of a dataset
+#[derive(Debug, Clone)]
+pub struct SplitPlan {
+    key: Option<SplitGroupKey>,
+    regex: Option<Regex>,
+    /// Image key → group key
+    image_groups: HashMap<String, String>,
+    /// Group key → assigned split
+    group_splits: HashMap<String, Split>,
+    /// Group key → cross-validation fold (k-fold mode only)
+    group_folds: HashMap<String, usize>,
+    /// Number of folds (0 when k-fold mode is off)
+    pub folds: usize,
+    /// Summary of the achieved ratios
+    pub report: SplitReport,
+}
+
+impl SplitPlan {
+    /// Look up the split planned for an image
+    ///
+    /// Images that were not part of the plan (e.g. found later) still follow
+    /// their group if the group key can be derived from the path alone.
+    pub fn split_for(&self, image_key: &str) -> Option<Split> {
+        let group = self.group_for(image_key)?;
+        self.group_splits.get(&group).copied()
+    }
+
+    /// Look up the cross-validation fold of an image
+    ///
+    /// Returns `None` outside k-fold mode and for held-out test images.
+    pub fn fold_for(&self, image_key: &str) -> Option<usize> {
+        let group = self.group_for(image_key)?;
+        self.group_folds.get(&group).copied()
+    }
+
+    /// Group key recorded for an image, if the image was part of the plan
+    pub fn group_of(&self, image_key: &str) -> Option<&str> {
+        self.image_groups.get(image_key).map(|s| s.as_str())
+    }
+
+    fn group_for(&self, image_key: &str) -> Option<String> {
+        match self.image_groups.get(image_key) {
+            Some(group) => Some(group.clone()),
+            None => group_key_from_path(
+                self.key.as_ref()?,
+                self.regex.as_ref(),
+                Path::new(image_key),
+            ),
+        }
+    }
+}
+
+/// Image and label counts of one split group
+#[derive(Debug, Clone, Default)]
+pub struct GroupStats {
+    pub key: String,
+    /// Number of images in the group
+    pub images: usize,
+    /// Label → number of images in the group containing it
+    pub labels: HashMap<String, usize>,
+}
+
+/// Resolve the split for an image, using the plan when one exists and the
+/// path-hash splitter otherwise
+pub fn resolve_split(
+    config: &ConversionConfig,
+    plan: Option<&SplitPlan>,
+    image_key: &str,
+) -> Split {
+    plan.and_then(|p| p.split_for(image_key))
+        .unwrap_or_else(|| {
+            determine_split(hash_string(image_key), config.val_size, config.test_size)
+        })
+}
+
+/// Derive the group key of an image from its path only
+///
+/// Returns `None` for flag-based keys (they need the annotation) and when the
+/// regex does not match.
+pub fn group_key_from_path(
+    key: &SplitGroupKey,
+    regex: Option<&Regex>,
+    image_path: &Path,
+) -> Option<String> {
+    match key {
+        SplitGroupKey::PathRegex(_) => {
+            let path_str = image_path.to_string_lossy().replace('\\', "/");
+            let caps = regex?.captures(&path_str)?;
+            caps.get(1)
+                .or_else(|| caps.get(0))
+                .map(|m| m.as_str().to_string())
+        }
+        SplitGroupKey::ParentFolder => image_path.parent().map(|p| p.to_string_lossy().to_string()),
+        SplitGroupKey::Flag(_) => None,
+    }
+}
+
+/// Derive the group key from image-level LabelMe flags
+fn group_key_from_flags(prefix: &str, flags: Option<&HashMap<String, bool>>) -> Option<String> {
+    // Pick the smallest matching name so the result does not depend on map order
+    flags?
+        .iter()
+        .filter(|(name, enabled)| **enabled && name.starts_with(prefix))
+        .map(|(name, _)| name.clone())
+        .min()
+}
+
+/// Build a split plan for the given JSON files
+///
+/// A plan is built when group-aware splitting, stratification or k-fold mode
+/// is enabled. Returns `None` otherwise, in which case callers fall back to
+/// the per-image path hash.
+///
+/// In k-fold mode `test_size` is still held out as a test split; all other
+/// images stay in the train split and are distributed over the folds, with
+/// each fold serving once as validation set (`val_size` is ignored).
+pub fn plan_splits(config: &ConversionConfig, json_files: &[PathBuf]) -> Option<SplitPlan> {
+    if config.split_group.is_none() && !config.stratified && !config.kfold_enabled() {
+        if config.fixed_splits.is_empty() {
+            return None;
+        }
+        return Some(fixed_plan(config));
+    }
+
+    let key = config.split_group.clone();
+    let regex = match &key {
+        Some(SplitGroupKey::PathRegex(pattern)) => Regex::new(pattern).ok(),
+        _ => None,
+    };
+
+    let mut image_groups: HashMap<String, String> = HashMap::new();
+    let mut stats: HashMap<String, GroupStats> = HashMap::new();
+    let mut seen: HashSet<String> = HashSet::new();
+
+    for json_path in json_files {
+        let Ok(annotation) = read_labelme_json(json_path) else {
+            continue;
+        };
+        let image_path = resolve_image_path(json_path, &annotation.image_path);
+        let image_key = image_path.to_string_lossy().to_string();
+        if !seen.insert(image_key.clone()) {
+            continue;
+        }
+
+        let group = match &key {
+            Some(SplitGroupKey::Flag(prefix)) => {
+                group_key_from_flags(prefix, annotation.flags.as_ref())
+            }
+            Some(key) => group_key_from_path(key, regex.as_ref(), &image_path),
+            None => None,
+        }
+        // Images without a key form their own group
+        .unwrap_or_else(|| image_key.clone());
+
+        let entry = stats.entry(group.clone()).or_insert_with(|| GroupStats {
+            key: group.clone(),
+            ..Default::default()
+        });
+        entry.images += 1;
+        let labels: HashSet<&str> = annotation.shapes.iter().map(|s| s.label.as_str()).collect();
+        for label in labels {
+            *entry.labels.entry(label.to_string()).or_insert(0) += 1;
+        }
+
+        image_groups.insert(image_key, group);
+    }
+
+    if config.include_background {
+        for image_path in find_background_images(&config.input_dir, &seen) {
+            let image_key = image_path.to_string_lossy().to_string();
+            let group = key
+                .as_ref()
+                .and_then(|k| group_key_from_path(k, regex.as_ref(), &image_path))
+                .unwrap_or_else(|| image_key.clone());
+            stats
+                .entry(group.clone())
+                .or_insert_with(|| GroupStats {
+                    key: group.clone(),
+                    ..Default::default()
+                })
+                .images += 1;
+            image_groups.insert(image_key, group);
+        }
+    }
+
+    let mut groups: Vec<GroupStats> = stats.into_values().collect();
+    // Sort first so the seeded shuffle is reproducible regardless of map order
+    groups.sort_by(|a, b| a.key.cmp(&b.key));
+
+    let kfold = config.kfold_enabled();
+    let val_size = if kfold { 0.0 } else { config.val_size };
+    let train_ratio = (1.0 - val_size - config.test_size).max(0.0) as f64;
+    let targets = [train_ratio, val_size as f64, config.test_size as f64];
+    let bins = assign_groups(&groups, &targets, config.seed, config.stratified);
+
+    let splits = [Split::Train, Split::Val, Split::Test];
+    let group_splits: HashMap<String, Split> = groups
+        .iter()
+        .zip(&bins)
+        .map(|(group, bin)| (group.key.clone(), splits[*bin]))
+        .collect();
+
+    let mut group_folds: HashMap<String, usize> = HashMap::new();
+    if kfold {
+        let pool: Vec<GroupStats> = groups
+            .iter()
+            .zip(&bins)
+            .filter(|(_, bin)| **bin == 0)
+            .map(|(group, _)| group.clone())
+            .collect();
+        let fold_targets = vec![1.0; config.kfold];
+        let fold_bins = assign_groups(&pool, &fold_targets, config.seed, config.stratified);
+        for (group, fold) in pool.iter().zip(fold_bins) {
+            group_folds.insert(group.key.clone(), fold);
+        }
+    }
+
+    let folds = if kfold { config.kfold } else { 0 };
+    let report = build_report(config, &groups, &group_splits, &group_folds, folds);
+
+    Some(SplitPlan {
+        key,
+        regex,
+        image_groups,
+        group_splits,
+        group_folds,
+        folds,
+        report,
+    })
+}
+
+/// Build a plan from `ConversionConfig::fixed_splits`
+///
+/// Every split forms one group. Images without a predefined split fall back
+/// to the path hash in `resolve_split`.
+fn fixed_plan(config: &ConversionConfig) -> SplitPlan {
+    let mut image_groups = HashMap::new();
+    let mut group_splits = HashMap::new();
+    let mut stats: HashMap<String, GroupStats> = HashMap::new();
+
+    for (image_key, split) in &config.fixed_splits {
+        let group = split.as_str().to_string();
+        image_groups.insert(image_key.clone(), group.clone());
+        group_splits.insert(group.clone(), *split);
+        stats
+            .entry(group.clone())
+            .or_insert_with(|| GroupStats {
+                key: group,
+                ..Default::default()
+            })
+            .images += 1;
+    }
+
+    let groups: Vec<GroupStats> = stats.into_values().collect();
+    let report = build_report(config, &groups, &group_splits, &HashMap::new(), 0);
+
+    SplitPlan {
+        key: None,
+        regex: None,
+        image_groups,
+        group_splits,
+        group_folds: HashMap::new(),
+        folds: 0,
+        report,
+    }
+}
+
+/// Assign groups to bins so that bin sizes follow `targets`
+///
+/// Groups are shuffled with `seed`, then placed largest first into the bin
+/// with the largest remaining deficit. With `stratified`, the deficit also
+/// counts each label of the group relative to its dataset-wide frequency, so
+/// rare labels are spread over the bins. Bins with a zero target never receive
+/// a group. Returns the bin index for each input group.
+pub fn assign_groups(
+    groups: &[GroupStats],
+    targets: &[f64],
+    seed: u64,
+    stratified: bool,
+) -> Vec<usize> {
+    let total: usize = groups.iter().map(|g| g.images).sum();
+    let target_sum: f64 = targets.iter().sum();
+
+    let mut label_totals: HashMap<&str, usize> = HashMap::new();
+    if stratified {
+        for group in groups {
+            for (label, count) in &group.labels {
+                *label_totals.entry(label.as_str()).or_insert(0) += count;
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..groups.len()).collect();
+    let mut rng = StdRng::seed_from_u64(seed);
+    order.shuffle(&mut rng);
+    // Stable sort keeps the shuffled order among equally sized groups
+    order.sort_by(|&a, &b| groups[b].images.cmp(&groups[a].images));
+
+    let mut filled = vec![0usize; targets.len()];
+    let mut filled_labels: Vec<HashMap<&str, usize>> = vec![HashMap::new(); targets.len()];
+    let mut assignment = vec![0usize; groups.len()];
+
+    for idx in order {
+        let group = &groups[idx];
+        let mut best_bin = 0;
+        let mut best_score = f64::NEG_INFINITY;
+
+        for (bin, target) in targets.iter().enumerate() {
+            if *target <= 0.0 {
+                continue;
+            }
+            let share = target / target_sum;
+            let mut score = (share * total as f64 - filled[bin] as f64) / total.max(1) as f64;
+
+            if stratified {
+                for label in group.labels.keys() {
+                    let label_total = label_totals[label.as_str()] as f64;
+                    let label_filled =
+                        filled_labels[bin].get(label.as_str()).copied().unwrap_or(0) as f64;
+                    score += (share * label_total - label_filled) / label_total;
+                }
+            }
+
+            if score > best_score {
+                best_score = score;
+                best_bin = bin;
+            }
+        }
+
+        filled[best_bin] += group.images;
+        for (label, count) in &group.labels {
+            *filled_labels[best_bin].entry(label.as_str()).or_insert(0) += count;
+        }
+        assignment[idx] = best_bin;
+    }
+
+    assignment
+}
+
+fn build_report(
+    config: &ConversionConfig,
+    groups: &[GroupStats],
+    group_splits: &HashMap<String, Split>,
+    group_folds: &HashMap<String, usize>,
+    folds: usize,
+) -> SplitReport {
+    let mut report = SplitReport {
+        group_count: groups.len(),
+        largest_group: groups.iter().map(|g| g.images).max().unwrap_or(0),
+        requested_val: if folds > 0 { 0.0 } else { config.val_size },
+        requested_test: config.test_size,
+        fold_images: vec![0; folds],
+        ..Default::default()
+    };
+
+    for group in groups {
+        match group_splits.get(&group.key) {
+            Some(Split::Val) => report.val_images += group.images,
+            Some(Split::Test) => report.test_images += group.images,
+            _ => report.train_images += group.images,
+        }
+        if let Some(&fold) = group_folds.get(&group.key) {
+            report.fold_images[fold] += group.images;
+        }
+    }
+
+    let total = (report.train_images + report.val_images + report.test_images) as f32;
+    if total > 0.0 {
+        report.achieved_train = report.train_images as f32 / total;
+        report.achieved_val = report.val_images as f32 / total;
+        report.achieved_test = report.test_images as f32 / total;
+    }
+
+    report
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_group_key_from_path() {
+        let path = Path::new("/data/cam01/session_03/frame_0001.jpg");
+
+        let parent = group_key_from_path(&SplitGroupKey::ParentFolder, None, path);
+        assert_eq!(parent.as_deref(), Some("/data/cam01/session_03"));
+
+        let key = SplitGroupKey::PathRegex(r"(cam\d+)/".to_string());
+        let regex = Regex::new(r"(cam\d+)/").unwrap();
+        let captured = group_key_from_path(&key, Some(&regex), path);
+        assert_eq!(captured.as_deref(), Some("cam01"));
+
+        let flag = group_key_from_path(&SplitGroupKey::Flag(String::new()), None, path);
+        assert!(flag.is_none());
+    }
+
+    #[test]
+    fn test_group_key_from_flags() {
+        let mut flags = HashMap::new();
+        flags.insert("video_b".to_string(), true);
+        flags.insert("video_a".to_string(), false);
+        flags.insert("reviewed".to_string(), true);
+
+        assert_eq!(
+            group_key_from_flags("video_", Some(&flags)).as_deref(),
+            Some("video_b")
+        );
+        assert!(group_key_from_flags("camera_", Some(&flags)).is_none());
+        assert!(group_key_from_flags("", None).is_none());
+    }
+
+    fn group(key: &str, images: usize, labels: &[&str]) -> GroupStats {
+        GroupStats {
+            key: key.to_string(),
+            images,
+            labels: labels.iter().map(|l| (l.to_string(), images)).collect(),
+        }
+    }
+
+    #[test]
+    fn test_assign_groups_follows_targets() {
+        let groups: Vec<GroupStats> = (0..20).map(|i| group(&format!("g{}", i), 5, &[])).collect();
+        let bins = assign_groups(&groups, &[0.7, 0.2, 0.1], 42, false);
+
+        let mut counts = [0usize; 3];
+        for (bin, g) in bins.iter().zip(&groups) {
+            counts[*bin] += g.images;
+        }
+
+        assert_eq!(counts, [70, 20, 10]);
+    }
+
+    #[test]
+    fn test_assign_groups_skips_zero_targets() {
+        let groups = vec![group("a", 10, &[]), group("b", 3, &[]), group("c", 1, &[])];
+        let bins = assign_groups(&groups, &[0.8, 0.2, 0.0], 7, false);
+        assert!(bins.iter().all(|&bin| bin != 2));
+
+        // Same seed gives the same assignment
+        assert_eq!(bins, assign_groups(&groups, &[0.8, 0.2, 0.0], 7, false));
+    }
+
+    #[test]
+    fn test_assign_groups_stratified_spreads_rare_label() {
+        // 12 common images and 4 images of a rare defect, into 4 folds
+        let mut groups: Vec<GroupStats> = (0..12)
+            .map(|i| group(&format!("ok{}", i), 1, &["ok"]))
+            .collect();
+        groups.extend((0..4).map(|i| group(&format!("defect{}", i), 1, &["defect"])));
+
+        let bins = assign_groups(&groups, &[1.0; 4], 3, true);
+
+        let mut defect_per_fold = [0usize; 4];
+        let mut images_per_fold = [0usize; 4];
+        for (bin, g) in bins.iter().zip(&groups) {
+            images_per_fold[*bin] += 1;
+            if g.labels.contains_key("defect") {
+                defect_per_fold[*bin] += 1;
+            }
+        }
+
+        assert_eq!(defect_per_fold, [1, 1, 1, 1]);
+        assert_eq!(images_per_fold, [4, 4, 4, 4]);
+    }
+
+    #[test]
+    fn test_split_group_key_validate() {
+        assert!(SplitGroupKey::PathRegex(r"(\w+)_\d+".to_string())
+            .validate()
+            .is_ok());
+        assert!(SplitGroupKey::PathRegex("(unclosed".to_string())
+            .validate()
+            .is_err());
+        assert!(SplitGroupKey::ParentFolder.validate().is_ok());
+    }
+
+    #[test]
+    fn test_fixed_splits_plan() {
+        let mut config = ConversionConfig::default();
+        config.fixed_splits.insert("/voc/a.jpg".to_string(), Split::Train);
+        config.fixed_splits.insert("/voc/b.jpg".to_string(), Split::Val);
+        config.fixed_splits.insert("/voc/c.jpg".to_string(), Split::Val);
+
+        let plan = plan_splits(&config, &[]).unwrap();
+        assert_eq!(plan.split_for("/voc/b.jpg"), Some(Split::Val));
+        assert_eq!(plan.split_for("/voc/unlisted.jpg"), None);
+        assert_eq!(plan.report.val_images, 2);
+        assert_eq!(plan.report.train_images, 1);
+    }
+}
@@ -0,0 +1,481 @@
+//! Geometric dataset statistics
+//!
+//! Computes the numbers needed to tune anchors, input size and augmentation:
+//! per-class box size/aspect/position distributions, instances per image,
+//! image resolutions and label co-occurrence. All results are typed so the
+//! frontend can chart them directly.
+
+use crate::labelme_convert::config::ShapeOptions;
+use crate::labelme_convert::conversion::{calculate_coco_bbox, shape_outline};
+use crate::labelme_convert::io::{find_json_files, read_labelme_json};
+use rayon::prelude::*;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// Default number of histogram bins
+pub const DEFAULT_HISTOGRAM_BINS: usize = 20;
+
+/// Equal-width histogram
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Histogram {
+    /// `counts.len() + 1` bin edges
+    pub bin_edges: Vec<f64>,
+    pub counts: Vec<usize>,
+}
+
+impl Histogram {
+    /// Build a histogram of `values` with `bins` equal-width bins
+    pub fn from_values(values: &[f64], bins: usize) -> Self {
+        if values.is_empty() || bins == 0 {
+            return Self::default();
+        }
+
+        let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+        let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+        // A single distinct value still gets one non-empty bin
+        let width = if max > min {
+            (max - min) / bins as f64
+        } else {
+            1.0
+        };
+
+        let bin_edges = (0..=bins).map(|i| min + width * i as f64).collect();
+        let mut counts = vec![0; bins];
+        for value in values {
+            let idx = (((value - min) / width) as usize).min(bins - 1);
+            counts[idx] += 1;
+        }
+
+        Self { bin_edges, counts }
+    }
+}
+
+/// Summary statistics and histogram of one measurement
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Distribution {
+    pub count: usize,
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    pub std_dev: f64,
+    pub median: f64,
+    pub p05: f64,
+    pub p95: f64,
+    pub histogram: Histogram,
+}
+
+impl Distribution {
+    /// Compute the distribution of `values`
+    pub fn from_values(values: &[f64], bins: usize) -> Self {
+        if values.is_empty() {
+            return Self::default();
+        }
+
+        let mut sorted = values.to_vec();
+        sorted.sort_by(|a, b| a.total_cmp(b));
+
+        let count = sorted.len();
+        let mean = sorted.iter().sum::<f64>() / count as f64;
+        let variance = sorted.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / count as f64;
+
+        Self {
+            count,
+            min: sorted[0],
+            max: sorted[count - 1],
+            mean,
+            std_dev: variance.sqrt(),
+            median: percentile(&sorted, 0.5),
+            p05: percentile(&sorted, 0.05),
+            p95: percentile(&sorted, 0.95),
+            histogram: Histogram::from_values(&sorted, bins),
+        }
+    }
+}
+
+/// Linear-interpolated percentile of sorted values
+fn percentile(sorted: &[f64], q: f64) -> f64 {
+    let pos = q * (sorted.len() - 1) as f64;
+    let lower = pos.floor() as usize;
+    let upper = pos.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (pos - lower as f64)
+}
+
+/// Box geometry of one class
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ClassGeometry {
+    pub label: String,
+    pub instances: usize,
+    /// Number of images containing the class
+    pub images: usize,
+    /// Box width in pixels
+    pub width: Distribution,
+    /// Box height in pixels
+    pub height: Distribution,
+    /// Box area in pixels
+    pub area: Distribution,
+    /// Box width / height
+    pub aspect_ratio: Distribution,
+    /// Box width relative to the image width
+    pub relative_width: Distribution,
+    /// Box height relative to the image height
+    pub relative_height: Distribution,
+    /// Box center x normalized to [0, 1]
+    pub center_x: Distribution,
+    /// Box center y normalized to [0, 1]
+    pub center_y: Distribution,
+}
+
+/// Number of images sharing one resolution
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolutionCount {
+    pub width: u32,
+    pub height: u32,
+    pub images: usize,
+}
+
+/// Number of images with a given instance count
+#[derive(Debug, Clone, Serialize)]
+pub struct InstanceCountBin {
+    pub instances: usize,
+    pub images: usize,
+}
+
+/// Label co-occurrence counted per image
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct CooccurrenceMatrix {
+    /// Row/column labels, sorted
+    pub labels: Vec<String>,
+    /// `counts[i][j]` = images containing both `labels[i]` and `labels[j]`;
+    /// the diagonal holds the number of images containing the label
+    pub counts: Vec<Vec<usize>>,
+}
+
+/// Complete geometric statistics of a LabelMe dataset
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct DatasetStatistics {
+    pub total_images: usize,
+    pub images_with_annotations: usize,
+    pub total_instances: usize,
+    /// Shapes without a usable bounding box (e.g. points, degenerate boxes)
+    pub skipped_instances: usize,
+    /// Shapes per label, including shapes without a bounding box
+    pub label_counts: BTreeMap<String, usize>,
+    /// Shapes per label and shape type
+    pub shape_types: BTreeMap<String, BTreeMap<String, usize>>,
+    /// Per-class geometry, sorted by label
+    pub classes: Vec<ClassGeometry>,
+    /// Geometry over all classes
+    pub overall: ClassGeometry,
+    pub instances_per_image: Vec<InstanceCountBin>,
+    /// Image resolutions, most common first
+    pub resolutions: Vec<ResolutionCount>,
+    pub image_width: Distribution,
+    pub image_height: Distribution,
+    pub cooccurrence: CooccurrenceMatrix,
+    /// Files that could not be read
+    pub errors: Vec<String>,
+}
+
+/// Raw measurements of one box
+struct BoxSample {
+    label: String,
+    width: f64,
+    height: f64,
+    rel_width: f64,
+    rel_height: f64,
+    center_x: f64,
+    center_y: f64,
+}
+
+/// Measurements of one annotation file
+struct FileSample {
+    image_width: u32,
+    image_height: u32,
+    instances: usize,
+    labels: Vec<String>,
+    /// Label and shape type of every shape
+    shapes: Vec<(String, String)>,
+    boxes: Vec<BoxSample>,
+    skipped: usize,
+}
+
+/// Compute statistics for all LabelMe files below `input_dir`
+pub fn compute_statistics(input_dir: &Path, bins: usize) -> Result<DatasetStatistics, String> {
+    if !input_dir.exists() {
+        return Err(format!("Directory does not exist: {}", input_dir.display()));
+    }
+
+    let json_files = find_json_files(input_dir);
+    let samples: Vec<Result<FileSample, String>> = json_files
+        .par_iter()
+        .map(|path| sample_file(path))
+        .collect();
+
+    let mut errors = Vec::new();
+    let samples: Vec<FileSample> = samples
+        .into_iter()
+        .filter_map(|s| s.map_err(|e| errors.push(e)).ok())
+        .collect();
+
+    let mut stats = aggregate(&samples, bins);
+    stats.errors = errors;
+    Ok(stats)
+}
+
+fn sample_file(path: &Path) -> Result<FileSample, String> {
+    let annotation = read_labelme_json(path)?;
+    let image_w = annotation.image_width as f64;
+    let image_h = annotation.image_height as f64;
+
+    let mut boxes = Vec::new();
+    let mut skipped = 0;
+    for shape in &annotation.shapes {
+        let [x, y, w, h] = calculate_coco_bbox(&shape_outline(shape, &ShapeOptions::default()));
+        if w <= 0.0 || h <= 0.0 || image_w <= 0.0 || image_h <= 0.0 {
+            skipped += 1;
+            continue;
+        }
+        boxes.push(BoxSample {
+            label: shape.label.clone(),
+            width: w,
+            height: h,
+            rel_width: w / image_w,
+            rel_height: h / image_h,
+            center_x: (x + w / 2.0) / image_w,
+            center_y: (y + h / 2.0) / image_h,
+        });
+    }
+
+    let mut labels: Vec<String> = annotation.shapes.iter().map(|s| s.label.clone()).collect();
+    labels.sort();
+    labels.dedup();
+
+    Ok(FileSample {
+        image_width: annotation.image_width,
+        image_height: annotation.image_height,
+        instances: annotation.shapes.len(),
+        labels,
+        shapes: annotation
+            .shapes
+            .iter()
+            .map(|s| (s.label.clone(), s.shape_type.clone()))
+            .collect(),
+        boxes,
+        skipped,
+    })
+}
+
+fn aggregate(samples: &[FileSample], bins: usize) -> DatasetStatistics {
+    let mut stats = DatasetStatistics {
+        total_images: samples.len(),
+        ..Default::default()
+    };
+
+    let mut per_class: BTreeMap<&str, Vec<&BoxSample>> = BTreeMap::new();
+    let mut class_images: HashMap<&str, usize> = HashMap::new();
+    let mut instance_counts: BTreeMap<usize, usize> = BTreeMap::new();
+    let mut resolutions: HashMap<(u32, u32), usize> = HashMap::new();
+    let mut all_boxes: Vec<&BoxSample> = Vec::new();
+
+    for sample in samples {
+        stats.total_instances += sample.instances;
+        stats.skipped_instances += sample.skipped;
+        if sample.instances > 0 {
+            stats.images_with_annotations += 1;
+        }
+        *instance_counts.entry(sample.instances).or_insert(0) += 1;
+        *resolutions
+            .entry((sample.image_width, sample.image_height))
+            .or_insert(0) += 1;
+
+        for label in &sample.labels {
+            *class_images.entry(label.as_str()).or_insert(0) += 1;
+            per_class.entry(label.as_str()).or_default();
+        }
+        for (label, shape_type) in &sample.shapes {
+            *stats.label_counts.entry(label.clone()).or_insert(0) += 1;
+            *stats
+                .shape_types
+                .entry(label.clone())
+                .or_default()
+                .entry(shape_type.clone())
+                .or_insert(0) += 1;
+        }
+        for b in &sample.boxes {
+            per_class.entry(b.label.as_str()).or_default().push(b);
+            all_boxes.push(b);
+        }
+    }
+
+    stats.classes = per_class
+        .iter()
+        .map(|(label, boxes)| {
+            let mut geometry = class_geometry(label, boxes, bins);
+            geometry.images = class_images.get(label).copied().unwrap_or(0);
+            geometry
+        })
+        .collect();
+    stats.overall = class_geometry("all", &all_boxes, bins);
+    stats.overall.images = stats.images_with_annotations;
+
+    stats.instances_per_image = instance_counts
+        .into_iter()
+        .map(|(instances, images)| InstanceCountBin { instances, images })
+        .collect();
+
+    let mut resolutions: Vec<ResolutionCount> = resolutions
+        .into_iter()
+        .map(|((width, height), images)| ResolutionCount {
+            width,
+            height,
+            images,
+        })
+        .collect();
+    resolutions.sort_by(|a, b| {
+        b.images
+            .cmp(&a.images)
+            .then((a.width, a.height).cmp(&(b.width, b.height)))
+    });
+    stats.resolutions = resolutions;
+
+    let widths: Vec<f64> = samples.iter().map(|s| s.image_width as f64).collect();
+    let heights: Vec<f64> = samples.iter().map(|s| s.image_height as f64).collect();
+    stats.image_width = Distribution::from_values(&widths, bins);
+    stats.image_height = Distribution::from_values(&heights, bins);
+
+    stats.cooccurrence = cooccurrence(samples);
+    stats
+}
+
+fn class_geometry(label: &str, boxes: &[&BoxSample], bins: usize) -> ClassGeometry {
+    let collect = |f: fn(&BoxSample) -> f64| -> Vec<f64> { boxes.iter().map(|b| f(b)).collect() };
+
+    ClassGeometry {
+        label: label.to_string(),
+        instances: boxes.len(),
+        images: 0,
+        width: Distribution::from_values(&collect(|b| b.width), bins),
+        height: Distribution::from_values(&collect(|b| b.height), bins),
+        area: Distribution::from_values(&collect(|b| b.width * b.height), bins),
+        aspect_ratio: Distribution::from_values(&collect(|b| b.width / b.height), bins),
+        relative_width: Distribution::from_values(&collect(|b| b.rel_width), bins),
+        relative_height: Distribution::from_values(&collect(|b| b.rel_height), bins),
+        center_x: Distribution::from_values(&collect(|b| b.center_x), bins),
+        center_y: Distribution::from_values(&collect(|b| b.center_y), bins),
+    }
+}
+
+fn cooccurrence(samples: &[FileSample]) -> CooccurrenceMatrix {
+    let mut labels: Vec<String> = samples.iter().flat_map(|s| s.labels.clone()).collect();
+    labels.sort();
+    labels.dedup();
+
+    let index: HashMap<&str, usize> = labels
+        .iter()
+        .enumerate()
+        .map(|(i, l)| (l.as_str(), i))
+        .collect();
+    let mut counts = vec![vec![0; labels.len()]; labels.len()];
+
+    for sample in samples {
+        let ids: Vec<usize> = sample.labels.iter().map(|l| index[l.as_str()]).collect();
+        for &i in &ids {
+            for &j in &ids {
+                counts[i][j] += 1;
+            }
+        }
+    }
+
+    CooccurrenceMatrix { labels, counts }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample(labels: &[&str], boxes: &[(&str, f64, f64)]) -> FileSample {
+        FileSample {
+            image_width: 100,
+            image_height: 50,
+            instances: boxes.len(),
+            labels: labels.iter().map(|l| l.to_string()).collect(),
+            shapes: boxes
+                .iter()
+                .map(|(label, _, _)| (label.to_string(), "rectangle".to_string()))
+                .collect(),
+            boxes: boxes
+                .iter()
+                .map(|(label, w, h)| BoxSample {
+                    label: label.to_string(),
+                    width: *w,
+                    height: *h,
+                    rel_width: w / 100.0,
+                    rel_height: h / 50.0,
+                    center_x: 0.5,
+                    center_y: 0.5,
+                })
+                .collect(),
+            skipped: 0,
+        }
+    }
+
+    #[test]
+    fn test_distribution() {
+        let dist = Distribution::from_values(&[1.0, 2.0, 3.0, 4.0, 5.0], 4);
+        assert_eq!(dist.count, 5);
+        assert_eq!(dist.min, 1.0);
+        assert_eq!(dist.max, 5.0);
+        assert!((dist.mean - 3.0).abs() < 1e-9);
+        assert!((dist.median - 3.0).abs() < 1e-9);
+        assert_eq!(dist.histogram.counts, vec![1, 1, 1, 2]);
+        assert_eq!(dist.histogram.bin_edges.len(), 5);
+
+        let single = Distribution::from_values(&[7.0, 7.0], 3);
+        assert_eq!(single.histogram.counts, vec![2, 0, 0]);
+    }
+
+    #[test]
+    fn test_aggregate() {
+        let samples = vec![
+            sample(
+                &["car", "person"],
+                &[("car", 20.0, 10.0), ("person", 5.0, 10.0)],
+            ),
+            sample(&["car"], &[("car", 40.0, 10.0)]),
+            sample(&[], &[]),
+        ];
+        let stats = aggregate(&samples, 5);
+
+        assert_eq!(stats.total_images, 3);
+        assert_eq!(stats.images_with_annotations, 2);
+        assert_eq!(stats.total_instances, 3);
+
+        let car = &stats.classes[0];
+        assert_eq!(car.label, "car");
+        assert_eq!(car.instances, 2);
+        assert_eq!(car.images, 2);
+        assert!((car.aspect_ratio.mean - 3.0).abs() < 1e-9);
+        assert_eq!(stats.label_counts["car"], 2);
+        assert_eq!(stats.shape_types["person"]["rectangle"], 1);
+
+        let bins: Vec<(usize, usize)> = stats
+            .instances_per_image
+            .iter()
+            .map(|b| (b.instances, b.images))
+            .collect();
+        assert_eq!(bins, vec![(0, 1), (1, 1), (2, 1)]);
+
+        assert_eq!(stats.resolutions.len(), 1);
+        assert_eq!(stats.resolutions[0].images, 3);
+    }
+
+    #[test]
+    fn test_cooccurrence() {
+        let samples = vec![sample(&["car", "person"], &[]), sample(&["car"], &[])];
+        let matrix = cooccurrence(&samples);
+
+        assert_eq!(matrix.labels, vec!["car", "person"]);
+        assert_eq!(matrix.counts, vec![vec![2, 1], vec![1, 1]]);
+    }
+}
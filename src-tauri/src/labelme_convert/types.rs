@@ -5,6 +5,9 @@
 //
 // Adapted and modified for dataset-app
 
+use crate::labelme_convert::augment::AugmentReport;
+use crate::labelme_convert::balance::BalanceReport;
+use crate::labelme_convert::split::SplitReport;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -74,6 +77,32 @@ pub struct LabelMeOutputDirs {
     pub output_dir: PathBuf,
 }
 
+/// Output directories for CVAT dataset (no split)
+#[derive(Debug, Clone)]
+pub struct CvatOutputDirs {
+    pub base_dir: PathBuf,
+    pub images_dir: PathBuf,
+}
+
+/// Output directories for Label Studio tasks (no split)
+#[derive(Debug, Clone)]
+pub struct LabelStudioOutputDirs {
+    pub base_dir: PathBuf,
+    pub images_dir: PathBuf,
+}
+
+/// Output directories for mask datasets (images/ and masks/ per split)
+#[derive(Debug, Clone)]
+pub struct MaskOutputDirs {
+    pub base_dir: PathBuf,
+    pub train_masks_dir: PathBuf,
+    pub val_masks_dir: PathBuf,
+    pub train_images_dir: PathBuf,
+    pub val_images_dir: PathBuf,
+    pub test_masks_dir: Option<PathBuf>,
+    pub test_images_dir: Option<PathBuf>,
+}
+
 /// Split data containers
 #[derive(Debug, Default)]
 pub struct SplitData {
@@ -92,6 +121,17 @@ pub struct InvalidAnnotation {
     pub points_count: usize,
 }
 
+/// File format the input dataset is stored in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DatasetSourceFormat {
+    /// LabelMe JSON files
+    #[default]
+    LabelMe,
+    /// Pascal VOC XML files in `Annotations/`
+    PascalVoc,
+}
+
 /// Detected input annotation format based on sampling
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum InputAnnotationFormat {
@@ -116,6 +156,48 @@ impl InputAnnotationFormat {
     }
 }
 
+/// LabelMe shape type, as declared by `shape_type`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShapeKind {
+    Polygon,
+    Rectangle,
+    Circle,
+    Line,
+    LineStrip,
+    /// `point` or `points`
+    Point,
+    Mask,
+    /// Missing or unrecognized shape type (treated like a polygon)
+    Other,
+}
+
+impl ShapeKind {
+    pub fn of(shape: &Shape) -> Self {
+        match shape.shape_type.as_str() {
+            "polygon" => ShapeKind::Polygon,
+            "rectangle" => ShapeKind::Rectangle,
+            "circle" => ShapeKind::Circle,
+            "line" => ShapeKind::Line,
+            "linestrip" => ShapeKind::LineStrip,
+            "point" | "points" => ShapeKind::Point,
+            "mask" => ShapeKind::Mask,
+            _ => ShapeKind::Other,
+        }
+    }
+
+    pub fn expected_points_description(&self) -> &'static str {
+        match self {
+            ShapeKind::Rectangle => "矩形需要 2 或 4 個點",
+            ShapeKind::Circle => "圓形需要 2 個點（圓心與邊緣點）",
+            ShapeKind::Line => "線段需要 2 個點",
+            ShapeKind::LineStrip => "折線需要至少 2 個點",
+            ShapeKind::Point => "點需要至少 1 個點",
+            ShapeKind::Mask => "遮罩需要 2 個點",
+            ShapeKind::Polygon | ShapeKind::Other => "多邊形需要至少 3 個點",
+        }
+    }
+}
+
 /// Invalid annotation reason types
 #[derive(Debug, Clone, Copy)]
 pub enum InvalidReason {
@@ -123,11 +205,18 @@ pub enum InvalidReason {
     ZeroArea,
     InsufficientPoints,
     LabelNotInList,
+    /// Shape type cannot be represented in the output format (e.g. mask in CVAT)
+    UnsupportedShape,
     /// Points count doesn't match expected format (e.g., polygon with only 2 points)
     PointsCountMismatch {
         expected_format: InputAnnotationFormat,
         actual_points: usize,
     },
+    /// Points count doesn't match the declared shape type (e.g., circle with 3 points)
+    ShapePointsMismatch {
+        shape_kind: ShapeKind,
+        actual_points: usize,
+    },
 }
 
 impl InvalidReason {
@@ -137,6 +226,7 @@ impl InvalidReason {
             InvalidReason::ZeroArea => "標註面積為零（width 或 height <= 0）".to_string(),
             InvalidReason::InsufficientPoints => "多邊形點數不足（需要至少 3 個點）".to_string(),
             InvalidReason::LabelNotInList => "標籤不在選定列表中".to_string(),
+            InvalidReason::UnsupportedShape => "輸出格式不支援此形狀類型".to_string(),
             InvalidReason::PointsCountMismatch { expected_format, actual_points } => {
                 format!(
                     "點數不符合資料集格式（{}，實際 {} 個點）",
@@ -144,6 +234,13 @@ impl InvalidReason {
                     actual_points
                 )
             }
+            InvalidReason::ShapePointsMismatch { shape_kind, actual_points } => {
+                format!(
+                    "點數不符合形狀類型（{}，實際 {} 個點）",
+                    shape_kind.expected_points_description(),
+                    actual_points
+                )
+            }
         }
     }
 }
@@ -169,6 +266,51 @@ pub struct ProcessingStats {
     pub skipped_labels: Vec<String>,
     /// Detailed invalid annotation records (limited to first 100)
     pub invalid_annotations: Vec<InvalidAnnotation>,
+    /// Achieved split ratios when group-aware splitting is enabled
+    pub split_report: Option<SplitReport>,
+    /// Per-class distribution of the train split when balancing is enabled
+    pub balance_report: Option<BalanceReport>,
+    /// Polygon vertex counts before/after simplification or resampling
+    pub polygon_vertices: Option<VertexReport>,
+    /// Converted and skipped annotation counts per shape type
+    pub shape_types: ShapeTypeReport,
+    /// Augmented copies of the train split when augmentation is enabled
+    pub augment_report: Option<AugmentReport>,
+}
+
+/// Per shape type annotation counts
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ShapeTypeReport {
+    /// Shape type → annotations written to the output
+    pub converted: HashMap<String, usize>,
+    /// Shape type → annotations dropped by the line/point settings
+    pub skipped: HashMap<String, usize>,
+}
+
+impl ShapeTypeReport {
+    pub fn record_converted(&mut self, shape_type: &str) {
+        *self.converted.entry(shape_type.to_string()).or_insert(0) += 1;
+    }
+
+    pub fn record_skipped(&mut self, shape_type: &str) {
+        *self.skipped.entry(shape_type.to_string()).or_insert(0) += 1;
+    }
+}
+
+/// Vertex totals for polygons passed through simplification/resampling
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VertexReport {
+    pub polygons: usize,
+    pub vertices_before: usize,
+    pub vertices_after: usize,
+}
+
+impl VertexReport {
+    pub fn record(&mut self, before: usize, after: usize) {
+        self.polygons += 1;
+        self.vertices_before += before;
+        self.vertices_after += after;
+    }
 }
 
 impl ProcessingStats {
@@ -233,6 +375,14 @@ impl ProcessingStats {
         }
     }
 
+    /// Merge polygon vertex totals from one file
+    pub fn add_vertex_report(&mut self, report: VertexReport) {
+        let total = self.polygon_vertices.get_or_insert_with(VertexReport::default);
+        total.polygons += report.polygons;
+        total.vertices_before += report.vertices_before;
+        total.vertices_after += report.vertices_after;
+    }
+
     /// Add an invalid annotation record (limited to first 100 to avoid memory issues)
     /// Note: Only real errors should be added here, not label filtering skips
     pub fn add_invalid_annotation(&mut self, annotation: InvalidAnnotation) {
@@ -252,6 +402,8 @@ pub struct ConversionResult {
     pub output_dir: String,
     pub stats: ProcessingStats,
     pub errors: Vec<String>,
+    /// Rendered sample images (see `ConversionConfig::previews`)
+    pub previews: Vec<String>,
 }
 
 impl ConversionResult {
@@ -261,6 +413,7 @@ impl ConversionResult {
             output_dir,
             stats,
             errors: Vec::new(),
+            previews: Vec::new(),
         }
     }
 
@@ -270,6 +423,7 @@ impl ConversionResult {
             output_dir: String::new(),
             stats: ProcessingStats::default(),
             errors,
+            previews: Vec::new(),
         }
     }
 }
@@ -315,6 +469,34 @@ impl OutputDirectories for YoloOutputDirs {
     }
 }
 
+impl OutputDirectories for MaskOutputDirs {
+    fn base_dir(&self) -> &std::path::Path {
+        &self.base_dir
+    }
+
+    fn get_output_dir(&self, split: Split, file_type: FileType) -> &std::path::Path {
+        match (split, file_type) {
+            (Split::Val, FileType::Image) => &self.val_images_dir,
+            (Split::Val, FileType::Label) => &self.val_masks_dir,
+            (Split::Test, FileType::Image) => {
+                self.test_images_dir.as_ref().unwrap_or(&self.train_images_dir)
+            }
+            (Split::Test, FileType::Label) => {
+                self.test_masks_dir.as_ref().unwrap_or(&self.train_masks_dir)
+            }
+            // classes.json sits in the dataset root
+            (_, FileType::Annotation) => &self.base_dir,
+            // Train, and train as default for Split::None
+            (_, FileType::Image) => &self.train_images_dir,
+            (_, FileType::Label) => &self.train_masks_dir,
+        }
+    }
+
+    fn uses_splits(&self) -> bool {
+        true
+    }
+}
+
 impl OutputDirectories for CocoOutputDirs {
     fn base_dir(&self) -> &std::path::Path {
         &self.base_dir
@@ -355,6 +537,42 @@ impl OutputDirectories for LabelMeOutputDirs {
     }
 }
 
+impl OutputDirectories for CvatOutputDirs {
+    fn base_dir(&self) -> &std::path::Path {
+        &self.base_dir
+    }
+
+    fn get_output_dir(&self, _split: Split, file_type: FileType) -> &std::path::Path {
+        match file_type {
+            FileType::Image => &self.images_dir,
+            // annotations.xml sits in the dataset root
+            FileType::Label | FileType::Annotation => &self.base_dir,
+        }
+    }
+
+    fn uses_splits(&self) -> bool {
+        false
+    }
+}
+
+impl OutputDirectories for LabelStudioOutputDirs {
+    fn base_dir(&self) -> &std::path::Path {
+        &self.base_dir
+    }
+
+    fn get_output_dir(&self, _split: Split, file_type: FileType) -> &std::path::Path {
+        match file_type {
+            FileType::Image => &self.images_dir,
+            // tasks.json and the labeling config sit in the dataset root
+            FileType::Label | FileType::Annotation => &self.base_dir,
+        }
+    }
+
+    fn uses_splits(&self) -> bool {
+        false
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
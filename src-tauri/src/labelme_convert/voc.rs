@@ -0,0 +1,649 @@
+//! Pascal VOC XML import
+//!
+//! Reads a VOC dataset (`Annotations/*.xml`, `JPEGImages/`, optional
+//! `ImageSets/Main/{train,val,trainval,test}.txt`) into LabelMe shapes:
+//! - `<bndbox>` → rectangle
+//! - `<polygon>` (either `<x1>/<y1>...` or `<pt><x>/<y></pt>` children) → polygon
+//! - `difficult` / `truncated` / `occluded` → shape flags
+//! - `pose` (unless "Unspecified") → shape description
+//!
+//! VOC input is picked up automatically by `convert()`: the annotations are
+//! staged as LabelMe JSON files referencing the original images, and the
+//! `ImageSets/Main` splits are kept unless another split mode is requested.
+
+use crate::labelme_convert::config::ConversionConfig;
+use crate::labelme_convert::io::write_labelme_json;
+use crate::labelme_convert::pipeline::Split;
+use crate::labelme_convert::types::{
+    is_image_extension, ConversionResult, DatasetSourceFormat, LabelMeAnnotation, Shape,
+    IMG_FORMATS,
+};
+use serde::Serialize;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Directory holding the VOC XML files
+pub const VOC_ANNOTATIONS_DIR: &str = "Annotations";
+
+/// Directory holding the VOC images
+const VOC_IMAGES_DIR: &str = "JPEGImages";
+
+/// Directory holding the VOC split lists
+const VOC_SPLITS_DIR: &str = "ImageSets/Main";
+
+/// One parsed VOC annotation file
+#[derive(Debug, Clone)]
+pub struct VocAnnotation {
+    pub filename: String,
+    /// Image width from `<size>` (0 when missing)
+    pub width: u32,
+    /// Image height from `<size>` (0 when missing)
+    pub height: u32,
+    pub shapes: Vec<Shape>,
+}
+
+// ============================================================================
+// Parsing
+// ============================================================================
+
+fn child<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.children().find(|c| c.has_tag_name(name))
+}
+
+fn child_text<'a>(node: roxmltree::Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name)?.text().map(str::trim)
+}
+
+fn child_number(node: roxmltree::Node, name: &str) -> Option<f64> {
+    child_text(node, name)?.parse().ok()
+}
+
+fn child_flag(node: roxmltree::Node, name: &str) -> Option<bool> {
+    child_text(node, name).map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+}
+
+/// Parse a `<polygon>` element in either of the common layouts
+fn parse_polygon(node: roxmltree::Node) -> Vec<(f64, f64)> {
+    let pts: Vec<(f64, f64)> = node
+        .children()
+        .filter(|c| c.has_tag_name("pt"))
+        .filter_map(|pt| Some((child_number(pt, "x")?, child_number(pt, "y")?)))
+        .collect();
+    if !pts.is_empty() {
+        return pts;
+    }
+
+    // <x1>..</x1><y1>..</y1><x2>..</x2>...
+    (1..)
+        .map_while(|i| {
+            Some((
+                child_number(node, &format!("x{}", i))?,
+                child_number(node, &format!("y{}", i))?,
+            ))
+        })
+        .collect()
+}
+
+fn parse_object(object: roxmltree::Node) -> Option<Shape> {
+    let label = child_text(object, "name")?.to_string();
+
+    let polygon = child(object, "polygon")
+        .map(parse_polygon)
+        .filter(|points| points.len() >= 3);
+    let (shape_type, points) = match polygon {
+        Some(points) => ("polygon", points),
+        None => {
+            let bndbox = child(object, "bndbox")?;
+            let points = vec![
+                (child_number(bndbox, "xmin")?, child_number(bndbox, "ymin")?),
+                (child_number(bndbox, "xmax")?, child_number(bndbox, "ymax")?),
+            ];
+            ("rectangle", points)
+        }
+    };
+
+    let mut flags = HashMap::new();
+    flags.insert(
+        "difficult".to_string(),
+        child_flag(object, "difficult").unwrap_or(false),
+    );
+    flags.insert(
+        "truncated".to_string(),
+        child_flag(object, "truncated").unwrap_or(false),
+    );
+    if let Some(occluded) = child_flag(object, "occluded") {
+        flags.insert("occluded".to_string(), occluded);
+    }
+
+    let description = child_text(object, "pose")
+        .filter(|pose| !pose.is_empty() && !pose.eq_ignore_ascii_case("unspecified"))
+        .map(|pose| format!("pose={}", pose));
+
+    Some(Shape {
+        label,
+        points,
+        group_id: None,
+        shape_type: shape_type.to_string(),
+        description,
+        mask: None,
+        flags: Some(flags),
+    })
+}
+
+/// Parse the content of a VOC annotation file
+///
+/// Objects without a name or usable geometry are skipped.
+pub fn parse_voc_xml(content: &str) -> Result<VocAnnotation, String> {
+    let document =
+        roxmltree::Document::parse(content).map_err(|e| format!("Failed to parse XML: {}", e))?;
+
+    let root = document.root_element();
+    if !root.has_tag_name("annotation") {
+        return Err(format!(
+            "Not a VOC annotation file: root element is <{}>",
+            root.tag_name().name()
+        ));
+    }
+
+    let size = child(root, "size");
+    let dimension = |name| size.and_then(|s| child_number(s, name)).unwrap_or(0.0) as u32;
+
+    Ok(VocAnnotation {
+        filename: child_text(root, "filename").unwrap_or_default().to_string(),
+        width: dimension("width"),
+        height: dimension("height"),
+        shapes: root
+            .children()
+            .filter(|c| c.has_tag_name("object"))
+            .filter_map(parse_object)
+            .collect(),
+    })
+}
+
+/// Read and parse a VOC annotation file
+pub fn read_voc_annotation(path: &Path) -> Result<VocAnnotation, String> {
+    let content = fs::read_to_string(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    parse_voc_xml(&content).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+// ============================================================================
+// Dataset layout
+// ============================================================================
+
+fn has_voc_annotations(dir: &Path) -> bool {
+    fs::read_dir(dir.join(VOC_ANNOTATIONS_DIR))
+        .map(|entries| {
+            entries.flatten().any(|e| {
+                e.path()
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("xml"))
+            })
+        })
+        .unwrap_or(false)
+}
+
+/// Locate a VOC dataset root (the folder containing `Annotations/`)
+///
+/// Checks `dir` itself, then up to two levels below it so that
+/// `VOCdevkit/VOC2012` layouts are found as well.
+pub fn find_voc_root(dir: &Path) -> Option<PathBuf> {
+    if has_voc_annotations(dir) {
+        return Some(dir.to_path_buf());
+    }
+
+    WalkDir::new(dir)
+        .min_depth(1)
+        .max_depth(2)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+        .map(|e| e.into_path())
+        .find(|p| has_voc_annotations(p))
+}
+
+/// VOC dataset root for auto-detected conversion input
+///
+/// Runs on every conversion, so only `dir` and its top-level folders are
+/// checked, and folders with LabelMe JSON files at the top level are never
+/// treated as VOC.
+pub fn find_voc_input(dir: &Path) -> Option<PathBuf> {
+    let mut entries: Vec<PathBuf> = fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .collect();
+    let has_json = entries.iter().any(|p| {
+        p.is_file()
+            && p.extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("json"))
+    });
+    if has_json {
+        return None;
+    }
+    if has_voc_annotations(dir) {
+        return Some(dir.to_path_buf());
+    }
+
+    entries.sort();
+    entries
+        .into_iter()
+        .find(|p| p.is_dir() && has_voc_annotations(p))
+}
+
+/// All XML files in `Annotations/`, sorted
+pub fn find_voc_xml_files(root: &Path) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = fs::read_dir(root.join(VOC_ANNOTATIONS_DIR))
+        .map(|entries| {
+            entries
+                .flatten()
+                .map(|e| e.path())
+                .filter(|p| {
+                    p.extension()
+                        .is_some_and(|ext| ext.eq_ignore_ascii_case("xml"))
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+    files.sort();
+    files
+}
+
+/// Read `ImageSets/Main` split lists (file stem → split)
+///
+/// `trainval.txt` is only used when `train.txt`/`val.txt` are missing; its
+/// entries go to train. Class-specific lists (`car_train.txt`) are ignored.
+pub fn read_voc_splits(root: &Path) -> HashMap<String, Split> {
+    let splits_dir = root.join(VOC_SPLITS_DIR);
+    let read_list = |name: &str| -> Option<Vec<String>> {
+        let content = fs::read_to_string(splits_dir.join(format!("{}.txt", name))).ok()?;
+        Some(
+            content
+                .lines()
+                .filter_map(|line| line.split_whitespace().next())
+                .map(str::to_string)
+                .collect(),
+        )
+    };
+
+    let mut splits = HashMap::new();
+    let train = read_list("train");
+    let val = read_list("val");
+    if train.is_none() && val.is_none() {
+        for stem in read_list("trainval").unwrap_or_default() {
+            splits.insert(stem, Split::Train);
+        }
+    }
+    for (list, split) in [
+        (train, Split::Train),
+        (val, Split::Val),
+        (read_list("test"), Split::Test),
+    ] {
+        for stem in list.unwrap_or_default() {
+            splits.insert(stem, split);
+        }
+    }
+    splits
+}
+
+/// Find the image of an annotation in `JPEGImages/`
+fn find_voc_image(root: &Path, annotation: &VocAnnotation, xml_stem: &str) -> Option<PathBuf> {
+    let images_dir = root.join(VOC_IMAGES_DIR);
+    if !annotation.filename.is_empty() {
+        let path = images_dir.join(&annotation.filename);
+        if path.is_file() {
+            return Some(path);
+        }
+    }
+
+    // Fall back to the XML stem with any known image extension
+    IMG_FORMATS
+        .iter()
+        .flat_map(|ext| [ext.to_string(), ext.to_uppercase()])
+        .map(|ext| images_dir.join(format!("{}.{}", xml_stem, ext)))
+        .find(|p| {
+            p.is_file()
+                && p.extension()
+                    .is_some_and(|e| is_image_extension(&e.to_string_lossy()))
+        })
+}
+
+// ============================================================================
+// Import
+// ============================================================================
+
+/// Result of importing a VOC dataset
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct VocImportResult {
+    pub output_dir: String,
+    /// LabelMe JSON files written
+    pub images: usize,
+    pub shapes: usize,
+    pub train_images: usize,
+    pub val_images: usize,
+    pub test_images: usize,
+    /// Annotations whose image was not found in `JPEGImages/`
+    pub missing_images: Vec<String>,
+    /// XML files that could not be parsed
+    pub errors: Vec<String>,
+    pub labels: Vec<String>,
+}
+
+/// How converted files are laid out
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ImportLayout {
+    /// Copy images next to the JSON, one subfolder per split
+    Dataset,
+    /// Reference the original images by absolute path, flat output
+    Staging,
+}
+
+/// Convert every VOC annotation to a LabelMe JSON file
+///
+/// Returns the import summary and the split of every written image, keyed
+/// like the conversion pipelines key images (resolved image path).
+fn write_labelme_files(
+    root: &Path,
+    output_dir: &Path,
+    layout: ImportLayout,
+) -> Result<(VocImportResult, HashMap<String, Split>), String> {
+    fs::create_dir_all(output_dir)
+        .map_err(|e| format!("Failed to create output directory: {}", e))?;
+
+    let splits = read_voc_splits(root);
+    let mut result = VocImportResult {
+        output_dir: output_dir.to_string_lossy().to_string(),
+        ..Default::default()
+    };
+    let mut assignments = HashMap::new();
+    let mut labels = BTreeSet::new();
+
+    for xml_path in find_voc_xml_files(root) {
+        let annotation = match read_voc_annotation(&xml_path) {
+            Ok(annotation) => annotation,
+            Err(e) => {
+                result.errors.push(e);
+                continue;
+            }
+        };
+        let stem = xml_path
+            .file_stem()
+            .map(|s| s.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        let split = splits.get(&stem).copied();
+        let target_dir = match (layout, split) {
+            (ImportLayout::Dataset, Some(split)) => output_dir.join(split.as_str()),
+            _ => output_dir.to_path_buf(),
+        };
+        fs::create_dir_all(&target_dir)
+            .map_err(|e| format!("Failed to create output directory: {}", e))?;
+
+        let image = find_voc_image(root, &annotation, &stem);
+        if image.is_none() {
+            result
+                .missing_images
+                .push(if annotation.filename.is_empty() {
+                    stem.clone()
+                } else {
+                    annotation.filename.clone()
+                });
+        }
+
+        let (mut width, mut height) = (annotation.width, annotation.height);
+        let image_size = image
+            .as_ref()
+            .filter(|_| width == 0 || height == 0)
+            .and_then(|p| imagesize::size(p).ok());
+        if let Some(size) = image_size {
+            width = size.width as u32;
+            height = size.height as u32;
+        }
+
+        let image_name = image
+            .as_ref()
+            .and_then(|p| p.file_name())
+            .map(|n| n.to_string_lossy().to_string())
+            .unwrap_or_else(|| annotation.filename.clone());
+        let image_path = match (layout, &image) {
+            (ImportLayout::Dataset, Some(source)) => {
+                fs::copy(source, target_dir.join(&image_name))
+                    .map_err(|e| format!("Failed to copy image {}: {}", source.display(), e))?;
+                image_name
+            }
+            (ImportLayout::Staging, Some(source)) => source.to_string_lossy().to_string(),
+            (_, None) => image_name,
+        };
+
+        let json_path = target_dir.join(format!("{}.json", stem));
+        if let Some(split) = split {
+            let image_key = crate::labelme_convert::io::resolve_image_path(&json_path, &image_path);
+            assignments.insert(image_key.to_string_lossy().to_string(), split);
+            match split {
+                Split::Val => result.val_images += 1,
+                Split::Test => result.test_images += 1,
+                _ => result.train_images += 1,
+            }
+        }
+
+        labels.extend(annotation.shapes.iter().map(|s| s.label.clone()));
+        result.shapes += annotation.shapes.len();
+
+        let labelme = LabelMeAnnotation {
+            version: "5.0.1".to_string(),
+            flags: Some(HashMap::new()),
+            shapes: annotation.shapes,
+            image_path,
+            image_data: None,
+            image_height: height,
+            image_width: width,
+        };
+        write_labelme_json(&json_path, &labelme)?;
+        result.images += 1;
+    }
+
+    result.labels = labels.into_iter().collect();
+    Ok((result, assignments))
+}
+
+/// Import a VOC dataset as a LabelMe dataset
+///
+/// Images are copied next to their JSON. Images listed in `ImageSets/Main`
+/// go into `train/`, `val/` or `test/` subfolders; the rest stay at the top.
+pub fn import_voc(input_dir: &Path, output_dir: &Path) -> Result<VocImportResult, String> {
+    let root = find_voc_root(input_dir)
+        .ok_or_else(|| format!("No VOC Annotations folder found in {}", input_dir.display()))?;
+    write_labelme_files(&root, output_dir, ImportLayout::Dataset).map(|(result, _)| result)
+}
+
+/// Run a conversion on a VOC dataset
+///
+/// The annotations are staged as LabelMe JSON in a temporary directory and
+/// converted with the regular pipelines. Output location and dataset name
+/// are resolved against the original input directory.
+pub fn convert_voc(config: &ConversionConfig, voc_root: &Path) -> ConversionResult {
+    let staging = match tempfile::tempdir() {
+        Ok(dir) => dir,
+        Err(e) => {
+            return ConversionResult::failure(vec![format!(
+                "Failed to create staging directory: {}",
+                e
+            )])
+        }
+    };
+
+    let (import, assignments) =
+        match write_labelme_files(voc_root, staging.path(), ImportLayout::Staging) {
+            Ok(staged) => staged,
+            Err(e) => return ConversionResult::failure(vec![e]),
+        };
+    println!(
+        "📦 已讀取 VOC 資料集：{} 個標註檔（train {} / val {} / test {}）",
+        import.images, import.train_images, import.val_images, import.test_images
+    );
+
+    let mut staged = config.clone();
+    staged.input_dir = staging.path().to_path_buf();
+    staged.source_format = Some(DatasetSourceFormat::LabelMe);
+    staged.output_dir = Some(config.get_output_dir());
+    staged.custom_dataset_name = Some(config.get_dataset_folder_name());
+    // Keep the published splits unless another split mode was requested
+    if staged.split_group.is_none() && !staged.stratified && !staged.kfold_enabled() {
+        staged.fixed_splits = assignments;
+    }
+
+    let mut result = crate::labelme_convert::convert(&staged);
+    result.errors.extend(import.errors);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_voc_xml() {
+        let xml = r#"<annotation>
+  <filename>000001.jpg</filename>
+  <size><width>353</width><height>500</height><depth>3</depth></size>
+  <object>
+    <name>dog</name><pose>Left</pose><truncated>1</truncated><difficult>0</difficult>
+    <bndbox><xmin>48</xmin><ymin>240</ymin><xmax>195.5</xmax><ymax>371</ymax></bndbox>
+  </object>
+  <object>
+    <name>cat</name>
+    <polygon><x1>1</x1><y1>2</y1><x2>10</x2><y2>2</y2><x3>10</x3><y3>20</y3></polygon>
+    <bndbox><xmin>1</xmin><ymin>2</ymin><xmax>10</xmax><ymax>20</ymax></bndbox>
+  </object>
+  <object>
+    <name>bird</name>
+    <polygon><pt><x>0</x><y>0</y></pt><pt><x>4</x><y>0</y></pt><pt><x>4</x><y>4</y></pt></polygon>
+  </object>
+  <object><name>broken</name></object>
+</annotation>"#;
+
+        let annotation = parse_voc_xml(xml).unwrap();
+        assert_eq!(annotation.filename, "000001.jpg");
+        assert_eq!((annotation.width, annotation.height), (353, 500));
+        assert_eq!(annotation.shapes.len(), 3);
+
+        let dog = &annotation.shapes[0];
+        assert_eq!(dog.shape_type, "rectangle");
+        assert_eq!(dog.points, vec![(48.0, 240.0), (195.5, 371.0)]);
+        let flags = dog.flags.as_ref().unwrap();
+        assert_eq!(flags.get("truncated"), Some(&true));
+        assert_eq!(flags.get("difficult"), Some(&false));
+        assert_eq!(dog.description.as_deref(), Some("pose=Left"));
+
+        assert_eq!(annotation.shapes[1].shape_type, "polygon");
+        assert_eq!(annotation.shapes[1].points.len(), 3);
+        assert_eq!(annotation.shapes[2].points[2], (4.0, 4.0));
+
+        assert!(parse_voc_xml("<root/>").is_err());
+    }
+
+    #[test]
+    fn test_read_voc_splits() {
+        use tempfile::TempDir;
+
+        let dir = TempDir::new().unwrap();
+        let main = dir.path().join(VOC_SPLITS_DIR);
+        fs::create_dir_all(&main).unwrap();
+        fs::write(main.join("train.txt"), "a\nb\n").unwrap();
+        fs::write(main.join("val.txt"), "c\n").unwrap();
+        fs::write(main.join("trainval.txt"), "a\nb\nc\nd\n").unwrap();
+        fs::write(main.join("test.txt"), "e\n").unwrap();
+        fs::write(main.join("dog_train.txt"), "a  1\nz -1\n").unwrap();
+
+        let splits = read_voc_splits(dir.path());
+        assert_eq!(splits.len(), 4);
+        assert_eq!(splits.get("a"), Some(&Split::Train));
+        assert_eq!(splits.get("c"), Some(&Split::Val));
+        assert_eq!(splits.get("e"), Some(&Split::Test));
+        assert_eq!(splits.get("d"), None);
+    }
+
+    #[test]
+    fn test_import_voc_layout() {
+        use tempfile::TempDir;
+
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().join("VOCdevkit").join("VOC2007");
+        fs::create_dir_all(root.join(VOC_ANNOTATIONS_DIR)).unwrap();
+        fs::create_dir_all(root.join(VOC_IMAGES_DIR)).unwrap();
+        fs::create_dir_all(root.join(VOC_SPLITS_DIR)).unwrap();
+        for stem in ["a", "b"] {
+            fs::write(
+                root.join(VOC_ANNOTATIONS_DIR).join(format!("{}.xml", stem)),
+                format!(
+                    "<annotation><filename>{}.jpg</filename><size><width>10</width><height>10</height></size>\
+                     <object><name>dog</name><bndbox><xmin>1</xmin><ymin>1</ymin><xmax>5</xmax><ymax>5</ymax></bndbox></object></annotation>",
+                    stem
+                ),
+            )
+            .unwrap();
+            fs::write(
+                root.join(VOC_IMAGES_DIR).join(format!("{}.jpg", stem)),
+                b"jpg",
+            )
+            .unwrap();
+        }
+        fs::write(root.join(VOC_SPLITS_DIR).join("val.txt"), "b\n").unwrap();
+
+        assert_eq!(find_voc_root(dir.path()), Some(root.clone()));
+        // Conversion input only looks at the top-level folders
+        assert_eq!(find_voc_input(dir.path()), None);
+        assert_eq!(
+            find_voc_input(&dir.path().join("VOCdevkit")),
+            Some(root.clone())
+        );
+        fs::write(dir.path().join("VOCdevkit").join("a.json"), "{}").unwrap();
+        assert_eq!(find_voc_input(&dir.path().join("VOCdevkit")), None);
+
+        let output = dir.path().join("out");
+        let result = import_voc(dir.path(), &output).unwrap();
+        assert_eq!(result.images, 2);
+        assert_eq!(result.val_images, 1);
+        assert_eq!(result.labels, vec!["dog"]);
+        assert!(output.join("a.json").is_file());
+        assert!(output.join("a.jpg").is_file());
+        assert!(output.join("val").join("b.json").is_file());
+        assert!(output.join("val").join("b.jpg").is_file());
+    }
+
+    #[test]
+    fn test_convert_selected_voc_source() {
+        use crate::labelme_convert::config::OutputFormat;
+        use tempfile::TempDir;
+
+        let dir = TempDir::new().unwrap();
+        let root = dir.path().join("VOC2007");
+        fs::create_dir_all(root.join(VOC_ANNOTATIONS_DIR)).unwrap();
+        fs::create_dir_all(root.join(VOC_IMAGES_DIR)).unwrap();
+        fs::write(
+            root.join(VOC_ANNOTATIONS_DIR).join("a.xml"),
+            "<annotation><filename>a.jpg</filename><size><width>10</width><height>10</height></size>\
+             <object><name>dog</name><bndbox><xmin>1</xmin><ymin>1</ymin><xmax>5</xmax><ymax>5</ymax></bndbox></object></annotation>",
+        )
+        .unwrap();
+        image::RgbImage::new(10, 10)
+            .save(root.join(VOC_IMAGES_DIR).join("a.jpg"))
+            .unwrap();
+
+        let mut config = ConversionConfig::new(dir.path().to_path_buf());
+        config.output_dir = Some(dir.path().join("out"));
+        config.output_format = OutputFormat::Yolo;
+        config.source_format = Some(DatasetSourceFormat::PascalVoc);
+
+        let result = crate::labelme_convert::convert(&config);
+        assert!(result.success, "{:?}", result.errors);
+        assert_eq!(result.stats.processed_files, 1);
+        assert_eq!(result.stats.total_annotations, 1);
+    }
+}
@@ -5,18 +5,23 @@
 //
 // Adapted and modified for dataset-app
 
+use crate::labelme_convert::augment::{augmented_file_name, Augmenter, AUGMENT_LOG_FILE};
+use crate::labelme_convert::balance::plan_balance;
 use crate::labelme_convert::config::ConversionConfig;
-use crate::labelme_convert::conversion::shape_to_yolo_line;
+use crate::labelme_convert::conversion::{shape_to_yolo_line, PolygonRefiner};
 use crate::labelme_convert::io::{
-    copy_image, create_dataset_yaml, extract_embedded_image, find_background_images,
+    copy_image, create_balanced_dataset_yaml, create_dataset_yaml, create_fold_datasets,
+    extract_embedded_image, find_background_images,
     find_json_files, read_labelme_json, resolve_image_path, setup_yolo_directories, write_file,
 };
 use crate::labelme_convert::pipeline::{
-    determine_split, hash_string, ConversionPipeline, FileType, OutputDirectories,
-    ProcessedFileResult, ProcessingContext,
+    ConversionPipeline, FileType, OutputDirectories,
+    ProcessedFileResult, ProcessingContext, Split,
+};
+use crate::labelme_convert::split::{plan_splits, resolve_split};
+use crate::labelme_convert::types::{
+    ConversionResult, InputAnnotationFormat, InvalidAnnotation, Shape,
 };
-use crate::labelme_convert::types::{ConversionResult, InputAnnotationFormat, InvalidAnnotation};
-use std::collections::HashSet;
 use std::path::Path;
 
 // ============================================================================
@@ -61,8 +66,7 @@ impl ConversionPipeline for YoloPipeline {
         context.mark_image_processed(image_key.clone());
 
         // Determine split (train/val/test)
-        let path_hash = hash_string(&image_key);
-        let split = determine_split(path_hash, config.val_size, config.test_size);
+        let split = resolve_split(config, context.split_plan.as_ref(), &image_key);
 
         // Get output directories for this split
         let labels_dir = output_dirs.get_output_dir(split, FileType::Label);
@@ -81,7 +85,7 @@ impl ConversionPipeline for YoloPipeline {
             .map(|s| s.to_string_lossy().to_string())
             .unwrap_or_else(|| "unknown".to_string());
 
-        if let Some(image_data) = &annotation.image_data {
+        let output_image = if let Some(image_data) = &annotation.image_data {
             // Extract embedded image
             let ext = image_path
                 .extension()
@@ -89,19 +93,17 @@ impl ConversionPipeline for YoloPipeline {
                 .unwrap_or_else(|| "png".to_string());
             let dest_path = images_dir.join(format!("{}.{}", image_stem, ext));
             extract_embedded_image(image_data, &dest_path)?;
+            dest_path
         } else if image_path.exists() {
             // Copy image file
             copy_image(&image_path, images_dir)
-                .map_err(|e| format!("Failed to copy image: {}", e))?;
+                .map_err(|e| format!("Failed to copy image: {}", e))?
         } else {
             return Err(format!("Image file not found: {}", image_path.display()));
-        }
+        };
 
-        // Generate YOLO label file
-        let mut yolo_lines = Vec::new();
-        let mut annotation_count = 0;
-        let mut skipped_count = 0;
-        let mut invalid_annotations = Vec::new();
+        // Remember the output path so finalize() can write the image lists
+        context.record_output_image(&image_key, split, output_image.clone());
 
         // Get filename for error reporting
         let file_name = json_path
@@ -109,59 +111,48 @@ impl ConversionPipeline for YoloPipeline {
             .map(|s| s.to_string_lossy().to_string())
             .unwrap_or_else(|| "unknown".to_string());
 
-        // Get input format from config
-        let input_format = config
-            .detected_input_format
-            .unwrap_or(InputAnnotationFormat::Unknown);
-
-        for shape in &annotation.shapes {
-            if let Some(&class_id) = context.label_map.get(&shape.label) {
-                match shape_to_yolo_line(
-                    shape,
-                    class_id,
-                    annotation.image_width,
-                    annotation.image_height,
-                    config.annotation_format,
-                    input_format,
-                ) {
-                    Ok(line) => {
-                        yolo_lines.push(line);
-                        annotation_count += 1;
-                    }
-                    Err(reason) => {
-                        invalid_annotations.push(InvalidAnnotation {
-                            file: file_name.clone(),
-                            label: shape.label.clone(),
-                            reason: reason.as_str(),
-                            shape_type: shape.shape_type.clone(),
-                            points_count: shape.points.len(),
-                        });
-                        skipped_count += 1;
-                    }
-                }
-            } else {
-                // Label not in the predefined list
-                context.add_skipped_label(&shape.label);
-                skipped_count += 1;
-            }
-        }
+        // Generate YOLO label file
+        let labels = yolo_label_lines(
+            &annotation.shapes,
+            annotation.image_width,
+            annotation.image_height,
+            &file_name,
+            config,
+            context,
+            true,
+        );
 
         // Write label file
         let label_path = labels_dir.join(format!("{}.txt", image_stem));
-        let content = yolo_lines.join("\n");
+        let content = labels.lines.join("\n");
         write_file(&label_path, &content)
             .map_err(|e| format!("Failed to write label file: {}", e))?;
 
+        // Write augmented copies of train images
+        if split == Split::Train && context.augmenter.is_some() {
+            write_augmented_copies(
+                &annotation.shapes,
+                &image_key,
+                &output_image,
+                &image_stem,
+                images_dir,
+                labels_dir,
+                &file_name,
+                config,
+                context,
+            )?;
+        }
+
         // Check if this image became empty after label filtering
         // Conditions: no output lines, but original had shapes, and we're using label filtering
-        let is_filtered_empty = yolo_lines.is_empty()
+        let is_filtered_empty = labels.lines.is_empty()
             && !annotation.shapes.is_empty()
             && !config.label_list.is_empty();
 
         Ok(ProcessedFileResult {
-            annotations_processed: annotation_count,
-            annotations_skipped: skipped_count,
-            invalid_annotations,
+            annotations_processed: labels.annotations,
+            annotations_skipped: labels.skipped,
+            invalid_annotations: labels.invalid,
             is_filtered_empty,
             filtered_empty_file_name: if is_filtered_empty {
                 Some(file_name)
@@ -177,9 +168,37 @@ impl ConversionPipeline for YoloPipeline {
         output_dirs: &dyn OutputDirectories,
         context: &ProcessingContext,
     ) -> Result<(), String> {
-        // Create dataset.yaml
-        create_dataset_yaml(output_dirs.base_dir(), &context.label_map, config.has_test_split())
-            .map_err(|e| format!("Failed to create dataset.yaml: {}", e))?;
+        // Create dataset.yaml (train.txt list when balancing repeats images)
+        let has_repeats = context.balance_plan.as_ref().is_some_and(|p| p.has_repeats());
+        if has_repeats {
+            create_balanced_dataset_yaml(
+                output_dirs.base_dir(),
+                &context.label_map,
+                config.has_test_split(),
+                &context.balanced_train,
+            )
+        } else {
+            create_dataset_yaml(output_dirs.base_dir(), &context.label_map, config.has_test_split())
+        }
+        .map_err(|e| format!("Failed to create dataset.yaml: {}", e))?;
+
+        // Create per-fold train/val lists and dataset.yaml files
+        if let Some(plan) = context.split_plan.as_ref().filter(|p| p.folds > 0) {
+            create_fold_datasets(
+                output_dirs.base_dir(),
+                &context.label_map,
+                config.has_test_split(),
+                plan.folds,
+                &context.fold_images,
+                &context.balanced_train.iter().cloned().collect(),
+            )
+            .map_err(|e| format!("Failed to create fold datasets: {}", e))?;
+        }
+
+        // Record the parameters of every augmented image
+        if let Some(augmenter) = &context.augmenter {
+            augmenter.write_log(&output_dirs.base_dir().join(AUGMENT_LOG_FILE))?;
+        }
         Ok(())
     }
 }
@@ -219,8 +238,24 @@ pub fn convert_to_yolo(config: &ConversionConfig) -> ConversionResult {
         pipeline.gather_labels(&json_files, &mut context);
     }
 
+    // Plan group-aware splits up front so whole groups land in one split
+    context.split_plan = plan_splits(config, &json_files);
+    context.stats.split_report = context.split_plan.as_ref().map(|p| p.report.clone());
+
+    // Plan train split balancing (dropped images and repeats)
+    context.balance_plan = plan_balance(config, &json_files, context.split_plan.as_ref());
+    context.stats.balance_report = context.balance_plan.as_ref().map(|p| p.report.clone());
+
+    // Augmented copies are generated while the train images are exported
+    context.augmenter = Augmenter::from_config(config);
+
     // Process each JSON file
     for json_path in &json_files {
+        if context.balance_plan.as_ref().is_some_and(|p| p.is_dropped_json(json_path)) {
+            context.stats.increment_skipped();
+            continue;
+        }
+
         match pipeline.process_file(json_path, config, output_dirs.as_ref(), &mut context) {
             Ok(result) => {
                 context.stats.increment_processed();
@@ -245,13 +280,14 @@ pub fn convert_to_yolo(config: &ConversionConfig) -> ConversionResult {
 
     // Process background images if enabled
     if config.include_background {
-        let bg_files =
-            process_background_images(config, output_dirs.as_ref(), &context.processed_images);
+        let bg_files = process_background_images(config, output_dirs.as_ref(), &mut context);
         for file_name in bg_files {
             context.stats.add_background_file(file_name);
         }
     }
 
+    context.stats.augment_report = context.augmenter.as_ref().map(|a| a.report.clone());
+
     // Update stats with labels
     for label in context.label_map.keys() {
         context.stats.add_label(label.clone());
@@ -286,35 +322,191 @@ pub fn convert_to_yolo(config: &ConversionConfig) -> ConversionResult {
 // Helper functions
 // ============================================================================
 
+/// YOLO label lines of one image with their counts
+#[derive(Debug, Default)]
+struct YoloLabels {
+    lines: Vec<String>,
+    annotations: usize,
+    skipped: usize,
+    invalid: Vec<InvalidAnnotation>,
+}
+
+/// Convert the shapes of one image to YOLO label lines
+///
+/// Shape type and vertex statistics are only recorded when `record_stats`
+/// is set, so augmented copies do not count twice.
+fn yolo_label_lines(
+    shapes: &[Shape],
+    image_width: u32,
+    image_height: u32,
+    file_name: &str,
+    config: &ConversionConfig,
+    context: &mut ProcessingContext,
+    record_stats: bool,
+) -> YoloLabels {
+    let mut labels = YoloLabels::default();
+
+    // Get input format from config
+    let input_format = config
+        .detected_input_format
+        .unwrap_or(InputAnnotationFormat::Unknown);
+
+    let mut refiner = PolygonRefiner::new(config.polygon_options);
+
+    for shape in shapes {
+        if let Some(&class_id) = context.label_map.get(&shape.label) {
+            match shape_to_yolo_line(
+                shape,
+                class_id,
+                image_width,
+                image_height,
+                config.annotation_format,
+                input_format,
+                &config.shape_options,
+                &mut refiner,
+            ) {
+                Ok(Some(line)) => {
+                    labels.lines.push(line);
+                    labels.annotations += 1;
+                    if record_stats {
+                        context.stats.shape_types.record_converted(&shape.shape_type);
+                    }
+                }
+                Ok(None) => {
+                    // Line or point shape skipped by configuration
+                    if record_stats {
+                        context.stats.shape_types.record_skipped(&shape.shape_type);
+                    }
+                    labels.skipped += 1;
+                }
+                Err(reason) => {
+                    labels.invalid.push(InvalidAnnotation {
+                        file: file_name.to_string(),
+                        label: shape.label.clone(),
+                        reason: reason.as_str(),
+                        shape_type: shape.shape_type.clone(),
+                        points_count: shape.points.len(),
+                    });
+                    labels.skipped += 1;
+                }
+            }
+        } else {
+            // Label not in the predefined list
+            context.add_skipped_label(&shape.label);
+            labels.skipped += 1;
+        }
+    }
+
+    if let Some(report) = refiner.report().filter(|_| record_stats) {
+        context.stats.add_vertex_report(report);
+    }
+
+    labels
+}
+
+/// Write the augmented copies of one train image with their label files
+#[allow(clippy::too_many_arguments)]
+fn write_augmented_copies(
+    shapes: &[Shape],
+    image_key: &str,
+    output_image: &Path,
+    image_stem: &str,
+    images_dir: &Path,
+    labels_dir: &Path,
+    file_name: &str,
+    config: &ConversionConfig,
+    context: &mut ProcessingContext,
+) -> Result<(), String> {
+    let Some(mut augmenter) = context.augmenter.take() else {
+        return Ok(());
+    };
+
+    let image = match image::open(output_image) {
+        Ok(image) => image,
+        Err(e) => {
+            context.augmenter = Some(augmenter);
+            return Err(format!("Failed to read image for augmentation: {}", e));
+        }
+    };
+    let extension = output_image
+        .extension()
+        .map(|e| e.to_string_lossy().to_string())
+        .unwrap_or_default();
+
+    let mut result = Ok(());
+    for copy in 1..=augmenter.copies() {
+        let aug_name = augmented_file_name(image_stem, &extension, copy);
+        let augmented = augmenter.augment(image_key, copy, &image, shapes, &aug_name);
+
+        let aug_image = images_dir.join(&aug_name);
+        if let Err(e) = augmented.image.save(&aug_image) {
+            result = Err(format!("Failed to write augmented image: {}", e));
+            break;
+        }
+
+        let labels = yolo_label_lines(
+            &augmented.shapes,
+            augmented.image.width(),
+            augmented.image.height(),
+            file_name,
+            config,
+            context,
+            false,
+        );
+        augmenter.add_annotations(labels.annotations);
+
+        let label_path = labels_dir.join(format!("{}_aug{}.txt", image_stem, copy));
+        if let Err(e) = write_file(&label_path, &labels.lines.join("\n")) {
+            result = Err(format!("Failed to write label file: {}", e));
+            break;
+        }
+
+        context.record_output_image(&format!("{}#aug{}", image_key, copy), Split::Train, aug_image);
+    }
+
+    // Put the augmenter back even when a copy failed
+    context.augmenter = Some(augmenter);
+    result
+}
+
 /// Process background images (images without annotations)
 /// Returns the list of background image file names
 fn process_background_images(
     config: &ConversionConfig,
     output_dirs: &dyn OutputDirectories,
-    processed_images: &HashSet<String>,
+    context: &mut ProcessingContext,
 ) -> Vec<String> {
-    let bg_images = find_background_images(&config.input_dir, processed_images);
+    let bg_images = find_background_images(&config.input_dir, &context.processed_images);
     let mut bg_files = Vec::new();
 
     for image_path in bg_images {
         let image_key = image_path.to_string_lossy().to_string();
 
+        // Skip backgrounds dropped by balancing
+        if context.balance_plan.as_ref().is_some_and(|p| p.is_dropped(&image_key)) {
+            continue;
+        }
+
         // Determine split
-        let path_hash = hash_string(&image_key);
-        let split = determine_split(path_hash, config.val_size, config.test_size);
+        let split = resolve_split(config, context.split_plan.as_ref(), &image_key);
 
         let labels_dir = output_dirs.get_output_dir(split, FileType::Label);
         let images_dir = output_dirs.get_output_dir(split, FileType::Image);
 
         // Copy image
-        if let Err(e) = copy_image(&image_path, images_dir) {
-            eprintln!(
-                "Failed to copy background image {}: {}",
-                image_path.display(),
-                e
-            );
-            continue;
-        }
+        let output_image = match copy_image(&image_path, images_dir) {
+            Ok(path) => path,
+            Err(e) => {
+                eprintln!(
+                    "Failed to copy background image {}: {}",
+                    image_path.display(),
+                    e
+                );
+                continue;
+            }
+        };
+
+        context.record_output_image(&image_key, split, output_image);
 
         // Create empty label file
         let image_stem = image_path
@@ -378,4 +570,56 @@ mod tests {
             Path::new("/test/labels/test")
         );
     }
+
+    #[test]
+    fn test_convert_with_augmentation() {
+        use crate::labelme_convert::augment::AugmentConfig;
+        use crate::labelme_convert::io::write_labelme_json;
+        use crate::labelme_convert::types::LabelMeAnnotation;
+
+        let input = tempfile::tempdir().unwrap();
+        let output = tempfile::tempdir().unwrap();
+        image::RgbImage::new(20, 10).save(input.path().join("a.png")).unwrap();
+        let annotation = LabelMeAnnotation {
+            version: "5.0.1".to_string(),
+            flags: None,
+            shapes: vec![Shape {
+                label: "box".to_string(),
+                points: vec![(2.0, 2.0), (6.0, 8.0)],
+                group_id: None,
+                shape_type: "rectangle".to_string(),
+                description: None,
+                mask: None,
+                flags: None,
+            }],
+            image_path: "a.png".to_string(),
+            image_data: None,
+            image_height: 10,
+            image_width: 20,
+        };
+        write_labelme_json(&input.path().join("a.json"), &annotation).unwrap();
+
+        let config = ConversionConfig::new(input.path().to_path_buf())
+            .with_output_dir(output.path().to_path_buf())
+            .with_custom_name(Some("aug".to_string()))
+            .with_val_size(0.0)
+            .with_augment(Some(AugmentConfig {
+                copies: 2,
+                horizontal_flip: 1.0,
+                ..Default::default()
+            }));
+
+        let result = convert_to_yolo(&config);
+        assert!(result.errors.is_empty(), "{:?}", result.errors);
+        let report = result.stats.augment_report.unwrap();
+        assert_eq!((report.source_images, report.augmented_images), (1, 2));
+        assert_eq!(report.augmented_annotations, 2);
+
+        // Flipped box: x 2..6 becomes 14..18 in a 20 px wide image
+        let base = output.path().join("aug");
+        assert!(base.join("images/train/a_aug1.png").exists());
+        let label = std::fs::read_to_string(base.join("labels/train/a_aug2.txt")).unwrap();
+        assert_eq!(label, "0 0.800000 0.500000 0.200000 0.600000");
+        assert!(base.join(AUGMENT_LOG_FILE).exists());
+    }
 }
@@ -34,6 +34,7 @@ pub fn run() {
             // Dataset analysis
             commands::dataset::get_dataset_stats,
             commands::dataset::get_labelme_summary,
+            commands::dataset::get_labelme_statistics,
             // Directory operations
             commands::directory::get_directory_images,
             commands::directory::get_paginated_images,
@@ -42,6 +43,11 @@ pub fn run() {
             commands::dataset::save_annotation,
             // Advanced processing
             commands::advanced::crop_and_remap_annotations,
+            commands::advanced::crop_and_remap_with_rules,
+            commands::advanced::crop_and_remap_async,
+            commands::advanced::crop_and_remap_to_dataset,
+            commands::advanced::cancel_crop_job,
+            commands::advanced::paste_back_crop_edits,
             commands::advanced::generate_annotated_previews,
             commands::advanced::crop_remap_adapter,
             // LabelMe conversion commands
@@ -56,6 +62,13 @@ pub fn run() {
             commands::labelme_convert::scan_labelme_labels_async,
             commands::labelme_convert::scan_labelme_labels_with_counts_async,
             commands::labelme_convert::analyze_labelme_dataset_async,
+            commands::labelme_convert::analyze_labelme_merge,
+            commands::labelme_convert::merge_labelme_datasets,
+            commands::labelme_convert::diff_labelme_datasets,
+            commands::labelme_convert::import_cvat_annotations,
+            commands::labelme_convert::import_label_studio_tasks,
+            commands::labelme_convert::import_voc_dataset,
+            commands::labelme_convert::import_mask_dataset,
             // External module functions
             core::labelme2yolo::export_to_yolo_new,
             core::preview::generate_single_annotated_preview